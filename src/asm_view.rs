@@ -0,0 +1,186 @@
+use crate::buildlog;
+use crate::console;
+use crate::demangle::demangle;
+use crate::highlight;
+use crate::translator::{rustc_query, RustcCommand};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// /Qasm-view[:function]: assembly with the source lines it came from
+#[derive(Debug, Clone)]
+pub struct AsmView {
+    /// The `.s` file rustc writes
+    pub asm: PathBuf,
+    /// Only functions whose demangled path contains this
+    pub function: Option<String>,
+    /// Written there instead of printed (/Qasm-view-file)
+    pub report: Option<PathBuf>,
+}
+
+/// Jumps forward by at most this many lines show the lines in between
+const GAP: usize = 4;
+
+/// Interleaves the assembly with its source and prints or writes it
+///
+/// The `.loc` directives of the line tables say which source line each
+/// instruction belongs to; a line is shown when it changes, like
+/// `objdump -S`. Directives and debug sections are left out, so what
+/// remains are functions, their branch labels and instructions.
+pub fn show(cmd: &RustcCommand, view: &AsmView) -> Result<()> {
+    let path = cmd.resolve(&view.asm);
+    let Ok(asm) = std::fs::read_to_string(&path) else {
+        console::eprint(&format!(
+            "[icx-rustc] warning: no assembly in {} (several codegen units write one .s each; use -Ccodegen-units=1)",
+            view.asm.display()
+        ));
+        return Ok(());
+    };
+    
+    let mut files: HashMap<u32, PathBuf> = HashMap::new();
+    let mut sources: HashMap<PathBuf, Option<Vec<String>>> = HashMap::new();
+    let mut out: Vec<String> = Vec::new();
+    let mut shown = false;
+    let mut found = false;
+    let mut debug_section = false;
+    let mut last: Option<(u32, usize)> = None;
+    
+    for line in asm.lines() {
+        let trimmed = line.trim();
+        // 段切换：调试段的内容不显示
+        if let Some(section) = trimmed.strip_prefix(".section") {
+            let name = section.trim();
+            debug_section = name.starts_with(".debug") || name.starts_with("__DWARF") || name.starts_with(".note");
+            continue;
+        }
+        if matches!(trimmed, ".text" | ".data" | ".bss") {
+            debug_section = false;
+            continue;
+        }
+        if debug_section {
+            continue;
+        }
+        // .file 1 "dir" "name" [md5 ...]（DWARF 5）或 .file 1 "path"
+        if let Some(rest) = trimmed.strip_prefix(".file\t").or_else(|| trimmed.strip_prefix(".file ")) {
+            if let Some((number, path)) = file_directive(rest) {
+                files.insert(number, path);
+            }
+            continue;
+        }
+        
+        // 函数开始
+        if let Some(label) = trimmed.strip_suffix(':').filter(|l| !line.starts_with([' ', '\t']) && !is_local(l)) {
+            let name = demangle(label);
+            shown = view.function.as_deref().is_none_or(|f| name.contains(f));
+            if shown {
+                found = true;
+                out.push(String::new());
+                out.push(format!("{}:", name).yellow().bold().to_string());
+            }
+            last = None;
+            continue;
+        }
+        if !shown {
+            continue;
+        }
+        
+        if let Some(rest) = trimmed.strip_prefix(".loc\t").or_else(|| trimmed.strip_prefix(".loc ")) {
+            let mut words = rest.split_whitespace();
+            let (Some(Ok(file)), Some(Ok(number))) = (words.next().map(str::parse::<u32>), words.next().map(str::parse::<usize>)) else {
+                continue;
+            };
+            if number == 0 || last == Some((file, number)) {
+                continue;
+            }
+            let Some(path) = files.get(&file) else {
+                continue;
+            };
+            let text = sources.entry(path.clone())
+                .or_insert_with(|| read_source(cmd, path));
+            // 换文件时（多为内联进来的标准库代码）先显示文件名
+            if last.is_none_or(|(f, _)| f != file) {
+                out.push(format!("  --> {}", path.display()).bright_black().to_string());
+            }
+            // 小步前进时补上中间的行
+            let from = match last {
+                Some((f, previous)) if f == file && number > previous && number - previous <= GAP && text.is_some() => previous + 1,
+                _ => number,
+            };
+            for n in from..=number {
+                let source = text.as_ref().and_then(|t| t.get(n - 1)).map(String::as_str).unwrap_or("");
+                out.push(format!(
+                    "{} {}",
+                    format!("{:>5} |", n).bright_black(),
+                    highlight::rust(source)
+                ));
+            }
+            last = Some((file, number));
+            continue;
+        }
+        
+        if trimmed.is_empty() || trimmed.starts_with('.') && !trimmed.ends_with(':') || trimmed.starts_with(['#', ';', '@']) {
+            continue;
+        }
+        // 分支标签（.LBB0_2:），.Ltmp 之类的辅助标签不显示
+        if let Some(label) = trimmed.strip_suffix(':') {
+            if label.starts_with(".LBB") || label.starts_with("LBB") {
+                out.push(label.bright_magenta().to_string() + ":");
+            }
+            continue;
+        }
+        out.push(instruction(trimmed));
+    }
+    
+    if !found {
+        match &view.function {
+            Some(function) => console::eprint(&format!("[icx-rustc] warning: /Qasm-view: no function matches '{}'", function)),
+            None => console::eprint("[icx-rustc] warning: /Qasm-view: the assembly contains no functions"),
+        }
+        return Ok(());
+    }
+    let text = out.join("\n");
+    match &view.report {
+        Some(report) => {
+            let report = cmd.resolve(report);
+            std::fs::write(&report, format!("{}\n", buildlog::plain(text.trim_start())))
+                .with_context(|| format!("Failed to write {}", report.display()))?;
+        }
+        None => console::print(text.trim_start()),
+    }
+    Ok(())
+}
+
+/// The lines of a source file; the standard library's from rust-src, if installed
+fn read_source(cmd: &RustcCommand, path: &Path) -> Option<Vec<String>> {
+    let text = std::fs::read_to_string(cmd.resolve(path)).ok().or_else(|| {
+        // /rustc/<commit>/library/... -> <sysroot>/lib/rustlib/src/rust/library/...
+        let relative = path.strip_prefix("/rustc").ok()?.components().skip(1).collect::<PathBuf>();
+        let sysroot = rustc_query(&cmd.executable, &["--print", "sysroot"])?;
+        std::fs::read_to_string(Path::new(sysroot.trim()).join("lib/rustlib/src/rust").join(relative)).ok()
+    })?;
+    Some(text.lines().map(str::to_string).collect())
+}
+
+/// `1 "dir" "name" md5 0x...` or `1 "path"` -> (1, dir/name)
+fn file_directive(rest: &str) -> Option<(u32, PathBuf)> {
+    let (number, rest) = rest.split_once(char::is_whitespace)?;
+    let number = number.parse().ok()?;
+    let quoted: Vec<&str> = rest.split('"').skip(1).step_by(2).collect();
+    let path = match quoted.as_slice() {
+        [dir, name, ..] if !Path::new(name).is_absolute() => Path::new(dir).join(name),
+        [_, name, ..] | [name] => PathBuf::from(name),
+        [] => return None,
+    };
+    Some((number, path))
+}
+
+/// Assembler-internal labels (ELF `.L`, Mach-O `L` / `ltmp`)
+fn is_local(label: &str) -> bool {
+    label.starts_with(".L") || label.starts_with("L") && !label.starts_with("L_") || label.starts_with("ltmp") || label.starts_with("__unnamed")
+}
+
+fn instruction(line: &str) -> String {
+    let (mnemonic, operands) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    format!("        {} {}", format!("{:<7}", mnemonic).bright_blue(), operands.trim())
+}
@@ -0,0 +1,125 @@
+use crate::cli::Args;
+use crate::config::Config;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `ICX_AUDIT_LOG`, else `audit-log` in icx-rustc.toml (relative to the file)
+pub fn path(config: &Config) -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("ICX_AUDIT_LOG").filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let path = config.audit_log.as_ref()?;
+    let dir = config.path.as_ref().and_then(|p| p.parent());
+    Some(dir.map_or_else(|| path.clone(), |d| d.join(path)))
+}
+
+/// Appends one JSON line for a compile to the audit log, if there is one
+///
+/// The record holds the time (UTC), user, host, working directory, the
+/// arguments as given and the translated commands. It is written with a
+/// single append, so builds running side by side do not interleave; a
+/// log that cannot be written fails the build rather than going unrecorded.
+pub fn record(config: &Config, args: &Args, commands: &[RustcCommand]) -> Result<()> {
+    let Some(path) = path(config) else {
+        return Ok(());
+    };
+    let original = if args.original.is_empty() {
+        args.command_line.get(1..).unwrap_or_default()
+    } else {
+        &args.original[..]
+    };
+    // 每个单元：C 编译、rustc、其后的归档与优化步骤，按执行顺序
+    let mut lines = Vec::new();
+    for cmd in commands {
+        lines.extend(cmd.c_steps.iter().map(|s| s.display()));
+        if !cmd.input_files.is_empty() || !cmd.link_inputs.is_empty() {
+            lines.push(cmd.display());
+        }
+        lines.extend(cmd.archive.iter().chain(&cmd.bolt).chain(&cmd.wasm).map(|s| s.display()));
+    }
+    let record = json!({
+        "time": timestamp(SystemTime::now()),
+        "user": user(),
+        "host": host(),
+        "pid": std::process::id(),
+        "cwd": std::env::current_dir().ok().map(|d| d.display().to_string()),
+        "tool": format!("icx-rustc {}", env!("CARGO_PKG_VERSION")),
+        "arguments": original,
+        "commands": lines,
+    });
+    let mut line = serde_json::to_string(&record)?;
+    line.push('\n');
+    
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+    file.write_all(line.as_bytes()).with_context(|| format!("Failed to write audit log {}", path.display()))
+}
+
+fn user() -> Option<String> {
+    ["USER", "USERNAME", "LOGNAME"].iter()
+        .find_map(|v| std::env::var(v).ok().filter(|u| !u.is_empty()))
+        .or_else(account)
+}
+
+/// The login name of the real user id (services and containers often have no USER)
+#[cfg(unix)]
+fn account() -> Option<String> {
+    // 安全性：getpwuid 返回静态缓冲区，立即复制；其他线程不调用它
+    unsafe {
+        let entry = libc::getpwuid(libc::getuid());
+        if entry.is_null() || (*entry).pw_name.is_null() {
+            return Some(format!("uid {}", libc::getuid()));
+        }
+        Some(std::ffi::CStr::from_ptr((*entry).pw_name).to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(unix))]
+fn account() -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+pub fn host() -> Option<String> {
+    let mut name = [0u8; 256];
+    // 安全性：缓冲区长度正确，gethostname 不会越界写入
+    let ok = unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } == 0;
+    let end = name.iter().position(|&b| b == 0)?;
+    ok.then(|| String::from_utf8_lossy(&name[..end]).into_owned())
+}
+
+#[cfg(not(unix))]
+pub fn host() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+/// `2026-10-14T08:30:00.125Z`
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rest) = (secs / 86400, secs % 86400);
+    // 由天数推算公历日期（Howard Hinnant 的 civil_from_days）
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, rest / 3600, rest % 3600 / 60, rest % 60, since.subsec_millis()
+    )
+}
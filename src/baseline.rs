@@ -0,0 +1,128 @@
+use crate::diagnostics::Diagnostic;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Warnings accepted by a /Qdiag-baseline:record run
+///
+/// A warning is identified by its file, code and message with digits and
+/// whitespace normalized, so it survives edits that only move it to a
+/// different line. Each entry absorbs as many occurrences as were recorded.
+#[derive(Debug, Default)]
+pub struct Baseline {
+    known: IndexMap<String, u32>,
+    /// Occurrences matched in the current build
+    seen: Mutex<IndexMap<String, u32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BaselineFile {
+    version: u32,
+    warnings: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    file: String,
+    code: String,
+    message: String,
+    count: u32,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {} (record one with /Qdiag-baseline:record)", path.display()))?;
+        let file: BaselineFile = serde_json::from_str(&text)
+            .with_context(|| format!("{} is not a diagnostics baseline", path.display()))?;
+        let known = file.warnings.into_iter()
+            .map(|e| (key(&e.file, &e.code, &e.message), e.count))
+            .collect();
+        Ok(Self { known, seen: Mutex::default() })
+    }
+    
+    /// Whether a warning is covered by the baseline, consuming one recorded occurrence
+    pub fn absorb(&self, diag: &Diagnostic) -> bool {
+        let Some((file, code, message)) = identity(diag) else {
+            return false;
+        };
+        let key = key(&file, &code, &message);
+        let Some(&known) = self.known.get(&key) else {
+            return false;
+        };
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        let seen = seen.entry(key).or_default();
+        *seen += 1;
+        *seen <= known
+    }
+    
+    /// Recorded warnings that did not occur in this build
+    pub fn fixed(&self) -> u32 {
+        let seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        self.known.iter()
+            .map(|(key, &known)| known.saturating_sub(seen.get(key).copied().unwrap_or_default()))
+            .sum()
+    }
+    
+    /// Starts matching a new build (--watch)
+    pub fn reset(&self) {
+        self.seen.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// Writes the warnings of a build as the new baseline; returns how many were recorded
+pub fn record(path: &Path, diagnostics: &[Diagnostic]) -> Result<u32> {
+    let mut counts: IndexMap<(String, String, String), u32> = IndexMap::new();
+    for identity in diagnostics.iter().filter_map(identity) {
+        *counts.entry(identity).or_default() += 1;
+    }
+    let total = counts.values().sum();
+    
+    let file = BaselineFile {
+        version: 1,
+        warnings: counts.into_iter()
+            .map(|((file, code, message), count)| Entry { file, code, message, count })
+            .collect(),
+    };
+    let text = serde_json::to_string_pretty(&file)?;
+    std::fs::write(path, text + "\n").with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(total)
+}
+
+/// (file, code, normalized message) of a warning; errors are never baselined
+fn identity(diag: &Diagnostic) -> Option<(String, String, String)> {
+    if diag.level != "warning" {
+        return None;
+    }
+    let file = diag.primary_span()
+        .map(|s| s.file_name.replace('\\', "/"))
+        .unwrap_or_default();
+    let code = diag.code.as_ref().map(|c| c.code.clone()).unwrap_or_default();
+    
+    // 数字归一化：行号、计数等变化不算新警告
+    let mut message = String::new();
+    for word in diag.message.split_whitespace() {
+        if !message.is_empty() {
+            message.push(' ');
+        }
+        let mut digits = false;
+        for c in word.chars() {
+            if c.is_ascii_digit() {
+                if !digits {
+                    message.push('N');
+                }
+                digits = true;
+            } else {
+                message.push(c);
+                digits = false;
+            }
+        }
+    }
+    Some((file, code, message))
+}
+
+fn key(file: &str, code: &str, message: &str) -> String {
+    format!("{}\t{}\t{}", file, code, message)
+}
@@ -0,0 +1,27 @@
+//! `icx-cargo`：运行同目录下的 `icx-rustc cargo ...`
+
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    let exe = std::env::current_exe().ok()
+        .map(|p| p.with_file_name(format!("icx-rustc{}", std::env::consts::EXE_SUFFIX)))
+        .filter(|p| p.is_file())
+        .unwrap_or_else(|| PathBuf::from("icx-rustc"));
+    let mut command = Command::new(&exe);
+    command.arg("cargo").args(std::env::args_os().skip(1));
+    
+    // Unix 上直接替换进程，Ctrl+C 与退出码不经过这一层
+    #[cfg(unix)]
+    let error = {
+        use std::os::unix::process::CommandExt;
+        command.exec()
+    };
+    #[cfg(not(unix))]
+    let error = match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(4)),
+        Err(e) => e,
+    };
+    eprintln!("icx-cargo error: failed to run {}: {}", exe.display(), error);
+    std::process::exit(3);
+}
@@ -0,0 +1,83 @@
+use crate::cc::CStep;
+use crate::cli::Args;
+use crate::console;
+use crate::exit_code;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// /Qbolt:<profile>: optimizes the linked binary with llvm-bolt
+///
+/// The profile is what `perf2bolt` (or an instrumented run) recorded,
+/// `.fdata` or YAML. The binary is linked with `--emit-relocs` so BOLT
+/// can move functions, then rewritten next to itself and renamed over
+/// the original; a failed run leaves the linked binary alone. ELF only.
+pub fn translate(cmd: &mut RustcCommand, args: &Args, target: &str) -> Result<()> {
+    let Some(profile) = &args.bolt else {
+        return Ok(());
+    };
+    if args.compile_only {
+        return Ok(());
+    }
+    if target.contains("-windows") || target.contains("-apple-") || target.starts_with("wasm") {
+        console::eprint("[icx-rustc] warning: /Qbolt needs an ELF target, ignored");
+        return Ok(());
+    }
+    let program = match std::env::var_os("ICX_BOLT") {
+        Some(program) => PathBuf::from(program),
+        None => which::which("llvm-bolt")
+            .context("/Qbolt needs llvm-bolt on PATH or ICX_BOLT")?,
+    };
+    let binary = cmd.artifact_of(&["bin", "cdylib", "dylib"]).context("The binary's name cannot be predicted with --emit")?;
+    let mut output = binary.clone().into_os_string();
+    output.push(".bolt");
+    let output = PathBuf::from(output);
+    
+    cmd.args.push("-Clink-arg=-Wl,--emit-relocs".to_string());
+    let args = vec![
+        binary.display().to_string(),
+        "-o".to_string(),
+        output.display().to_string(),
+        format!("-data={}", profile.display()),
+        "-reorder-blocks=ext-tsp".to_string(),
+        "-reorder-functions=hfsort".to_string(),
+        "-split-functions".to_string(),
+        "-split-all-cold".to_string(),
+        "-icf=1".to_string(),
+        "-use-gnu-stack".to_string(),
+    ];
+    cmd.bolt = Some(CStep { program, args, source: binary, output });
+    Ok(())
+}
+
+/// The profile the step reads (part of the cache key)
+pub fn profile(step: &CStep) -> Option<PathBuf> {
+    step.args.iter().find_map(|a| a.strip_prefix("-data=")).map(PathBuf::from)
+}
+
+/// Runs llvm-bolt and replaces the binary with its output
+pub fn run(cmd: &RustcCommand, step: &CStep) -> Result<i32> {
+    let binary = cmd.resolve(&step.source);
+    let output = cmd.resolve(&step.output);
+    let mut command = Command::new(&step.program);
+    command.args(&step.args);
+    if let Some(dir) = &cmd.cwd {
+        command.current_dir(dir);
+    }
+    let result = command.output().with_context(|| format!("Failed to run {}", step.program.display()))?;
+    if !result.status.success() {
+        for line in String::from_utf8_lossy(&result.stderr).lines() {
+            console::eprint(line);
+        }
+        let _ = std::fs::remove_file(&output);
+        return Ok(match result.status.code() {
+            Some(_) => exit_code::COMPILE_ERROR,
+            None => exit_code::KILLED,
+        });
+    }
+    // 同一目录内改名，其他进程看到的要么是旧文件要么是优化后的文件
+    std::fs::rename(&output, &binary)
+        .with_context(|| format!("Failed to replace {} with {}", binary.display(), output.display()))?;
+    Ok(exit_code::SUCCESS)
+}
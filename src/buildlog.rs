@@ -0,0 +1,44 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::borrow::Cow;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+static LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Opens the /Qlog file for this invocation (truncated unless `append`)
+pub fn init(path: &Path, append: bool) -> Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    
+    LOG.set(Mutex::new(file)).ok();
+    Ok(())
+}
+
+/// Appends one line to the log, without ANSI colors or links; no-op without /Qlog
+pub fn write(prefix: &str, text: &str) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    let text = plain(text);
+    
+    if let Ok(mut file) = log.lock() {
+        for line in text.lines() {
+            let _ = writeln!(file, "{}{}", prefix, line);
+        }
+    }
+}
+
+/// `text` without ANSI colors and OSC 8 links
+pub fn plain(text: &str) -> Cow<'_, str> {
+    static ANSI_RE: OnceLock<Regex> = OnceLock::new();
+    let ansi = ANSI_RE.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m|\x1b\]8;;[^\x1b]*\x1b\\").unwrap());
+    ansi.replace_all(text, "")
+}
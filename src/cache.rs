@@ -0,0 +1,375 @@
+use crate::bolt;
+use crate::console;
+use crate::diagnostics::format_size;
+use crate::exit_code;
+use crate::translator::{rustc_query, RustcCommand};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// Cache directories --gc-cache prunes: /Qcache entries, and the
+/// incremental state of --watch and --daemon (one directory per crate)
+const COLLECTED: [&str; 2] = ["objects", "incremental"];
+
+/// Built-in compilation cache
+///
+/// An entry is keyed on the translated command line, the full `rustc -vV`
+/// output and the contents of the crate roots, externs and link inputs.
+/// Each entry also records every source file rustc read (from dep-info) with
+/// its hash, so editing a `mod` file invalidates the entry as well, and the
+/// compiler's stderr, which a hit replays so its warnings are reported again.
+pub struct Cache {
+    dir: PathBuf,
+    rustc_version: String,
+}
+
+impl Cache {
+    pub fn open(executable: &str) -> Result<Self> {
+        let rustc_version = rustc_query(executable, &["-vV"])
+            .with_context(|| format!("Failed to run {} -vV", executable))?;
+        
+        let dir = cache_root().join("objects");
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        
+        Ok(Self { dir, rustc_version })
+    }
+    
+    /// Copies the cached artifacts into place and returns the compiler's
+    /// stderr of the cached compile; `None` on a miss
+    pub fn restore(&self, cmd: &RustcCommand) -> Result<Option<Vec<String>>> {
+        let Some(outputs) = cmd.outputs() else {
+            return Ok(None);
+        };
+        let entry = self.dir.join(self.key(cmd)?);
+        let Ok(manifest) = std::fs::read_to_string(entry.join("manifest")) else {
+            return Ok(None);
+        };
+        // 没有记录 stderr 的旧条目无法重放诊断，按未命中处理
+        let Ok(stderr) = std::fs::read_to_string(entry.join("stderr")) else {
+            return Ok(None);
+        };
+        
+        // 任一源文件内容变化即视为未命中
+        for line in manifest.lines() {
+            let Some((hash, path)) = line.split_once(' ') else {
+                continue;
+            };
+            if hash_file(&cmd.resolve(Path::new(path))).ok().as_deref() != Some(hash) {
+                return Ok(None);
+            }
+        }
+        
+        for (index, out) in outputs.iter().enumerate() {
+            let cached = entry.join(index.to_string());
+            if !cached.exists() {
+                return Ok(None);
+            }
+            let dest = cmd.resolve(out);
+            if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            std::fs::copy(&cached, &dest)
+                .with_context(|| format!("Failed to restore {}", dest.display()))?;
+        }
+        // 命中即算使用过，--gc-cache 按此判断条目的新旧
+        let _ = std::fs::File::options()
+            .append(true)
+            .open(entry.join("manifest"))
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        
+        Ok(Some(stderr.lines().map(str::to_string).collect()))
+    }
+    
+    /// Stores the artifacts and the compiler's stderr of a successful compilation
+    pub fn store(&self, cmd: &RustcCommand, stderr: &[String]) -> Result<()> {
+        let Some(outputs) = cmd.outputs() else {
+            return Ok(());
+        };
+        let entry = self.dir.join(self.key(cmd)?);
+        std::fs::create_dir_all(&entry)
+            .with_context(|| format!("Failed to create {}", entry.display()))?;
+        
+        for (index, out) in outputs.iter().enumerate() {
+            let src = cmd.resolve(out);
+            std::fs::copy(&src, entry.join(index.to_string()))
+                .with_context(|| format!("Failed to cache {}", src.display()))?;
+        }
+        let lines: String = stderr.iter().map(|line| format!("{}\n", line)).collect();
+        std::fs::write(entry.join("stderr"), lines)
+            .with_context(|| format!("Failed to write {}", entry.display()))?;
+        
+        let mut manifest = String::new();
+        for source in source_files(cmd, &entry.join("dep-info.d"))? {
+            manifest.push_str(&format!("{} {}\n", hash_file(&cmd.resolve(&source))?, source.display()));
+        }
+        
+        // 清单最后写入，不完整的条目不会被命中
+        let tmp = entry.join("manifest.tmp");
+        std::fs::write(&tmp, manifest)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, entry.join("manifest"))
+            .with_context(|| format!("Failed to write {}", entry.display()))?;
+        
+        Ok(())
+    }
+    
+    fn key(&self, cmd: &RustcCommand) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(cmd.display());
+        hasher.update(&self.rustc_version);
+        for (key, val) in &cmd.env_vars {
+            hasher.update(format!("{}={}", key, val));
+        }
+        
+        // dep-info 不包含 extern 与链接输入，直接按内容计入
+        let externs = cmd.args.iter()
+            .filter_map(|a| a.strip_prefix("--extern="))
+            .filter_map(|spec| spec.split_once('='))
+            .map(|(_, path)| PathBuf::from(path));
+        for file in cmd.input_files.iter().cloned().chain(externs).chain(cmd.link_inputs.iter().cloned()) {
+            hasher.update(hash_file(&cmd.resolve(&file))?);
+        }
+        // /Qbolt：产物还取决于 profile
+        if let Some(step) = &cmd.bolt {
+            hasher.update(step.display());
+            if let Some(profile) = bolt::profile(step) {
+                hasher.update(hash_file(&cmd.resolve(&profile))?);
+            }
+        }
+        for step in &cmd.wasm {
+            hasher.update(step.display());
+        }
+        
+        Ok(hex(&hasher.finalize()))
+    }
+}
+
+/// Locates an external cache wrapper and checks that it can drive rustc
+///
+/// The wrapper is launched as `<wrapper> <rustc> -vV`; anything that does not
+/// answer with the rustc version (e.g. a ccache build without Rust support)
+/// is rejected instead of silently breaking every compilation.
+pub fn find_wrapper(name: &str, executable: &str) -> Result<PathBuf> {
+    let path = which::which(name)
+        .with_context(|| format!("Cache wrapper '{}' not found", name))?;
+    
+    let output = Command::new(&path)
+        .arg(executable)
+        .arg("-vV")
+        .output()
+        .with_context(|| format!("Failed to run {}", path.display()))?;
+    anyhow::ensure!(
+        output.status.success() && output.stdout.starts_with(b"rustc "),
+        "Cache wrapper '{}' does not support rustc",
+        path.display()
+    );
+    
+    Ok(path)
+}
+
+/// `ICX_CACHE_DIR`, or the per-user cache directory
+pub fn cache_root() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ICX_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    
+    let base = if cfg!(windows) {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+    };
+    base.unwrap_or_else(std::env::temp_dir).join("icx-rustc")
+}
+
+/// Writes a generated input (manifest, resource script) under the cache
+/// directory, in a directory named by its content, and returns its path
+pub fn generated_file(kind: &str, text: &str, name: &str) -> Result<PathBuf> {
+    let hash = hex(&Sha256::digest(text.as_bytes()));
+    let dir = cache_root().join(kind).join(&hash[..16]);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(name);
+    if !path.is_file() {
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(path)
+}
+
+/// Source files read by the compilation, via a dep-info only rustc run
+pub fn source_files(cmd: &RustcCommand, dep_file: &Path) -> Result<Vec<PathBuf>> {
+    if cmd.input_files.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let mut command = Command::new(&cmd.executable);
+    command.args(cmd.args.iter().filter(|a| !a.starts_with("--emit=") && !a.starts_with("--out-dir=")));
+    command.args(&cmd.input_files);
+    command.arg("--emit=dep-info").arg("-o").arg(dep_file);
+    command.envs(cmd.env_vars.iter().map(|(k, v)| (k, v)));
+    if let Some(dir) = &cmd.cwd {
+        command.current_dir(dir);
+    }
+    
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to spawn {}", cmd.executable))?;
+    anyhow::ensure!(status.success(), "Failed to collect dep-info for the cache");
+    
+    let deps = std::fs::read_to_string(dep_file)
+        .with_context(|| format!("Failed to read {}", dep_file.display()))?;
+    let _ = std::fs::remove_file(dep_file);
+    
+    // 第一条规则："<输出>: <依赖> <依赖> ..."，空格以反斜杠转义
+    let rule = deps.lines().next().unwrap_or_default();
+    let list = rule.split_once(": ").map(|(_, list)| list).unwrap_or_default();
+    let mut files = Vec::new();
+    let mut current = String::new();
+    let mut chars = list.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => current.push(chars.next().unwrap_or(' ')),
+            ' ' => {
+                if !current.is_empty() {
+                    files.push(PathBuf::from(std::mem::take(&mut current)));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        files.push(PathBuf::from(current));
+    }
+    
+    Ok(files)
+}
+
+/// --gc-cache[=<days>]: removes cache entries unused for that many days
+///
+/// An entry counts as used when any file in it was last written; restoring
+/// a /Qcache hit touches its manifest. With --gc-cache-size the oldest of
+/// the remaining entries go as well, until the rest fits.
+pub fn gc(days: u64, max_size: Option<&str>) -> Result<i32> {
+    let limit = max_size.map(parse_size).transpose()?;
+    let root = cache_root();
+    let mut entries = Vec::new();
+    for kind in COLLECTED {
+        let Ok(dir) = std::fs::read_dir(root.join(kind)) else {
+            continue;
+        };
+        for entry in dir.flatten() {
+            let path = entry.path();
+            let (size, used) = usage(&path);
+            entries.push((path, size, used));
+        }
+    }
+    // 最近使用的在前：超出上限时从末尾删起
+    entries.sort_by_key(|(_, _, used)| std::cmp::Reverse(*used));
+    
+    let cutoff = SystemTime::now() - Duration::from_secs(days.saturating_mul(86_400));
+    let (mut kept, mut kept_size, mut removed, mut reclaimed) = (0, 0, 0, 0);
+    for (path, size, used) in entries {
+        if used >= cutoff && limit.is_none_or(|limit| kept_size + size <= limit) {
+            kept += 1;
+            kept_size += size;
+            continue;
+        }
+        let result = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        match result {
+            Ok(()) => {
+                removed += 1;
+                reclaimed += size;
+            }
+            // 正在使用的条目（Windows 上被打开的文件）留到下次
+            Err(e) => {
+                console::eprint(&format!("[icx-rustc] warning: Failed to remove {}: {}", path.display(), e));
+                kept += 1;
+                kept_size += size;
+            }
+        }
+    }
+    
+    println!(
+        "[icx-rustc] cache {}: {} removed ({} reclaimed), {} kept ({})",
+        root.display(), removed, format_size(reclaimed), kept, format_size(kept_size)
+    );
+    Ok(exit_code::SUCCESS)
+}
+
+/// Total size of the files under a path and the time the newest was written
+fn usage(path: &Path) -> (u64, SystemTime) {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return (0, SystemTime::UNIX_EPOCH);
+    };
+    let mut size = if meta.is_file() { meta.len() } else { 0 };
+    let mut used = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if meta.is_dir() {
+        for entry in std::fs::read_dir(path).into_iter().flatten().flatten() {
+            let (s, u) = usage(&entry.path());
+            size += s;
+            used = used.max(u);
+        }
+    }
+    (size, used)
+}
+
+/// `500M`, `10G`, `1024K` or a number of bytes
+fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let digits = text.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let scale: u64 = match text[digits.len()..].to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => anyhow::bail!("Invalid cache size '{}' (expected e.g. 500M or 10G)", text),
+    };
+    let number: f64 = digits.trim().parse()
+        .with_context(|| format!("Invalid cache size '{}' (expected e.g. 500M or 10G)", text))?;
+    // 负数转换为 u64 时会变成 0，清空整个缓存
+    if number.is_sign_negative() {
+        anyhow::bail!("Invalid cache size '{}' (expected e.g. 500M or 10G)", text);
+    }
+    Ok((number * scale as f64) as u64)
+}
+
+pub fn hash_file(path: &Path) -> Result<String> {
+    let data = std::fs::read(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hex(&Sha256::digest(data)))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_size;
+    
+    #[test]
+    fn sizes_with_suffixes() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("1024K").unwrap(), 1 << 20);
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size(" 10g ").unwrap(), 10 << 30);
+        assert_eq!(parse_size("2T").unwrap(), 2 << 40);
+        assert_eq!(parse_size("1.5G").unwrap(), 3 << 29);
+        assert_eq!(parse_size("64KB").unwrap(), 64 << 10);
+        assert_eq!(parse_size("64KiB").unwrap(), 64 << 10);
+        assert_eq!(parse_size("64 MiB").unwrap(), 64 << 20);
+    }
+    
+    #[test]
+    fn invalid_sizes() {
+        for text in ["", "G", "10X", "10GG", "ten", "1,5G", "-1G", "inf", "NaN"] {
+            assert!(parse_size(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cli;
+use crate::config;
+use crate::console;
+use crate::exit_code;
+use crate::translator;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+use std::process::Command;
+
+/// The cargo command line if we were started as `icx-cargo` or `icx-rustc cargo`
+pub fn invocation(raw: &[String]) -> Option<&[String]> {
+    let program = raw.first().map(Path::new)?;
+    let stem = program.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    if stem == "icx-cargo" {
+        return Some(&raw[1..]);
+    }
+    (raw.get(1).map(String::as_str) == Some("cargo")).then(|| &raw[2..])
+}
+
+/// `icx-cargo build /O3 /arch:AVX2 --release`: runs cargo with the Intel-style options applied
+///
+/// Options in Intel spelling (`/O3`, `/W4`, `-xHost`, ...) are
+/// taken by icx-cargo, everything else and all arguments after `--` are
+/// cargo's. Codegen options go into the rustflags, so cargo rebuilds when
+/// they change; icx-rustc wraps the workspace's compiles for its
+/// diagnostics, getting the rest of the options through `ICX_FLAGS`.
+pub fn run(words: &[String]) -> Result<i32> {
+    let Some(subcommand) = words.first().filter(|w| !w.starts_with('-') && !w.starts_with('/')) else {
+        print_usage();
+        return Ok(exit_code::USAGE_ERROR);
+    };
+    
+    let mut icx = Vec::new();
+    let mut cargo = Vec::new();
+    let mut rest = words[1..].iter();
+    for word in rest.by_ref() {
+        if word == "--" {
+            cargo.push(word.clone());
+            break;
+        }
+        if is_icx_option(word) {
+            icx.push(word.clone());
+        } else {
+            cargo.push(word.clone());
+        }
+    }
+    cargo.extend(rest.cloned());
+    
+    let args = cli::parse_layered(&config::load()?, icx.clone())?;
+    console::init_color(args.color);
+    
+    let mut rustflags = translator::cargo_rustflags(&args)?;
+    let lto = rustflags.iter().any(|f| f.starts_with("-Clto"));
+    rustflags.retain(|f| !f.starts_with("-Clto"));
+    
+    let program = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(&program);
+    let mut shown = vec![program.clone()];
+    
+    // 与 cargo 的配置合并：已设置 RUSTFLAGS 时 build.rustflags 不再生效
+    let encoded = std::env::var("CARGO_ENCODED_RUSTFLAGS").ok().filter(|v| !v.is_empty());
+    let plain = std::env::var("RUSTFLAGS").ok().filter(|v| !v.trim().is_empty());
+    if !rustflags.is_empty() {
+        if encoded.is_some() || plain.is_some() {
+            let mut flags: Vec<String> = match (&encoded, &plain) {
+                (Some(encoded), _) => encoded.split('\x1f').map(str::to_string).collect(),
+                (None, Some(plain)) => plain.split_whitespace().map(str::to_string).collect(),
+                (None, None) => Vec::new(),
+            };
+            flags.extend(rustflags.iter().cloned());
+            command.env("CARGO_ENCODED_RUSTFLAGS", flags.join("\x1f"));
+            shown.insert(0, format!("CARGO_ENCODED_RUSTFLAGS='{}'", flags.join(" ")));
+        } else {
+            let list = serde_json::to_string(&rustflags)?;
+            command.arg("--config").arg(format!("build.rustflags={}", list));
+            shown.push(format!("--config 'build.rustflags={}'", list));
+        }
+    }
+    // /O3 的 LTO 交给 profile（只作用于最终产物）
+    if lto {
+        let setting = format!("profile.{}.lto=\"fat\"", profile(&cargo));
+        shown.push(format!("--config '{}'", setting));
+        command.arg("--config").arg(setting);
+    }
+    
+    let exe = std::env::current_exe().context("Failed to locate icx-rustc")?;
+    command.env("RUSTC_WORKSPACE_WRAPPER", &exe);
+    if !icx.is_empty() {
+        let flags = shlex::try_join(icx.iter().map(String::as_str)).context("Options contain a nul byte")?;
+        command.env("ICX_FLAGS", &flags);
+        shown.insert(0, format!("ICX_FLAGS='{}'", flags));
+    }
+    command.arg(subcommand).args(&cargo);
+    shown.push(subcommand.clone());
+    shown.extend(cargo.iter().cloned());
+    
+    if args.verbose {
+        console::eprint(&format!("{} {}", "[icx-rustc]".bright_blue().bold(), shown.join(" ").dimmed()));
+    }
+    let status = command.status().with_context(|| format!("Failed to run {}", program))?;
+    Ok(status.code().unwrap_or(exit_code::KILLED))
+}
+
+/// Options in Intel / MSVC spelling; cargo has none of these
+///
+/// An existing absolute path (`--manifest-path /src/Cargo.toml`) is not one.
+fn is_icx_option(word: &str) -> bool {
+    cli::is_slash_option(word)
+        || word.starts_with("-Q")
+        || word.starts_with("-x")
+        || matches!(word, "-O0" | "-O1" | "-O2" | "-O3" | "-Ox")
+}
+
+/// The cargo profile being built (`--release`, `--profile <name>`)
+fn profile(cargo: &[String]) -> String {
+    let mut words = cargo.iter().take_while(|w| *w != "--");
+    let mut profile = "dev".to_string();
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            "--release" | "-r" => profile = "release".to_string(),
+            "--profile" => profile = words.next().cloned().unwrap_or(profile),
+            other => {
+                if let Some(name) = other.strip_prefix("--profile=") {
+                    profile = name.to_string();
+                }
+            }
+        }
+    }
+    profile
+}
+
+fn print_usage() {
+    println!("Usage: icx-cargo <cargo command> [icx options] [cargo options] [-- args]");
+    println!();
+    println!("  icx-cargo build /O3 /arch:AVX2 --release");
+    println!("  icx-cargo test /W4 /Qdiag-error-limit:5");
+    println!();
+    println!("Options in Intel spelling (/O3, /arch:..., -xHost, -Q...) apply to the build,");
+    println!("all others go to cargo. Also available as `icx-rustc cargo ...`.");
+}
@@ -0,0 +1,388 @@
+use crate::cli::Args;
+use crate::cmdline;
+use crate::console;
+use crate::exit_code;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A step run before rustc: a C or C++ source compiled to an object, or
+/// bindings generated for a header (/Qbindgen)
+#[derive(Debug, Clone)]
+pub struct CStep {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// Source or header, watched in --watch mode
+    pub source: PathBuf,
+    pub output: PathBuf,
+}
+
+impl CStep {
+    pub fn display(&self) -> String {
+        let mut parts = vec![self.program.display().to_string()];
+        parts.extend(self.args.iter().cloned());
+        cmdline::join(parts.iter().map(String::as_str))
+    }
+}
+
+/// C and C++ inputs, compiled with the system C compiler
+pub fn is_source(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("c" | "cc" | "cpp" | "cxx" | "c++"))
+}
+
+/// Windows resource scripts (icons, version information, manifests)
+pub fn is_resource(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("rc"))
+}
+
+fn is_cpp(path: &Path) -> bool {
+    is_source(path) && path.extension().is_some_and(|e| e != "c")
+}
+
+/// Plans the C compiles of `sources` with the settings of the Rust compile
+///
+/// Called once the rustc arguments are final: optimization, debug info,
+/// CPU, CRT linkage (`+crt-static` selects /MT over /MD) and the panic
+/// strategy (`unwind` lets Rust panics unwind through C frames) follow the
+/// Rust side, /D, /U, /I and the warning options apply to both languages.
+/// Objects go next to the other artifacts (/Fo<dir>/, /Qoutdir or the
+/// current directory); without /c they are linked into the program, with
+/// the C++ runtime for C++ sources. Sources of the same name get
+/// `<name>-1.o` and so on; an object that would replace the Rust output is
+/// an error.
+pub fn plan(cmd: &mut RustcCommand, args: &Args, sources: &[PathBuf], target: &str) -> Result<()> {
+    let msvc_target = target.contains("-msvc");
+    let mut cpp_linked = false;
+    // rustc 的产物不能被目标文件覆盖
+    let rust_outputs = if cmd.input_files.is_empty() { Vec::new() } else { cmd.outputs().unwrap_or_default() };
+    let mut objects: Vec<PathBuf> = Vec::new();
+    
+    for source in sources {
+        let cpp = is_cpp(source);
+        let (program, mut words) = compiler(cpp, msvc_target)
+            .with_context(|| format!("No C{} compiler found for {} (set {})", if cpp { "++" } else { "" }, source.display(), if cpp { "CXX" } else { "CC" }))?;
+        let msvc = is_msvc_style(&program);
+        // 单个 C 源文件 /c 时 -o 指定的就是它的目标文件
+        let object = match (&cmd.output, args.compile_only && sources.len() == 1 && cmd.input_files.is_empty()) {
+            (Some(output), true) => output.clone(),
+            _ => unique(object_path(args, source, msvc), &objects),
+        };
+        if let Some(input) = rust_outputs.contains(&object).then(|| cmd.input_files.first()).flatten() {
+            anyhow::bail!(
+                "The object of {} would overwrite {}, the output of {}; use /Fo<dir>/ or rename one of them",
+                source.display(), object.display(), input.display()
+            );
+        }
+        objects.push(object.clone());
+        words.extend(flags(cmd, args, &program, cpp, target));
+        if msvc {
+            words.extend(["/c".to_string(), source.display().to_string(), format!("/Fo{}", object.display())]);
+        } else {
+            words.extend(["-c".to_string(), source.display().to_string(), "-o".to_string(), object.display().to_string()]);
+        }
+        
+        cmd.c_steps.push(CStep { program, args: words, source: source.clone(), output: object.clone() });
+        if !args.compile_only {
+            cmd.link_inputs.push(object);
+            cpp_linked |= cpp;
+        }
+    }
+    
+    // MSVC 的 C++ 运行时由目标文件中的默认库指令引入
+    if cpp_linked && !msvc_target {
+        let runtime = if target.contains("-apple-") || target.contains("-freebsd") { "-lc++" } else { "-lstdc++" };
+        cmd.link_libs.push(runtime.to_string());
+    }
+    Ok(())
+}
+
+/// Plans the resource compiles of `.rc` inputs, like cl does
+///
+/// MSVC targets use `RC`, rc.exe or llvm-rc and link the `.res`; MinGW
+/// targets use `WINDRES`, windres or llvm-windres, whose COFF object
+/// `<name>.res.o` the GNU linker accepts. /D and /I apply; other targets
+/// have no resources and the scripts are skipped with a warning.
+pub fn resources(cmd: &mut RustcCommand, args: &Args, scripts: &[PathBuf], target: &str) -> Result<()> {
+    if !target.contains("-windows") {
+        for script in scripts {
+            console::eprint(&format!("[icx-rustc] warning: {}: resource scripts need a Windows target, ignored", script.display()));
+        }
+        return Ok(());
+    }
+    
+    let msvc = target.contains("-msvc");
+    let (variable, candidates): (&str, &[&str]) = if msvc {
+        ("RC", &["rc", "llvm-rc"])
+    } else {
+        ("WINDRES", &["windres", "x86_64-w64-mingw32-windres", "llvm-windres"])
+    };
+    let (program, words) = match std::env::var(variable).ok().and_then(|v| shlex::split(&v)).filter(|w| !w.is_empty()) {
+        Some(words) => (PathBuf::from(&words[0]), words[1..].to_vec()),
+        None => candidates.iter()
+            .find_map(|name| which::which(name).ok())
+            .map(|path| (path, Vec::new()))
+            .with_context(|| format!("No resource compiler found for {} (set {})", scripts[0].display(), variable))?,
+    };
+    
+    for script in scripts {
+        let stem = script.file_stem().unwrap_or_default().to_string_lossy();
+        let output = object_path(args, script, false).with_file_name(if msvc {
+            format!("{}.res", stem)
+        } else {
+            format!("{}.res.o", stem)
+        });
+        let mut words = words.clone();
+        if msvc {
+            words.push("/nologo".to_string());
+            words.extend(args.defines.iter().map(|d| format!("/D{}", d)));
+            words.extend(args.includes.iter().map(|i| format!("/I{}", i.display())));
+            words.push(format!("/fo{}", output.display()));
+            words.push(script.display().to_string());
+        } else {
+            words.extend(["-O".to_string(), "coff".to_string()]);
+            words.extend(args.defines.iter().map(|d| format!("-D{}", d)));
+            words.extend(args.includes.iter().map(|i| format!("-I{}", i.display())));
+            words.extend([script.display().to_string(), "-o".to_string(), output.display().to_string()]);
+        }
+        
+        cmd.c_steps.push(CStep { program: program.clone(), args: words, source: script.clone(), output: output.clone() });
+        if !args.compile_only {
+            cmd.link_inputs.push(output);
+        }
+    }
+    Ok(())
+}
+
+/// /Qbindgen:<header>: generates `<stem>.rs` bindings in `OUT_DIR` before the compile
+///
+/// bindgen gets the same /I, /D and /U as the C compiles (and the target
+/// when cross-compiling); the source includes the bindings as a build
+/// script's would, `include!(concat!(env!("OUT_DIR"), "/<stem>.rs"))`.
+/// `OUT_DIR` is `bindgen/` under /Qoutdir or the current directory.
+pub fn bindgen(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let program = match std::env::var_os("ICX_BINDGEN") {
+        Some(program) => PathBuf::from(program),
+        None => which::which("bindgen")
+            .context("/Qbindgen needs bindgen on PATH (cargo install bindgen-cli) or ICX_BINDGEN")?,
+    };
+    let dir = match &args.out_dir {
+        Some(out_dir) => out_dir.join("bindgen"),
+        None => PathBuf::from("bindgen"),
+    };
+    // env! 需要绝对路径，include! 的相对路径按源文件所在目录解析
+    let absolute = std::path::absolute(cmd.resolve(&dir))
+        .with_context(|| format!("Failed to resolve {}", dir.display()))?;
+    cmd.env_vars.push(("OUT_DIR".to_string(), absolute.display().to_string()));
+    
+    for header in &args.bindgen {
+        let stem = header.file_stem().with_context(|| format!("Invalid header name '{}'", header.display()))?;
+        let output = dir.join(format!("{}.rs", stem.to_string_lossy()));
+        let mut words = vec![header.display().to_string(), "-o".to_string(), output.display().to_string(), "--".to_string()];
+        words.extend(args.includes.iter().map(|i| format!("-I{}", i.display())));
+        words.extend(args.defines.iter().map(|d| format!("-D{}", d)));
+        words.extend(args.undefines.iter().map(|u| format!("-U{}", u)));
+        if let Some(target) = &cmd.target {
+            words.push(format!("--target={}", target));
+        }
+        cmd.c_steps.push(CStep { program: program.clone(), args: words, source: header.clone(), output });
+    }
+    Ok(())
+}
+
+/// The archiver call adding the object inputs to the staticlib rustc writes
+///
+/// `AR` when set, else lib.exe (or llvm-lib) for MSVC targets and ar (or
+/// llvm-ar) elsewhere, so C users get one `.lib` / `.a` from one call.
+pub fn archive(cmd: &RustcCommand, target: &str) -> Result<CStep> {
+    let library = cmd.artifact_of(&["staticlib"]).context("The static library's name cannot be predicted with --emit")?;
+    let candidates: &[&str] = if target.contains("-msvc") { &["lib", "llvm-lib"] } else { &["ar", "llvm-ar"] };
+    let (program, mut words) = match std::env::var("AR").ok().and_then(|v| shlex::split(&v)).filter(|w| !w.is_empty()) {
+        Some(words) => (PathBuf::from(&words[0]), words[1..].to_vec()),
+        None => candidates.iter()
+            .find_map(|name| which::which(name).ok())
+            .map(|path| (path, Vec::new()))
+            .with_context(|| format!("No archiver found to add the objects to {} (set AR)", library.display()))?,
+    };
+    
+    let stem = program.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_ascii_lowercase();
+    if stem == "lib" || stem == "llvm-lib" {
+        words.extend(["/nologo".to_string(), format!("/OUT:{}", library.display()), library.display().to_string()]);
+    } else {
+        words.extend(["rs".to_string(), library.display().to_string()]);
+    }
+    words.extend(cmd.link_inputs.iter().map(|o| o.display().to_string()));
+    Ok(CStep { program, args: words, source: library.clone(), output: library })
+}
+
+/// Runs the planned steps; the tools print their own diagnostics
+pub fn run(cmd: &RustcCommand) -> Result<i32> {
+    for step in &cmd.c_steps {
+        let code = run_step(cmd, step)?;
+        if code != exit_code::SUCCESS {
+            return Ok(code);
+        }
+    }
+    Ok(exit_code::SUCCESS)
+}
+
+/// Runs the archiver step
+///
+/// GNU ar loads the system's LLVM plugin, which complains about every
+/// member with Rust's (newer) LLVM bitcode; those lines are dropped.
+pub fn run_archive(cmd: &RustcCommand, step: &CStep) -> Result<i32> {
+    let mut command = Command::new(&step.program);
+    command.args(&step.args);
+    if let Some(dir) = &cmd.cwd {
+        command.current_dir(dir);
+    }
+    let output = command.output().with_context(|| format!("Failed to run {}", step.program.display()))?;
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if !line.starts_with("bfd plugin:") {
+            console::eprint(line);
+        }
+    }
+    Ok(match output.status.code() {
+        Some(0) => exit_code::SUCCESS,
+        Some(_) => exit_code::COMPILE_ERROR,
+        None => exit_code::KILLED,
+    })
+}
+
+/// Runs one step in the compiler's working directory
+pub fn run_step(cmd: &RustcCommand, step: &CStep) -> Result<i32> {
+    let output = cmd.resolve(&step.output);
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut command = Command::new(&step.program);
+    command.args(&step.args);
+    if let Some(dir) = &cmd.cwd {
+        command.current_dir(dir);
+    }
+    let status = command.status().with_context(|| format!("Failed to run {}", step.program.display()))?;
+    Ok(match status.code() {
+        Some(0) => exit_code::SUCCESS,
+        Some(_) => exit_code::COMPILE_ERROR,
+        None => exit_code::KILLED,
+    })
+}
+
+/// `CC`/`CXX` (may include a launcher such as `ccache gcc`), else the first compiler on PATH
+fn compiler(cpp: bool, msvc_target: bool) -> Option<(PathBuf, Vec<String>)> {
+    let variable = if cpp { "CXX" } else { "CC" };
+    if let Some(words) = std::env::var(variable).ok().and_then(|v| shlex::split(&v)).filter(|w| !w.is_empty()) {
+        return Some((PathBuf::from(&words[0]), words[1..].to_vec()));
+    }
+    
+    let candidates: &[&str] = match (msvc_target, cpp) {
+        (true, _) => &["cl", "clang-cl"],
+        (false, true) => &["c++", "clang++", "g++"],
+        (false, false) => &["cc", "clang", "gcc"],
+    };
+    candidates.iter().find_map(|name| which::which(name).ok()).map(|path| (path, Vec::new()))
+}
+
+fn is_msvc_style(program: &Path) -> bool {
+    let stem = program.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_ascii_lowercase();
+    stem == "cl" || stem == "clang-cl"
+}
+
+/// Where the object of a source goes: /Fo<dir>/, /Qoutdir or the current directory
+pub fn object_path(args: &Args, source: &Path, msvc: bool) -> PathBuf {
+    let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+    let name = format!("{}.{}", stem, if msvc { "obj" } else { "o" });
+    match (&args.msvc_obj, &args.out_dir) {
+        (Some(fo), _) if fo.to_string_lossy().ends_with(['/', '\\']) || fo.is_dir() => fo.join(name),
+        (_, Some(dir)) => dir.join(name),
+        _ => PathBuf::from(name),
+    }
+}
+
+/// `object`, or `<stem>-<n>.<ext>` with the first `n` not yet in `taken`
+/// (sources of the same name in different directories)
+fn unique(object: PathBuf, taken: &[PathBuf]) -> PathBuf {
+    if !taken.contains(&object) {
+        return object;
+    }
+    let stem = object.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = object.extension().unwrap_or_default().to_string_lossy().into_owned();
+    (1..)
+        .map(|n| object.with_file_name(format!("{}-{}.{}", stem, n, ext)))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or(object)
+}
+
+/// The value of the last `prefix` argument on the rustc command line
+fn last_value<'a>(cmd: &'a RustcCommand, prefix: &str) -> Option<&'a str> {
+    cmd.args.iter().rev().find_map(|a| a.strip_prefix(prefix))
+}
+
+fn flags(cmd: &RustcCommand, args: &Args, program: &Path, cpp: bool, target: &str) -> Vec<String> {
+    let msvc = is_msvc_style(program);
+    let opt = last_value(cmd, "-Copt-level=").unwrap_or("0");
+    let debug = cmd.args.iter().any(|a| a == "-g")
+        || last_value(cmd, "-Cdebuginfo=").is_some_and(|level| level != "0" && level != "none");
+    let unwind = last_value(cmd, "-Cpanic=") == Some("unwind");
+    let crt_static = cmd.args.iter()
+        .filter_map(|a| a.strip_prefix("-Ctarget-feature="))
+        .any(|features| features.split(',').any(|f| f == "+crt-static"));
+    let mut flags = Vec::new();
+    
+    if msvc {
+        flags.push("/nologo".to_string());
+        flags.push(match opt { "0" => "/Od", "1" | "s" | "z" => "/O1", _ => "/O2" }.to_string());
+        if debug {
+            flags.push("/Z7".to_string());
+        }
+        // 与 Rust 标准库使用同一 CRT
+        flags.push(if crt_static { "/MT" } else { "/MD" }.to_string());
+        if cpp {
+            // extern "C" 函数也可能被 Rust 的 panic 展开穿过
+            flags.push(if unwind { "/EHs" } else { "/EHsc" }.to_string());
+        }
+        flags.extend(args.defines.iter().map(|d| format!("/D{}", d)));
+        flags.extend(args.undefines.iter().map(|u| format!("/U{}", u)));
+        flags.extend(args.includes.iter().map(|i| format!("/I{}", i.display())));
+        if let Some(level) = &args.warn_level {
+            flags.push(format!("/W{}", if level == "all" { "4" } else { level }));
+        }
+        if args.wx {
+            flags.push("/WX".to_string());
+        }
+        return flags;
+    }
+    
+    flags.push(format!("-O{}", opt));
+    if debug {
+        flags.push("-g".to_string());
+    }
+    // rustc 默认生成位置无关的可执行文件
+    if !target.contains("-windows") {
+        flags.push("-fPIC".to_string());
+    }
+    if unwind && !cpp {
+        flags.push("-fexceptions".to_string());
+    }
+    if let Some(cpu) = last_value(cmd, "-Ctarget-cpu=").filter(|_| target.starts_with("x86")) {
+        flags.push(format!("-march={}", cpu));
+    }
+    // 交叉编译时 clang 需要知道目标
+    let clang = program.file_name().is_some_and(|n| n.to_string_lossy().contains("clang"));
+    if cmd.target.is_some() && clang {
+        flags.push(format!("--target={}", target));
+    }
+    flags.extend(args.defines.iter().map(|d| format!("-D{}", d)));
+    flags.extend(args.undefines.iter().map(|u| format!("-U{}", u)));
+    flags.extend(args.includes.iter().map(|i| format!("-I{}", i.display())));
+    match args.warn_level.as_deref() {
+        Some("0") => flags.push("-w".to_string()),
+        Some("1" | "2") | None => {}
+        Some("3") => flags.push("-Wall".to_string()),
+        Some(_) => flags.extend(["-Wall".to_string(), "-Wextra".to_string()]),
+    }
+    if args.wx {
+        flags.push("-Werror".to_string());
+    }
+    flags
+}
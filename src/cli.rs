@@ -1,136 +1,1103 @@
-﻿use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
-
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum OptLevel {
-    O0, O1, O2, O3, Ox,
-}
-
-#[derive(Debug, Clone, Copy, ValueEnum)]
-pub enum WarnLevel {
-    W0, W1, W2, W3, Wall, WX,
-}
-
-#[derive(Debug, Parser)]
-#[command(name = "icx-rustc")]
-#[command(about = "Intel-style Rust compiler wrapper")]
-#[command(trailing_var_arg = true)]
-pub struct Args {
-    /// Input files
-    pub files: Vec<PathBuf>,
-    
-    /// Optimization level
-    #[arg(long = "O", value_enum)]
-    pub opt_level: Option<OptLevel>,
-    
-    /// MSVC-style optimization
-    #[arg(short = 'O', value_name = "level")]
-    pub msvc_opt: Option<String>,
-    
-    /// Compile only
-    #[arg(short = 'c', long = "c")]
-    pub compile_only: bool,
-    
-    /// Output file
-    #[arg(short = 'o', long = "o")]
-    pub output: Option<PathBuf>,
-    
-    /// MSVC-style object output
-    #[arg(long = "Fo")]
-    pub msvc_obj: Option<PathBuf>,
-    
-    /// MSVC-style executable output
-    #[arg(long = "Fe")]
-    pub msvc_exe: Option<PathBuf>,
-    
-    /// Target architecture
-    #[arg(long = "arch")]
-    pub arch: Option<String>,
-    
-    /// Optimize for host
-    #[arg(long = "xHost")]
-    pub xhost: bool,
-    
-    /// Define macro
-    #[arg(short = 'D', long = "D")]
-    pub defines: Vec<String>,
-    
-    /// Undefine macro
-    #[arg(short = 'U', long = "U")]
-    pub undefines: Vec<String>,
-    
-    /// Include directory
-    #[arg(short = 'I', long = "I")]
-    pub includes: Vec<PathBuf>,
-    
-    /// Warning level
-    #[arg(short = 'W', long = "W")]
-    pub warn_level: Option<String>,
-    
-    /// Warnings as errors
-    #[arg(long = "WX")]
-    pub wx: bool,
-    
-    /// Linker arguments (MSVC style)
-    #[arg(long = "link")]
-    pub link_args: Vec<String>,
-    
-    /// Verbose
-    #[arg(short = 'v', long = "v")]
-    pub verbose: bool,
-    
-    /// Dry run
-    #[arg(long = "###")]
-    pub dry_run: bool,
-    
-    /// Show version
-    #[arg(long = "version")]
-    pub version: bool,
-    
-    /// Show help
-    #[arg(long = "help")]
-    pub help: bool,
-    
-    /// Rust edition
-    #[arg(long = "edition")]
-    pub edition: Option<String>,
-    
-    /// Crate type
-    #[arg(long = "crate-type")]
-    pub crate_type: Option<String>,
-    
-    /// Target triple
-    #[arg(long = "target")]
-    pub target: Option<String>,
-    
-    /// Release mode
-    #[arg(long = "release")]
-    pub release: bool,
-    
-    /// Optimize diagnostics output
-    #[arg(long = "optimize-diagnostics", default_value = "true")]
-    pub optimize_diagnostics: bool,
-    
-    /// Raw rustc flags (pass-through)
-    #[arg(last = true)]
-    pub raw_args: Vec<String>,
-}
-
-pub fn parse_args() -> Args {
-    let args: Vec<String> = std::env::args()
-        .map(|arg| {
-            if arg.starts_with('/') && !arg.starts_with("//") {
-                let without_slash = &arg[1..];
-                if without_slash.contains(':') {
-                    format!("--{}", without_slash.replace(':', "="))
-                } else {
-                    format!("-{}", without_slash)
-                }
-            } else {
-                arg
-            }
-        })
-        .collect();
-    
-    Args::parse_from(args)
-}
+use crate::console;
+use crate::exit_code;
+use crate::suggest::did_you_mean;
+use anyhow::{Context, Result};
+use clap::builder::{OsStringValueParser, TypedValueParser};
+use clap::{CommandFactory, Parser, ValueEnum};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OptLevel {
+    O0, O1, O2, O3, Ox,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum WarnLevel {
+    W0, W1, W2, W3, Wall, WX,
+}
+
+/// End-of-build summary (--summary-format)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SummaryFormat {
+    /// "finished N error(s), M warning(s)" lines
+    #[default]
+    Text,
+    /// Additionally one JSON object for CI, on stdout or in --summary-file
+    Json,
+}
+
+/// Console rendering of diagnostics (/Qdiag-format)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DiagFormat {
+    /// Colored header, location and source snippet
+    #[default]
+    Icx,
+    /// One `path(line,col): error RS0308: message` line per diagnostic
+    Msvc,
+}
+
+/// What /Qdiag-baseline does with the baseline file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BaselineMode {
+    /// Store the warnings of this build as the accepted baseline
+    Record,
+    /// Show only warnings not in the baseline and fail if there are any
+    Check,
+}
+
+/// Shells --completions writes a script for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// Where options come from, from the lowest precedence to the highest
+///
+/// Built-in defaults come below all of them. Later sources override
+/// single-valued options and add to lists such as /D.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// `options` in icx-rustc.toml
+    ConfigFile,
+    /// `ICX_OPTIONS`
+    Environment,
+    /// The arguments (ICX_FLAGS in cargo wrapper mode)
+    CommandLine,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::ConfigFile => "config file",
+            Source::Environment => "ICX_OPTIONS",
+            Source::CommandLine => "command line",
+        })
+    }
+}
+
+/// The options one source contributed, normalized like the command line
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub source: Source,
+    pub words: Vec<String>,
+}
+
+/// Which symbols /Qsymbols lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SymbolsMode {
+    /// Exported and undefined symbols
+    All,
+    /// Symbols the output defines for others
+    Exported,
+    /// Symbols that must come from other objects or libraries
+    Undefined,
+}
+
+/// What /Qcov-report produces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CoverageFormat {
+    /// Per-file line, function and region coverage on the console
+    Summary,
+    /// Annotated sources in coverage/
+    Html,
+    /// An lcov trace in lcov.info
+    Lcov,
+}
+
+/// When to color the output (/Qcolor)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// On a terminal, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(name = "icx-rustc")]
+#[command(about = "Intel-style Rust compiler wrapper")]
+#[command(disable_help_flag = true)]
+#[command(args_override_self = true)]
+pub struct Args {
+    /// Input files
+    pub files: Vec<PathBuf>,
+    
+    /// Compile and run a snippet, printing its value
+    #[arg(long = "Qeval", value_name = "CODE", help_heading = "Code Generation")]
+    pub eval: Option<String>,
+    
+    /// Crate name of a source read from stdin ('-')
+    #[arg(long = "Qstdin-name", value_name = "NAME", help_heading = "Code Generation")]
+    pub stdin_name: Option<String>,
+    
+    /// Optimization level (O0, O1, O2, O3, Ox)
+    #[arg(long = "O", value_enum, value_name = "LEVEL", help_heading = "Optimization Options")]
+    pub opt_level: Option<OptLevel>,
+    
+    /// Optimization level, MSVC style: /O0 to /O3, /Ox (default /O2)
+    #[arg(short = 'O', value_name = "LEVEL", help_heading = "Optimization Options")]
+    pub msvc_opt: Option<String>,
+    
+    /// Compile only, do not link
+    #[arg(short = 'c', long = "c", help_heading = "Code Generation")]
+    pub compile_only: bool,
+    
+    /// Output file name
+    #[arg(short = 'o', long = "o", value_name = "FILE", help_heading = "Code Generation")]
+    pub output: Option<PathBuf>,
+    
+    /// Output directory for all artifacts
+    #[arg(long = "Qoutdir", alias = "out-dir", value_name = "DIR", help_heading = "Code Generation")]
+    pub out_dir: Option<PathBuf>,
+    
+    /// MSVC-style object output
+    #[arg(long = "Fo", value_name = "FILE", help_heading = "Code Generation")]
+    pub msvc_obj: Option<PathBuf>,
+    
+    /// MSVC-style executable output
+    #[arg(long = "Fe", value_name = "FILE", help_heading = "Code Generation")]
+    pub msvc_exe: Option<PathBuf>,
+    
+    /// Output kinds to emit (obj, asm, llvm-ir, llvm-bc, mir, metadata, dep-info, link)
+    #[arg(long = "emit", alias = "Femit", value_delimiter = ',', value_name = "KINDS", help_heading = "Code Generation")]
+    pub emit: Vec<String>,
+    
+    /// Target a specific architecture (AVX2, AVX512, ...)
+    #[arg(long = "arch", value_name = "FEATURE", help_heading = "Optimization Options")]
+    pub arch: Option<String>,
+    
+    /// Optimize for the host architecture
+    #[arg(long = "xHost", help_heading = "Optimization Options")]
+    pub xhost: bool,
+    
+    /// Define macro
+    #[arg(short = 'D', long = "D", value_name = "NAME[=VALUE]", help_heading = "Preprocessor")]
+    pub defines: Vec<String>,
+    
+    /// Undefine macro
+    #[arg(short = 'U', long = "U", value_name = "NAME", help_heading = "Preprocessor")]
+    pub undefines: Vec<String>,
+    
+    /// Include directory
+    #[arg(short = 'I', long = "I", value_name = "DIR", help_heading = "Preprocessor")]
+    pub includes: Vec<PathBuf>,
+    
+    /// Warning level: /W0 to /W4, /Wall
+    #[arg(short = 'W', long = "W", value_name = "LEVEL", help_heading = "Diagnostics")]
+    pub warn_level: Option<String>,
+    
+    /// Warnings as errors
+    #[arg(long = "WX", help_heading = "Diagnostics")]
+    pub wx: bool,
+    
+    /// Disable warnings by ICX number or lint name (/wd20001, /Qdiag-disable:dead_code)
+    #[arg(long = "wd", alias = "Qdiag-disable", value_name = "ID", value_delimiter = ',', help_heading = "Diagnostics")]
+    pub disable_diags: Vec<String>,
+    
+    /// File listing warnings to silence, optionally per file pattern
+    #[arg(long = "Qdiag-suppress-file", value_name = "FILE", help_heading = "Diagnostics")]
+    pub diag_suppress_file: Option<PathBuf>,
+    
+    /// Report warnings as errors by ICX number or lint name (/we20001)
+    #[arg(long = "we", alias = "Qdiag-error", value_name = "ID", value_delimiter = ',', help_heading = "Diagnostics")]
+    pub error_diags: Vec<String>,
+    
+    /// Embed an application manifest in the Windows executable (EMBED, NO)
+    #[arg(long = "MANIFEST", value_name = "MODE", num_args = 0..=1, require_equals = true, default_missing_value = "EMBED",
+        help_heading = "Linking")]
+    pub manifest: Option<String>,
+    
+    /// Manifest file to embed (implies /MANIFEST)
+    #[arg(long = "MANIFESTFILE", value_name = "FILE", help_heading = "Linking")]
+    pub manifest_file: Option<PathBuf>,
+    
+    /// Requested execution level: asInvoker, highestAvailable, requireAdministrator
+    #[arg(long = "MANIFESTUAC", value_name = "LEVEL", help_heading = "Linking")]
+    pub manifest_uac: Option<String>,
+    
+    /// DPI awareness in the manifest: unaware, system, permonitor, permonitorv2
+    #[arg(long = "Qmanifest-dpi", value_name = "MODE", help_heading = "Linking")]
+    pub manifest_dpi: Option<String>,
+    
+    /// Version fields stamped into the binary (1.2.3,product=App,company=Acme)
+    #[arg(long = "Qversion-info", value_name = "FIELDS", value_delimiter = ',', help_heading = "Linking")]
+    pub version_info: Vec<String>,
+    
+    /// Import library for a Windows DLL (default: <name>.lib next to it)
+    #[arg(long = "IMPLIB", value_name = "FILE", help_heading = "Linking")]
+    pub implib: Option<PathBuf>,
+    
+    /// Linker arguments (MSVC style)
+    #[arg(long = "link", value_name = "OPTIONS", help_heading = "Linking")]
+    pub link_args: Vec<String>,
+    
+    /// Linker to use: lld, mold, gold, bfd or link (MSVC)
+    #[arg(long = "Qlinker", value_name = "NAME", value_parser = ["lld", "mold", "gold", "bfd", "link"],
+        help_heading = "Linking")]
+    pub linker: Option<String>,
+    
+    /// Compile translation units in parallel (0 = one per core)
+    #[arg(long = "MP", value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "0",
+        help_heading = "Code Generation")]
+    pub parallel: Option<usize>,
+    
+    /// Working directory for the spawned compiler
+    #[arg(long = "cwd", alias = "Qdir", value_name = "DIR", help_heading = "Diagnostics")]
+    pub cwd: Option<PathBuf>,
+    
+    /// Kill the compiler after this many seconds
+    #[arg(long = "Qtimeout", value_name = "SECONDS", help_heading = "Diagnostics")]
+    pub timeout: Option<u64>,
+    
+    /// Compilation cache backend: builtin, or an external wrapper such as sccache
+    #[arg(long = "Qcache", value_name = "BACKEND", num_args = 0..=1, require_equals = true, default_missing_value = "builtin",
+        help_heading = "Code Generation")]
+    pub cache: Option<String>,
+    
+    /// Remove cache and incremental entries unused for this many days (default 30)
+    #[arg(long = "gc-cache", value_name = "DAYS", num_args = 0..=1, require_equals = true, default_missing_value = "30",
+        help_heading = "Code Generation")]
+    pub gc_cache: Option<u64>,
+    
+    /// --gc-cache, also removing the oldest entries until the rest fits (e.g. 10G)
+    #[arg(long = "gc-cache-size", value_name = "SIZE", help_heading = "Code Generation")]
+    pub gc_cache_size: Option<String>,
+    
+    /// Skip compiles whose sources, flags and compiler match the last successful one
+    #[arg(long = "Qup-to-date", help_heading = "Code Generation")]
+    pub up_to_date: bool,
+    
+    /// Print a compile phase timing breakdown (nightly rustc)
+    #[arg(long = "Qtime", alias = "ftime-report", help_heading = "Diagnostics")]
+    pub time: bool,
+    
+    /// Unstable rustc option, passed as -Z<flag> (nightly rustc)
+    #[arg(long = "Qunstable", value_name = "FLAG", help_heading = "Diagnostics")]
+    pub unstable: Vec<String>,
+    
+    /// Let a stable or beta rustc accept -Z options (RUSTC_BOOTSTRAP=1)
+    #[arg(long = "Qunstable-bootstrap", help_heading = "Diagnostics")]
+    pub unstable_bootstrap: bool,
+    
+    /// Report peak memory and CPU time of the compiler
+    #[arg(long = "Qresource-report", help_heading = "Diagnostics")]
+    pub resource_report: bool,
+    
+    /// Compile on a remote host over SSH, falling back to local on failure
+    #[arg(long = "Qremote", value_name = "HOST", help_heading = "Diagnostics")]
+    pub remote: Option<String>,
+    
+    /// Colored output: auto (terminal, honoring NO_COLOR), always or never
+    #[arg(long = "Qcolor", alias = "color", value_enum, value_name = "WHEN", default_value = "auto",
+        help_heading = "Diagnostics")]
+    pub color: ColorChoice,
+    
+    /// Language of the wrapper's own messages: en-US or zh-CN (default: from LANG)
+    #[arg(long = "Qlocale", value_name = "NAME", help_heading = "Diagnostics")]
+    pub locale: Option<String>,
+    
+    /// Diagnostic layout: icx (default) or msvc for IDE and MSBuild problem matchers
+    #[arg(long = "Qdiag-format", value_enum, value_name = "FORMAT", default_value = "icx",
+        help_heading = "Diagnostics")]
+    pub diag_format: DiagFormat,
+    
+    /// Show at most N errors per compile, counting the rest (0 = no limit)
+    #[arg(long = "Qdiag-error-limit", alias = "diag-error-limit", value_name = "N", help_heading = "Diagnostics")]
+    pub diag_error_limit: Option<u32>,
+    
+    /// Kill the compiler once the error limit is reached
+    #[arg(long = "Qdiag-error-limit-abort", help_heading = "Diagnostics")]
+    pub diag_error_limit_abort: bool,
+    
+    /// Also write the diagnostics as plain text to FILE (default: <source>.diag)
+    #[arg(long = "Qdiag-file", alias = "diag-file", value_name = "FILE", num_args = 0..=1, require_equals = true,
+        default_missing_value = "", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Diagnostics")]
+    pub diag_file: Option<PathBuf>,
+    
+    /// Print the N most frequent diagnostic codes at the end of the build (0 = all)
+    #[arg(long = "Qdiag-stats", value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "10",
+        help_heading = "Diagnostics")]
+    pub diag_stats: Option<usize>,
+    
+    /// Record the build's warnings as a baseline, or check against it
+    #[arg(long = "Qdiag-baseline", value_enum, value_name = "MODE", help_heading = "Diagnostics")]
+    pub diag_baseline: Option<BaselineMode>,
+    
+    /// Baseline file for /Qdiag-baseline
+    #[arg(long = "Qdiag-baseline-file", value_name = "FILE", default_value = "icx-baseline.json",
+        help_heading = "Diagnostics")]
+    pub diag_baseline_file: PathBuf,
+    
+    /// Apply rustc's machine-applicable suggestions to the sources
+    #[arg(long = "Qfix", alias = "fix", help_heading = "Diagnostics")]
+    pub fix: bool,
+    
+    /// Build a static library that also contains the object inputs
+    #[arg(long = "Qar", help_heading = "Linking")]
+    pub ar: bool,
+    
+    /// Generate Rust bindings for a C header into OUT_DIR before compiling
+    #[arg(long = "Qbindgen", value_name = "HEADER", help_heading = "Preprocessor")]
+    pub bindgen: Vec<PathBuf>,
+    
+    /// Build the units described by a project file (TOML or JSON)
+    #[arg(long = "project", alias = "Qproject", value_name = "FILE", help_heading = "Code Generation")]
+    pub project: Option<PathBuf>,
+    
+    /// Print each compile's diagnostics grouped under per-file headers
+    #[arg(long = "Qdiag-group", help_heading = "Diagnostics")]
+    pub diag_group: bool,
+    
+    /// End-of-build summary format: text, or json for CI
+    #[arg(long = "summary-format", alias = "Qsummary-format", value_enum, value_name = "FORMAT", default_value = "text",
+        help_heading = "Diagnostics")]
+    pub summary_format: SummaryFormat,
+    
+    /// Write the JSON summary to this file instead of stdout (implies --summary-format=json)
+    #[arg(long = "summary-file", alias = "Qsummary-file", value_name = "FILE", help_heading = "Diagnostics")]
+    pub summary_file: Option<PathBuf>,
+    
+    /// Record the invocation, timings, diagnostics and artifact hashes as JSON
+    #[arg(long = "Qbuild-report", value_name = "FILE", help_heading = "Diagnostics")]
+    pub build_report: Option<PathBuf>,
+    
+    /// Write all diagnostics of the build to a SARIF 2.1.0 log
+    #[arg(long = "Qsarif", value_name = "FILE", help_heading = "Diagnostics")]
+    pub sarif: Option<PathBuf>,
+    
+    /// Tee the command, compiler output and diagnostics to a log file
+    #[arg(long = "Qlog", value_name = "FILE", help_heading = "Diagnostics")]
+    pub log: Option<PathBuf>,
+    
+    /// Append to the /Qlog file instead of overwriting it
+    #[arg(long = "Qlog-append", help_heading = "Diagnostics")]
+    pub log_append: bool,
+    
+    /// Run a shell command at a build stage (after the icx-rustc.toml hooks)
+    #[arg(long = "Qhook", value_name = "STAGE=COMMAND", help_heading = "Build hooks")]
+    pub hooks: Vec<String>,
+    
+    /// Run as a compile server for later invocations
+    #[arg(long = "daemon", alias = "Qdaemon", help_heading = "Diagnostics")]
+    pub daemon: bool,
+    
+    /// Recompile whenever an input or module file changes
+    #[arg(long = "watch", alias = "Qwatch", help_heading = "Diagnostics")]
+    pub watch: bool,
+    
+    /// Verbose: echo the commands and report each step
+    #[arg(short = 'v', long = "v", overrides_with = "quiet", help_heading = "Diagnostics")]
+    pub verbose: bool,
+    
+    /// Print only diagnostics: no command echo, progress or summary line
+    #[arg(long = "Qquiet", alias = "nologo", overrides_with = "verbose", help_heading = "Diagnostics")]
+    pub quiet: bool,
+    
+    /// Dry run; with a file name, also write the commands as a .sh / .bat script
+    // PathBuf 的默认解析器不接受空值，而空值表示只显示命令
+    #[arg(long = "###", value_name = "SCRIPT", num_args = 0..=1, require_equals = true, default_missing_value = "",
+        value_parser = OsStringValueParser::new().map(PathBuf::from), help_heading = "Diagnostics")]
+    pub dry_run: Option<PathBuf>,
+    
+    /// Treat unknown options as errors instead of ignoring them
+    #[arg(long = "Qstrict-options", help_heading = "Diagnostics")]
+    pub strict_options: bool,
+    
+    /// Show version
+    #[arg(long = "version", help_heading = "Diagnostics")]
+    pub version: bool,
+    
+    /// Show help
+    #[arg(long = "help", help_heading = "Diagnostics")]
+    pub help: bool,
+    
+    /// List every option with its description and rustc translation
+    #[arg(long = "help-long", help_heading = "Diagnostics")]
+    pub help_long: bool,
+    
+    /// Print the option reference as a roff man page
+    #[arg(long = "man", help_heading = "Diagnostics")]
+    pub man: bool,
+    
+    /// Print the effective settings and where each one came from
+    #[arg(long = "Qshow-config", help_heading = "Configuration")]
+    pub show_config: bool,
+    
+    /// Print a completion script for the shell, with both option spellings
+    #[arg(long = "completions", value_enum, value_name = "SHELL", help_heading = "Diagnostics")]
+    pub completions: Option<CompletionShell>,
+    
+    /// Explain an error code, ICX number or lint (like rustc --explain)
+    #[arg(long = "explain", value_name = "CODE", help_heading = "Diagnostics")]
+    pub explain: Option<String>,
+    
+    /// Rust edition (2015, 2018, 2021, 2024)
+    #[arg(long = "edition", value_name = "YEAR", help_heading = "Rust-specific")]
+    pub edition: Option<String>,
+    
+    /// Crate type, or a comma list of them
+    #[arg(long = "crate-type", value_name = "TYPE", help_heading = "Rust-specific")]
+    pub crate_type: Option<String>,
+    
+    /// Crate name (default: the sanitized file stem)
+    #[arg(long = "crate-name", value_name = "NAME", help_heading = "Rust-specific")]
+    pub crate_name: Option<String>,
+    
+    /// Target triple or alias (win64, win32, linux-x64, linux-arm64, macos-arm64, musl, wasm, wasi)
+    #[arg(long = "target", value_name = "TRIPLE", help_heading = "Rust-specific")]
+    pub target: Option<String>,
+    
+    /// Build a WebAssembly module (wasi, bindgen, opt)
+    #[arg(long = "Qwasm", value_name = "OPTIONS", num_args = 0..=1, require_equals = true, default_missing_value = "unknown",
+        help_heading = "Rust-specific")]
+    pub wasm: Option<String>,
+    
+    /// Custom sysroot (embedded targets, vendored std)
+    #[arg(long = "sysroot", value_name = "DIR", help_heading = "Rust-specific")]
+    pub sysroot: Option<PathBuf>,
+    
+    /// rustup toolchain to use (also accepted as a leading +<toolchain>)
+    #[arg(long = "toolchain", value_name = "NAME", help_heading = "Rust-specific")]
+    pub toolchain: Option<String>,
+    
+    /// Path to a custom rustc (overrides ICX_RUSTC)
+    #[arg(long = "rustc", value_name = "PATH", help_heading = "Rust-specific")]
+    pub rustc: Option<PathBuf>,
+    
+    /// Split debug info: packed, unpacked or off (platform default if no value)
+    #[arg(long = "Qsplit-debug", num_args = 0..=1, require_equals = true, default_missing_value = "default",
+        value_name = "KIND", help_heading = "Code Generation")]
+    pub split_debug: Option<String>,
+    
+    /// Strip symbols or debug info from the output (default: symbols)
+    #[arg(long = "Qstrip", num_args = 0..=1, require_equals = true, default_missing_value = "symbols",
+        value_name = "WHAT", help_heading = "Code Generation")]
+    pub strip: Option<String>,
+    
+    /// Write a make/ninja depfile for the output (default: <output>.d)
+    #[arg(long = "QMMD", alias = "QMD", value_name = "FILE", num_args = 0..=1, require_equals = true,
+        default_missing_value = "", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Code Generation")]
+    pub mmd: Option<PathBuf>,
+    
+    /// Record the compile commands in a compilation database (default: compile_commands.json)
+    #[arg(long = "Qcompile-commands", value_name = "FILE", num_args = 0..=1, require_equals = true,
+        default_missing_value = "compile_commands.json", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Code Generation")]
+    pub compile_commands: Option<PathBuf>,
+    
+    /// Write a CMake fragment that builds Rust targets with icx-rustc (default: icx-rustc.cmake)
+    #[arg(long = "emit-cmake", value_name = "FILE", num_args = 0..=1, require_equals = true,
+        default_missing_value = "icx-rustc.cmake", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Code Generation")]
+    pub emit_cmake: Option<PathBuf>,
+    
+    /// Write icx-rustc.props and icx-rustc.targets for Visual Studio projects (default: current directory)
+    #[arg(long = "emit-msbuild", value_name = "DIR", num_args = 0..=1, require_equals = true,
+        default_missing_value = ".", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Code Generation")]
+    pub emit_msbuild: Option<PathBuf>,
+    
+    /// Write a build.ninja for the project or inputs instead of building (default: build.ninja)
+    #[arg(long = "Qninja", value_name = "FILE", num_args = 0..=1, require_equals = true,
+        default_missing_value = "build.ninja", value_parser = OsStringValueParser::new().map(PathBuf::from),
+        help_heading = "Code Generation")]
+    pub ninja: Option<PathBuf>,
+    
+    /// Show the generated assembly interleaved with source lines (optionally one function)
+    #[arg(long = "Qasm-view", value_name = "FUNCTION", num_args = 0..=1, require_equals = true, default_missing_value = "",
+        help_heading = "Diagnostics")]
+    pub asm_view: Option<String>,
+    
+    /// Write the /Qasm-view listing to FILE instead of printing it
+    #[arg(long = "Qasm-view-file", value_name = "FILE", help_heading = "Diagnostics")]
+    pub asm_view_file: Option<PathBuf>,
+    
+    /// Report section and symbol sizes of the linked binary (the N largest, default 10)
+    #[arg(long = "Qsize-report", value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "10",
+        help_heading = "Diagnostics")]
+    pub size_report: Option<usize>,
+    
+    /// List the exported and undefined symbols of the outputs, demangled
+    #[arg(long = "Qsymbols", value_enum, value_name = "WHICH", num_args = 0..=1, require_equals = true, default_missing_value = "all",
+        help_heading = "Diagnostics")]
+    pub symbols: Option<SymbolsMode>,
+    
+    /// Build a libFuzzer target, optionally with a sanitizer (address)
+    #[arg(long = "Qfuzz", value_name = "SANITIZER", num_args = 0..=1, require_equals = true, default_missing_value = "libfuzzer",
+        help_heading = "Diagnostics")]
+    pub fuzz: Option<String>,
+    
+    /// Build the vendored crates the sources use, from a `cargo vendor` directory
+    #[arg(long = "Qdeps", value_name = "DIR", help_heading = "Rust-specific")]
+    pub deps: Option<PathBuf>,
+    
+    /// Enable features of vendored crates (crate/feature, comma separated)
+    #[arg(long = "Qdeps-feature", value_name = "CRATE/FEATURE", value_delimiter = ',', help_heading = "Rust-specific")]
+    pub deps_features: Vec<String>,
+    
+    /// Instrument the code for source-based coverage (-Cinstrument-coverage)
+    #[arg(long = "Qcov", help_heading = "Diagnostics")]
+    pub coverage: bool,
+    
+    /// Coverage report of instrumented binaries that have run (summary, html, lcov)
+    #[arg(long = "Qcov-report", value_enum, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "summary",
+        help_heading = "Diagnostics")]
+    pub cov_report: Option<CoverageFormat>,
+    
+    /// Optimize the linked binary with llvm-bolt using this profile (.fdata or YAML)
+    #[arg(long = "Qbolt", value_name = "PROFILE", help_heading = "Optimization Options")]
+    pub bolt: Option<PathBuf>,
+    
+    /// Compile the /Qoffload-kernel sources for a GPU (nvptx64[:sm_XX], spirv)
+    #[arg(long = "Qoffload", value_name = "DEVICE", help_heading = "Optimization Options")]
+    pub offload: Option<String>,
+    
+    /// Kernel sources compiled for the /Qoffload device
+    #[arg(long = "Qoffload-kernel", value_name = "FILE", value_delimiter = ',', help_heading = "Optimization Options")]
+    pub offload_kernels: Vec<PathBuf>,
+    
+    /// Enable overflow checks regardless of optimization level
+    #[arg(long = "Qoverflow-checks", overrides_with = "no_overflow_checks", help_heading = "Optimization Options")]
+    pub overflow_checks: bool,
+    
+    /// Disable overflow checks
+    #[arg(long = "Qoverflow-checks-", overrides_with = "overflow_checks", help_heading = "Optimization Options")]
+    pub no_overflow_checks: bool,
+    
+    /// Enable debug assertions regardless of optimization level
+    #[arg(long = "Qdebug-assertions", overrides_with = "no_debug_assertions", help_heading = "Optimization Options")]
+    pub debug_assertions: bool,
+    
+    /// Disable debug assertions
+    #[arg(long = "Qdebug-assertions-", overrides_with = "debug_assertions", help_heading = "Optimization Options")]
+    pub no_debug_assertions: bool,
+    
+    /// Reproducible build (remap paths, stable metadata, deterministic link)
+    #[arg(long = "Brepro", help_heading = "Code Generation")]
+    pub repro: bool,
+    
+    /// Release mode: /O3 unless an optimization level is given
+    #[arg(long = "release", help_heading = "Optimization Options")]
+    pub release: bool,
+    
+    /// Optimize diagnostics output
+    #[arg(long = "optimize-diagnostics", default_value = "true", help_heading = "Diagnostics")]
+    pub optimize_diagnostics: bool,
+    
+    /// Per-file extra options: <file>="<options>"
+    #[arg(long = "Qfile-opts", value_name = "FILE=OPTIONS", help_heading = "Code Generation")]
+    pub file_opts: Vec<String>,
+    
+    /// Compile, then run the executable with the arguments after `--`
+    #[arg(long = "run", help_heading = "Code Generation")]
+    pub run: bool,
+    
+    /// Raw rustc flags (pass-through)
+    #[arg(last = true)]
+    pub raw_args: Vec<String>,
+    
+    /// Normalized command line (for re-parsing with per-file options)
+    #[arg(skip)]
+    pub command_line: Vec<String>,
+    
+    /// The arguments as given, before response files and option rewriting
+    #[arg(skip)]
+    pub original: Vec<String>,
+    
+    /// The options of each source, in precedence order (/Qshow-config)
+    #[arg(skip)]
+    pub layers: Vec<Layer>,
+}
+
+pub fn parse_args() -> Args {
+    let original: Vec<String> = std::env::args().skip(1).collect();
+    let layers = match expand_response_files(std::env::args().collect(), 0).and_then(|raw| {
+        let mut layers = lower_layers(&crate::config::load()?)?;
+        layers.push((Source::CommandLine, raw));
+        Ok(layers)
+    }) {
+        Ok(layers) => layers,
+        Err(e) => {
+            crate::driver::report_error(&e);
+            std::process::exit(exit_code::USAGE_ERROR);
+        }
+    };
+    let (args, layers, unknown) = merge_layers(layers);
+    
+    // 未知选项：默认忽略并提示，/Qstrict-options 时报错
+    let strict = args.iter().any(|a| a == "--Qstrict-options");
+    if !unknown.is_empty() {
+        report_unknown_options(&unknown, strict);
+        if strict {
+            std::process::exit(exit_code::USAGE_ERROR);
+        }
+    }
+    
+    let mut parsed = Args::parse_from(&args);
+    parsed.command_line = args;
+    parsed.original = original;
+    parsed.layers = layers;
+    parsed
+}
+
+/// Parses icx options over the icx-rustc.toml options and `ICX_OPTIONS`
+/// (icx-cargo, and `ICX_FLAGS` in cargo wrapper mode)
+///
+/// Unknown options are reported and ignored, as on the command line.
+pub fn parse_layered(config: &crate::config::Config, words: Vec<String>) -> Result<Args> {
+    let mut layers = lower_layers(config)?;
+    layers.push((Source::CommandLine, std::iter::once("icx-rustc".to_string()).chain(words).collect()));
+    let (args, layers, unknown) = merge_layers(layers);
+    if !unknown.is_empty() {
+        report_unknown_options(&unknown, false);
+    }
+    
+    let mut parsed = Args::try_parse_from(&args)?;
+    parsed.command_line = args;
+    parsed.layers = layers;
+    Ok(parsed)
+}
+
+/// The options below the command line: icx-rustc.toml, then `ICX_OPTIONS`
+///
+/// Each comes with a program name, like a command line.
+fn lower_layers(config: &crate::config::Config) -> Result<Vec<(Source, Vec<String>)>> {
+    let mut layers = Vec::new();
+    if let Some(options) = &config.options {
+        let path = config.path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+        let words = options.words().with_context(|| format!("Invalid options in {}", path))?;
+        layers.push((Source::ConfigFile, words));
+    }
+    if let Some(text) = std::env::var("ICX_OPTIONS").ok().filter(|t| !t.trim().is_empty()) {
+        let words = shlex::split(&text).with_context(|| format!("Invalid ICX_OPTIONS '{}'", text))?;
+        layers.push((Source::Environment, words));
+    }
+    for (source, words) in &mut layers {
+        // "--" 之后是 rustc 的参数，只有命令行能给出
+        if words.iter().any(|w| w == "--") {
+            anyhow::bail!("'--' is only accepted on the command line, not in {}", source);
+        }
+        words.insert(0, "icx-rustc".to_string());
+    }
+    Ok(layers)
+}
+
+/// Normalizes each source and joins them in precedence order, so later
+/// ones win; returns the command line, the layers and the unknown options
+fn merge_layers(layers: Vec<(Source, Vec<String>)>) -> (Vec<String>, Vec<Layer>, Vec<(String, Source)>) {
+    let mut command_line = Vec::new();
+    let mut normalized = Vec::new();
+    let mut unknown = Vec::new();
+    for (source, raw) in layers {
+        let (known, skipped) = split_unknown_options(pretokenize(&raw));
+        unknown.extend(skipped.iter().map(|&i| (raw[i].clone(), source)));
+        let mut known = known.into_iter();
+        // 程序名取自命令行
+        let program = known.next().unwrap_or_default();
+        if source == Source::CommandLine {
+            command_line.insert(0, program);
+        }
+        let words: Vec<String> = known.collect();
+        command_line.extend(words.iter().cloned());
+        normalized.push(Layer { source, words });
+    }
+    (command_line, normalized, unknown)
+}
+
+/// Response files may name further response files, up to this depth
+const MAX_RESPONSE_DEPTH: usize = 8;
+
+/// Replaces `@file` arguments with the arguments in the file
+///
+/// The file is split as cl and icx do: at whitespace and line breaks,
+/// with double quotes around arguments containing spaces and `\"` for a
+/// literal quote. Other backslashes are kept, so Windows paths need no
+/// escaping. UTF-16 files with a byte order mark, as MSBuild writes
+/// them, are read as well as UTF-8.
+fn expand_response_files(raw: Vec<String>, depth: usize) -> Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(raw.len());
+    for (i, arg) in raw.into_iter().enumerate() {
+        let Some(path) = arg.strip_prefix('@').filter(|p| !p.is_empty() && (i > 0 || depth > 0)) else {
+            expanded.push(arg);
+            continue;
+        };
+        if depth >= MAX_RESPONSE_DEPTH {
+            anyhow::bail!("Response files nested too deeply at '{}'", arg);
+        }
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read response file {}", path))?;
+        let text = decode_response_file(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to read response file {}: {}", path, e))?;
+        expanded.extend(expand_response_files(response_file_words(&text), depth + 1)?);
+    }
+    Ok(expanded)
+}
+
+/// Decodes a response file by its byte order mark: UTF-16 LE or BE, as
+/// MSBuild writes them, or UTF-8 with or without one
+fn decode_response_file(bytes: &[u8]) -> Result<String> {
+    let utf16 = |rest: &[u8], unit: fn([u8; 2]) -> u16| {
+        let units = rest.chunks_exact(2);
+        if !units.remainder().is_empty() {
+            anyhow::bail!("UTF-16 text with an odd number of bytes");
+        }
+        let units: Vec<u16> = units.map(|c| unit([c[0], c[1]])).collect();
+        String::from_utf16(&units).map_err(|_| anyhow::anyhow!("Invalid UTF-16 text"))
+    };
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Ok(std::str::from_utf8(rest).context("Invalid UTF-8 text")?.to_string()),
+        // 没有字节序标记的 UTF-16 也是合法的 UTF-8，但参数中不会有 NUL
+        bytes if bytes.contains(&0) => anyhow::bail!("Text contains NUL bytes (UTF-16 without a byte order mark?)"),
+        bytes => Ok(std::str::from_utf8(bytes).context("Invalid UTF-8 text")?.to_string()),
+    }
+}
+
+fn response_file_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut started = false;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'"') => {
+                word.push('"');
+                chars.next();
+            }
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started || !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                started = false;
+            }
+            c => word.push(c),
+        }
+    }
+    if started || !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Parses a command line already normalized by [`parse_args`] (--daemon requests)
+pub fn parse_normalized(command_line: Vec<String>) -> Result<Args> {
+    let mut args = Args::try_parse_from(&command_line)?;
+    args.command_line = command_line;
+    Ok(args)
+}
+
+/// Parses options given as one string, such as `ICX_FLAGS` in cargo wrapper mode
+///
+/// Unknown options are reported and ignored, as on the command line.
+pub fn parse_flags(text: &str) -> Result<Args> {
+    let words = shlex::split(text)
+        .with_context(|| format!("Invalid options '{}'", text))?;
+    parse_options(words)
+}
+
+/// Parses a list of icx options without the program name (icx-cargo)
+pub fn parse_options(words: Vec<String>) -> Result<Args> {
+    let raw: Vec<String> = std::iter::once("icx-rustc".to_string()).chain(words).collect();
+    let (args, unknown) = split_unknown_options(pretokenize(&raw));
+    if !unknown.is_empty() {
+        let unknown: Vec<(String, Source)> = unknown.iter().map(|&i| (raw[i].clone(), Source::CommandLine)).collect();
+        report_unknown_options(&unknown, false);
+    }
+    
+    let mut parsed = Args::try_parse_from(&args)?;
+    parsed.command_line = args;
+    Ok(parsed)
+}
+
+/// Re-parses the command line with extra options appended (`/Qfile-opts`)
+///
+/// Later options win for single-valued flags, list flags such as /D accumulate.
+pub fn reparse_with(args: &Args, extra: &str) -> Result<Args> {
+    let extra = shlex::split(extra)
+        .with_context(|| format!("Invalid per-file options '{}'", extra))?;
+    let raw: Vec<String> = std::iter::once("icx-rustc".to_string()).chain(extra).collect();
+    let (extra, unknown) = split_unknown_options(pretokenize(&raw));
+    if let Some(&i) = unknown.first() {
+        anyhow::bail!("Unknown option '{}' in per-file options", raw[i]);
+    }
+    
+    // 附加选项需放在 "--" 之前
+    let mut command_line = args.command_line.clone();
+    let at = command_line.iter().position(|a| a == "--").unwrap_or(command_line.len());
+    command_line.splice(at..at, extra.into_iter().skip(1));
+    
+    let mut unit = Args::try_parse_from(&command_line)?;
+    unit.command_line = command_line;
+    unit.original = args.original.clone();
+    Ok(unit)
+}
+
+/// Rewrites MSVC/Intel spellings (`/O2`, `/arch:AVX2`, `+nightly`) to clap syntax
+///
+/// Only genuine options are rewritten: a word starting with `/` that is no
+/// option name and names an existing file, or whose first component is an
+/// existing directory (`/home/me/main.rs`), stays a path, and so does the
+/// value after an option that takes it separately (`-o /tmp/out`).
+/// Everything after a bare `/link` goes to the linker, as with cl.
+fn pretokenize(raw: &[String]) -> Vec<String> {
+    let command = Args::command();
+    let separate: Vec<String> = command.get_arguments()
+        .filter(|a| a.get_action().takes_values() && !a.is_require_equals_set())
+        .filter(|a| a.get_num_args().is_none_or(|n| n.min_values() > 0))
+        .flat_map(|a| {
+            let longs = a.get_long().into_iter().chain(a.get_all_aliases().unwrap_or_default());
+            longs.map(|l| format!("--{}", l)).chain(a.get_short().map(|s| format!("-{}", s)))
+        })
+        .collect();
+    
+    let mut words = Vec::with_capacity(raw.len());
+    let mut iter = raw.iter().enumerate();
+    while let Some((i, arg)) = iter.next() {
+        // `--` 之后是 rustc（/run 时是程序）的参数，原样保留
+        if arg == "--" {
+            words.push(arg.clone());
+            words.extend(iter.by_ref().map(|(_, arg)| arg.clone()));
+            break;
+        }
+        // 与 cargo/rustup 一致：+<toolchain> 只能作为第一个参数
+        let word = if i == 1 && arg.starts_with('+') {
+            format!("--toolchain={}", &arg[1..])
+        } else if i > 0 && is_slash_option(arg) {
+            let without_slash = &arg[1..];
+            if without_slash.contains(':') {
+                format!("--{}", without_slash.replacen(':', "=", 1))
+            } else {
+                format!("-{}", without_slash)
+            }
+        } else {
+            arg.clone()
+        };
+        if arg == "/link" {
+            let linker: Vec<&String> = iter.clone().map(|(_, a)| a).take_while(|a| *a != "--").collect();
+            words.extend(linker.iter().map(|a| format!("--link={}", a)));
+            for _ in 0..linker.len() {
+                iter.next();
+            }
+            continue;
+        }
+        // 单独给出值的选项（-o <file>、/Qoutdir <dir>）：下一个词是值
+        let takes_next = separate.iter().any(|s| *s == word || (s.starts_with("--") && s[1..] == word));
+        words.push(word);
+        if takes_next {
+            if let Some((_, value)) = iter.next() {
+                words.push(value.clone());
+            }
+        }
+    }
+    words
+}
+
+/// Whether a word starting with `/` is an option rather than an absolute path
+///
+/// A word whose name (up to `:` or `=`, less a trailing `-`) is an option of
+/// the table is one, even where a directory of that name exists (`/run` on
+/// Linux). Otherwise a path names an existing file, or starts with an
+/// existing directory: `/usr/lib/x.so` is a path, `/Fo/tmp/out.o` and
+/// `/I/opt/include` are options.
+pub fn is_slash_option(word: &str) -> bool {
+    static NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    
+    if !word.starts_with('/') || word.starts_with("//") || word.len() < 2 {
+        return false;
+    }
+    let names = NAMES.get_or_init(|| {
+        let command = Args::command();
+        let mut names: Vec<String> = long_options(&command).into_iter().map(|(long, _)| long.to_string()).collect();
+        names.extend(command.get_arguments().filter_map(|a| a.get_short()).map(String::from));
+        names
+    });
+    let name = word[1..].split([':', '=']).next().unwrap_or_default();
+    if names.iter().any(|n| n == name || Some(n.as_str()) == name.strip_suffix('-')) {
+        return true;
+    }
+    let path = std::path::Path::new(word);
+    if path.exists() {
+        return false;
+    }
+    // 第一段是已有目录时按路径处理（/home/...、/Users/...，输出文件尚不存在）
+    match word[1..].find('/') {
+        Some(end) => !std::path::Path::new(&word[..end + 1]).is_dir(),
+        None => true,
+    }
+}
+
+/// Normalizes option spellings against the clap option table and removes
+/// the ones it does not know about, returning their argv indices
+///
+/// Single-dash Intel spellings (`-xHost`, `-Qoutdir`) are rewritten to their
+/// long form, and value-taking long options accept an attached value the
+/// MSVC way (`-Fofoo.o`).
+fn split_unknown_options(args: Vec<String>) -> (Vec<String>, Vec<usize>) {
+    let command = Args::command();
+    let longs = long_options(&command);
+    let shorts: Vec<char> = command.get_arguments()
+        .filter_map(|a| a.get_short())
+        .collect();
+    
+    let mut known = Vec::with_capacity(args.len());
+    let mut unknown = Vec::new();
+    let mut iter = args.into_iter().enumerate();
+    
+    // argv[0]
+    known.extend(iter.next().map(|(_, arg)| arg));
+    
+    while let Some((i, arg)) = iter.next() {
+        if arg == "--" {
+            known.push(arg);
+            known.extend(iter.by_ref().map(|(_, arg)| arg));
+            break;
+        }
+        
+        if let Some(rest) = arg.strip_prefix("--") {
+            let name = rest.split('=').next().unwrap_or(rest);
+            if longs.iter().any(|(l, _)| *l == name) {
+                known.push(arg);
+            } else {
+                unknown.push(i);
+            }
+        } else if let Some(rest) = arg.strip_prefix('-').filter(|r| !r.is_empty()) {
+            let name = rest.split('=').next().unwrap_or(rest);
+            if longs.iter().any(|(l, _)| *l == name) {
+                known.push(format!("-{}", arg));
+            } else if rest.chars().next().is_some_and(|c| shorts.contains(&c)) {
+                known.push(arg);
+            } else if let Some((long, _)) = longs.iter()
+                .filter(|(l, takes_value)| *takes_value && l.len() > 1 && rest.starts_with(l))
+                .max_by_key(|(l, _)| l.len())
+            {
+                known.push(format!("--{}={}", long, &rest[long.len()..]));
+            } else {
+                unknown.push(i);
+            }
+        } else {
+            known.push(arg);
+        }
+    }
+    
+    (known, unknown)
+}
+
+/// All long option names and aliases, with whether they take a value
+fn long_options(command: &clap::Command) -> Vec<(&str, bool)> {
+    command.get_arguments()
+        .flat_map(|arg| {
+            let takes_value = arg.get_action().takes_values();
+            arg.get_long()
+                .into_iter()
+                .chain(arg.get_all_aliases().unwrap_or_default())
+                .map(move |long| (long, takes_value))
+        })
+        .collect()
+}
+
+fn report_unknown_options(unknown: &[(String, Source)], strict: bool) {
+    let command = Args::command();
+    let longs = long_options(&command);
+    
+    let header = if strict {
+        format!("{}: unknown option(s):", "icx-rustc error".bright_red().bold())
+    } else {
+        "[icx-rustc] warning: ignoring unknown option(s):".to_string()
+    };
+    console::eprint(&header);
+    
+    for (option, source) in unknown {
+        let trimmed = option.trim_start_matches(['-', '/']);
+        let name = trimmed.split(['=', ':']).next().unwrap_or(trimmed);
+        let prefix = &option[..option.len() - trimmed.len()];
+        let from = match source {
+            Source::CommandLine => String::new(),
+            source => format!(" (from {})", source),
+        };
+        match did_you_mean(name, longs.iter().map(|(l, _)| *l)) {
+            Some(suggestion) => console::eprint(&format!("  {}{} (did you mean '{}{}'?)", option, from, prefix, suggestion)),
+            None => console::eprint(&format!("  {}{}", option, from)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_response_file, is_slash_option};
+    
+    fn utf16(text: &str, unit: fn(u16) -> [u8; 2], bom: [u8; 2]) -> Vec<u8> {
+        bom.into_iter().chain(text.encode_utf16().flat_map(unit)).collect()
+    }
+    
+    #[test]
+    fn utf8_with_and_without_bom() {
+        assert_eq!(decode_response_file(b"/O2 a.rs\r\n").unwrap(), "/O2 a.rs\r\n");
+        assert_eq!(decode_response_file(b"\xEF\xBB\xBF/O2 \xC3\xA4.rs").unwrap(), "/O2 ä.rs");
+        assert_eq!(decode_response_file(b"").unwrap(), "");
+        assert!(decode_response_file(b"/O2 \xFF.rs").is_err());
+    }
+    
+    #[test]
+    fn utf16_by_byte_order_mark() {
+        let text = "/Fo:目标.obj \"a b.rs\"\r\n";
+        assert_eq!(decode_response_file(&utf16(text, u16::to_le_bytes, [0xFF, 0xFE])).unwrap(), text);
+        assert_eq!(decode_response_file(&utf16(text, u16::to_be_bytes, [0xFE, 0xFF])).unwrap(), text);
+        assert_eq!(decode_response_file(&[0xFF, 0xFE]).unwrap(), "");
+    }
+    
+    #[test]
+    fn malformed_utf16_is_an_error() {
+        let mut odd = utf16("/O2", u16::to_le_bytes, [0xFF, 0xFE]);
+        odd.push(b'x');
+        assert!(decode_response_file(&odd).is_err());
+        // 孤立的代理项
+        assert!(decode_response_file(&[0xFF, 0xFE, 0x00, 0xD8, b'a', 0]).is_err());
+        // 没有字节序标记的 UTF-16
+        assert!(decode_response_file(&utf16("/O2", u16::to_le_bytes, [0, 0])[2..]).is_err());
+    }
+    
+    #[test]
+    fn options_before_paths() {
+        assert!(is_slash_option("/O2"));
+        assert!(is_slash_option("/Fo:out.o"));
+        assert!(is_slash_option("/Qoverflow-checks-"));
+        assert!(is_slash_option("/I/opt/include"));
+        assert!(!is_slash_option("/"));
+        assert!(!is_slash_option("//server/share/a.rs"));
+        assert!(!is_slash_option("a.rs"));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn absolute_paths_are_not_options() {
+        use super::parse_options;
+        use std::path::PathBuf;
+        
+        // /run 在 Linux 上是目录，仍按选项处理
+        assert!(is_slash_option("/run"));
+        assert!(!is_slash_option("/tmp/x.rs"));
+        assert!(!is_slash_option("/home/u/missing.rs"));
+        
+        let args = parse_options(["/run", "/tmp/x.rs"].map(String::from).to_vec()).unwrap();
+        assert!(args.run);
+        assert_eq!(args.files, [PathBuf::from("/tmp/x.rs")]);
+        
+        let args = parse_options(["/home/u/missing.rs", "/link", "/LIBPATH:/usr/lib"].map(String::from).to_vec()).unwrap();
+        assert_eq!(args.files, [PathBuf::from("/home/u/missing.rs")]);
+        assert_eq!(args.link_args, ["/LIBPATH:/usr/lib"]);
+    }
+}
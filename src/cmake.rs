@@ -0,0 +1,180 @@
+use crate::cli::Args;
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+
+/// --emit-cmake[=file]: a CMake module for building Rust targets with icx-rustc
+///
+/// CMake has no Rust language support, so the module defines
+/// `icx_rust_executable` and `icx_rust_library`, which run icx-rustc from
+/// `add_custom_command` rules. Options given together with --emit-cmake
+/// become the default `ICX_RUSTC_FLAGS`; the build configuration adds
+/// its own (`ICX_RUSTC_FLAGS_<CONFIG>`).
+pub fn write(args: &Args, path: &Path) -> Result<i32> {
+    if !args.files.is_empty() {
+        console::eprint(&format!(
+            "{}: --emit-cmake takes options only; the sources are named in CMakeLists.txt",
+            i18n::t("icx-rustc error").bright_red().bold()
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    }
+    let exe = std::env::current_exe().context("Failed to locate icx-rustc")?;
+    // 生成文件时给出的其余选项作为默认选项
+    let flags: Vec<String> = args.command_line.iter().skip(1)
+        .filter(|a| !a.starts_with("--emit-cmake"))
+        .map(|a| quote_flag(a))
+        .collect();
+    
+    let text = TEMPLATE
+        .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        .replace("@ICX_RUSTC@", &escape(&exe.display().to_string().replace('\\', "/")))
+        .replace("@FLAGS@", &escape(&flags.join(" ")));
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    console::eprint(&format!("[icx-rustc] wrote {}; include() it from CMakeLists.txt", path.display()));
+    Ok(exit_code::SUCCESS)
+}
+
+/// Quotes an option for `separate_arguments(NATIVE_COMMAND)`
+fn quote_flag(flag: &str) -> String {
+    if flag.contains(char::is_whitespace) {
+        format!("\"{}\"", flag.replace('"', "\\\""))
+    } else {
+        flag.to_string()
+    }
+}
+
+/// Contents of a quoted CMake argument
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$").replace(';', "\\;")
+}
+
+const TEMPLATE: &str = r#"# icx-rustc integration for CMake, written by icx-rustc @VERSION@ --emit-cmake
+#
+# include() this file from CMakeLists.txt (or name it in CMAKE_PROJECT_INCLUDE),
+# then declare Rust targets; C and C++ sources are compiled and linked by
+# icx-rustc with them:
+#
+#   icx_rust_executable(app src/main.rs SOURCES src/fast.c FLAGS /arch:AVX2)
+#   icx_rust_library(core src/lib.rs STATIC)
+#   target_link_libraries(cpp_app PRIVATE core)
+#
+# Rebuilds follow the sources through /QMMD depfiles (CMake 3.21 or later).
+
+include_guard(GLOBAL)
+
+set(ICX_RUSTC "@ICX_RUSTC@" CACHE FILEPATH "icx-rustc compiler driver")
+set(ICX_RUSTC_FLAGS "@FLAGS@" CACHE STRING "icx-rustc options for every Rust target")
+set(ICX_RUSTC_LAUNCHER "" CACHE STRING "Command icx-rustc is launched through (like CMAKE_<LANG>_COMPILER_LAUNCHER)")
+if(CMAKE_HOST_WIN32)
+  set(_icx_response_default ON)
+else()
+  set(_icx_response_default OFF)
+endif()
+option(ICX_RUSTC_USE_RESPONSE_FILES "Pass sources and options to icx-rustc in an @response file" ${_icx_response_default})
+
+# Build configuration -> icx-rustc options (CMAKE_BUILD_TYPE or the multi-config choice)
+set(ICX_RUSTC_FLAGS_DEBUG "/Od /Qdebug-assertions /Qoverflow-checks" CACHE STRING "icx-rustc options for Debug")
+set(ICX_RUSTC_FLAGS_RELEASE "/O2" CACHE STRING "icx-rustc options for Release")
+set(ICX_RUSTC_FLAGS_RELWITHDEBINFO "/O2 /Qsplit-debug" CACHE STRING "icx-rustc options for RelWithDebInfo")
+set(ICX_RUSTC_FLAGS_MINSIZEREL "/O1 /Qstrip" CACHE STRING "icx-rustc options for MinSizeRel")
+
+function(_icx_rust_target name kind source)
+  cmake_parse_arguments(PARSE_ARGV 3 ICX "" "" "SOURCES;FLAGS;DEPENDS")
+  # Outputs and intermediate objects go to <name>.rust/; an executable <name>
+  # beside the custom target of the same name would clash in Ninja
+  set(dir "${CMAKE_CURRENT_BINARY_DIR}/${name}.rust")
+  file(MAKE_DIRECTORY "${dir}")
+  set(implib)
+  if(kind STREQUAL "bin")
+    set(output "${dir}/${name}${CMAKE_EXECUTABLE_SUFFIX}")
+  elseif(kind STREQUAL "staticlib")
+    set(output "${dir}/${CMAKE_STATIC_LIBRARY_PREFIX}${name}${CMAKE_STATIC_LIBRARY_SUFFIX}")
+  else()
+    set(output "${dir}/${CMAKE_SHARED_LIBRARY_PREFIX}${name}${CMAKE_SHARED_LIBRARY_SUFFIX}")
+    if(WIN32)
+      set(implib "${dir}/${CMAKE_IMPORT_LIBRARY_PREFIX}${name}${CMAKE_IMPORT_LIBRARY_SUFFIX}")
+    endif()
+  endif()
+  
+  set(inputs)
+  foreach(file IN LISTS source ICX_SOURCES)
+    get_filename_component(file "${file}" ABSOLUTE)
+    list(APPEND inputs "${file}")
+  endforeach()
+  separate_arguments(flags NATIVE_COMMAND "${ICX_RUSTC_FLAGS}")
+  list(APPEND flags ${ICX_FLAGS} "--crate-type=${kind}" "--crate-name=${name}")
+  if(implib)
+    list(APPEND flags "/IMPLIB:${implib}")
+  endif()
+  
+  set(depfile)
+  if(CMAKE_VERSION VERSION_GREATER_EQUAL 3.21)
+    list(APPEND flags "/QMMD:${output}.d")
+    set(depfile DEPFILE "${output}.d")
+  endif()
+  
+  set(arguments ${flags} ${inputs} -o "${output}")
+  set(response)
+  if(ICX_RUSTC_USE_RESPONSE_FILES)
+    set(content)
+    foreach(argument IN LISTS arguments)
+      string(REPLACE "\"" "\\\"" argument "${argument}")
+      string(APPEND content "\"${argument}\"\n")
+    endforeach()
+    # configure_file only touches the response file when it changed
+    file(WRITE "${dir}/arguments.rsp.in" "${content}")
+    configure_file("${dir}/arguments.rsp.in" "${dir}/arguments.rsp" COPYONLY)
+    set(response "${dir}/arguments.rsp")
+    set(arguments "@${response}")
+  endif()
+  
+  # Configuration options stay on the command line for multi-config generators
+  set(config_flags)
+  foreach(config DEBUG RELEASE RELWITHDEBINFO MINSIZEREL)
+    separate_arguments(options NATIVE_COMMAND "${ICX_RUSTC_FLAGS_${config}}")
+    string(REPLACE ";" "$<SEMICOLON>" options "${options}")
+    list(APPEND config_flags "$<$<CONFIG:${config}>:${options}>")
+  endforeach()
+  
+  add_custom_command(
+    OUTPUT "${output}"
+    COMMAND ${ICX_RUSTC_LAUNCHER} "${ICX_RUSTC}" ${config_flags} ${arguments}
+    DEPENDS ${inputs} ${response} ${ICX_DEPENDS}
+    ${depfile}
+    WORKING_DIRECTORY "${dir}"
+    COMMENT "Compiling Rust target ${name}"
+    COMMAND_EXPAND_LISTS
+    VERBATIM)
+  set(ICX_OUTPUT "${output}" PARENT_SCOPE)
+  set(ICX_IMPLIB "${implib}" PARENT_SCOPE)
+endfunction()
+
+# icx_rust_executable(<name> <main.rs> [SOURCES <c/c++ files>...] [FLAGS <options>...] [DEPENDS <files>...])
+function(icx_rust_executable name source)
+  _icx_rust_target(${name} bin ${source} ${ARGN})
+  add_custom_target(${name} ALL DEPENDS "${ICX_OUTPUT}")
+  set_target_properties(${name} PROPERTIES ICX_OUTPUT "${ICX_OUTPUT}")
+endfunction()
+
+# icx_rust_library(<name> <lib.rs> STATIC|SHARED [SOURCES ...] [FLAGS ...] [DEPENDS ...])
+# defines an imported library <name> for target_link_libraries
+function(icx_rust_library name source type)
+  if(type STREQUAL "STATIC")
+    _icx_rust_target(${name} staticlib ${source} ${ARGN})
+  elseif(type STREQUAL "SHARED")
+    _icx_rust_target(${name} cdylib ${source} ${ARGN})
+  else()
+    message(FATAL_ERROR "icx_rust_library: expected STATIC or SHARED, got '${type}'")
+  endif()
+  add_custom_target(${name}-rustc ALL DEPENDS "${ICX_OUTPUT}")
+  add_library(${name} ${type} IMPORTED GLOBAL)
+  set_target_properties(${name} PROPERTIES IMPORTED_LOCATION "${ICX_OUTPUT}")
+  if(ICX_IMPLIB)
+    set_target_properties(${name} PROPERTIES IMPORTED_IMPLIB "${ICX_IMPLIB}")
+  endif()
+  add_dependencies(${name} ${name}-rustc)
+endfunction()
+"#;
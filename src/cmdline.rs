@@ -0,0 +1,161 @@
+//! Command lines and paths that survive Windows tools
+//!
+//! Arguments are quoted so the receiving program splits them back into the
+//! same words: by the CRT rules for programs started directly, with carets
+//! added for cmd.exe. Paths too long for `MAX_PATH` are handed to the
+//! compiler in their `\\?\` form, which the linker accepts as well.
+
+use std::path::{Path, PathBuf};
+
+/// Longest directory path the Win32 APIs take without `\\?\` (MAX_PATH
+/// less room for an 8.3 file name)
+#[cfg(windows)]
+const MAX_DIR_PATH: usize = 248;
+
+/// Joins words into a command line the host splits back the same way
+/// (CRT rules on Windows, POSIX shell quoting elsewhere)
+pub fn join<'a>(words: impl IntoIterator<Item = &'a str>) -> String {
+    words.into_iter().map(quote).collect::<Vec<_>>().join(" ")
+}
+
+/// Quotes one word for the host, only where it is needed
+pub fn quote(word: &str) -> String {
+    if cfg!(windows) {
+        if word.is_empty() || word.contains([' ', '\t', '"']) {
+            quote_windows(word)
+        } else {
+            word.to_string()
+        }
+    } else if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+        word.to_string()
+    } else {
+        shlex::try_quote(word)
+            .map(|q| q.into_owned())
+            .unwrap_or_else(|_| word.to_string())
+    }
+}
+
+/// Double-quotes an argument for the Windows CRT command line parser
+///
+/// Backslashes are doubled only where they precede a quote, so paths
+/// stay as they are.
+pub fn quote_windows(word: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in word.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes an argument for a command line that cmd.exe reads first
+/// (batch files, `cmd /C`)
+///
+/// cmd toggles its quote state at every `"`, escaped or not, so `&`, `|`,
+/// `<`, `>`, `(`, `)` and `^` that it sees outside quotes get a caret;
+/// the program then receives the word unchanged. `%` is left to the
+/// caller, as batch files and `cmd /C` escape it differently.
+pub fn quote_cmd(word: &str) -> String {
+    let quoted = if word.is_empty() || word.contains([' ', '\t', '"']) {
+        quote_windows(word)
+    } else {
+        word.to_string()
+    };
+    let mut escaped = String::with_capacity(quoted.len());
+    let mut inside = false;
+    for c in quoted.chars() {
+        if c == '"' {
+            inside = !inside;
+        } else if !inside && matches!(c, '&' | '|' | '<' | '>' | '(' | ')' | '^') {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The `\\?\` form of a path too long for the Win32 limit; other paths,
+/// and every path on other hosts, are returned unchanged
+///
+/// Relative paths are made absolute first (against the process's working
+/// directory: resolve them against the compiler's before). Verbatim paths
+/// are not normalized by Windows, so `/`, `.` and `..` are resolved here.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    let Some(absolute) = std::path::absolute(path).ok() else {
+        return path.to_path_buf();
+    };
+    let Some(text) = absolute.to_str() else {
+        return path.to_path_buf();
+    };
+    if text.len() < MAX_DIR_PATH || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+    match text.strip_prefix(r"\\") {
+        Some(share) => PathBuf::from(format!(r"\\?\UNC\{}", share)),
+        None => PathBuf::from(format!(r"\\?\{}", text)),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// A path as shown to the user: without the `\\?\` prefix [`long_path`] adds
+pub fn display_path(path: &str) -> String {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", share)
+    } else if let Some(local) = path.strip_prefix(r"\\?\") {
+        local.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote_cmd, quote_windows};
+    
+    #[test]
+    fn windows_quoting() {
+        assert_eq!(quote_windows(""), r#""""#);
+        assert_eq!(quote_windows("a b"), r#""a b""#);
+        assert_eq!(quote_windows(r"C:\dir\file"), r#""C:\dir\file""#);
+        // 结尾的反斜杠加倍，否则会转义收尾的引号
+        assert_eq!(quote_windows(r"C:\dir\"), r#""C:\dir\\""#);
+        assert_eq!(quote_windows(r"a\\"), r#""a\\\\""#);
+        assert_eq!(quote_windows(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_windows(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(quote_windows(r#"a\\"b"#), r#""a\\\\\"b""#);
+    }
+    
+    #[test]
+    fn cmd_quoting() {
+        assert_eq!(quote_cmd(""), r#""""#);
+        assert_eq!(quote_cmd("plain"), "plain");
+        assert_eq!(quote_cmd("a&b|c"), "a^&b^|c");
+        assert_eq!(quote_cmd("x^y"), "x^^y");
+        assert_eq!(quote_cmd("(a)<b>"), "^(a^)^<b^>");
+        // 引号内的字符 cmd 不解释，不加脱字符
+        assert_eq!(quote_cmd("a b&c"), r#""a b&c""#);
+        // cmd 在 \" 处同样切换引号状态，其后的 & 位于引号外
+        assert_eq!(quote_cmd(r#"say "&" & (x)"#), r#""say \"^&\" & (x)""#);
+        assert_eq!(quote_cmd(r"dir\ ^"), r#""dir\ ^""#);
+    }
+}
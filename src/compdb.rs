@@ -0,0 +1,107 @@
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Serializes updates from parallel compiles of one process
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Another process (make -j) holds the lock file for milliseconds; an
+/// older one was left behind by a killed build
+const STALE: Duration = Duration::from_secs(10);
+
+/// /Qcompile-commands[:file]: records the compile in a compilation database
+///
+/// One entry per Rust input with the full rustc command line (the cache
+/// wrapper left out) and one per C or C++ source with its compiler's,
+/// in the `compile_commands.json` format of clang tooling. Entries of
+/// earlier compiles are kept; those for the same file and output are
+/// replaced, so repeated builds do not grow the database.
+pub fn record(path: &Path, cmd: &RustcCommand) -> Result<()> {
+    let directory = std::env::current_dir().context("Failed to read current directory")?;
+    let directory = match &cmd.cwd {
+        Some(dir) => directory.join(dir),
+        None => directory,
+    };
+    let directory = directory.display().to_string();
+    
+    let mut entries = Vec::new();
+    let output = cmd.artifact().or_else(|| cmd.output.clone());
+    let arguments = cmd.arguments();
+    for file in &cmd.input_files {
+        entries.push(entry(&directory, file, &arguments, output.as_deref()));
+    }
+    for step in &cmd.c_steps {
+        let mut arguments = vec![step.program.display().to_string()];
+        arguments.extend(step.args.iter().cloned());
+        entries.push(entry(&directory, &step.source, &arguments, Some(&step.output)));
+    }
+    if entries.is_empty() {
+        return Ok(());
+    }
+    
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _file_lock = FileLock::acquire(path)?;
+    let mut database: Vec<Value> = match std::fs::read_to_string(path) {
+        Ok(text) if !text.trim().is_empty() => serde_json::from_str(&text)
+            .with_context(|| format!("{} is not a compilation database", path.display()))?,
+        _ => Vec::new(),
+    };
+    database.retain(|old| !entries.iter().any(|new| same_compile(old, new)));
+    database.extend(entries);
+    
+    // 先写临时文件再改名，读取数据库的工具不会看到写了一半的文件
+    let temporary = PathBuf::from(format!("{}.tmp{}", path.display(), std::process::id()));
+    let text = serde_json::to_string_pretty(&database)?;
+    std::fs::write(&temporary, text + "\n").with_context(|| format!("Failed to write {}", temporary.display()))?;
+    std::fs::rename(&temporary, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn entry(directory: &str, file: &Path, arguments: &[String], output: Option<&Path>) -> Value {
+    let mut entry = json!({
+        "directory": directory,
+        "file": file.display().to_string(),
+        "arguments": arguments,
+    });
+    if let Some(output) = output {
+        entry["output"] = json!(output.display().to_string());
+    }
+    entry
+}
+
+fn same_compile(a: &Value, b: &Value) -> bool {
+    ["directory", "file", "output"].iter().all(|key| a.get(key) == b.get(key))
+}
+
+/// `<database>.lock`, created exclusively while the database is updated
+struct FileLock(PathBuf);
+
+impl FileLock {
+    fn acquire(database: &Path) -> Result<FileLock> {
+        let path = PathBuf::from(format!("{}.lock", database.display()));
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(FileLock(path)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path).and_then(|m| m.modified()).ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                        .is_some_and(|age| age > STALE);
+                    if stale {
+                        let _ = std::fs::remove_file(&path);
+                    }
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to create {}", path.display())),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::cli::{Args, CompletionShell};
+use crate::exit_code;
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+/// MSVC spellings with the value attached to a short option, which the
+/// option table cannot list (`/O2` is `-O2`)
+pub const ATTACHED: [(&str, &str); 10] = [
+    ("/O0", "Disable optimization"),
+    ("/O1", "Optimize for size"),
+    ("/O2", "Optimize for speed"),
+    ("/O3", "Maximum optimization"),
+    ("/Ox", "Maximum optimization"),
+    ("/W0", "Disable warnings"),
+    ("/W1", "Warning level 1"),
+    ("/W2", "Warning level 2"),
+    ("/W3", "Warning level 3"),
+    ("/W4", "Warning level 4"),
+];
+
+/// An Intel-style spelling to complete, `/name` or `/name:` before a value
+struct Slash {
+    word: String,
+    help: String,
+}
+
+impl Slash {
+    /// Whether the value follows without a space
+    fn open(&self) -> bool {
+        self.word.ends_with(':')
+    }
+}
+
+/// --completions <shell>: prints a completion script for icx-rustc
+///
+/// clap_complete generates the `--option` completions from the option
+/// table; each script then gets the `/option` and `/option:value`
+/// spellings (value lists included, as in `/Qcolor:always`) on words
+/// starting with `/`, where files still complete, since that is also
+/// how absolute paths start.
+pub fn print(shell: CompletionShell) -> Result<i32> {
+    let mut out = Vec::new();
+    let generator = match shell {
+        CompletionShell::Bash => Shell::Bash,
+        CompletionShell::Zsh => Shell::Zsh,
+        CompletionShell::Fish => Shell::Fish,
+        CompletionShell::Powershell => Shell::PowerShell,
+    };
+    clap_complete::generate(generator, &mut Args::command(), "icx-rustc", &mut out);
+    let script = String::from_utf8(out)?;
+    let slash = slash_words();
+    let script = match shell {
+        CompletionShell::Bash => bash(script, &slash),
+        CompletionShell::Zsh => zsh(script, &slash),
+        CompletionShell::Fish => fish(script, &slash),
+        CompletionShell::Powershell => powershell(script, &slash),
+    };
+    crate::console::print_document(&script)?;
+    Ok(exit_code::SUCCESS)
+}
+
+fn slash_words() -> Vec<Slash> {
+    let command = Args::command();
+    let mut words = Vec::new();
+    for arg in command.get_arguments().filter(|a| !a.is_hide_set()) {
+        let Some(long) = arg.get_long() else {
+            continue;
+        };
+        // /O、/W 只按 MSVC 的写法补全
+        if long.len() == 1 && ATTACHED.iter().any(|(word, _)| word[1..].starts_with(long)) {
+            continue;
+        }
+        let help = arg.get_help().map(|h| h.to_string()).unwrap_or_default();
+        let values: Vec<String> = arg.get_possible_values().iter()
+            .filter(|v| !v.is_hide_set())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        let takes_value = arg.get_action().takes_values();
+        // 值可省略（/Qdiag-file[:<file>]）时也补全不带值的写法
+        if !takes_value || arg.get_num_args().is_some_and(|n| n.min_values() == 0) {
+            words.push(Slash { word: format!("/{}", long), help: help.clone() });
+        }
+        if !takes_value {
+            continue;
+        }
+        if values.is_empty() {
+            words.push(Slash { word: format!("/{}:", long), help });
+        } else {
+            words.extend(values.iter().map(|v| Slash { word: format!("/{}:{}", long, v), help: help.clone() }));
+        }
+    }
+    words.extend(ATTACHED.iter().map(|(word, help)| Slash { word: word.to_string(), help: help.to_string() }));
+    words
+}
+
+fn bash(script: String, slash: &[Slash]) -> String {
+    let words: Vec<&str> = slash.iter().map(|s| s.word.as_str()).collect();
+    // ':' 在 COMP_WORDBREAKS 中，当前词从命令行取，补全结果去掉冒号之前的部分
+    format!(r#"{script}
+_icx__rustc_slash_opts="{words}"
+
+_icx__rustc_slash() {{
+    local line="${{COMP_LINE:0:COMP_POINT}}"
+    local cur="${{line##*[[:space:]]}}"
+    if [[ "${{cur}}" != /* ]]; then
+        _icx__rustc "$@"
+        return
+    fi
+    COMPREPLY=( $(compgen -W "${{_icx__rustc_slash_opts}}" -- "${{cur}}") $(compgen -f -- "${{cur}}") )
+    if [[ "${{cur}}" == *:* && "${{COMP_WORDBREAKS}}" == *:* ]]; then
+        local colon="${{cur%"${{cur##*:}}"}}"
+        COMPREPLY=( "${{COMPREPLY[@]#"$colon"}}" )
+    fi
+    if [[ ${{#COMPREPLY[@]}} -eq 1 && "${{COMPREPLY[0]}}" == *: ]]; then
+        compopt -o nospace 2>/dev/null
+    fi
+}}
+complete -F _icx__rustc_slash -o bashdefault -o default icx-rustc
+"#, words = words.join(" "))
+}
+
+fn zsh(script: String, slash: &[Slash]) -> String {
+    let entry = |s: &Slash| format!("        '{}:{}'", s.word.replace(':', "\\:"), s.help.replace('\'', "'\\''"));
+    let flags: Vec<String> = slash.iter().filter(|s| !s.open()).map(entry).collect();
+    let values: Vec<String> = slash.iter().filter(|s| s.open()).map(entry).collect();
+    let wrapper = format!(r#"_icx-rustc_slash() {{
+    if [[ $PREFIX != /* ]]; then
+        _icx-rustc "$@"
+        return
+    fi
+    local -a flags values
+    flags=(
+{flags}
+    )
+    values=(
+{values}
+    )
+    _describe -t options 'Intel-style option' flags
+    _describe -t options 'Intel-style option' values -S ''
+    _files
+}}
+
+if [ "$funcstack[1]" = "_icx-rustc" ]; then
+    # autoloaded from fpath: later completions go through the wrapper too
+    compdef _icx-rustc_slash icx-rustc
+    _icx-rustc_slash "$@"
+else
+    compdef _icx-rustc_slash icx-rustc
+fi
+"#, flags = flags.join("\n"), values = values.join("\n"));
+    // 用同时补全 /选项 的函数替换 clap 最后的注册部分（fpath 与 source 两种用法）
+    match script.rfind("if [ \"$funcstack[1]\" = \"_icx-rustc\" ]; then") {
+        Some(at) => format!("{}{}", &script[..at], wrapper),
+        None => format!("{}\n{}", script, wrapper),
+    }
+}
+
+fn fish(mut script: String, slash: &[Slash]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    script.push('\n');
+    for s in slash {
+        script.push_str(&format!(
+            "complete -c icx-rustc -n 'string match -q -- \"/*\" (commandline -ct)' -a {} -d {}\n",
+            quote(&s.word),
+            quote(&s.help)
+        ));
+    }
+    script
+}
+
+fn powershell(script: String, slash: &[Slash]) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    let results: String = slash.iter()
+        .map(|s| format!(
+            "            [CompletionResult]::new({}, {}, [CompletionResultType]::ParameterName, {})\n",
+            quote(&s.word),
+            quote(&s.word),
+            quote(if s.help.is_empty() { &s.word } else { &s.help })
+        ))
+        .collect();
+    // 插在 icx-rustc 分支的 break 之前，与 -- 选项一起按前缀筛选
+    let marker = "            break\n        }\n    })";
+    match script.find(marker) {
+        Some(at) => format!("{}{}{}", &script[..at], results, &script[at..]),
+        None => script,
+    }
+}
@@ -0,0 +1,237 @@
+use crate::cli::{Args, ColorChoice, Source};
+use crate::toml;
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, CommandFactory};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name looked up from the current directory upwards
+pub const FILE_NAME: &str = "icx-rustc.toml";
+
+/// Settings shared by every invocation in a tree (`icx-rustc.toml`)
+///
+/// ```toml
+/// options = "/W4 /Qdiag-format:msvc"
+/// audit-log = "logs/icx-audit.jsonl"
+///
+/// [hooks]
+/// pre-compile = "python tools/check_headers.py"
+/// post-link = ['codesign -s - "$ICX_OUTPUT"', "tools/upload.sh"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Where the settings were read from; `None` without a file
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Options applied before `ICX_OPTIONS` and the command line, which override them
+    pub options: Option<Options>,
+    /// Commands per build stage (see [`crate::hooks`])
+    #[serde(default)]
+    pub hooks: BTreeMap<String, Commands>,
+    /// Append-only log of every compile, relative to this file (ICX_AUDIT_LOG wins)
+    #[serde(rename = "audit-log", alias = "audit_log")]
+    pub audit_log: Option<PathBuf>,
+}
+
+/// One command or a list of them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Commands {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Commands {
+    pub fn list(&self) -> &[String] {
+        match self {
+            Commands::One(command) => std::slice::from_ref(command),
+            Commands::Many(commands) => commands,
+        }
+    }
+}
+
+/// Options as one command-line string or as a list of words
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Options {
+    Line(String),
+    Words(Vec<String>),
+}
+
+impl Options {
+    pub fn words(&self) -> Result<Vec<String>> {
+        match self {
+            Options::Line(line) => shlex::split(line).with_context(|| format!("Invalid options '{}'", line)),
+            Options::Words(words) => Ok(words.clone()),
+        }
+    }
+}
+
+/// `ICX_CONFIG`, else the nearest `icx-rustc.toml`; defaults without either
+pub fn load() -> Result<Config> {
+    let path = match std::env::var_os("ICX_CONFIG").filter(|p| !p.is_empty()) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => find(&std::env::current_dir().context("Failed to read current directory")?),
+    };
+    let Some(path) = path else {
+        return Ok(Config::default());
+    };
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read configuration {}", path.display()))?;
+    let value = toml::parse(&text).map_err(|e| anyhow::anyhow!("{} is not valid TOML: {}", path.display(), e))?;
+    let mut config: Config = serde_json::from_value(value)
+        .map_err(|e| anyhow::anyhow!("Invalid configuration {}: {}", path.display(), e))?;
+    config.path = Some(path);
+    Ok(config)
+}
+
+fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|d| d.join(FILE_NAME)).find(|p| p.is_file())
+}
+
+/// /Qshow-config: the effective options and settings, each with its source
+///
+/// Options come from the built-in defaults, icx-rustc.toml, `ICX_OPTIONS`
+/// and the command line, later ones winning; a value that replaced an
+/// earlier one shows what it overrode, so an option that "didn't take"
+/// can be traced to where it was set again.
+pub fn show(args: &Args) -> Result<i32> {
+    let config = load()?;
+    let file = config.path.as_ref().map_or_else(|| "none".to_string(), |p| p.display().to_string());
+    println!("Configuration file: {}", file);
+    for layer in args.layers.iter().filter(|l| !l.words.is_empty()) {
+        println!("{:<19} {}", format!("{}:", layer.source), layer.words.join(" "));
+    }
+    
+    let command = Args::command();
+    let merged = command.clone().try_get_matches_from(&args.command_line)?;
+    let layers: Vec<(Source, ArgMatches)> = args.layers.iter()
+        .filter_map(|layer| {
+            let words = std::iter::once("icx-rustc").chain(layer.words.iter().map(String::as_str));
+            Some((layer.source, command.clone().try_get_matches_from(words).ok()?))
+        })
+        .collect();
+    
+    println!();
+    println!("Options:");
+    let mut rows = Vec::new();
+    let mut sources = BTreeMap::new();
+    for arg in command.get_arguments().filter(|a| !a.is_hide_set() && !a.is_positional() && a.get_id() != "show_config") {
+        let id = arg.get_id().as_str();
+        let takes_value = arg.get_action().takes_values();
+        let shown = spelling(arg, &raw_values(&merged, id));
+        match merged.value_source(id) {
+            Some(ValueSource::CommandLine) => {}
+            Some(ValueSource::DefaultValue) if takes_value => {
+                rows.push((shown, "default".to_string()));
+                continue;
+            }
+            _ => continue,
+        }
+        let from: Vec<(Source, Vec<String>)> = layers.iter()
+            .filter(|(_, m)| m.value_source(id) == Some(ValueSource::CommandLine))
+            .map(|(source, m)| (*source, raw_values(m, id)))
+            .collect();
+        let source = match (arg.get_action(), &from[..]) {
+            (_, []) => "command line".to_string(),
+            // 列表选项（/D、/Qhook）各来源的值依次累加
+            (ArgAction::Append, from) => {
+                let mut names: Vec<String> = from.iter().map(|(s, _)| s.to_string()).collect();
+                names.dedup();
+                names.join(" + ")
+            }
+            (_, [earlier @ .., (last, _)]) => {
+                let overrides: Vec<String> = earlier.iter()
+                    .map(|(source, values)| format!("{} {}", source, spelling(arg, values)))
+                    .collect();
+                if overrides.is_empty() {
+                    last.to_string()
+                } else {
+                    format!("{} (overrides {})", last, overrides.join(", "))
+                }
+            }
+        };
+        rows.push((shown, source));
+        sources.insert(id.to_string(), from.last().map_or(Source::CommandLine, |(s, _)| *s));
+    }
+    print_rows(&rows);
+    
+    // 由环境变量或配置文件决定、命令行可覆盖的设置
+    println!();
+    println!("Settings:");
+    let mut rows = Vec::new();
+    let option = |id: &str| sources.get(id).map(|s| format!("{}", s));
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let rustc = crate::translator::rustc_executable(args)?;
+    let rustc_source = option("rustc")
+        .or_else(|| var("ICX_RUSTC").map(|_| "ICX_RUSTC".to_string()))
+        .or_else(|| option("toolchain").map(|s| format!("{} (/toolchain)", s)))
+        .unwrap_or_else(|| "default (PATH)".to_string());
+    rows.push((format!("rustc: {}", rustc), rustc_source));
+    if let Some(version) = crate::translator::RustcVersion::detect(&rustc) {
+        rows.push((format!("rustc version: {}", version), "rustc -vV".to_string()));
+    }
+    if let Some(host) = args.remote.clone().or_else(|| var("ICX_REMOTE")) {
+        rows.push((format!("remote: {}", host), option("remote").unwrap_or_else(|| "ICX_REMOTE".to_string())));
+    }
+    if let Some(backend) = args.cache.clone().or_else(|| var("ICX_CACHE_WRAPPER")) {
+        rows.push((format!("cache: {}", backend), option("cache").unwrap_or_else(|| "ICX_CACHE_WRAPPER".to_string())));
+    }
+    if let Some(path) = crate::audit::path(&config) {
+        let source = match (var("ICX_AUDIT_LOG"), &config.audit_log) {
+            (Some(_), Some(_)) => "ICX_AUDIT_LOG (overrides config file)",
+            (Some(_), None) => "ICX_AUDIT_LOG",
+            _ => "config file",
+        };
+        rows.push((format!("audit log: {}", path.display()), source.to_string()));
+    }
+    for (stage, commands) in &config.hooks {
+        for command in commands.list() {
+            rows.push((format!("{} hook: {}", stage, command), "config file".to_string()));
+        }
+    }
+    let color = match args.color {
+        ColorChoice::Auto if var("NO_COLOR").is_some() => Some(("never", "NO_COLOR")),
+        ColorChoice::Auto if var("CLICOLOR_FORCE").is_some_and(|v| v != "0") => Some(("always", "CLICOLOR_FORCE")),
+        _ => None,
+    };
+    if let Some((when, name)) = color {
+        rows.push((format!("color: {}", when), name.to_string()));
+    }
+    print_rows(&rows);
+    Ok(crate::exit_code::SUCCESS)
+}
+
+/// `  entry  source`, with the sources aligned after the longest entry
+fn print_rows(rows: &[(String, String)]) {
+    let width = rows.iter().map(|(entry, _)| entry.chars().count()).max().unwrap_or_default();
+    for (entry, source) in rows {
+        println!("  {:<width$}  {}", entry, source, width = width);
+    }
+}
+
+fn raw_values(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches.get_raw(id)
+        .map(|values| values.map(|v| v.to_string_lossy().into_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// An option as it would be written, `/Qcolor:always`, `/DNAME`, `/MP`
+fn spelling(arg: &Arg, values: &[String]) -> String {
+    let name = arg.get_long().map(str::to_string)
+        .or_else(|| arg.get_short().map(String::from))
+        .unwrap_or_default();
+    if !arg.get_action().takes_values() || values.is_empty() {
+        return format!("/{}", name);
+    }
+    let one = |value: &String| match value.as_str() {
+        // 值可省略时（/MP、/Qdiag-file）为空
+        "" => format!("/{}", name),
+        value if arg.get_short().is_some_and(|s| name == s.to_string()) => format!("/{}{}", name, value),
+        value => format!("/{}:{}", name, value),
+    };
+    values.iter().map(one).collect::<Vec<_>>().join(" ")
+}
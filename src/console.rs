@@ -0,0 +1,128 @@
+use crate::cli::ColorChoice;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+
+/// Which standard stream a line belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+type Sink = Box<dyn FnMut(Stream, &str) + Send>;
+
+static SINK: Mutex<Option<Sink>> = Mutex::new(None);
+
+/// Live status line at the bottom of the terminal (batch progress)
+static STATUS: Mutex<Option<String>> = Mutex::new(None);
+
+/// Decides whether this invocation prints ANSI colors
+///
+/// `auto` colors only when stderr, where diagnostics go, is a terminal
+/// other than `TERM=dumb`; a non-empty `NO_COLOR` turns it off and
+/// `CLICOLOR_FORCE` on.
+pub fn init_color(choice: ColorChoice) {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto if var("NO_COLOR").is_some() => false,
+        ColorChoice::Auto if var("CLICOLOR_FORCE").is_some_and(|v| v != "0") => true,
+        ColorChoice::Auto => std::io::stderr().is_terminal() && var("TERM").as_deref() != Some("dumb"),
+    };
+    colored::control::set_override(enabled);
+}
+
+/// Redirects compiler output, e.g. to a --daemon client; `None` restores the terminal
+pub fn set_sink(sink: Option<Sink>) {
+    if let Ok(mut current) = SINK.lock() {
+        *current = sink;
+    }
+}
+
+/// Whether output currently goes to a --daemon client instead of this terminal
+pub fn is_redirected() -> bool {
+    SINK.lock().map(|s| s.is_some()).unwrap_or(false)
+}
+
+/// Shows (or with `None` removes) the live status line on stderr
+pub fn set_status(text: Option<String>) {
+    let mut status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+    eprint!("\r\x1b[K");
+    if let Some(text) = &text {
+        eprint!("{}", text);
+    }
+    *status = text;
+}
+
+/// Columns of the terminal on stderr; `None` when it is not a terminal
+///
+/// `COLUMNS` overrides the measured width (but does not make a pipe wrap).
+pub fn width() -> Option<usize> {
+    if !std::io::stderr().is_terminal() {
+        return None;
+    }
+    std::env::var("COLUMNS").ok()
+        .and_then(|c| c.parse().ok())
+        .or_else(terminal_columns)
+        .filter(|&c| c > 0)
+}
+
+#[cfg(unix)]
+fn terminal_columns() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    ok.then_some(size.ws_col as usize)
+}
+
+#[cfg(windows)]
+fn terminal_columns() -> Option<usize> {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO, STD_ERROR_HANDLE,
+    };
+    let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
+    let ok = unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STD_ERROR_HANDLE), &mut info) } != 0;
+    // 可见窗口宽度，而不是缓冲区宽度
+    ok.then(|| (info.srWindow.Right - info.srWindow.Left + 1) as usize)
+}
+
+/// Prints a line to stdout (or the current sink)
+pub fn print(text: &str) {
+    write(Stream::Stdout, text);
+}
+
+/// Writes a whole document (/help, --man, a completion script) to stdout;
+/// a reader that stops early (`| head`) is no error
+pub fn print_document(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match stdout.write_all(text.as_bytes()).and_then(|()| stdout.flush()) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+/// Prints a line to stderr (or the current sink)
+pub fn eprint(text: &str) {
+    write(Stream::Stderr, text);
+}
+
+fn write(stream: Stream, text: &str) {
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = sink.as_mut() {
+        sink(stream, text);
+        return;
+    }
+    
+    // 输出前擦除状态行，输出后重新绘制
+    let status = STATUS.lock().unwrap_or_else(|e| e.into_inner());
+    if status.is_some() {
+        eprint!("\r\x1b[K");
+    }
+    match stream {
+        Stream::Stdout => println!("{}", text),
+        Stream::Stderr => eprintln!("{}", text),
+    }
+    if let Some(status) = status.as_ref() {
+        eprint!("{}", status);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::cli::{Args, CoverageFormat};
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use crate::translator::{self, rustc_query};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Standard library and registry sources are left out of the report
+const IGNORED: &str = r"/rustc/|[\\/]\.cargo[\\/]registry[\\/]|[\\/]\.rustup[\\/]";
+
+/// /Qcov-report[:summary|html|lcov]: coverage of binaries built with /Qcov
+///
+/// The inputs are the instrumented binaries, after they ran. The
+/// `.profraw` files they wrote (named in the inputs, or else all of them
+/// in the current directory) are merged into `<binary>.profdata` by
+/// llvm-profdata, then llvm-cov prints a per-file summary, writes an
+/// HTML report (`coverage/index.html`) or an lcov trace (`lcov.info`);
+/// /o names another directory or file. The llvm-tools of the toolchain
+/// are preferred, since their profile format matches the compiler's.
+pub fn report(args: &Args, format: CoverageFormat) -> Result<i32> {
+    let (mut profiles, binaries): (Vec<PathBuf>, Vec<PathBuf>) = args.files.iter().cloned()
+        .partition(|f| f.extension().is_some_and(|e| e == "profraw"));
+    let Some(binary) = binaries.first() else {
+        console::eprint(&format!(
+            "{}: /Qcov-report needs the instrumented binary (built with /Qcov, then run)",
+            i18n::t("icx-rustc error").bright_red().bold()
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    };
+    if profiles.is_empty() {
+        profiles = std::fs::read_dir(".").context("Failed to read current directory")?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "profraw"))
+            .collect();
+        profiles.sort();
+    }
+    if profiles.is_empty() {
+        console::eprint(&format!(
+            "{}: no .profraw files; run {} first (LLVM_PROFILE_FILE names them)",
+            i18n::t("icx-rustc error").bright_red().bold(),
+            binary.display()
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    }
+    
+    let rustc = translator::rustc_executable(args)?;
+    let mut data = binary.clone().into_os_string();
+    data.push(".profdata");
+    let data = PathBuf::from(data);
+    let (profdata, toolchain) = tool(&rustc, "ICX_LLVM_PROFDATA", "llvm-profdata")?;
+    let mut merge = Command::new(&profdata);
+    merge.args(["merge", "-sparse"]).args(&profiles).arg("-o").arg(&data);
+    let code = run(merge)?;
+    if code != exit_code::SUCCESS {
+        // PATH 上的 LLVM 通常比 rustc 的旧，读不了新版本的 .profraw
+        if !toolchain {
+            console::eprint(&format!(
+                "[icx-rustc] warning: {} may be older than the compiler's LLVM; try rustup component add llvm-tools",
+                profdata.display()
+            ));
+        }
+        return Ok(code);
+    }
+    
+    let mut cov = Command::new(tool(&rustc, "ICX_LLVM_COV", "llvm-cov")?.0);
+    cov.arg(match format {
+        CoverageFormat::Summary => "report",
+        CoverageFormat::Html => "show",
+        CoverageFormat::Lcov => "export",
+    });
+    cov.arg(binary);
+    // 其余二进制作为附加目标文件（同一次运行的多个程序）
+    for other in &binaries[1..] {
+        cov.arg("-object").arg(other);
+    }
+    cov.arg(format!("-instr-profile={}", data.display()));
+    cov.arg(format!("-ignore-filename-regex={}", IGNORED));
+    match format {
+        CoverageFormat::Summary => run(cov),
+        CoverageFormat::Html => {
+            let dir = args.output.clone().unwrap_or_else(|| PathBuf::from("coverage"));
+            cov.args(["-format=html", "-show-line-counts-or-regions", "-show-instantiations=false"]);
+            cov.arg(format!("-output-dir={}", dir.display()));
+            let code = run(cov)?;
+            if code == exit_code::SUCCESS && !args.quiet {
+                console::eprint(&format!("[icx-rustc] wrote {}", dir.join("index.html").display()));
+            }
+            Ok(code)
+        }
+        CoverageFormat::Lcov => {
+            let file = args.output.clone().unwrap_or_else(|| PathBuf::from("lcov.info"));
+            cov.arg("-format=lcov");
+            let output = cov.output().context("Failed to run llvm-cov")?;
+            for line in String::from_utf8_lossy(&output.stderr).lines() {
+                console::eprint(line);
+            }
+            if !output.status.success() {
+                return Ok(exit_code::COMPILE_ERROR);
+            }
+            std::fs::write(&file, &output.stdout).with_context(|| format!("Failed to write {}", file.display()))?;
+            if !args.quiet {
+                console::eprint(&format!("[icx-rustc] wrote {}", file.display()));
+            }
+            Ok(exit_code::SUCCESS)
+        }
+    }
+}
+
+/// `ICX_LLVM_*`, the toolchain's llvm-tools component, or PATH; whether it is the toolchain's
+fn tool(rustc: &str, variable: &str, name: &str) -> Result<(PathBuf, bool)> {
+    if let Some(program) = std::env::var_os(variable) {
+        return Ok((PathBuf::from(program), true));
+    }
+    let file = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    let sysroot = rustc_query(rustc, &["--print", "sysroot"]);
+    let host = rustc_query(rustc, &["-vV"])
+        .and_then(|v| v.lines().find_map(|l| l.strip_prefix("host: ").map(str::to_string)));
+    if let (Some(sysroot), Some(host)) = (sysroot, host) {
+        let path = Path::new(sysroot.trim()).join("lib/rustlib").join(host).join("bin").join(&file);
+        if path.is_file() {
+            return Ok((path, true));
+        }
+    }
+    which::which(name).map(|path| (path, false)).with_context(|| format!(
+        "/Qcov-report needs {} (rustup component add llvm-tools, or set {})", name, variable
+    ))
+}
+
+fn run(mut command: Command) -> Result<i32> {
+    let status = command.status().with_context(|| format!("Failed to run {}", command.get_program().to_string_lossy()))?;
+    Ok(match status.code() {
+        Some(0) => exit_code::SUCCESS,
+        Some(_) => exit_code::COMPILE_ERROR,
+        None => exit_code::KILLED,
+    })
+}
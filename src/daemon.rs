@@ -0,0 +1,333 @@
+use crate::cli::{self, Args};
+use crate::exit_code;
+use crate::hyperlink;
+use crate::i18n;
+use crate::interrupt;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Environment variables that only make sense in the client process
+const LOCAL_ENV: &[&str] = &["MAKEFLAGS", "MFLAGS", "CARGO_MAKEFLAGS"];
+
+/// One compile request; the command line is already normalized by the client
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    cwd: PathBuf,
+    args: Vec<String>,
+    /// The client's arguments as given (/Qbuild-report)
+    #[serde(default)]
+    original: Vec<String>,
+    env: Vec<(String, String)>,
+    color: bool,
+}
+
+/// Argument that starts the worker process for one --daemon request
+pub const WORKER: &str = "--daemon-worker";
+
+/// Runs the compile server (--daemon) until the process is killed
+///
+/// Each request runs in its own worker process, started with the client's
+/// environment and working directory, so parallel clients (`make -j8`)
+/// compile side by side and nothing a client chooses outlives its request.
+/// Compiler queries come from the store in the icx cache directory and
+/// every build reuses the incremental state there. Output is streamed back
+/// line by line: `1` stdout, `2` stderr, `x` exit code.
+pub fn serve() -> Result<i32> {
+    let listener = ipc::bind()?;
+    eprintln!(
+        "{} daemon listening on {}",
+        "[icx-rustc]".bright_blue().bold(),
+        listener.describe()
+    );
+
+    loop {
+        let stream = match listener.accept() {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("[icx-rustc] warning: daemon connection failed: {}", e);
+                continue;
+            }
+        };
+        std::thread::spawn(move || {
+            if let Err(e) = handle(stream) {
+                eprintln!("[icx-rustc] warning: daemon request failed: {}", e);
+            }
+        });
+    }
+}
+
+fn handle(stream: ipc::Stream) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut hello = String::new();
+    reader.read_line(&mut hello)?;
+    anyhow::ensure!(hello.trim_end() == ipc::hello(), "rejected client (version or token mismatch)");
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let request: Request = serde_json::from_str(&line).context("Malformed daemon request")?;
+
+    let writer = Arc::new(Mutex::new(stream));
+    let mut command = Command::new(std::env::current_exe().context("Failed to locate icx-rustc")?);
+    command.arg(WORKER)
+        .env_clear()
+        .envs(request.env.iter().map(|(k, v)| (k, v)))
+        .current_dir(&request.cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let mut stream = writer.lock().unwrap_or_else(|e| e.into_inner());
+            writeln!(stream, "2icx-rustc error: Failed to start a worker in {}: {}", request.cwd.display(), e)?;
+            writeln!(stream, "x{}", exit_code::INTERNAL_ERROR)?;
+            return Ok(());
+        }
+    };
+    let _tracked = interrupt::track(&child);
+    if let Some(mut stdin) = child.stdin.take() {
+        serde_json::to_writer(&mut stdin, &request)?;
+    }
+
+    let stdout = child.stdout.take().map(|out| {
+        let writer = Arc::clone(&writer);
+        std::thread::spawn(move || relay(out, '1', &writer))
+    });
+    let mut connected = child.stderr.take().is_none_or(|err| relay(err, '2', &writer));
+    // 客户端已断开（Ctrl+C）：结束 worker 及其编译器
+    if !connected {
+        stop(&mut child);
+    }
+    connected &= stdout.is_none_or(|t| t.join().unwrap_or(false));
+    let code = child.wait()?.code().unwrap_or(exit_code::INTERNAL_ERROR);
+    if connected {
+        writeln!(writer.lock().unwrap_or_else(|e| e.into_inner()), "x{}", code)?;
+    }
+    Ok(())
+}
+
+/// Forwards a worker's output lines with their tag; false once the client is gone
+fn relay(output: impl std::io::Read, tag: char, writer: &Mutex<ipc::Stream>) -> bool {
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+    let mut connected = true;
+    while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+        let text = String::from_utf8_lossy(&line);
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        if connected {
+            let mut stream = writer.lock().unwrap_or_else(|e| e.into_inner());
+            connected = writeln!(stream, "{}{}", tag, text).is_ok();
+        }
+        line.clear();
+    }
+    connected
+}
+
+/// Ends a worker the way Ctrl+C would, so it takes its compilers down too
+#[cfg(unix)]
+fn stop(child: &mut Child) {
+    // 安全性：向 worker 自己的进程组发送信号，不涉及内存
+    unsafe { libc::kill(-(child.id() as i32), libc::SIGTERM) };
+}
+
+/// Ends a worker; its job object takes its compilers down with it
+#[cfg(windows)]
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+}
+
+/// Runs one request in the worker process [`serve`] started for it
+///
+/// The environment and working directory are already the client's; the
+/// request comes on stdin, and stdout and stderr go back to the client.
+pub fn work() -> Result<i32> {
+    let request: Request = serde_json::from_reader(std::io::stdin().lock()).context("Malformed daemon request")?;
+    colored::control::set_override(request.color);
+    interrupt::install(false);
+
+    let code = match cli::parse_normalized(request.args) {
+        Ok(mut args) => {
+            args.original = request.original;
+            // 语言取自客户端的环境与参数
+            i18n::init(args.locale.as_deref());
+            crate::driver::compile(&args, true).unwrap_or_else(|e| {
+                crate::driver::report_error(&e);
+                exit_code::INTERNAL_ERROR
+            })
+        }
+        Err(e) => {
+            crate::driver::report_error(&e);
+            exit_code::USAGE_ERROR
+        }
+    };
+    Ok(code)
+}
+
+/// Sends the invocation to a running daemon; `None` means compile locally
+///
+/// Watch mode and /Qlog stay local, as does everything when no daemon is
+/// listening or `ICX_NO_DAEMON` is set.
+pub fn forward(args: &Args) -> Option<i32> {
+    if args.watch || args.log.is_some() || std::env::var_os("ICX_NO_DAEMON").is_some() {
+        return None;
+    }
+    let mut stream = ipc::connect()?;
+
+    let request = Request {
+        cwd: std::env::current_dir().ok()?,
+        args: args.command_line.clone(),
+        original: args.original.clone(),
+        env: std::env::vars()
+            .filter(|(k, _)| !LOCAL_ENV.contains(&k.as_str()) && k != "ICX_HYPERLINKS")
+            // worker 的输出经管道转发：是否使用超链接由客户端的终端决定
+            .chain([("ICX_HYPERLINKS".to_string(), if hyperlink::enabled() { "1" } else { "0" }.to_string())])
+            .collect(),
+        color: colored::control::SHOULD_COLORIZE.should_colorize(),
+    };
+    let request = serde_json::to_string(&request).ok()?;
+    writeln!(stream, "{}\n{}", ipc::hello(), request).ok()?;
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match line.split_at_checked(1) {
+            Some(("1", text)) => println!("{}", text),
+            Some(("2", text)) => eprintln!("{}", text),
+            Some(("x", code)) => return Some(code.parse().unwrap_or(exit_code::INTERNAL_ERROR)),
+            _ => {}
+        }
+    }
+
+    // 输出可能已部分显示，不再本地重新编译
+    eprintln!("{}: daemon connection lost", "icx-rustc error".bright_red().bold());
+    Some(exit_code::INTERNAL_ERROR)
+}
+
+/// Unix domain socket in the icx cache directory, readable by the owner only
+#[cfg(unix)]
+mod ipc {
+    use crate::cache::cache_root;
+    use anyhow::{Context, Result};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::PathBuf;
+
+    pub type Stream = UnixStream;
+
+    pub struct Listener(UnixListener, PathBuf);
+
+    fn socket_path() -> PathBuf {
+        cache_root().join("daemon.sock")
+    }
+
+    pub fn hello() -> String {
+        format!("icx-rustc {}", env!("CARGO_PKG_VERSION"))
+    }
+
+    pub fn connect() -> Option<Stream> {
+        UnixStream::connect(socket_path()).ok()
+    }
+
+    pub fn bind() -> Result<Listener> {
+        let path = socket_path();
+        anyhow::ensure!(connect().is_none(), "A daemon is already listening on {}", path.display());
+
+        // 上次未正常退出留下的套接字文件
+        let _ = std::fs::remove_file(&path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        Ok(Listener(listener, path))
+    }
+
+    impl Listener {
+        pub fn accept(&self) -> std::io::Result<Stream> {
+            self.0.accept().map(|(stream, _)| stream)
+        }
+
+        pub fn describe(&self) -> String {
+            self.1.display().to_string()
+        }
+    }
+}
+
+/// Loopback TCP; the port and a random token are published in the cache directory
+#[cfg(windows)]
+mod ipc {
+    use crate::cache::cache_root;
+    use anyhow::{Context, Result};
+    use std::hash::{BuildHasher, Hasher};
+    use std::net::{Ipv4Addr, TcpListener, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    pub type Stream = TcpStream;
+
+    pub struct Listener(TcpListener);
+
+    static TOKEN: OnceLock<String> = OnceLock::new();
+
+    fn port_file() -> PathBuf {
+        cache_root().join("daemon.port")
+    }
+
+    pub fn hello() -> String {
+        let token = TOKEN.get().map(String::as_str).unwrap_or_default();
+        format!("icx-rustc {} {}", env!("CARGO_PKG_VERSION"), token)
+    }
+
+    pub fn connect() -> Option<Stream> {
+        let published = std::fs::read_to_string(port_file()).ok()?;
+        let (port, token) = published.trim().split_once(' ')?;
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port.parse().ok()?)).ok()?;
+        TOKEN.get_or_init(|| token.to_string());
+        Some(stream)
+    }
+
+    pub fn bind() -> Result<Listener> {
+        anyhow::ensure!(connect().is_none(), "A daemon is already listening ({})", port_file().display());
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .context("Failed to listen on the loopback interface")?;
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        let token = format!("{:016x}", hasher.finish());
+
+        let path = port_file();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&path, format!("{} {}", listener.local_addr()?.port(), token))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        TOKEN.get_or_init(|| token);
+
+        Ok(Listener(listener))
+    }
+
+    impl Listener {
+        pub fn accept(&self) -> std::io::Result<Stream> {
+            self.0.accept().map(|(stream, _)| stream)
+        }
+
+        pub fn describe(&self) -> String {
+            self.0.local_addr().map(|a| a.to_string()).unwrap_or_default()
+        }
+    }
+}
@@ -0,0 +1,349 @@
+/// Rust symbol names as paths: `_ZN4demo4main17h0123456789abcdefE` -> `demo::main`
+///
+/// Both manglings rustc uses are understood: legacy (`_ZN...E`, user
+/// code by default) and v0 (`_R...`, the standard library). Hashes and
+/// crate disambiguators are dropped; other symbols (C functions) are
+/// returned as they are.
+pub fn demangle(symbol: &str) -> String {
+    // Mach-O 的符号多一个前导下划线；.llvm.<n> 之类是 LLVM 加的后缀
+    let symbol_name = symbol.split('.').next().unwrap_or(symbol);
+    let unprefixed = symbol_name.strip_prefix('_').filter(|s| s.starts_with("_ZN") || s.starts_with("_R")).unwrap_or(symbol_name);
+    if let Some(rest) = unprefixed.strip_prefix("_ZN") {
+        return legacy(rest).unwrap_or_else(|| symbol.to_string());
+    }
+    if let Some(rest) = unprefixed.strip_prefix("_R") {
+        let mut parser = V0 { s: rest.as_bytes(), pos: 0, depth: 0 };
+        // 可选的编码版本号
+        while parser.peek().is_some_and(|b| b.is_ascii_digit()) {
+            parser.pos += 1;
+        }
+        return parser.path(true).unwrap_or_else(|| symbol.to_string());
+    }
+    symbol.to_string()
+}
+
+fn legacy(mut rest: &str) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    while !rest.starts_with('E') {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let len = rest[..digits].parse::<usize>().ok().filter(|&l| digits > 0 && rest.len() >= digits + l)?;
+        parts.push(rest.get(digits..digits + len)?.to_string());
+        rest = &rest[digits + len..];
+    }
+    // 末尾的 h<16 位十六进制> 是哈希
+    if parts.last().is_some_and(|p| p.len() == 17 && p.starts_with('h') && p[1..].bytes().all(|b| b.is_ascii_hexdigit())) {
+        parts.pop();
+    }
+    let path = parts.iter()
+        .map(|p| p.strip_prefix('_').filter(|s| s.starts_with('$')).unwrap_or(p))
+        .collect::<Vec<_>>()
+        .join("::");
+    const ESCAPES: &[(&str, &str)] = &[
+        ("$LT$", "<"), ("$GT$", ">"), ("$RF$", "&"), ("$BP$", "*"), ("$C$", ","),
+        ("$u20$", " "), ("$u27$", "'"), ("$u5b$", "["), ("$u5d$", "]"), ("$u7b$", "{"),
+        ("$u7d$", "}"), ("$u7e$", "~"), ("$SP$", "@"), ("..", "::"),
+    ];
+    Some(ESCAPES.iter().fold(path, |path, (from, to)| path.replace(from, to)))
+}
+
+/// Backreferences make the grammar recursive; malformed input stops here
+const MAX_DEPTH: u32 = 64;
+
+/// A v0 symbol after `_R` (RFC 2603); backreferences are offsets into it
+struct V0<'a> {
+    s: &'a [u8],
+    pos: usize,
+    depth: u32,
+}
+
+impl V0<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+    
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+    
+    fn eat(&mut self, b: u8) -> bool {
+        let found = self.peek() == Some(b);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+    
+    /// `_` is 0, otherwise the digits plus one
+    fn base62(&mut self) -> Option<u64> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+        let mut value: u64 = 0;
+        loop {
+            let digit = match self.next()? {
+                b @ b'0'..=b'9' => b - b'0',
+                b @ b'a'..=b'z' => b - b'a' + 10,
+                b @ b'A'..=b'Z' => b - b'A' + 36,
+                b'_' => return value.checked_add(1),
+                _ => return None,
+            };
+            value = value.checked_mul(62)?.checked_add(digit as u64)?;
+        }
+    }
+    
+    /// `s<base62>` or nothing (0)
+    fn disambiguator(&mut self) -> Option<u64> {
+        if self.eat(b's') {
+            return self.base62()?.checked_add(1);
+        }
+        Some(0)
+    }
+    
+    fn decimal(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos]).ok()?.parse().ok()
+    }
+    
+    fn identifier(&mut self) -> Option<String> {
+        self.disambiguator()?;
+        self.undisambiguated()
+    }
+    
+    fn undisambiguated(&mut self) -> Option<String> {
+        // u：Punycode 编码的非 ASCII 名称，原样显示
+        let punycode = self.eat(b'u');
+        let len = self.decimal()?;
+        self.eat(b'_');
+        let bytes = self.s.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        let name = String::from_utf8_lossy(bytes).into_owned();
+        Some(if punycode { format!("{}[punycode]", name) } else { name })
+    }
+    
+    /// Continues at the offset of a `B` backreference, then comes back
+    fn backref<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        let target = self.base62()? as usize;
+        if target >= self.pos || self.depth >= MAX_DEPTH {
+            return None;
+        }
+        let saved = self.pos;
+        self.pos = target;
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        self.pos = saved;
+        result
+    }
+    
+    /// `value` paths write generics as `::<T>` (functions), types as `<T>`
+    fn path(&mut self, value: bool) -> Option<String> {
+        self.depth += 1;
+        let result = self.path_inner(value);
+        self.depth -= 1;
+        result
+    }
+    
+    fn path_inner(&mut self, value: bool) -> Option<String> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        Some(match self.next()? {
+            b'C' => self.identifier()?,
+            b'M' => {
+                self.disambiguator()?;
+                self.path(false)?;
+                format!("<{}>", self.ty()?)
+            }
+            b'X' => {
+                self.disambiguator()?;
+                self.path(false)?;
+                let ty = self.ty()?;
+                format!("<{} as {}>", ty, self.path(false)?)
+            }
+            b'Y' => {
+                let ty = self.ty()?;
+                format!("<{} as {}>", ty, self.path(false)?)
+            }
+            b'N' => {
+                let namespace = self.next()?;
+                let parent = self.path(value)?;
+                let disambiguator = self.disambiguator()?;
+                let name = self.undisambiguated()?;
+                match namespace {
+                    b'C' => format!("{}::{{closure#{}}}", parent, disambiguator),
+                    b'S' => format!("{}::{{shim:{}#{}}}", parent, name, disambiguator),
+                    b'A'..=b'Z' => format!("{}::{{{}:{}#{}}}", parent, namespace as char, name, disambiguator),
+                    _ => format!("{}::{}", parent, name),
+                }
+            }
+            b'I' => {
+                let base = self.path(value)?;
+                let mut args = Vec::new();
+                while !self.eat(b'E') {
+                    if let Some(arg) = self.generic_arg()? {
+                        args.push(arg);
+                    }
+                }
+                let separator = if value { "::" } else { "" };
+                format!("{}{}<{}>", base, separator, args.join(", "))
+            }
+            b'B' => self.backref(|p| p.path(value))?,
+            _ => return None,
+        })
+    }
+    
+    /// A type or const; lifetimes are left out (`None`)
+    fn generic_arg(&mut self) -> Option<Option<String>> {
+        if self.eat(b'L') {
+            self.base62()?;
+            return Some(None);
+        }
+        if self.eat(b'K') {
+            return self.constant().map(Some);
+        }
+        self.ty().map(Some)
+    }
+    
+    fn ty(&mut self) -> Option<String> {
+        self.depth += 1;
+        let result = self.ty_inner();
+        self.depth -= 1;
+        result
+    }
+    
+    fn ty_inner(&mut self) -> Option<String> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        let basic = match self.peek()? {
+            b'a' => "i8", b'b' => "bool", b'c' => "char", b'd' => "f64", b'e' => "str",
+            b'f' => "f32", b'h' => "u8", b'i' => "isize", b'j' => "usize", b'l' => "i32",
+            b'm' => "u32", b'n' => "i128", b'o' => "u128", b's' => "i16", b't' => "u16",
+            b'u' => "()", b'v' => "...", b'x' => "i64", b'y' => "u64", b'z' => "!",
+            b'p' => "_",
+            _ => "",
+        };
+        if !basic.is_empty() {
+            self.pos += 1;
+            return Some(basic.to_string());
+        }
+        Some(match self.peek()? {
+            b'C' | b'M' | b'X' | b'Y' | b'N' | b'I' => self.path(false)?,
+            b'B' => {
+                self.pos += 1;
+                self.backref(|p| p.ty())?
+            }
+            b'A' => {
+                self.pos += 1;
+                let ty = self.ty()?;
+                format!("[{}; {}]", ty, self.constant()?)
+            }
+            b'S' => {
+                self.pos += 1;
+                format!("[{}]", self.ty()?)
+            }
+            b'T' => {
+                self.pos += 1;
+                let mut types = Vec::new();
+                while !self.eat(b'E') {
+                    types.push(self.ty()?);
+                }
+                if types.len() == 1 {
+                    format!("({},)", types[0])
+                } else {
+                    format!("({})", types.join(", "))
+                }
+            }
+            b'R' | b'Q' => {
+                let mutable = self.next()? == b'Q';
+                if self.eat(b'L') {
+                    self.base62()?;
+                }
+                format!("&{}{}", if mutable { "mut " } else { "" }, self.ty()?)
+            }
+            b'P' | b'O' => {
+                let mutable = self.next()? == b'O';
+                format!("*{} {}", if mutable { "mut" } else { "const" }, self.ty()?)
+            }
+            b'F' => {
+                self.pos += 1;
+                self.fn_sig()?
+            }
+            b'D' => {
+                self.pos += 1;
+                self.binder()?;
+                let mut traits = Vec::new();
+                while !self.eat(b'E') {
+                    let mut path = self.path(false)?;
+                    while self.eat(b'p') {
+                        let name = self.undisambiguated()?;
+                        path = format!("{}<{} = {}>", path, name, self.ty()?);
+                    }
+                    traits.push(path);
+                }
+                // 对象的生命周期
+                if self.eat(b'L') {
+                    self.base62()?;
+                }
+                format!("dyn {}", traits.join(" + "))
+            }
+            _ => return None,
+        })
+    }
+    
+    fn binder(&mut self) -> Option<()> {
+        if self.eat(b'G') {
+            self.base62()?;
+        }
+        Some(())
+    }
+    
+    fn fn_sig(&mut self) -> Option<String> {
+        self.binder()?;
+        let unsafety = if self.eat(b'U') { "unsafe " } else { "" };
+        let abi = if self.eat(b'K') {
+            if self.eat(b'C') { "extern \"C\" ".to_string() } else { format!("extern \"{}\" ", self.undisambiguated()?.replace('_', "-")) }
+        } else {
+            String::new()
+        };
+        let mut params = Vec::new();
+        while !self.eat(b'E') {
+            params.push(self.ty()?);
+        }
+        let ret = self.ty()?;
+        let ret = if ret == "()" { String::new() } else { format!(" -> {}", ret) };
+        Some(format!("{}{}fn({}){}", unsafety, abi, params.join(", "), ret))
+    }
+    
+    /// `<type> <hex>_`, `p` (placeholder) or a backreference
+    fn constant(&mut self) -> Option<String> {
+        if self.eat(b'p') {
+            return Some("_".to_string());
+        }
+        if self.eat(b'B') {
+            return self.backref(|p| p.constant());
+        }
+        let ty = self.ty()?;
+        let negative = self.eat(b'n');
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_hexdigit()) {
+            self.pos += 1;
+        }
+        let hex = std::str::from_utf8(&self.s[start..self.pos]).ok()?;
+        if !self.eat(b'_') {
+            return None;
+        }
+        let value = u128::from_str_radix(if hex.is_empty() { "0" } else { hex }, 16).ok()?;
+        Some(match ty.as_str() {
+            "bool" => (value != 0).to_string(),
+            "char" => char::from_u32(value as u32).map_or(value.to_string(), |c| format!("{:?}", c)),
+            _ if negative => format!("-{}", value),
+            _ => value.to_string(),
+        })
+    }
+}
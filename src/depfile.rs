@@ -0,0 +1,166 @@
+use crate::cache;
+use crate::cc::{self, CStep};
+use crate::cli::Args;
+use crate::console;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// /QMMD: the make-style dependency file written after the compile
+#[derive(Debug, Clone)]
+pub struct Depfile {
+    /// Where the build system expects it (default: `<output>.d`)
+    pub path: PathBuf,
+    /// The file the rule is for: the output as the wrapper named it
+    pub target: PathBuf,
+    /// Dependency files of the C and C++ compiles, merged into `path`
+    pub parts: Vec<PathBuf>,
+}
+
+/// /QMMD[:file]: a depfile for make and ninja, as gcc -MMD writes it
+///
+/// rustc's dep-info names the `.d` file itself as a second target,
+/// which ninja rejects, and knows nothing of the C and C++ sources,
+/// resources or objects linked in. rustc writes its part to the depfile,
+/// gcc and clang write theirs next to each object (`-MMD -MF`), and
+/// [`rewrite`] combines them into one rule for the final output. A
+/// compile of C sources only keeps the compilers' own depfiles.
+pub fn translate(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(requested) = &args.mmd else {
+        return Ok(());
+    };
+    let requested = Some(requested).filter(|p| !p.as_os_str().is_empty());
+    
+    if cmd.input_files.is_empty() {
+        let single = cmd.c_steps.len() == 1;
+        for step in &mut cmd.c_steps {
+            let path = match requested {
+                Some(path) if single => path.clone(),
+                _ => step.output.with_extension("d"),
+            };
+            add_compiler_depfile(step, &path);
+        }
+        return Ok(());
+    }
+    
+    let Some(target) = cmd.artifact().or_else(|| cmd.outputs().and_then(|o| o.into_iter().next())) else {
+        console::eprint("[icx-rustc] warning: /QMMD: the output name cannot be predicted, no depfile written");
+        return Ok(());
+    };
+    let path = requested.cloned().unwrap_or_else(|| target.with_extension("d"));
+    if cmd.args.iter().any(|a| a.starts_with("--emit=")) {
+        cmd.args.push(format!("--emit=dep-info={}", path.display()));
+    } else {
+        cmd.args.push(format!("--emit=dep-info={},link", path.display()));
+    }
+    let mut parts = Vec::new();
+    for step in cmd.c_steps.iter_mut().filter(|s| cc::is_source(&s.source)) {
+        let part = step.output.with_extension("d");
+        if add_compiler_depfile(step, &part) {
+            parts.push(part);
+        }
+    }
+    cmd.depfile = Some(Depfile { path, target, parts });
+    Ok(())
+}
+
+/// `-MMD -MF <path>` for gcc and clang; cl has no depfiles
+fn add_compiler_depfile(step: &mut CStep, path: &Path) -> bool {
+    if !step.args.iter().any(|a| a == "-c") {
+        console::eprint(&format!("[icx-rustc] warning: /QMMD: no depfile for {} (cl has none; use clang-cl or gcc)", step.source.display()));
+        return false;
+    }
+    step.args.extend(["-MMD".to_string(), "-MF".to_string(), path.display().to_string()]);
+    true
+}
+
+/// Rewrites rustc's dep-info into one rule for the output after a successful build
+pub fn rewrite(cmd: &RustcCommand, depfile: &Depfile) -> Result<()> {
+    let path = cmd.resolve(&depfile.path);
+    let text = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (mut deps, comments) = parse(&text);
+    
+    for part in &depfile.parts {
+        let part = cmd.resolve(part);
+        if let Ok(text) = std::fs::read_to_string(&part) {
+            deps.extend(parse(&text).0);
+            let _ = std::fs::remove_file(&part);
+        }
+    }
+    // 生成的清单、版本资源等在缓存目录中，每次构建都会重写；C 的目标文件由源文件代表
+    let generated = cache::cache_root();
+    let extra = cmd.c_steps.iter().map(|s| &s.source)
+        .chain(cmd.link_inputs.iter().filter(|o| !cmd.c_steps.iter().any(|s| s.output == **o)))
+        .filter(|p| !cmd.resolve(p).starts_with(&generated))
+        .map(|p| p.display().to_string());
+    deps.extend(extra);
+    let mut seen = HashSet::new();
+    deps.retain(|d| seen.insert(d.clone()));
+    
+    let mut out = format!("{}:", escape(&depfile.target.display().to_string()));
+    for dep in &deps {
+        out.push_str(" \\\n  ");
+        out.push_str(&escape(dep));
+    }
+    out.push_str("\n\n");
+    // 与 gcc -MP 一样，删除的文件不会让 make 报错
+    for dep in &deps {
+        out.push_str(&format!("{}:\n", escape(dep)));
+    }
+    for comment in comments {
+        out.push_str(&format!("\n{}", comment));
+    }
+    std::fs::write(&path, out).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The prerequisites of the first rule and the `# env-dep:` comments
+fn parse(text: &str) -> (Vec<String>, Vec<String>) {
+    let joined = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    let comments = joined.lines().filter(|l| l.starts_with('#')).map(str::to_string).collect();
+    let Some(rule) = joined.lines().find(|l| !l.starts_with('#') && split_rule(l).is_some()) else {
+        return (Vec::new(), comments);
+    };
+    let prerequisites = split_rule(rule).unwrap_or_default();
+    (words(prerequisites), comments)
+}
+
+/// `target: deps` -> `deps`, where the colon may be part of a Windows drive
+fn split_rule(line: &str) -> Option<&str> {
+    let bytes = line.as_bytes();
+    let at = (0..bytes.len()).find(|&i| {
+        bytes[i] == b':' && !(i == 1 && bytes[0].is_ascii_alphabetic()) && !(i > 0 && bytes[i - 1] == b'\\')
+            && bytes.get(i + 1).is_none_or(|b| b.is_ascii_whitespace())
+    })?;
+    Some(&line[at + 1..])
+}
+
+/// Splits at unescaped spaces and undoes the escaping
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(' ') | Some('#')) => word.push(chars.next().unwrap_or(' ')),
+            '$' if chars.peek() == Some(&'$') => {
+                chars.next();
+                word.push('$');
+            }
+            c if c.is_whitespace() => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn escape(path: &str) -> String {
+    path.replace('$', "$$").replace(' ', "\\ ").replace('#', "\\#")
+}
@@ -0,0 +1,1017 @@
+use crate::cache;
+use crate::cli::Args;
+use crate::console;
+use crate::toml;
+use crate::translator::{rustc_query, RustcCommand};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Paths in sources that name the compiler's own crates, never a vendored one
+const BUILTIN: &[&str] = &["std", "core", "alloc", "proc_macro", "test", "self", "super", "crate", "Self"];
+
+/// /Qdeps:vendor_dir: builds the vendored crates a single-file program uses
+///
+/// `vendor_dir` holds one directory per crate with its Cargo.toml, the
+/// layout of `cargo vendor` (or of the registry sources under
+/// `~/.cargo/registry/src`). The crates the inputs name, in `use`,
+/// `extern crate` or a `name::` path, are resolved together with their
+/// dependencies: highest matching versions, default features plus those
+/// of /Qdeps-feature, optional dependencies, target-specific tables,
+/// build scripts and proc-macros. Each crate is compiled once into the
+/// icx cache, keyed by its features, toolchain and codegen settings, and
+/// the program gets the `--extern` flags cargo would pass.
+pub fn translate(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(dir) = &args.deps else {
+        return Ok(());
+    };
+    let packages = scan(dir)?;
+    // crate 目录里的 rust-toolchain.toml 会让 rustup 换编译器，所以直接用 sysroot 中的
+    let rustc = rustc_query(&cmd.executable, &["--print", "sysroot"])
+        .map(|sysroot| Path::new(sysroot.trim()).join("bin").join(format!("rustc{}", std::env::consts::EXE_SUFFIX)))
+        .filter(|path| path.is_file())
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| cmd.executable.clone());
+    let version = rustc_query(&rustc, &["-vV"]).context("Failed to query the rustc version")?;
+    let host = version.lines().find_map(|l| l.strip_prefix("host: ")).context("rustc -vV has no host")?.to_string();
+    let target = cmd.target.clone();
+    let host_cfg = cfg_list(&rustc, None);
+    let target_cfg = match &target {
+        Some(triple) => cfg_list(&rustc, Some(triple)),
+        None => host_cfg.clone(),
+    };
+    let setting = |prefix: &str| cmd.args.iter().rev().find_map(|a| a.strip_prefix(prefix)).map(str::to_string);
+    let mut graph = Graph {
+        packages: &packages,
+        nodes: Vec::new(),
+        index: HashMap::new(),
+        host: host.clone(),
+        target: target.clone().unwrap_or_else(|| host.clone()),
+        cross: target.clone(),
+        host_cfg,
+        target_cfg,
+    };
+    
+    // 根：源代码用到的 crate，以及 /Qdeps-feature 点名的
+    let mut wanted: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in used_crates(&cmd.input_files) {
+        if packages.iter().any(|p| p.lib_name == name) {
+            wanted.entry(name).or_default();
+        }
+    }
+    for spec in &args.deps_features {
+        let (name, feature) = spec.split_once('/').unwrap_or((spec, ""));
+        let name = name.replace('-', "_");
+        if !packages.iter().any(|p| p.lib_name == name) {
+            bail!("/Qdeps-feature: no crate '{}' in {}", name, dir.display());
+        }
+        let features = wanted.entry(name).or_default();
+        if !feature.is_empty() {
+            features.push(feature.to_string());
+        }
+    }
+    let mut roots = Vec::new();
+    for (name, features) in wanted {
+        let package = packages.iter().enumerate()
+            .filter(|(_, p)| p.lib_name == name)
+            .max_by(|a, b| a.1.version.cmp(&b.1.version))
+            .map(|(i, _)| i)
+            .context("vendored crate disappeared")?;
+        let host_only = packages[package].proc_macro;
+        let features = features.into_iter().chain(std::iter::once("default".to_string()));
+        roots.push((name, graph.activate(package, host_only, features).0));
+    }
+    if roots.is_empty() {
+        return Ok(());
+    }
+    graph.resolve()?;
+    
+    let settings = Settings {
+        rustc,
+        version,
+        opt_level: setting("-Copt-level=").unwrap_or_else(|| "0".to_string()),
+        panic: setting("-Cpanic="),
+        dry_run: args.dry_run.is_some(),
+        quiet: args.quiet,
+    };
+    let built = graph.build(&settings)?;
+    
+    for (name, node) in roots {
+        // 与 translator 一样写成一个参数：缓存键、远程编译、/Qup-to-date 与 /Qwatch 只识别 --extern=
+        cmd.args.push(format!("--extern={}={}", name, built[node].artifact.display()));
+    }
+    let mut search = BTreeSet::new();
+    for (node, output) in graph.nodes.iter().zip(&built) {
+        // 重新导出的 proc-macro（serde 的 derive）也要能找到
+        search.insert(format!("dependency={}", output.dir.display()));
+        if !node.host {
+            search.extend(output.script.search.iter().cloned());
+        }
+    }
+    for path in search {
+        cmd.args.push("-L".to_string());
+        cmd.args.push(path);
+    }
+    Ok(())
+}
+
+/// A crate in the vendor directory, from its Cargo.toml
+struct Package {
+    name: String,
+    version: Version,
+    manifest: Value,
+    dir: PathBuf,
+    lib_name: String,
+    lib_path: PathBuf,
+    edition: String,
+    proc_macro: bool,
+    build: Option<PathBuf>,
+    links: Option<String>,
+    features: BTreeMap<String, Vec<String>>,
+    /// Optional dependencies that are features of their own name
+    implicit: BTreeSet<String>,
+    dependencies: Vec<Dependency>,
+}
+
+impl Package {
+    fn version_text(&self) -> &str {
+        self.manifest["package"]["version"].as_str().unwrap_or_default()
+    }
+}
+
+struct Dependency {
+    /// The table key, which is also the extern name when `package` renames it
+    key: String,
+    package: String,
+    renamed: bool,
+    req: String,
+    optional: bool,
+    default_features: bool,
+    features: Vec<String>,
+    target: Option<String>,
+    build: bool,
+}
+
+/// Every crate directory of `dir` with a library target
+///
+/// The package paths are made absolute: crates compile from their own
+/// directory, not the one a relative `/Qdeps` was given against.
+fn scan(dir: &Path) -> Result<Vec<Package>> {
+    let dir = std::path::absolute(dir).with_context(|| format!("Failed to read vendor directory {}", dir.display()))?;
+    let entries = std::fs::read_dir(&dir).with_context(|| format!("Failed to read vendor directory {}", dir.display()))?;
+    let mut packages = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let manifest = entry.path().join("Cargo.toml");
+        let Ok(text) = std::fs::read_to_string(&manifest) else {
+            continue;
+        };
+        match toml::parse(&text) {
+            Ok(value) => packages.extend(package(entry.path(), value)),
+            Err(e) => console::eprint(&format!("[icx-rustc] warning: skipping {}: {}", manifest.display(), e)),
+        }
+    }
+    Ok(packages)
+}
+
+fn package(dir: PathBuf, manifest: Value) -> Option<Package> {
+    let info = manifest.get("package")?;
+    let name = info.get("name")?.as_str()?.to_string();
+    let version = Version::parse(info.get("version")?.as_str()?)?;
+    let lib = manifest.get("lib");
+    let lib_path = dir.join(lib.and_then(|l| l.get("path")).and_then(Value::as_str).unwrap_or("src/lib.rs"));
+    if !lib_path.is_file() {
+        return None;
+    }
+    let lib_name = lib.and_then(|l| l.get("name")).and_then(Value::as_str).unwrap_or(&name).replace('-', "_");
+    let proc_macro = lib.and_then(|l| l.get("proc-macro").or_else(|| l.get("proc_macro"))).and_then(Value::as_bool).unwrap_or(false);
+    let edition = info.get("edition").and_then(Value::as_str).unwrap_or("2015").to_string();
+    let build = match info.get("build") {
+        Some(Value::String(path)) => Some(dir.join(path)),
+        Some(Value::Bool(false)) => None,
+        _ => Some(dir.join("build.rs")).filter(|p| p.is_file()),
+    };
+    let links = info.get("links").and_then(Value::as_str).map(str::to_string);
+    
+    let features: BTreeMap<String, Vec<String>> = manifest.get("features").and_then(Value::as_object)
+        .map(|table| table.iter().map(|(k, v)| (k.clone(), strings(v))).collect())
+        .unwrap_or_default();
+    let mut dependencies = Vec::new();
+    dependency_tables(&manifest, None, &mut dependencies);
+    if let Some(targets) = manifest.get("target").and_then(Value::as_object) {
+        for (spec, table) in targets {
+            dependency_tables(table, Some(spec), &mut dependencies);
+        }
+    }
+    // 没有被任何特性以 dep:x 引用的可选依赖，自带同名特性
+    let explicit: BTreeSet<&str> = features.values().flatten().filter_map(|f| f.strip_prefix("dep:")).collect();
+    let implicit = dependencies.iter()
+        .filter(|d| d.optional && !explicit.contains(d.key.as_str()))
+        .map(|d| d.key.clone())
+        .collect();
+    Some(Package {
+        name, version, manifest, dir, lib_name, lib_path, edition, proc_macro, build, links,
+        features, implicit, dependencies,
+    })
+}
+
+/// `[dependencies]` and `[build-dependencies]` of a manifest or a `[target.*]` table
+fn dependency_tables(table: &Value, target: Option<&str>, out: &mut Vec<Dependency>) {
+    for (keys, build) in [(["dependencies", "dependencies"], false), (["build-dependencies", "build_dependencies"], true)] {
+        let Some(deps) = keys.iter().find_map(|k| table.get(*k)).and_then(Value::as_object) else {
+            continue;
+        };
+        for (key, spec) in deps {
+            let get = |name: &str| spec.get(name);
+            let package = get("package").and_then(Value::as_str);
+            out.push(Dependency {
+                key: key.clone(),
+                package: package.unwrap_or(key).to_string(),
+                renamed: package.is_some(),
+                req: spec.as_str().or_else(|| get("version").and_then(Value::as_str)).unwrap_or("*").to_string(),
+                optional: get("optional").and_then(Value::as_bool).unwrap_or(false),
+                default_features: get("default-features").or_else(|| get("default_features"))
+                    .and_then(Value::as_bool).unwrap_or(true),
+                features: get("features").map(strings).unwrap_or_default(),
+                target: target.map(str::to_string),
+                build,
+            });
+        }
+    }
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value.as_array().map(|a| a.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()).unwrap_or_default()
+}
+
+/// Crate names appearing as path roots in the sources (minus local modules)
+pub fn used_crates(files: &[PathBuf]) -> BTreeSet<String> {
+    let import = Regex::new(r"\b(?:use|extern\s+crate)\s+(?:::)?([A-Za-z_]\w*)").expect("valid regex");
+    let path = Regex::new(r"(?:^|[^\w:])(?:::)?([A-Za-z_]\w*)\s*::").expect("valid regex");
+    let module = Regex::new(r"\bmod\s+([A-Za-z_]\w*)").expect("valid regex");
+    let mut names = BTreeSet::new();
+    let mut modules = BTreeSet::new();
+    for file in files {
+        let Ok(source) = std::fs::read_to_string(file) else {
+            continue;
+        };
+        for regex in [&import, &path] {
+            names.extend(regex.captures_iter(&source).map(|c| c[1].to_string()));
+        }
+        modules.extend(module.captures_iter(&source).map(|c| c[1].to_string()));
+    }
+    names.retain(|n| !BUILTIN.contains(&n.as_str()) && !modules.contains(n));
+    names
+}
+
+/// `--print cfg` as `name` and `name="value"` lines
+fn cfg_list(rustc: &str, target: Option<&str>) -> BTreeSet<String> {
+    let mut args = vec!["--print", "cfg"];
+    if let Some(target) = target {
+        args.extend(["--target", target]);
+    }
+    rustc_query(rustc, &args).unwrap_or_default().lines().map(str::to_string).collect()
+}
+
+/// Evaluates a `cfg(...)` predicate body against `--print cfg`
+fn cfg_matches(rest: &mut &str, cfg: &BTreeSet<String>) -> bool {
+    *rest = rest.trim_start();
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+    let name = &rest[..end];
+    *rest = rest[end..].trim_start();
+    if name.is_empty() {
+        *rest = "";
+        return false;
+    }
+    if let Some(after) = rest.strip_prefix('(') {
+        *rest = after;
+        let mut values = Vec::new();
+        loop {
+            *rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(')') {
+                *rest = after;
+                break;
+            }
+            if rest.is_empty() {
+                break;
+            }
+            values.push(cfg_matches(rest, cfg));
+            *rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                *rest = after;
+            }
+        }
+        match name {
+            "all" => values.iter().all(|v| *v),
+            "any" => values.iter().any(|v| *v),
+            "not" => !values.first().copied().unwrap_or(false),
+            _ => false,
+        }
+    } else if let Some(after) = rest.strip_prefix('=') {
+        let after = after.trim_start().strip_prefix('"').unwrap_or(after);
+        let end = after.find('"').unwrap_or(after.len());
+        let value = &after[..end];
+        *rest = after.get(end + 1..).unwrap_or("");
+        cfg.contains(&format!("{}=\"{}\"", name, value))
+    } else {
+        cfg.contains(name)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct Version {
+    numbers: [u64; 3],
+    pre: String,
+}
+
+impl Version {
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.split('+').next()?.trim();
+        let (numbers, pre) = text.split_once('-').unwrap_or((text, ""));
+        let mut parts = numbers.split('.').map(|p| p.parse().ok());
+        let numbers = [parts.next()??, parts.next()??, parts.next()??];
+        Some(Self { numbers, pre: pre.to_string() })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // 预发布版本排在正式版本之前
+        self.numbers.cmp(&other.numbers)
+            .then_with(|| self.pre.is_empty().cmp(&other.pre.is_empty()))
+            .then_with(|| self.pre.cmp(&other.pre))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Cargo's requirement syntax: comma-separated `^` (the default), `~`,
+/// `=`, `>`, `>=`, `<`, `<=` and `*` wildcards
+fn matches(req: &str, version: &Version) -> bool {
+    req.split(',').all(|c| comparator(c.trim(), version))
+}
+
+fn comparator(text: &str, version: &Version) -> bool {
+    let (op, rest) = ["<=", ">=", "<", ">", "=", "^", "~"].iter()
+        .find_map(|op| text.strip_prefix(op).map(|r| (*op, r.trim())))
+        .unwrap_or(("^", text));
+    let (numbers, pre) = rest.split_once('-').unwrap_or((rest, ""));
+    let given: Vec<u64> = numbers.split('.').map_while(|p| p.parse().ok()).collect();
+    if given.is_empty() {
+        return version.pre.is_empty();
+    }
+    let mut lower = [0; 3];
+    lower[..given.len().min(3)].copy_from_slice(&given[..given.len().min(3)]);
+    // 预发布版本只匹配写明了同一版本号预发布的要求
+    if !version.pre.is_empty() && (pre.is_empty() || version.numbers != lower) {
+        return false;
+    }
+    let exact = Version { numbers: lower, pre: pre.to_string() };
+    let order = if given.len() >= 3 {
+        version.cmp(&exact)
+    } else {
+        version.numbers[..given.len()].cmp(&given[..])
+    };
+    let at_least = version >= &exact;
+    match op {
+        "=" => order == Ordering::Equal,
+        ">" => order == Ordering::Greater,
+        ">=" => order != Ordering::Less,
+        "<" => order == Ordering::Less,
+        "<=" => order != Ordering::Greater,
+        "~" => at_least && version.numbers[..given.len().min(2)] == lower[..given.len().min(2)],
+        // ^1.2.3 到 2.0.0，^0.2.3 到 0.3.0，^0.0.3 只有 0.0.3
+        _ => {
+            let fixed = match given.len() {
+                1 => 1,
+                2 if lower[0] == 0 => 2,
+                2 => 1,
+                _ if lower[0] > 0 => 1,
+                _ if lower[1] > 0 => 2,
+                _ => 3,
+            };
+            at_least && version.numbers[..fixed] == lower[..fixed]
+        }
+    }
+}
+
+/// A crate in one build graph: the target's, or the host's for build
+/// scripts and proc-macros
+struct Node {
+    package: usize,
+    host: bool,
+    features: BTreeSet<String>,
+    /// (extern name, node, build dependency)
+    edges: BTreeSet<(String, usize, bool)>,
+}
+
+struct Graph<'a> {
+    packages: &'a [Package],
+    nodes: Vec<Node>,
+    index: HashMap<(usize, bool), usize>,
+    host: String,
+    target: String,
+    /// --target of the program, passed on to its dependencies
+    cross: Option<String>,
+    host_cfg: BTreeSet<String>,
+    target_cfg: BTreeSet<String>,
+}
+
+struct Settings {
+    rustc: String,
+    version: String,
+    opt_level: String,
+    panic: Option<String>,
+    dry_run: bool,
+    quiet: bool,
+}
+
+/// A compiled crate
+struct Built {
+    dir: PathBuf,
+    artifact: PathBuf,
+    hash: String,
+    script: ScriptOutput,
+    /// Its directory and those of everything it depends on
+    closure: BTreeSet<PathBuf>,
+}
+
+impl Graph<'_> {
+    /// The node of `package`, with `features` requested; whether anything is new
+    fn activate(&mut self, package: usize, host: bool, features: impl IntoIterator<Item = String>) -> (usize, bool) {
+        let (node, mut changed) = match self.index.get(&(package, host)) {
+            Some(&node) => (node, false),
+            None => {
+                self.nodes.push(Node { package, host, features: BTreeSet::new(), edges: BTreeSet::new() });
+                self.index.insert((package, host), self.nodes.len() - 1);
+                (self.nodes.len() - 1, true)
+            }
+        };
+        for feature in features {
+            changed |= self.nodes[node].features.insert(feature);
+        }
+        (node, changed)
+    }
+    
+    /// Unifies features and dependencies until nothing changes
+    fn resolve(&mut self) -> Result<()> {
+        loop {
+            let mut changed = false;
+            let mut node = 0;
+            while node < self.nodes.len() {
+                changed |= self.expand(node)?;
+                node += 1;
+            }
+            if !changed {
+                return Ok(());
+            }
+        }
+    }
+    
+    fn expand(&mut self, node: usize) -> Result<bool> {
+        let packages = self.packages;
+        let package = &packages[self.nodes[node].package];
+        let host = self.nodes[node].host;
+        let mut features = self.nodes[node].features.clone();
+        let mut pending: Vec<String> = features.iter().cloned().collect();
+        let mut enabled = BTreeSet::new();
+        let mut requests = Vec::new();
+        while let Some(feature) = pending.pop() {
+            let Some(items) = package.features.get(&feature) else {
+                if package.implicit.contains(&feature) {
+                    enabled.insert(feature);
+                }
+                continue;
+            };
+            for item in items {
+                if let Some(dep) = item.strip_prefix("dep:") {
+                    enabled.insert(dep.to_string());
+                } else if let Some((dep, feature)) = item.split_once('/') {
+                    // x?/f 不启用 x，只在 x 另被启用时生效
+                    let weak = dep.ends_with('?');
+                    let dep = dep.trim_end_matches('?');
+                    if !weak {
+                        enabled.insert(dep.to_string());
+                        if package.implicit.contains(dep) && features.insert(dep.to_string()) {
+                            pending.push(dep.to_string());
+                        }
+                    }
+                    requests.push((dep.to_string(), feature.to_string()));
+                } else if features.insert(item.clone()) {
+                    pending.push(item.clone());
+                }
+            }
+        }
+        let mut changed = features.len() != self.nodes[node].features.len();
+        self.nodes[node].features = features;
+        
+        for dep in &package.dependencies {
+            if (dep.build && package.build.is_none()) || (dep.optional && !enabled.contains(&dep.key)) {
+                continue;
+            }
+            if let Some(spec) = &dep.target {
+                let (triple, cfg) = if host || dep.build { (&self.host, &self.host_cfg) } else { (&self.target, &self.target_cfg) };
+                let applies = match spec.strip_prefix("cfg(").and_then(|s| s.strip_suffix(')')) {
+                    Some(mut expr) => cfg_matches(&mut expr, cfg),
+                    None => spec == triple,
+                };
+                if !applies {
+                    continue;
+                }
+            }
+            let found = packages.iter().enumerate()
+                .filter(|(_, p)| p.name == dep.package && matches(&dep.req, &p.version))
+                .max_by(|a, b| a.1.version.cmp(&b.1.version))
+                .map(|(i, _)| i);
+            let Some(found) = found else {
+                bail!(
+                    "{} {} needs {} {}, which is not in the vendor directory",
+                    package.name, package.version_text(), dep.package, dep.req
+                );
+            };
+            let mut wanted = dep.features.clone();
+            if dep.default_features {
+                wanted.push("default".to_string());
+            }
+            wanted.extend(requests.iter().filter(|(key, _)| *key == dep.key).map(|(_, f)| f.clone()));
+            let dep_host = host || dep.build || packages[found].proc_macro;
+            let (target, new) = self.activate(found, dep_host, wanted);
+            let name = if dep.renamed { dep.key.replace('-', "_") } else { packages[found].lib_name.clone() };
+            changed |= new;
+            changed |= self.nodes[node].edges.insert((name, target, dep.build));
+        }
+        Ok(changed)
+    }
+    
+    /// Dependencies before dependents
+    fn order(&self) -> Vec<usize> {
+        fn visit(graph: &Graph, node: usize, seen: &mut Vec<bool>, order: &mut Vec<usize>) {
+            if std::mem::replace(&mut seen[node], true) {
+                return;
+            }
+            for (_, dep, _) in &graph.nodes[node].edges {
+                visit(graph, *dep, seen, order);
+            }
+            order.push(node);
+        }
+        let mut seen = vec![false; self.nodes.len()];
+        let mut order = Vec::new();
+        for node in 0..self.nodes.len() {
+            visit(self, node, &mut seen, &mut order);
+        }
+        order
+    }
+    
+    /// Compiles what the cache lacks; the outputs, indexed like the nodes
+    fn build(&self, settings: &Settings) -> Result<Vec<Built>> {
+        let mut built: Vec<Option<Built>> = (0..self.nodes.len()).map(|_| None).collect();
+        for index in self.order() {
+            let node = &self.nodes[index];
+            let package = &self.packages[node.package];
+            let deps: Vec<(&str, &Built, bool)> = node.edges.iter()
+                .map(|(name, dep, build)| (name.as_str(), built[*dep].as_ref().expect("dependencies come first"), *build))
+                .collect();
+            let features: Vec<&String> = node.features.iter()
+                .filter(|f| package.features.contains_key(*f) || package.implicit.contains(*f))
+                .collect();
+            let opt_level = if node.host { "0" } else { &settings.opt_level };
+            let panic = settings.panic.as_deref().filter(|_| !node.host);
+            
+            let mut hasher = Sha256::new();
+            hasher.update(env!("CARGO_PKG_VERSION"));
+            hasher.update(&settings.version);
+            hasher.update(package.dir.display().to_string());
+            hasher.update(format!("{} {} {:?} {:?}", package.name, package.version_text(), features, node.host));
+            hasher.update(format!("{} {} {:?}", if node.host { &self.host } else { &self.target }, opt_level, panic));
+            for (name, dep, build) in &deps {
+                hasher.update(format!("{} {} {}", name, dep.hash, build));
+            }
+            let hash = cache::hex(&hasher.finalize())[..16].to_string();
+            let version = package.version_text();
+            let dir = cache::cache_root().join("deps").join(format!("{}-{}-{}", package.name, version, hash));
+            let artifact = dir.join(if package.proc_macro {
+                format!("{}{}-{}{}", std::env::consts::DLL_PREFIX, package.lib_name, hash, std::env::consts::DLL_SUFFIX)
+            } else {
+                format!("lib{}-{}.rlib", package.lib_name, hash)
+            });
+            
+            let marker = dir.join(".icx-done");
+            let script = if marker.is_file() {
+                std::fs::read_to_string(dir.join("build-output")).map(|t| ScriptOutput::parse(&t)).unwrap_or_default()
+            } else if settings.dry_run {
+                console::eprint(&format!("[icx-rustc] deps: would build {} {} into {}", package.name, version, dir.display()));
+                ScriptOutput::default()
+            } else {
+                if !settings.quiet {
+                    console::eprint(&format!("[icx-rustc] deps: compiling {} {}", package.name, version));
+                }
+                std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+                let unit = Unit { graph: self, settings, package, node, features: &features, deps: &deps, dir: &dir, hash: &hash, opt_level };
+                let script = match &package.build {
+                    Some(build) => unit.run_build_script(build)?,
+                    None => ScriptOutput::default(),
+                };
+                unit.compile(&script)?;
+                std::fs::write(&marker, "").with_context(|| format!("Failed to write {}", marker.display()))?;
+                script
+            };
+            let mut closure: BTreeSet<PathBuf> = deps.iter().flat_map(|d| d.1.closure.iter().cloned()).collect();
+            closure.insert(dir.clone());
+            built[index] = Some(Built { dir, artifact, hash, script, closure });
+        }
+        Ok(built.into_iter().map(|b| b.expect("every node is built")).collect())
+    }
+}
+
+/// One crate being compiled
+struct Unit<'a> {
+    graph: &'a Graph<'a>,
+    settings: &'a Settings,
+    package: &'a Package,
+    node: &'a Node,
+    features: &'a [&'a String],
+    deps: &'a [(&'a str, &'a Built, bool)],
+    dir: &'a Path,
+    hash: &'a str,
+    opt_level: &'a str,
+}
+
+impl Unit<'_> {
+    /// The `CARGO_PKG_*` variables of the package
+    fn package_env(&self, command: &mut Command) {
+        let info = &self.package.manifest["package"];
+        let text = |key: &str| info.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+        let version = &self.package.version;
+        command.env("CARGO_PKG_NAME", &self.package.name)
+            .env("CARGO_PKG_VERSION", self.package.version_text())
+            .env("CARGO_PKG_VERSION_MAJOR", version.numbers[0].to_string())
+            .env("CARGO_PKG_VERSION_MINOR", version.numbers[1].to_string())
+            .env("CARGO_PKG_VERSION_PATCH", version.numbers[2].to_string())
+            .env("CARGO_PKG_VERSION_PRE", &version.pre)
+            .env("CARGO_PKG_AUTHORS", info.get("authors").map(strings).unwrap_or_default().join(":"))
+            .env("CARGO_PKG_DESCRIPTION", text("description"))
+            .env("CARGO_PKG_HOMEPAGE", text("homepage"))
+            .env("CARGO_PKG_REPOSITORY", text("repository"))
+            .env("CARGO_PKG_LICENSE", text("license"))
+            .env("CARGO_PKG_RUST_VERSION", text("rust-version"))
+            .env("CARGO_MANIFEST_DIR", &self.package.dir)
+            .env("CARGO_MANIFEST_PATH", self.package.dir.join("Cargo.toml"));
+    }
+    
+    /// Flags shared by the library and its build script
+    fn rustc(&self, crate_name: &str, source: &Path, build: bool) -> Command {
+        let mut command = Command::new(&self.settings.rustc);
+        command.arg(format!("--edition={}", self.package.edition))
+            .arg(format!("--crate-name={}", crate_name))
+            .arg(source)
+            .arg("--cap-lints=allow");
+        for feature in self.features {
+            command.arg("--cfg").arg(format!("feature=\"{}\"", feature));
+        }
+        for (name, dep, _) in self.deps.iter().filter(|d| d.2 == build) {
+            command.arg("--extern").arg(format!("{}={}", name, dep.artifact.display()));
+        }
+        // 依赖的依赖由 rustc 按 -L dependency= 查找
+        let search: BTreeSet<&PathBuf> = self.deps.iter().filter(|d| d.2 == build).flat_map(|d| &d.1.closure).collect();
+        for dir in search {
+            command.arg("-L").arg(format!("dependency={}", dir.display()));
+        }
+        self.package_env(&mut command);
+        command.env("CARGO_CRATE_NAME", crate_name);
+        command.current_dir(&self.package.dir);
+        command
+    }
+    
+    fn run_build_script(&self, source: &Path) -> Result<ScriptOutput> {
+        let exe = self.dir.join(format!("build-script-build{}", std::env::consts::EXE_SUFFIX));
+        let mut compile = self.rustc("build_script_build", source, true);
+        compile.arg("--crate-type=bin").arg("-Copt-level=0").arg("-o").arg(&exe);
+        run(compile, &format!("the build script of {}", self.package.name))?;
+        
+        let out_dir = self.dir.join("out");
+        std::fs::create_dir_all(&out_dir).with_context(|| format!("Failed to create {}", out_dir.display()))?;
+        let (triple, cfg) = if self.node.host {
+            (&self.graph.host, &self.graph.host_cfg)
+        } else {
+            (&self.graph.target, &self.graph.target_cfg)
+        };
+        let mut script = Command::new(&exe);
+        self.package_env(&mut script);
+        script.current_dir(&self.package.dir)
+            .env("OUT_DIR", &out_dir)
+            .env("TARGET", triple)
+            .env("HOST", &self.graph.host)
+            .env("NUM_JOBS", std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).to_string())
+            .env("OPT_LEVEL", self.opt_level)
+            .env("PROFILE", if self.opt_level == "0" { "debug" } else { "release" })
+            .env("DEBUG", "false")
+            .env("RUSTC", &self.settings.rustc)
+            .env("CARGO_ENCODED_RUSTFLAGS", "")
+            .env_remove("RUSTC_WRAPPER");
+        if let Some(links) = &self.package.links {
+            script.env("CARGO_MANIFEST_LINKS", links);
+        }
+        // CARGO_CFG_TARGET_OS=linux、CARGO_CFG_UNIX= 等，多个值以逗号连接
+        let mut cfgs: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for line in cfg {
+            let (name, value) = line.split_once('=').unwrap_or((line, ""));
+            let values = cfgs.entry(format!("CARGO_CFG_{}", name.to_ascii_uppercase())).or_default();
+            if !value.is_empty() {
+                values.push(value.trim_matches('"'));
+            }
+        }
+        for (name, values) in cfgs {
+            script.env(name, values.join(","));
+        }
+        for feature in self.features {
+            script.env(format!("CARGO_FEATURE_{}", feature.to_ascii_uppercase().replace('-', "_")), "1");
+        }
+        for (_, dep, build) in self.deps {
+            let Some(links) = dep.script.links.as_ref().filter(|_| !build) else {
+                continue;
+            };
+            for (key, value) in &dep.script.metadata {
+                script.env(format!("DEP_{}_{}", links.to_ascii_uppercase().replace('-', "_"), key.to_ascii_uppercase()), value);
+            }
+        }
+        
+        let output = script.output().with_context(|| format!("Failed to run {}", exe.display()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if !output.status.success() {
+            for line in stdout.lines().chain(String::from_utf8_lossy(&output.stderr).lines()) {
+                console::eprint(line);
+            }
+            bail!("The build script of {} failed", self.package.name);
+        }
+        let mut text = stdout;
+        if let Some(links) = &self.package.links {
+            text.push_str(&format!("\ncargo::icx-links={}\n", links));
+        }
+        std::fs::write(self.dir.join("build-output"), &text).context("Failed to save the build script output")?;
+        let script = ScriptOutput::parse(&text);
+        for warning in &script.warnings {
+            console::eprint(&format!("[icx-rustc] warning: {}: {}", self.package.name, warning));
+        }
+        Ok(script)
+    }
+    
+    fn compile(&self, script: &ScriptOutput) -> Result<()> {
+        let mut command = self.rustc(&self.package.lib_name, &self.package.lib_path, false);
+        if self.package.proc_macro {
+            command.arg("--crate-type=proc-macro").arg("--extern").arg("proc_macro");
+        } else {
+            command.arg("--crate-type=lib");
+        }
+        command.arg(format!("-Copt-level={}", self.opt_level))
+            .arg(format!("-Cmetadata={}", self.hash))
+            .arg(format!("-Cextra-filename=-{}", self.hash))
+            .arg("--out-dir").arg(self.dir);
+        if !self.node.host {
+            if let Some(target) = &self.graph.cross {
+                command.arg("--target").arg(target);
+            }
+            if let Some(panic) = &self.settings.panic {
+                command.arg(format!("-Cpanic={}", panic));
+            }
+        }
+        if self.package.build.is_some() {
+            command.env("OUT_DIR", self.dir.join("out"));
+        }
+        for cfg in &script.cfgs {
+            command.arg("--cfg").arg(cfg);
+        }
+        for (key, value) in &script.env {
+            command.env(key, value);
+        }
+        command.args(&script.flags);
+        run(command, &format!("{} {}", self.package.name, self.package.version_text()))
+    }
+}
+
+fn run(mut command: Command, what: &str) -> Result<()> {
+    let output = command.output().with_context(|| format!("Failed to run {}", command.get_program().to_string_lossy()))?;
+    if !output.status.success() {
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            console::eprint(line);
+        }
+        bail!("Failed to compile vendored crate {}", what);
+    }
+    Ok(())
+}
+
+/// What a build script printed (`cargo:key=value` or `cargo::key=value`)
+#[derive(Default)]
+struct ScriptOutput {
+    cfgs: Vec<String>,
+    env: Vec<(String, String)>,
+    /// `-l`/`-L` flags for the crate itself
+    flags: Vec<String>,
+    /// `-L` values that the final link needs
+    search: Vec<String>,
+    metadata: Vec<(String, String)>,
+    links: Option<String>,
+    warnings: Vec<String>,
+}
+
+impl ScriptOutput {
+    fn parse(text: &str) -> Self {
+        let mut output = Self::default();
+        for line in text.lines() {
+            let (new_syntax, rest) = match line.strip_prefix("cargo::") {
+                Some(rest) => (true, rest),
+                None => match line.strip_prefix("cargo:") {
+                    Some(rest) => (false, rest),
+                    None => continue,
+                },
+            };
+            let Some((key, value)) = rest.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key {
+                "rustc-cfg" => output.cfgs.push(value),
+                "rustc-env" => {
+                    if let Some((name, value)) = value.split_once('=') {
+                        output.env.push((name.to_string(), value.to_string()));
+                    }
+                }
+                "rustc-link-lib" => output.flags.extend(["-l".to_string(), value]),
+                "rustc-link-search" => {
+                    output.flags.extend(["-L".to_string(), value.clone()]);
+                    output.search.push(value);
+                }
+                "rustc-flags" => {
+                    let words: Vec<&str> = value.split_whitespace().collect();
+                    for pair in words.chunks(2) {
+                        if let [flag @ ("-l" | "-L"), value] = pair {
+                            output.flags.extend([flag.to_string(), value.to_string()]);
+                            if *flag == "-L" {
+                                output.search.push(value.to_string());
+                            }
+                        }
+                    }
+                }
+                "warning" => output.warnings.push(value),
+                "icx-links" => output.links = Some(value),
+                "metadata" if new_syntax => {
+                    if let Some((name, value)) = value.split_once('=') {
+                        output.metadata.push((name.to_string(), value.to_string()));
+                    }
+                }
+                "rustc-link-arg" | "rustc-link-arg-bins" | "rustc-check-cfg" | "rerun-if-changed" | "rerun-if-env-changed"
+                | "rustc-cdylib-link-arg" | "error" => {}
+                _ if !new_syntax => output.metadata.push((key.to_string(), value)),
+                _ => {}
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A vendor directory of `(dir, Cargo.toml)` crates, each with an empty lib.rs
+    fn vendor(crates: &[(&str, &str)]) -> (tempfile::TempDir, Vec<Package>) {
+        vendor_in(tempfile::tempdir().unwrap(), crates)
+    }
+    
+    fn vendor_in(dir: tempfile::TempDir, crates: &[(&str, &str)]) -> (tempfile::TempDir, Vec<Package>) {
+        for (name, manifest) in crates {
+            let src = dir.path().join(name).join("src");
+            std::fs::create_dir_all(&src).unwrap();
+            std::fs::write(src.join("lib.rs"), "").unwrap();
+            std::fs::write(dir.path().join(name).join("Cargo.toml"), manifest).unwrap();
+        }
+        let packages = scan(dir.path()).unwrap();
+        (dir, packages)
+    }
+    
+    fn graph(packages: &[Package]) -> Graph<'_> {
+        Graph {
+            packages,
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            host: "x86_64-unknown-linux-gnu".to_string(),
+            target: "x86_64-unknown-linux-gnu".to_string(),
+            cross: None,
+            host_cfg: BTreeSet::from(["unix".to_string()]),
+            target_cfg: BTreeSet::from(["unix".to_string()]),
+        }
+    }
+    
+    /// `name` activated with its default features and `features`, unresolved
+    fn graph_with<'a>(packages: &'a [Package], name: &str, features: &[&str]) -> Graph<'a> {
+        let mut graph = graph(packages);
+        let package = packages.iter().position(|p| p.name == name).unwrap();
+        let features = features.iter().map(|f| f.to_string()).chain(["default".to_string()]);
+        graph.activate(package, false, features);
+        graph
+    }
+    
+    /// Activates `root` with its default features and resolves the graph
+    fn resolve<'a>(packages: &'a [Package], root: &str) -> Graph<'a> {
+        let mut graph = graph_with(packages, root, &[]);
+        graph.resolve().unwrap();
+        graph
+    }
+    
+    /// (version, sorted features) of every node of `name`
+    fn nodes(graph: &Graph, name: &str) -> Vec<(String, Vec<String>)> {
+        graph.nodes.iter()
+            .filter(|n| graph.packages[n.package].name == name)
+            .map(|n| (graph.packages[n.package].version_text().to_string(), n.features.iter().cloned().collect()))
+            .collect()
+    }
+    
+    const LOG: &str = "[features]\ndefault = [\"std\"]\nstd = []\nkv = []\nserde = []\n";
+    
+    #[test]
+    fn requirements() {
+        let v = |text| Version::parse(text).unwrap();
+        assert!(matches("1.2", &v("1.9.0")));
+        assert!(!matches("1.2", &v("2.0.0")));
+        assert!(!matches("1.2.3", &v("1.2.2")));
+        assert!(matches("0.2.3", &v("0.2.9")));
+        assert!(!matches("0.2.3", &v("0.3.0")));
+        assert!(matches("=0.0.3", &v("0.0.3")) && !matches("^0.0.3", &v("0.0.4")));
+        assert!(matches("~1.2", &v("1.2.7")) && !matches("~1.2", &v("1.3.0")));
+        assert!(matches(">=1.0, <1.5", &v("1.4.9")) && !matches(">=1.0, <1.5", &v("1.5.0")));
+        assert!(matches("1.*", &v("1.8.0")) && matches("*", &v("3.0.0")));
+        assert!(!matches("1", &v("1.1.0-beta.1")));
+        assert!(matches("1.1.0-beta.1", &v("1.1.0-beta.2")));
+    }
+    
+    #[test]
+    fn picks_the_highest_matching_version() {
+        let (_dir, packages) = vendor(&[
+            ("log-0.3.9", &format!("[package]\nname = \"log\"\nversion = \"0.3.9\"\n{}", LOG)),
+            ("log-0.4.8", &format!("[package]\nname = \"log\"\nversion = \"0.4.8\"\n{}", LOG)),
+            ("log-0.4.21", &format!("[package]\nname = \"log\"\nversion = \"0.4.21\"\n{}", LOG)),
+            ("log-0.5.0-rc.1", &format!("[package]\nname = \"log\"\nversion = \"0.5.0-rc.1\"\n{}", LOG)),
+            ("app", "[package]\nname = \"app\"\nversion = \"1.0.0\"\n[dependencies]\nlog = \"0.4.5\"\n"),
+            ("old", "[package]\nname = \"old\"\nversion = \"1.0.0\"\n[dependencies]\nlog = \"=0.3.9\"\n"),
+        ]);
+        let graph = resolve(&packages, "app");
+        assert_eq!(nodes(&graph, "log"), [("0.4.21".to_string(), vec!["default".to_string(), "std".to_string()])]);
+        let graph = resolve(&packages, "old");
+        assert_eq!(nodes(&graph, "log")[0].0, "0.3.9");
+    }
+    
+    #[test]
+    fn missing_dependency_is_an_error() {
+        let (_dir, packages) = vendor(&[
+            ("log", &format!("[package]\nname = \"log\"\nversion = \"0.4.21\"\n{}", LOG)),
+            ("app", "[package]\nname = \"app\"\nversion = \"1.0.0\"\n[dependencies]\nlog = \"0.5\"\n"),
+        ]);
+        let error = graph_with(&packages, "app", &[]).resolve().unwrap_err();
+        assert_eq!(error.to_string(), "app 1.0.0 needs log 0.5, which is not in the vendor directory");
+    }
+    
+    #[test]
+    fn unifies_features() {
+        let (_dir, packages) = vendor(&[
+            ("log", &format!("[package]\nname = \"log\"\nversion = \"0.4.21\"\n{}", LOG)),
+            ("serde", "[package]\nname = \"serde\"\nversion = \"1.0.0\"\n[features]\nderive = []\n"),
+            ("kv", "[package]\nname = \"kv\"\nversion = \"1.0.0\"\n\
+                [dependencies]\nlog = { version = \"0.4\", default-features = false, features = [\"kv\"] }\n"),
+            ("json", "[package]\nname = \"json\"\nversion = \"1.0.0\"\n\
+                [dependencies]\nlog = { version = \"0.4\", default-features = false }\n\
+                serde = { version = \"1\", optional = true }\n\
+                [features]\ndefault = [\"log/serde\", \"serde?/derive\"]\n"),
+            ("wasm", "[package]\nname = \"wasm\"\nversion = \"1.0.0\"\n\
+                [target.'cfg(windows)'.dependencies]\nserde = \"1\"\n"),
+            ("app", "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\
+                [dependencies]\nkv = \"1\"\njson = \"1\"\nwasm = \"1\"\n"),
+        ]);
+        let graph = resolve(&packages, "app");
+        // 两个依赖方的特性合并到同一个 log 节点；x?/f 不会启用可选的 serde，cfg(windows) 也不适用
+        assert_eq!(nodes(&graph, "log"), [("0.4.21".to_string(), vec!["kv".to_string(), "serde".to_string()])]);
+        assert!(nodes(&graph, "serde").is_empty());
+        
+        let mut graph = graph_with(&packages, "json", &["serde"]);
+        graph.resolve().unwrap();
+        assert_eq!(nodes(&graph, "serde"), [("1.0.0".to_string(), vec!["default".to_string(), "derive".to_string()])]);
+    }
+    
+    #[test]
+    fn relative_vendor_directory() {
+        // rustc 在包目录中运行，包路径须是绝对路径
+        let manifest = "[package]\nname = \"bar-baz\"\nversion = \"1.0.0\"\nbuild = \"build.rs\"\n";
+        let (dir, _) = vendor_in(tempfile::tempdir_in(".").unwrap(), &[("bar-baz", manifest)]);
+        let packages = scan(Path::new(dir.path().file_name().unwrap())).unwrap();
+        let root = dir.path().join("bar-baz");
+        assert_eq!(packages[0].dir, root);
+        assert_eq!(packages[0].lib_path, root.join("src/lib.rs"));
+        assert_eq!(packages[0].build, Some(root.join("build.rs")));
+    }
+}
@@ -0,0 +1,29 @@
+use crate::buildlog;
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Serializes writes from parallel compiles sharing one /Qdiag-file
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Empties the /Qdiag-file at the start of a build
+///
+/// Truncating up front means the file always reflects the last build,
+/// also for units that produced no diagnostics.
+pub fn create(path: &Path) -> Result<()> {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    std::fs::write(path, "").with_context(|| format!("Failed to create diagnostics file {}", path.display()))
+}
+
+/// Appends formatted diagnostics as plain text
+pub fn append(path: &Path, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Ok(mut file) = OpenOptions::new().append(true).create(true).open(path) {
+        let _ = writeln!(file, "{}", buildlog::plain(text));
+    }
+}
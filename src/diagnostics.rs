@@ -1,142 +1,967 @@
-﻿use colored::Colorize;
-use regex::Regex;
-use std::sync::OnceLock;
-
-pub struct DiagnosticReporter {
-    error_regex: Regex,
-    warning_regex: Regex,
-    note_regex: Regex,
-    help_regex: Regex,
-    location_regex: Regex,
-}
-
-impl DiagnosticReporter {
-    pub fn new() -> Self {
-        static ERROR_RE: OnceLock<Regex> = OnceLock::new();
-        static WARNING_RE: OnceLock<Regex> = OnceLock::new();
-        static NOTE_RE: OnceLock<Regex> = OnceLock::new();
-        static HELP_RE: OnceLock<Regex> = OnceLock::new();
-        static LOCATION_RE: OnceLock<Regex> = OnceLock::new();
-
-        Self {
-            error_regex: ERROR_RE
-                .get_or_init(|| Regex::new(r"^error(\[E\d+\])?:").unwrap())
-                .clone(),
-            warning_regex: WARNING_RE
-                .get_or_init(|| Regex::new(r"^warning:").unwrap())
-                .clone(),
-            note_regex: NOTE_RE
-                .get_or_init(|| Regex::new(r"^\s*= note:").unwrap())
-                .clone(),
-            help_regex: HELP_RE
-                .get_or_init(|| Regex::new(r"^\s*= help:").unwrap())
-                .clone(),
-            location_regex: LOCATION_RE
-                .get_or_init(|| Regex::new(r"^\s*--> (.+):(\d+):(\d+)").unwrap())
-                .clone(),
-        }
-    }
-
-    /// Reports a diagnostic line and returns (warnings, errors) count delta
-    pub fn report(&self, line: &str) -> (u32, u32) {
-        let mut warnings = 0;
-        let mut errors = 0;
-
-        // Check for location line first (comes before error/warning)
-        if let Some(caps) = self.location_regex.captures(line) {
-            let file = &caps[1];
-            let row = &caps[2];
-            let col = &caps[3];
-            println!(
-                "     {} {}:{}:{}",
-                "-->".bright_blue(),
-                file.bright_cyan(),
-                row.bright_yellow(),
-                col.bright_yellow()
-            );
-            return (0, 0);
-        }
-
-        // Error detection
-        if self.error_regex.is_match(line) {
-            errors = 1;
-            let formatted = self.format_error(line);
-            println!("{}", formatted);
-        }
-        // Warning detection  
-        else if self.warning_regex.is_match(line) {
-            warnings = 1;
-            let formatted = self.format_warning(line);
-            println!("{}", formatted);
-        }
-        // Note
-        else if self.note_regex.is_match(line) {
-            let formatted = self.format_note(line);
-            println!("{}", formatted);
-        }
-        // Help
-        else if self.help_regex.is_match(line) {
-            let formatted = self.format_help(line);
-            println!("{}", formatted);
-        }
-        // Code context (lines with | )
-        else if line.trim_start().starts_with('|') {
-            println!("{}", self.format_code_line(line));
-        }
-        // Generic continuation
-        else {
-            println!("     {}", line.bright_black());
-        }
-
-        (warnings, errors)
-    }
-
-    fn format_error(&self, line: &str) -> String {
-        let msg = self.error_regex.replace(line, "");
-        format!(
-            "{} {} {}",
-            "error".bright_red().bold(),
-            "[ICX]".bright_cyan(),
-            msg.bright_white()
-        )
-    }
-
-    fn format_warning(&self, line: &str) -> String {
-        let msg = self.warning_regex.replace(line, "");
-        format!(
-            "{} {} {}",
-            "warning".bright_yellow().bold(),
-            "[ICX]".bright_cyan(),
-            msg.bright_white()
-        )
-    }
-
-    fn format_note(&self, line: &str) -> String {
-        let msg = line.replace("= note:", "");
-        format!(
-            "     {} {}",
-            "note:".bright_blue(),
-            msg.trim().bright_white()
-        )
-    }
-
-    fn format_help(&self, line: &str) -> String {
-        let msg = line.replace("= help:", "");
-        format!(
-            "     {} {}",
-            "help:".bright_green(),
-            msg.trim().bright_white()
-        )
-    }
-
-    fn format_code_line(&self, line: &str) -> String {
-        // Format code with syntax highlighting hints
-        if line.contains('^') {
-            // This is the pointer line
-            format!("     {}", line.bright_green().bold())
-        } else {
-            // Regular code line
-            format!("     {}", line.bright_black())
-        }
-    }
-}
\ No newline at end of file
+use crate::baseline::Baseline;
+use crate::buildlog;
+use crate::cmdline;
+use crate::console;
+use crate::highlight;
+use crate::hyperlink;
+use crate::i18n;
+use crate::numbering;
+use crate::suppress::{self, Suppression};
+use colored::{ColoredString, Colorize};
+use indexmap::{IndexMap, IndexSet};
+use regex::Regex;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+/// A diagnostic emitted by `rustc --error-format=json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    #[serde(rename = "$message_type", default)]
+    pub message_type: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    #[serde(default)]
+    pub level: String,
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosticCode {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    #[serde(default)]
+    pub column_end: usize,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub text: Vec<SpanLine>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<String>,
+    #[serde(default)]
+    pub byte_start: usize,
+    #[serde(default)]
+    pub byte_end: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpanLine {
+    pub text: String,
+    pub highlight_start: usize,
+    pub highlight_end: usize,
+}
+
+impl Diagnostic {
+    /// Parses one stderr line; `None` if it is not a JSON message
+    pub fn parse(line: &str) -> Option<Self> {
+        if !line.starts_with('{') {
+            return None;
+        }
+        let mut diagnostic: Diagnostic = serde_json::from_str(line).ok()?;
+        diagnostic.shorten_paths();
+        Some(diagnostic)
+    }
+    
+    /// Shows the `\\?\` paths handed to rustc for long inputs as the user wrote them
+    fn shorten_paths(&mut self) {
+        for span in &mut self.spans {
+            span.file_name = cmdline::display_path(&span.file_name);
+        }
+        for child in &mut self.children {
+            child.shorten_paths();
+        }
+    }
+
+    pub fn primary_span(&self) -> Option<&DiagnosticSpan> {
+        self.spans.iter().find(|s| s.is_primary).or(self.spans.first())
+    }
+}
+
+/// Reformats rustc's output in the Intel style, one line or JSON diagnostic at a time
+pub struct DiagnosticReporter {
+    error_regex: Regex,
+    warning_regex: Regex,
+    note_regex: Regex,
+    help_regex: Regex,
+    location_regex: Regex,
+    summary_regex: Regex,
+    condensed: bool,
+    quiet: bool,
+    msvc: bool,
+    /// Buffered output per file (/Qdiag-group); `None` prints as it arrives
+    groups: Option<RefCell<IndexMap<String, FileGroup>>>,
+    /// Warnings silenced per file (/Qdiag-suppress-file)
+    suppress: Vec<Suppression>,
+    /// Directory relative file names are resolved against for OSC 8 links
+    links: Option<PathBuf>,
+    /// Warnings accepted by /Qdiag-baseline:check
+    baseline: Option<Arc<Baseline>>,
+    /// Errors and warnings shown so far, to collapse repeats (macro expansions)
+    seen: RefCell<IndexMap<String, Repeat>>,
+    /// Terminal columns to wrap and fit output to; `None` leaves lines as they are
+    width: Option<usize>,
+}
+
+struct Repeat {
+    /// Group the diagnostic was shown under (its primary file)
+    file: String,
+    message: String,
+    location: String,
+    count: u32,
+}
+
+#[derive(Default)]
+struct FileGroup {
+    errors: u32,
+    warnings: u32,
+    lines: Vec<String>,
+}
+
+impl Default for DiagnosticReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagnosticReporter {
+    pub fn new() -> Self {
+        static ERROR_RE: OnceLock<Regex> = OnceLock::new();
+        static WARNING_RE: OnceLock<Regex> = OnceLock::new();
+        static NOTE_RE: OnceLock<Regex> = OnceLock::new();
+        static HELP_RE: OnceLock<Regex> = OnceLock::new();
+        static LOCATION_RE: OnceLock<Regex> = OnceLock::new();
+        static SUMMARY_RE: OnceLock<Regex> = OnceLock::new();
+
+        Self {
+            error_regex: ERROR_RE
+                .get_or_init(|| Regex::new(r"^error(\[E\d+\])?:").unwrap())
+                .clone(),
+            warning_regex: WARNING_RE
+                .get_or_init(|| Regex::new(r"^warning:").unwrap())
+                .clone(),
+            note_regex: NOTE_RE
+                .get_or_init(|| Regex::new(r"^\s*= note:").unwrap())
+                .clone(),
+            help_regex: HELP_RE
+                .get_or_init(|| Regex::new(r"^\s*= help:").unwrap())
+                .clone(),
+            location_regex: LOCATION_RE
+                .get_or_init(|| Regex::new(r"^\s*--> (.+):(\d+):(\d+)").unwrap())
+                .clone(),
+            summary_regex: SUMMARY_RE
+                .get_or_init(|| {
+                    Regex::new(r"^(error: aborting due to|warning: \d+ warnings? emitted)").unwrap()
+                })
+                .clone(),
+            condensed: false,
+            quiet: false,
+            msvc: false,
+            groups: None,
+            suppress: Vec::new(),
+            links: None,
+            baseline: None,
+            seen: RefCell::default(),
+            width: None,
+        }
+    }
+
+    /// Drops warnings matched by file-scoped suppressions, together with
+    /// rustc's warning count, which would include them
+    pub fn suppress(mut self, rules: Vec<Suppression>) -> Self {
+        self.suppress = rules;
+        self
+    }
+    
+    /// Drops warnings covered by the baseline, like [`Self::suppress`]
+    pub fn baseline(mut self, baseline: Option<Arc<Baseline>>) -> Self {
+        self.baseline = baseline;
+        self
+    }
+    
+    /// Makes `-->` locations clickable, resolving relative names against `base`
+    pub fn hyperlinks(mut self, base: Option<PathBuf>) -> Self {
+        self.links = base;
+        self
+    }
+    
+    /// Wraps messages with a hanging indent and fits code lines to `columns`
+    pub fn width(mut self, columns: Option<usize>) -> Self {
+        // 太窄时换行只会更难读
+        self.width = columns.filter(|&c| c >= 40);
+        self
+    }
+    
+    /// Only the header and location of each diagnostic (watch mode)
+    pub fn condensed(mut self, condensed: bool) -> Self {
+        self.condensed = condensed;
+        self
+    }
+
+    /// Leaves out rustc's trailer lines such as "aborting due to" (/Qquiet)
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// One `path(line,col): error RS0308: message` line per diagnostic (/Qdiag-format:msvc)
+    pub fn msvc(mut self, msvc: bool) -> Self {
+        self.msvc = msvc;
+        self
+    }
+
+    /// Buffers diagnostics and prints them under per-file headers (/Qdiag-group)
+    pub fn grouped(mut self, grouped: bool) -> Self {
+        self.groups = grouped.then(|| RefCell::new(IndexMap::new()));
+        self
+    }
+
+    /// Takes a rendered diagnostic; returns what should be printed right away
+    ///
+    /// In grouped mode the output is kept under `file` (`None`: output without
+    /// a location, such as linker errors) until [`Self::flush`].
+    pub fn emit(&self, file: Option<&str>, rendered: String, warnings: u32, errors: u32) -> Option<String> {
+        let Some(groups) = &self.groups else {
+            return Some(rendered).filter(|r| !r.is_empty());
+        };
+        if rendered.is_empty() {
+            return None;
+        }
+        let mut groups = groups.borrow_mut();
+        let group = groups.entry(file.unwrap_or_default().to_string()).or_default();
+        group.errors += errors;
+        group.warnings += warnings;
+        group.lines.push(rendered);
+        None
+    }
+
+    /// The buffered diagnostics, one block per file in first-seen order
+    pub fn flush(&self) -> Option<String> {
+        let mut groups = std::mem::take(&mut *self.groups.as_ref()?.borrow_mut());
+        for repeat in self.seen.borrow().values().filter(|r| r.count > 1) {
+            if let Some(group) = groups.get_mut(&repeat.file) {
+                group.lines.push(Self::format_repeat(repeat));
+            }
+        }
+        // 无位置的输出放在最后
+        let (located, other): (Vec<_>, Vec<_>) = groups.into_iter().partition(|(file, _)| !file.is_empty());
+
+        let mut out = Vec::new();
+        for (file, group) in located.into_iter().chain(other) {
+            let name = if file.is_empty() { "(no location)".to_string() } else { file };
+            let counts = i18n::fill("{} error(s), {} warning(s)", &[&group.errors, &group.warnings]);
+            let counts = if group.errors > 0 { counts.bright_red() } else { counts.bright_yellow() };
+            out.push(format!("{} {} {}", "==>".bright_blue().bold(), name.bright_cyan().bold(), counts));
+            out.extend(group.lines);
+        }
+        (!out.is_empty()).then(|| out.join("\n"))
+    }
+
+    /// Formats a diagnostic line and returns it with the (warnings, errors) count delta
+    pub fn format(&self, line: &str) -> (String, u32, u32) {
+        // Check for location line first (comes before error/warning)
+        if let Some(caps) = self.location_regex.captures(line) {
+            let file = &caps[1];
+            let row = &caps[2];
+            let col = &caps[3];
+            let location = format!("{}:{}:{}", file.bright_cyan(), row.bright_yellow(), col.bright_yellow());
+            let location = match &self.links {
+                Some(base) => hyperlink::link(&location, file, row.parse().unwrap_or(1), col.parse().unwrap_or(1), base),
+                None => location,
+            };
+            return (format!("     {} {}", "-->".bright_blue(), location), 0, 0);
+        }
+
+        // rustc 自己的结尾行（aborting due to 等）不是诊断
+        if self.summary_regex.is_match(line) {
+            if self.quiet || self.msvc || self.groups.is_some() || self.filters() {
+                return (String::new(), 0, 0);
+            }
+            return (format!("     {}", line.bright_black()), 0, 0);
+        }
+
+        // MSVC 格式：原样输出（链接器消息本身已是该格式），只统计
+        if self.msvc {
+            let (warnings, errors) = if self.error_regex.is_match(line) {
+                (0, 1)
+            } else if self.warning_regex.is_match(line) {
+                (1, 0)
+            } else {
+                (0, 0)
+            };
+            return (line.to_string(), warnings, errors);
+        }
+
+        // Error detection
+        if self.error_regex.is_match(line) {
+            (self.format_error(line), 0, 1)
+        }
+        // Warning detection
+        else if self.warning_regex.is_match(line) {
+            (self.format_warning(line), 1, 0)
+        }
+        // Note
+        else if self.note_regex.is_match(line) {
+            (self.format_note(line), 0, 0)
+        }
+        // Help
+        else if self.help_regex.is_match(line) {
+            (self.format_help(line), 0, 0)
+        }
+        // Code context (lines with | )
+        else if line.trim_start().starts_with('|') {
+            (self.format_code_line(line), 0, 0)
+        }
+        // Generic continuation
+        else {
+            (format!("     {}", line.bright_black()), 0, 0)
+        }
+    }
+
+    /// Whether some warnings are left out, so rustc's own counts would be wrong
+    fn filters(&self) -> bool {
+        !self.suppress.is_empty() || self.baseline.is_some()
+            || self.seen.borrow().values().any(|r| r.count > 1)
+    }
+
+    /// Counts a diagnostic seen before (same level, code, message and location)
+    ///
+    /// Only the first occurrence is shown and counted; [`Self::repeated`]
+    /// reports how often the others came.
+    fn is_repeat(&self, diag: &Diagnostic) -> bool {
+        if diag.level != "error" && diag.level != "warning" {
+            return false;
+        }
+        let span = diag.primary_span();
+        let location = span
+            .map(|s| format!("{}:{}:{}", s.file_name, s.line_start, s.column_start))
+            .unwrap_or_default();
+        let code = diag.code.as_ref().map(|c| c.code.as_str()).unwrap_or_default();
+        let key = format!("{}\t{}\t{}\t{}", diag.level, code, location, diag.message);
+
+        let mut seen = self.seen.borrow_mut();
+        let repeat = seen.entry(key).or_insert_with(|| Repeat {
+            file: span.map(|s| s.file_name.clone()).unwrap_or_default(),
+            message: diag.message.clone(),
+            location,
+            count: 0,
+        });
+        repeat.count += 1;
+        repeat.count > 1
+    }
+
+    fn format_repeat(repeat: &Repeat) -> String {
+        let text = if repeat.location.is_empty() {
+            i18n::fill("{} (repeated {} times)", &[&repeat.message, &repeat.count])
+        } else {
+            i18n::fill("{} at {} (repeated {} times)", &[&repeat.message, &repeat.location, &repeat.count])
+        };
+        format!("     {} {}", i18n::t("note:").bright_blue(), text.bright_black())
+    }
+
+    /// One note per diagnostic that was collapsed, printed after the compile
+    ///
+    /// In grouped mode the notes are part of the file blocks instead.
+    pub fn repeated(&self) -> Option<String> {
+        if self.groups.is_some() || self.quiet || self.msvc {
+            return None;
+        }
+        let notes: Vec<String> = self.seen.borrow().values()
+            .filter(|r| r.count > 1)
+            .map(Self::format_repeat)
+            .collect();
+        (!notes.is_empty()).then(|| notes.join("\n"))
+    }
+
+    /// Renders a JSON diagnostic and returns it with the (warnings, errors) count delta
+    pub fn render(&self, diag: &Diagnostic) -> (String, u32, u32) {
+        // 非诊断消息（如 future-incompat 报告）不显示
+        if !diag.message_type.is_empty() && diag.message_type != "diagnostic" {
+            return (String::new(), 0, 0);
+        }
+        if suppress::is_suppressed(&self.suppress, diag)
+            || self.is_repeat(diag)
+            || self.baseline.as_ref().is_some_and(|b| b.absorb(diag))
+        {
+            return (String::new(), 0, 0);
+        }
+
+        // rustc 自己的结尾行（aborting due to 等）不是诊断
+        let plain = format!("{}: {}", diag.level, diag.message);
+        let trimmed = self.condensed || self.quiet || self.msvc || self.groups.is_some() || self.filters();
+        if trimmed && diag.spans.is_empty()
+            && (self.summary_regex.is_match(&plain) || diag.level == "failure-note")
+        {
+            return (String::new(), 0, 0);
+        }
+        if diag.spans.is_empty() && self.summary_regex.is_match(&plain) {
+            return (format!("     {}", plain.bright_black()), 0, 0);
+        }
+
+        if self.msvc {
+            return self.render_msvc(diag);
+        }
+
+        let code = diag.code.as_ref().map(|c| c.code.as_str());
+        let (mut out, warnings, errors) = match diag.level.as_str() {
+            "error" | "error: internal compiler error" => {
+                (self.format_header(i18n::t("error").bright_red().bold(), code, &diag.message), 0, 1)
+            }
+            "warning" => {
+                (self.format_header(i18n::t("warning").bright_yellow().bold(), code, &diag.message), 1, 0)
+            }
+            "note" => (self.format_note(&format!("= note: {}", diag.message)), 0, 0),
+            "help" => (self.format_help(&format!("= help: {}", diag.message)), 0, 0),
+            _ => (format!("     {}", diag.message.bright_black()), 0, 0),
+        };
+
+        if let Some(span) = diag.primary_span() {
+            out.push('\n');
+            out.push_str(&self.format_location(span));
+        }
+        if self.condensed {
+            return (out, warnings, errors);
+        }
+        self.render_snippet(&mut out, &diag.spans);
+
+        for child in &diag.children {
+            // 空的替换表示删除，不再附上 ``
+            let message = match child.spans.iter().find_map(|s| s.suggested_replacement.as_ref()).filter(|r| !r.is_empty()) {
+                Some(replacement) => format!("{}: `{}`", child.message, replacement),
+                None => child.message.clone(),
+            };
+            out.push('\n');
+            match child.level.as_str() {
+                "help" => out.push_str(&self.format_help(&format!("= help: {}", message))),
+                _ => out.push_str(&self.format_note(&format!("= note: {}", message))),
+            }
+        }
+
+        (out, warnings, errors)
+    }
+
+    /// `src/main.rs(12,5): error RS0308: mismatched types`, without colors
+    fn render_msvc(&self, diag: &Diagnostic) -> (String, u32, u32) {
+        let (level, warnings, errors) = match diag.level.as_str() {
+            "error" | "error: internal compiler error" => ("error", 0, 1),
+            "warning" => ("warning", 1, 0),
+            "help" => ("note", 0, 0),
+            level => (level, 0, 0),
+        };
+        let origin = match diag.primary_span() {
+            Some(span) => format!("{}({},{})", span.file_name, span.line_start, span.column_start),
+            None => "rustc".to_string(),
+        };
+        
+        // E0308 -> RS0308；lint 名称原样作为代码
+        let line = match diag.code.as_ref().map(|c| c.code.as_str()) {
+            Some(code) => {
+                let code = match code.strip_prefix('E').filter(|n| n.chars().all(|c| c.is_ascii_digit())) {
+                    Some(number) => format!("RS{}", number),
+                    None => code.to_string(),
+                };
+                format!("{}: {} {}: {}", origin, level, code, diag.message)
+            }
+            None => format!("{}: {}: {}", origin, level, diag.message),
+        };
+        (line.replace('\n', " "), warnings, errors)
+    }
+
+    /// Appends the source lines covered by the spans with underlines and labels
+    fn render_snippet(&self, out: &mut String, spans: &[DiagnosticSpan]) {
+        let width = spans.iter()
+            .map(|s| s.line_end.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        // 同一行的多个 span 只显示一次源代码，主 span 在前
+        let mut lines: Vec<(&str, usize, &str, Vec<&DiagnosticSpan>)> = Vec::new();
+        for span in spans {
+            for (offset, text) in span.text.iter().enumerate() {
+                let line_no = span.line_start + offset;
+                match lines.iter_mut().find(|(f, n, _, _)| *f == span.file_name && *n == line_no) {
+                    Some((_, _, _, group)) => group.push(span),
+                    None => lines.push((&span.file_name, line_no, &text.text, vec![span])),
+                }
+            }
+        }
+
+        // 主 span 所在文件优先，其余按行号排列
+        let primary_file = spans.iter().find(|s| s.is_primary).map(|s| s.file_name.as_str());
+        lines.sort_by_key(|(file, line_no, _, _)| (Some(*file) != primary_file, *line_no));
+
+        // 代码行超出终端宽度时左右截断，保证标记的列可见且与 ^^^ 对齐
+        let room = self.width.map(|w| w.saturating_sub(width + 8).max(20));
+
+        for (_, line_no, text, mut group) in lines {
+            group.sort_by_key(|s| !s.is_primary);
+            let first = group.iter()
+                .filter_map(|s| s.text.get(line_no - s.line_start))
+                .map(|h| h.highlight_start.saturating_sub(1))
+                .min()
+                .unwrap_or_default();
+            let (text, shift) = fit_line(text, first, room);
+            out.push('\n');
+            out.push_str(&self.format_source(&format!("{:>width$} ", line_no), &format!(" {}", text)));
+
+            for span in group {
+                let Some(highlight) = span.text.get(line_no - span.line_start) else {
+                    continue;
+                };
+                let start = highlight.highlight_start.saturating_sub(1).saturating_sub(shift);
+                let mut len = highlight.highlight_end.saturating_sub(highlight.highlight_start).max(1);
+                if let Some(room) = room {
+                    len = len.min(room.saturating_sub(start).max(1));
+                }
+                let marker = if span.is_primary { "^" } else { "-" };
+                out.push('\n');
+                let underline = format!(
+                    "{:>width$} | {}{} {}",
+                    "",
+                    " ".repeat(start),
+                    marker.repeat(len),
+                    span.label.as_deref().unwrap_or("")
+                );
+                out.push_str(&self.format_code_line(underline.trim_end()));
+            }
+        }
+    }
+
+    fn format_location(&self, span: &DiagnosticSpan) -> String {
+        let position = format!(":{}:{}", span.line_start, span.column_start);
+        let name = match self.width {
+            Some(width) => elide_path(&span.file_name, width.saturating_sub(9 + position.len())),
+            None => span.file_name.clone(),
+        };
+        let location = format!(
+            "{}:{}:{}",
+            name.bright_cyan(),
+            span.line_start.to_string().bright_yellow(),
+            span.column_start.to_string().bright_yellow()
+        );
+        let location = match &self.links {
+            Some(base) => hyperlink::link(&location, &span.file_name, span.line_start, span.column_start, base),
+            None => location,
+        };
+        format!("     {} {}", "-->".bright_blue(), location)
+    }
+
+    fn format_header(&self, level: ColoredString, code: Option<&str>, message: &str) -> String {
+        // 有 ICX 编号时一并显示：[#10308: E0308]
+        let code = code.map(|code| match numbering::number(code) {
+            Some(number) => format!("[#{}: {}]", number, code),
+            None => format!("[{}]", code),
+        });
+        let prefix = match code {
+            Some(code) => format!("{} {} {}", level, "[ICX]".bright_cyan(), code.bright_black()),
+            None => format!("{} {}", level, "[ICX]".bright_cyan()),
+        };
+        let message = self.wrap(message, columns(&buildlog::plain(&prefix)) + 1, 5);
+        format!("{} {}", prefix, message.bright_white())
+    }
+
+    /// Word-wraps `text` to the terminal; continuation lines are indented by `indent`
+    ///
+    /// `used` is how many columns of the first line precede the text. Lines
+    /// of a multi-line message are indented too when nothing is wrapped.
+    fn wrap(&self, text: &str, used: usize, indent: usize) -> String {
+        let width = self.width.unwrap_or(usize::MAX);
+        let indent = indent.min(width / 2);
+        let mut out = String::new();
+        let mut column = used;
+        for (i, line) in text.lines().enumerate() {
+            if i > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+                column = indent;
+            }
+            for (j, word) in line.split(' ').enumerate() {
+                let len = columns(word);
+                if j > 0 && column + 1 + len > width {
+                    out.push('\n');
+                    out.push_str(&" ".repeat(indent));
+                    column = indent;
+                } else if j > 0 {
+                    out.push(' ');
+                    column += 1;
+                }
+                out.push_str(word);
+                column += len;
+            }
+        }
+        out
+    }
+
+    fn format_error(&self, line: &str) -> String {
+        let msg = self.error_regex.replace(line, "");
+        let prefix = format!("{} {}", i18n::t("error").bright_red().bold(), "[ICX]".bright_cyan());
+        let msg = self.wrap(msg.trim_start(), columns(&buildlog::plain(&prefix)) + 1, 5);
+        format!("{} {}", prefix, msg.bright_white())
+    }
+
+    fn format_warning(&self, line: &str) -> String {
+        let msg = self.warning_regex.replace(line, "");
+        let prefix = format!("{} {}", i18n::t("warning").bright_yellow().bold(), "[ICX]".bright_cyan());
+        let msg = self.wrap(msg.trim_start(), columns(&buildlog::plain(&prefix)) + 1, 5);
+        format!("{} {}", prefix, msg.bright_white())
+    }
+
+    fn format_note(&self, line: &str) -> String {
+        let msg = line.replace("= note:", "");
+        let label = i18n::t("note:");
+        let indent = 6 + columns(label);
+        format!("     {} {}", label.bright_blue(), self.wrap(msg.trim(), indent, indent).bright_white())
+    }
+
+    fn format_help(&self, line: &str) -> String {
+        let msg = line.replace("= help:", "");
+        let label = i18n::t("help:");
+        let indent = 6 + columns(label);
+        format!("     {} {}", label.bright_green(), self.wrap(msg.trim(), indent, indent).bright_white())
+    }
+
+    fn format_code_line(&self, line: &str) -> String {
+        // 行号栏为空的是指示行（^^^ / ---），其余是源代码
+        match line.split_once('|') {
+            Some((gutter, code)) if !gutter.trim().is_empty() => self.format_source(gutter, code),
+            Some((_, marks)) if marks.trim_start().starts_with(['^', '-']) => {
+                // This is the pointer line
+                format!("     {}", line.bright_green().bold())
+            }
+            _ => format!("     {}", line.bright_black()),
+        }
+    }
+
+    /// A source line of the code context: line-number gutter and highlighted code
+    fn format_source(&self, gutter: &str, code: &str) -> String {
+        format!("     {}{}", format!("{}|", gutter).bright_blue(), highlight::rust(code))
+    }
+}
+
+/// What a compile produced, shown under its summary line
+#[derive(Debug, Default)]
+pub struct BuildInfo {
+    /// Outputs that exist on disk, with their size in bytes
+    pub artifacts: Vec<(PathBuf, u64)>,
+    pub target: String,
+    /// Codegen options in effect (-Copt-level=, -Ctarget-cpu=, ...)
+    pub flags: Vec<String>,
+    /// Cache and incremental state ("cache hit", "sccache", "incremental")
+    pub cache: Vec<String>,
+}
+
+/// Prints the end-of-build summary line, and for a successful build what it produced
+pub fn print_summary(errors: u32, warnings: u32, elapsed_ms: u64, info: Option<&BuildInfo>) {
+    let status = if errors > 0 {
+        i18n::t("failed").bright_red().bold()
+    } else {
+        i18n::t("finished").bright_green().bold()
+    };
+    let summary = format!(
+        "{} {}",
+        status,
+        i18n::fill("{} error(s), {} warning(s) in {} ms", &[&errors, &warnings, &elapsed_ms])
+    );
+    buildlog::write("", &summary);
+    console::eprint(&summary);
+    if let Some(info) = info.filter(|_| errors == 0) {
+        print_build_info(info);
+    }
+}
+
+/// Summary for a compile restored from the builtin cache
+pub fn print_cache_hit(elapsed_ms: u64, info: &BuildInfo) {
+    let summary = format!(
+        "{} {}",
+        i18n::t("finished").bright_green().bold(),
+        i18n::fill("restored from cache in {} ms", &[&elapsed_ms])
+    );
+    buildlog::write("", &summary);
+    console::eprint(&summary);
+    print_build_info(info);
+}
+
+/// The summary line of a compile /Qup-to-date skipped
+pub fn print_up_to_date(info: &BuildInfo) {
+    let summary = format!("{} {}", i18n::t("finished").bright_green().bold(), i18n::t("up to date"));
+    buildlog::write("", &summary);
+    console::eprint(&summary);
+    print_build_info(info);
+}
+
+fn print_build_info(info: &BuildInfo) {
+    let mut lines = Vec::new();
+    for (path, size) in &info.artifacts {
+        lines.push(format!(
+            "     {} {} ({})",
+            i18n::t("output:").bright_blue(),
+            path.display().to_string().bright_cyan(),
+            format_size(*size)
+        ));
+    }
+    let mut details: Vec<String> = Some(info.target.clone())
+        .filter(|t| !t.is_empty())
+        .into_iter()
+        .chain(info.flags.iter().cloned())
+        .collect();
+    if !info.cache.is_empty() {
+        details.push(format!("({})", info.cache.join(", ")));
+    }
+    if !details.is_empty() {
+        lines.push(format!("     {} {}", i18n::t("target:").bright_blue(), details.join(" ").bright_black()));
+    }
+    for line in lines {
+        buildlog::write("", &line);
+        console::eprint(&line);
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1_048_576..1_073_741_824 => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1_073_741_824.0),
+    }
+}
+
+/// Intel-style phase names for the top-level `-Ztime-passes` passes
+const TIME_PHASES: &[(&str, &str)] = &[
+    ("parse_crate", "parse"),
+    ("macro_expand_crate", "expand"),
+    ("resolve_crate", "resolve"),
+    ("type_check_crate", "typeck"),
+    ("MIR_borrow_checking", "borrowck"),
+    ("codegen_crate", "codegen"),
+    ("LLVM_passes", "LLVM"),
+    ("link", "link"),
+];
+
+/// Parses a `time:   0.043; rss: ...\tparse_crate` line into (pass, seconds)
+pub fn parse_time_pass(line: &str) -> Option<(String, f64)> {
+    let rest = line.strip_prefix("time:")?;
+    let (secs, pass) = rest.split_once(';')?;
+    let pass = pass.rsplit(['\t', ' ']).next()?;
+    Some((pass.to_string(), secs.trim().parse().ok()?))
+}
+
+/// Prints the /Qtime phase breakdown
+pub fn print_time_report(passes: &[(String, f64)]) {
+    let Some(total) = passes.iter().rev().find(|(p, _)| p == "total").map(|(_, t)| *t) else {
+        return;
+    };
+    
+    let mut lines = vec![format!("{}", "Phase timing:".bold())];
+    let mut accounted = 0.0;
+    for (pass, phase) in TIME_PHASES {
+        // 增量编译等情况下同名阶段可能出现多次
+        let times: Vec<f64> = passes.iter().filter(|(p, _)| p == pass).map(|(_, t)| *t).collect();
+        if !times.is_empty() {
+            let secs: f64 = times.iter().sum();
+            accounted += secs;
+            lines.push(format_phase(phase, secs, total));
+        }
+    }
+    lines.push(format_phase("other", (total - accounted).max(0.0), total));
+    lines.push(format!("  {:<10} {:>8.3} s", "total", total));
+    
+    for line in lines {
+        buildlog::write("", &line);
+        console::eprint(&line);
+    }
+}
+
+fn format_phase(name: &str, secs: f64, total: f64) -> String {
+    let share = if total > 0.0 { secs * 100.0 / total } else { 0.0 };
+    format!("  {:<10} {:>8.3} s {:>5.1}%", name, secs, share)
+}
+
+/// Error and warning counts of a build, per source file and per code
+#[derive(Debug, Default)]
+pub struct DiagnosticStats {
+    pub errors: u32,
+    pub warnings: u32,
+    /// (errors, warnings) per file of the primary span, in first-seen order
+    pub files: IndexMap<String, (u32, u32)>,
+    /// Occurrences per error code or lint name
+    pub codes: IndexMap<String, CodeStats>,
+}
+
+/// How often one error code or lint occurred in a build
+#[derive(Debug, Default)]
+pub struct CodeStats {
+    pub count: u32,
+    pub error: bool,
+    /// Files of the primary spans
+    pub files: IndexSet<String>,
+}
+
+/// Statistics pass over the diagnostics collected from one invocation
+///
+/// The diagnostics were already rendered live; this only accumulates the
+/// build-wide counts reported by [`print_build_summary`].
+pub fn post_process(stats: &mut DiagnosticStats, diagnostics: &[Diagnostic]) {
+    for diag in diagnostics {
+        let error = diag.level.starts_with("error");
+        if error {
+            stats.errors += 1;
+        } else {
+            stats.warnings += 1;
+        }
+        
+        // 无位置的诊断（例如链接错误）不计入按文件统计
+        if let Some(span) = diag.primary_span() {
+            let counts = stats.files.entry(span.file_name.clone()).or_default();
+            if error {
+                counts.0 += 1;
+            } else {
+                counts.1 += 1;
+            }
+        }
+        if let Some(code) = &diag.code {
+            let entry = stats.codes.entry(code.code.clone()).or_default();
+            entry.count += 1;
+            entry.error |= error;
+            if let Some(span) = diag.primary_span() {
+                entry.files.insert(span.file_name.clone());
+            }
+        }
+    }
+}
+
+/// Build-wide summary after several translation units
+pub fn print_build_summary(stats: &DiagnosticStats, units: usize, elapsed_ms: u64) {
+    let status = if stats.errors > 0 {
+        i18n::t("build failed").bright_red().bold()
+    } else {
+        i18n::t("build finished").bright_green().bold()
+    };
+    let summary = format!(
+        "{} {}",
+        status,
+        i18n::fill(
+            "{} error(s), {} warning(s) in {} unit(s), {} ms",
+            &[&stats.errors, &stats.warnings, &units, &elapsed_ms]
+        )
+    );
+    buildlog::write("", &summary);
+    console::eprint(&summary);
+}
+
+/// Table of the most frequent codes of a build (/Qdiag-stats); `top` 0 lists all
+pub fn print_diag_stats(stats: &DiagnosticStats, top: usize) {
+    let mut codes: Vec<(&String, &CodeStats)> = stats.codes.iter().collect();
+    // 次数相同时保持首次出现的顺序
+    codes.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.count));
+    let shown = if top == 0 { codes.len() } else { top.min(codes.len()) };
+    
+    let title = if shown < codes.len() {
+        i18n::fill("diagnostic statistics (top {} of {} codes)", &[&shown, &codes.len()])
+    } else {
+        i18n::fill("diagnostic statistics ({} codes)", &[&codes.len()])
+    };
+    let mut lines = vec![
+        title.bright_white().bold().to_string(),
+        format!(
+            "  {} {}  {}  {}",
+            pad(i18n::t("count"), 7, true),
+            pad(i18n::t("files"), 7, true),
+            pad(i18n::t("level"), 7, false),
+            i18n::t("code")
+        ).bright_black().to_string(),
+    ];
+    for (code, entry) in &codes[..shown] {
+        let level = if entry.error {
+            pad(i18n::t("error"), 7, false).bright_red()
+        } else {
+            pad(i18n::t("warning"), 7, false).bright_yellow()
+        };
+        let id = match numbering::number(code) {
+            Some(number) => format!("#{} {}", number, code),
+            None => code.to_string(),
+        };
+        lines.push(format!("  {:>7} {:>7}  {}  {}", entry.count, entry.files.len(), level, id));
+    }
+    let uncoded = stats.errors + stats.warnings - stats.codes.values().map(|c| c.count).sum::<u32>();
+    if uncoded > 0 {
+        lines.push(format!("  {:>7} {:>7}  {:<7}  {}", uncoded, "", "", "(no code)").bright_black().to_string());
+    }
+    
+    let table = lines.join("\n");
+    buildlog::write("", &table);
+    console::eprint(&table);
+}
+
+/// Terminal columns of plain text; CJK characters take two
+fn columns(text: &str) -> usize {
+    text.chars().map(|c| if c >= '\u{2E80}' { 2 } else { 1 }).sum()
+}
+
+/// Pads to `width` terminal columns
+fn pad(text: &str, width: usize, right: bool) -> String {
+    let fill = " ".repeat(width.saturating_sub(columns(text)));
+    if right {
+        fill + text
+    } else {
+        format!("{}{}", text, fill)
+    }
+}
+
+/// Cuts a source line to `room` columns around the first marked column
+///
+/// Returns the text and how many characters were dropped on the left; a
+/// dropped side is shown as `…` in place of one character, so the marker
+/// columns only move by the shift.
+fn fit_line(text: &str, first: usize, room: Option<usize>) -> (String, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let Some(room) = room.filter(|&r| chars.len() > r) else {
+        return (text.to_string(), 0);
+    };
+    // 标记前保留约四分之一的上下文
+    let shift = first.saturating_sub(room / 4).min(chars.len().saturating_sub(room));
+    let mut out = String::new();
+    if shift > 0 {
+        out.push('…');
+    }
+    let from = if shift > 0 { shift + 1 } else { 0 };
+    let to = (shift + room).min(chars.len());
+    if to < chars.len() {
+        out.extend(&chars[from..to - 1]);
+        out.push('…');
+    } else {
+        out.extend(&chars[from..to]);
+    }
+    (out, shift)
+}
+
+/// Shortens a path to `room` columns by replacing leading directories with `…`
+fn elide_path(path: &str, room: usize) -> String {
+    if columns(path) <= room {
+        return path.to_string();
+    }
+    // 尽量保留完整的末尾路径组件
+    let separators: Vec<usize> = path.match_indices(['/', '\\']).map(|(i, _)| i).collect();
+    for &i in &separators {
+        let tail = &path[i..];
+        if columns(tail) < room {
+            return format!("…{}", tail);
+        }
+    }
+    let tail: String = path.chars().rev().take(room.saturating_sub(1)).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("…{}", tail)
+}
@@ -0,0 +1,687 @@
+//! The icx-rustc command: dispatch and compilation
+use crate::{
+    asm_view, audit, baseline, bolt, buildlog, cache, cargo, cc, cli, cmake, compdb, completions, config,
+    console, coverage, daemon, depfile, diagfile, diagnostics, eval, executor, exit_code, explain,
+    fix, hooks, i18n, interrupt, manual, msbuild, ninja, parallel, progress, project, runner, sarif,
+    script, size_report, stamp, stdin, summary, symbols, translator, watch, wrapper,
+};
+use anyhow::Result;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub fn main() {
+    let code = run().unwrap_or_else(|e| {
+        report_error(&e);
+        exit_code::INTERNAL_ERROR
+    });
+    std::process::exit(code);
+}
+
+pub fn report_error(e: &anyhow::Error) {
+    console::eprint(&format!("{}: {}", i18n::t("icx-rustc error").bright_red().bold(), e));
+}
+
+fn run() -> Result<i32> {
+    let raw: Vec<String> = std::env::args().collect();
+    if wrapper::is_invocation(&raw) {
+        interrupt::install(false);
+        return wrapper::run(&raw);
+    }
+    if let Some(words) = cargo::invocation(&raw) {
+        return cargo::run(words);
+    }
+    if raw.get(1).is_some_and(|a| a == daemon::WORKER) {
+        return daemon::work();
+    }
+    
+    let mut args = cli::parse_args();
+    // --daemon 服务端按每个请求中客户端的选择着色
+    console::init_color(args.color);
+    i18n::init(args.locale.as_deref());
+    
+    // 显示版本信息
+    if args.version {
+        print_version();
+        return Ok(exit_code::SUCCESS);
+    }
+    
+    // 显示
+    if args.help {
+        return manual::print_help();
+    }
+    if args.show_config {
+        return config::show(&args);
+    }
+    if args.help_long {
+        return manual::print_long();
+    }
+    if args.man {
+        return manual::print_man();
+    }
+    if let Some(shell) = args.completions {
+        return completions::print(shell);
+    }
+    if args.gc_cache.is_some() || args.gc_cache_size.is_some() {
+        return cache::gc(args.gc_cache.unwrap_or(30), args.gc_cache_size.as_deref());
+    }
+    if let Some(code) = &args.explain {
+        return explain::run(&args, code);
+    }
+    if let Some(format) = args.cov_report {
+        return coverage::report(&args, format);
+    }
+    if let Some(path) = &args.emit_cmake {
+        return cmake::write(&args, path);
+    }
+    if let Some(dir) = &args.emit_msbuild {
+        return msbuild::write(&args, dir);
+    }
+    // 项目文件：各单元在本进程内按依赖顺序编译
+    if let Some(path) = &args.project {
+        if let Some(file) = &args.ninja {
+            return project::write_ninja(&args, path, file);
+        }
+        interrupt::install(false);
+        return project::run(&args, path, |unit| compile(unit, false));
+    }
+    
+    if let Some(file) = &args.ninja {
+        return ninja::from_inputs(&args, file);
+    }
+    // /Qeval 与 "-"：源代码写入临时文件，返回前删除
+    let _eval = match eval::prepare(&mut args) {
+        Ok(source) => source,
+        Err(e) => {
+            report_error(&e);
+            return Ok(exit_code::USAGE_ERROR);
+        }
+    };
+    let _stdin = match stdin::capture(&mut args) {
+        Ok(source) => source,
+        Err(e) => {
+            report_error(&e);
+            return Ok(exit_code::USAGE_ERROR);
+        }
+    };
+    if args.run {
+        interrupt::install(false);
+        return runner::run(&args, |build| compile(build, false));
+    }
+    
+    // 编译服务器：服务端常驻，客户端转发请求
+    if args.daemon {
+        interrupt::install(true);
+        return daemon::serve();
+    }
+    if let Some(code) = daemon::forward(&args) {
+        return Ok(code);
+    }
+    interrupt::install(false);
+    
+    compile(&args, args.watch)
+}
+
+/// Translates and runs one invocation (also for requests to the --daemon server)
+pub fn compile(args: &cli::Args, incremental: bool) -> Result<i32> {
+    if let Some(path) = &args.log {
+        buildlog::init(path, args.log_append)?;
+    }
+    
+    // 构建钩子：icx-rustc.toml 中的在前，/Qhook 的在后
+    let config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            report_error(&e);
+            return Ok(exit_code::USAGE_ERROR);
+        }
+    };
+    let hooks = match hooks::Hooks::new(&config, &args.hooks) {
+        Ok(hooks) => hooks.map(Arc::new),
+        Err(e) => {
+            report_error(&e);
+            return Ok(exit_code::USAGE_ERROR);
+        }
+    };
+    if let Some(hooks) = hooks.as_ref().filter(|_| args.dry_run.is_none()) {
+        let code = hooks.run(hooks::Stage::PreTranslate, args, None, None)?;
+        if code != exit_code::SUCCESS {
+            return Ok(code);
+        }
+    }
+    
+    // 翻译参数（/c 多文件时每个输入一个 rustc 调用）
+    let translate_start = Instant::now();
+    let mut commands = match translator::translate_units(args) {
+        Ok(commands) => commands,
+        Err(e) => {
+            report_error(&e);
+            return Ok(exit_code::USAGE_ERROR);
+        }
+    };
+    let translate_time = translate_start.elapsed();
+    if args.dry_run.is_none() {
+        audit::record(&config, args, &commands)?;
+    }
+    
+    // 缓存：内置缓存，或 sccache 等外部包装器（也可由 ICX_CACHE_WRAPPER 指定）
+    let backend = args.cache.clone().or_else(|| std::env::var("ICX_CACHE_WRAPPER").ok());
+    // 内置缓存命中时没有诊断，/Qfix 需要真正编译
+    let cache = match backend.as_deref() {
+        Some("builtin") if args.dry_run.is_none() && !args.fix => {
+            for cmd in &mut commands {
+                cmd.cached = true;
+            }
+            Some(cache::Cache::open(&commands[0].executable)?)
+        }
+        Some("builtin") | None => None,
+        Some(wrapper) => {
+            match cache::find_wrapper(wrapper, &commands[0].executable) {
+                Ok(path) => {
+                    for cmd in &mut commands {
+                        cmd.wrapper = Some(path.clone());
+                    }
+                }
+                Err(e) => {
+                    report_error(&e);
+                    return Ok(exit_code::USAGE_ERROR);
+                }
+            }
+            None
+        }
+    };
+    
+    // /Qdiag-baseline:check：所有翻译单元共用一份基线
+    let baseline = match args.diag_baseline {
+        Some(cli::BaselineMode::Check) if args.dry_run.is_none() => {
+            match baseline::Baseline::load(&args.diag_baseline_file) {
+                Ok(baseline) => Some(Arc::new(baseline)),
+                Err(e) => {
+                    report_error(&e);
+                    return Ok(exit_code::USAGE_ERROR);
+                }
+            }
+        }
+        _ => None,
+    };
+    for cmd in &mut commands {
+        cmd.baseline = baseline.clone();
+        cmd.hooks = hooks.clone();
+    }
+    
+    // 监视模式与编译服务器：增量编译；监视模式下诊断只显示摘要行
+    if incremental {
+        let dir = cache::cache_root().join("incremental");
+        for cmd in &mut commands {
+            if !cmd.args.iter().any(|a| a.starts_with("-Cincremental=")) {
+                cmd.args.push(format!("-Cincremental={}", dir.display()));
+            }
+            cmd.condensed = args.watch;
+        }
+    }
+    
+    // --###=<file>：写出可重放的脚本
+    if let Some(path) = args.dry_run.as_ref().filter(|p| !p.as_os_str().is_empty()) {
+        script::write(path, &commands)?;
+        if !args.quiet {
+            console::eprint(&format!("{} wrote {}", "[icx-rustc]".bright_blue().bold(), path.display()));
+        }
+    }
+    
+    // /MP：各翻译单元并行编译（受 make jobserver 限制）
+    let jobs = match args.parallel {
+        Some(0) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        Some(n) => n,
+        None => 1,
+    };
+    // 源文件互为 crate 时按依赖顺序逐个编译
+    let jobs = if commands.iter().any(|c| !c.needs.is_empty()) { 1 } else { jobs };
+    let build = || -> Result<i32> {
+        let batch = commands.len() > 1 && args.dry_run.is_none() && !args.quiet;
+        let progress = batch.then(|| progress::Progress::new(commands.len()));
+        let stats = Mutex::new(diagnostics::DiagnosticStats::default());
+        let collected = Mutex::new(Vec::new());
+        let reports = Mutex::new(Vec::new());
+        let recorded = Mutex::new(Vec::new());
+        let fixes = Mutex::new(Vec::new());
+        let failed = Mutex::new(vec![false; commands.len()]);
+        let build_start = Instant::now();
+        if let Some(baseline) = &baseline {
+            baseline.reset();
+        }
+        
+        // /Qdiag-file：每次构建重新写入（/MP 时多个单元可共用一个文件）
+        if args.dry_run.is_none() {
+            let mut files: Vec<&PathBuf> = commands.iter().filter_map(|c| c.diag_file.as_ref()).collect();
+            files.dedup();
+            for path in files {
+                diagfile::create(path)?;
+            }
+        }
+        let results = parallel::run_jobs(commands.len(), jobs, |i| {
+            // Ctrl+C 后不再启动其余翻译单元
+            if interrupt::requested() {
+                return Ok(interrupt::exit_status());
+            }
+            let unit = commands[i].input_files.first()
+                .map(|f| f.display().to_string())
+                .unwrap_or_default();
+            if commands[i].needs.iter().any(|&j| failed.lock().unwrap_or_else(|e| e.into_inner())[j]) {
+                failed.lock().unwrap_or_else(|e| e.into_inner())[i] = true;
+                console::eprint(&format!("[icx-rustc] skipping {}: a crate it uses failed to build", unit));
+                return Ok(exit_code::COMPILE_ERROR);
+            }
+            if let Some(progress) = &progress {
+                progress.start(&unit);
+            }
+            let start = Instant::now();
+            let report = compile_unit(args, cache.as_ref(), &stats, &collected, &recorded, &fixes, &commands[i]);
+            if let Some(progress) = &progress {
+                let ok = report.as_ref().is_ok_and(|r| r.code == exit_code::SUCCESS);
+                progress.finish(&unit, start.elapsed(), ok);
+            }
+            let report = report?;
+            let code = report.code;
+            if code != exit_code::SUCCESS {
+                failed.lock().unwrap_or_else(|e| e.into_inner())[i] = true;
+            }
+            reports.lock().unwrap_or_else(|e| e.into_inner()).push((i, report));
+            Ok(code)
+        });
+        
+        // 与 cl 一致：继续编译其余文件，返回第一个失败码
+        let mut status = exit_code::SUCCESS;
+        for code in results {
+            let code = code?;
+            if status == exit_code::SUCCESS {
+                status = code;
+            }
+        }
+        if interrupt::requested() {
+            return Ok(interrupt::exit_status());
+        }
+        if let Some(path) = args.sarif.as_ref().filter(|_| args.dry_run.is_none()) {
+            sarif::write(path, &collected.lock().unwrap_or_else(|e| e.into_inner()))?;
+        }
+        let stats = stats.lock().unwrap_or_else(|e| e.into_inner());
+        if batch {
+            let elapsed = build_start.elapsed().as_millis() as u64;
+            diagnostics::print_build_summary(&stats, commands.len(), elapsed);
+        }
+        if let Some(top) = args.diag_stats.filter(|_| args.dry_run.is_none()) {
+            diagnostics::print_diag_stats(&stats, top);
+        }
+        
+        // /Qdiag-baseline：记录本次的警告，或只让新警告导致失败
+        let baseline_file = &args.diag_baseline_file;
+        if args.diag_baseline == Some(cli::BaselineMode::Record) && args.dry_run.is_none() {
+            let count = baseline::record(baseline_file, &recorded.lock().unwrap_or_else(|e| e.into_inner()))?;
+            if !args.quiet {
+                console::eprint(&format!(
+                    "{} recorded {} warning(s) in {}",
+                    "[icx-rustc]".bright_blue().bold(),
+                    count,
+                    baseline_file.display()
+                ));
+            }
+        }
+        if let Some(baseline) = &baseline {
+            let fixed = baseline.fixed();
+            if fixed > 0 && !args.quiet {
+                console::eprint(&format!(
+                    "{} {} baseline warning(s) no longer occur; update {} with /Qdiag-baseline:record",
+                    "[icx-rustc]".bright_blue().bold(),
+                    fixed,
+                    baseline_file.display()
+                ));
+            }
+            if stats.warnings > 0 {
+                let message = format!(
+                    "{} {} {} new warning(s) not in the baseline {}",
+                    i18n::t("error").bright_red().bold(),
+                    "[ICX]".bright_cyan(),
+                    stats.warnings,
+                    baseline_file.display()
+                );
+                buildlog::write("", &message);
+                console::eprint(&message);
+                if status == exit_code::SUCCESS {
+                    status = exit_code::COMPILE_ERROR;
+                }
+            }
+        }
+        
+        // /Qfix：所有翻译单元结束后统一修改源文件
+        if args.fix && args.dry_run.is_none() {
+            fix::apply(&fixes.lock().unwrap_or_else(|e| e.into_inner()), args.quiet)?;
+        }
+        
+        // --summary-format=json 与 /Qbuild-report：按命令行顺序列出各翻译单元
+        let json = args.summary_format == cli::SummaryFormat::Json || args.summary_file.is_some();
+        if (json || args.build_report.is_some()) && args.dry_run.is_none() {
+            let mut reports = std::mem::take(&mut *reports.lock().unwrap_or_else(|e| e.into_inner()));
+            reports.sort_by_key(|(i, _)| *i);
+            let reports: Vec<summary::UnitReport> = reports.into_iter().map(|(_, r)| r).collect();
+            let totals = summary::summary(status, build_start.elapsed(), &stats, &reports);
+            if json {
+                summary::write(args.summary_file.as_deref(), &totals)?;
+            }
+            if let Some(path) = &args.build_report {
+                summary::write_report(path, args, &commands[0].executable, translate_time, totals, &reports)?;
+            }
+        }
+        Ok(status)
+    };
+    
+    if args.watch && args.dry_run.is_none() {
+        return watch::run(&commands, build);
+    }
+    build()
+}
+
+/// Runs one translated rustc invocation, with its pre- and post-compile hooks
+fn compile_unit(
+    args: &cli::Args,
+    cache: Option<&cache::Cache>,
+    stats: &Mutex<diagnostics::DiagnosticStats>,
+    collected: &Mutex<Vec<diagnostics::Diagnostic>>,
+    recorded: &Mutex<Vec<diagnostics::Diagnostic>>,
+    fixes: &Mutex<Vec<fix::Fix>>,
+    rustc_cmd: &translator::RustcCommand,
+) -> Result<summary::UnitReport> {
+    let Some(hooks) = rustc_cmd.hooks.as_deref().filter(|_| args.dry_run.is_none()) else {
+        return build_unit(args, cache, stats, collected, recorded, fixes, rustc_cmd);
+    };
+    let start = Instant::now();
+    let code = hooks.run(hooks::Stage::PreCompile, args, Some(rustc_cmd), None)?;
+    let pre = ("pre-hooks", start.elapsed());
+    if code != exit_code::SUCCESS {
+        return Ok(summary::UnitReport {
+            input: rustc_cmd.input_files.first()
+                .map(|f| f.display().to_string())
+                .unwrap_or_default(),
+            code,
+            elapsed: start.elapsed(),
+            phases: vec![pre],
+            ..Default::default()
+        });
+    }
+    let mut report = build_unit(args, cache, stats, collected, recorded, fixes, rustc_cmd)?;
+    if hooks.has(hooks::Stage::PreCompile) {
+        report.phases.insert(0, pre);
+    }
+    let start = Instant::now();
+    let code = hooks.run(hooks::Stage::PostCompile, args, Some(rustc_cmd), Some(report.code))?;
+    // 链接后钩子只针对可执行文件与动态库
+    let code = if code == exit_code::SUCCESS && report.code == exit_code::SUCCESS && rustc_cmd.links() {
+        hooks.run(hooks::Stage::PostLink, args, Some(rustc_cmd), Some(report.code))?
+    } else {
+        code
+    };
+    if hooks.has(hooks::Stage::PostCompile) || hooks.has(hooks::Stage::PostLink) {
+        report.phases.push(("post-hooks", start.elapsed()));
+    }
+    if report.code == exit_code::SUCCESS {
+        report.code = code;
+    }
+    Ok(report)
+}
+
+fn build_unit(
+    args: &cli::Args,
+    cache: Option<&cache::Cache>,
+    stats: &Mutex<diagnostics::DiagnosticStats>,
+    collected: &Mutex<Vec<diagnostics::Diagnostic>>,
+    recorded: &Mutex<Vec<diagnostics::Diagnostic>>,
+    fixes: &Mutex<Vec<fix::Fix>>,
+    rustc_cmd: &translator::RustcCommand,
+) -> Result<summary::UnitReport> {
+    let start = Instant::now();
+    let mut report = summary::UnitReport {
+        input: rustc_cmd.input_files.first()
+            .map(|f| f.display().to_string())
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    for compile in &rustc_cmd.c_steps {
+        buildlog::write("[icx-rustc] ", &compile.display());
+    }
+    let rust = !rustc_cmd.input_files.is_empty() || !rustc_cmd.link_inputs.is_empty();
+    if rust {
+        report.command = rustc_cmd.arguments();
+    }
+    if rust {
+        buildlog::write("[icx-rustc] ", &rustc_cmd.display());
+    }
+    for step in rustc_cmd.archive.iter().chain(&rustc_cmd.bolt).chain(&rustc_cmd.wasm) {
+        buildlog::write("[icx-rustc] ", &step.display());
+    }
+    
+    // 显示命令（verbose 模式；/Qquiet 时只在不写脚本的 --### 中显示）
+    let echo = match &args.dry_run {
+        Some(script) => !args.quiet || script.as_os_str().is_empty(),
+        None => args.verbose,
+    };
+    if echo {
+        for compile in &rustc_cmd.c_steps {
+            console::eprint(&format!("{} {}", "[icx-rustc]".bright_blue().bold(), compile.display().dimmed()));
+        }
+        if rust {
+            console::eprint(&format!(
+                "{} {}",
+                "[icx-rustc]".bright_blue().bold(),
+                rustc_cmd.display().dimmed()
+            ));
+        }
+        for step in rustc_cmd.archive.iter().chain(&rustc_cmd.bolt).chain(&rustc_cmd.wasm) {
+            console::eprint(&format!("{} {}", "[icx-rustc]".bright_blue().bold(), step.display().dimmed()));
+        }
+    }
+    
+    // --### 也记录，可以不构建就生成数据库
+    if let Some(path) = &args.compile_commands {
+        compdb::record(path, rustc_cmd)?;
+    }
+    
+    if args.dry_run.is_some() {
+        return Ok(report);
+    }
+    
+    // C/C++ 源文件先编译，目标文件是 rustc 的链接输入
+    let step = Instant::now();
+    report.code = cc::run(rustc_cmd)?;
+    if !rustc_cmd.c_steps.is_empty() {
+        report.phases.push(("c-compile", step.elapsed()));
+    }
+    if let Some(mode) = rustc_cmd.symbols.filter(|_| report.code == exit_code::SUCCESS && !rust) {
+        symbols::print(rustc_cmd, mode)?;
+    }
+    if report.code != exit_code::SUCCESS || !rust {
+        report.elapsed = start.elapsed();
+        return Ok(report);
+    }
+    
+    // /Qup-to-date：源文件、选项与编译器都未变时不再编译，只重放上次的诊断
+    if let Some(stderr) = args.up_to_date.then(|| stamp::is_current(rustc_cmd)).flatten() {
+        let replayed = executor::replay(rustc_cmd, &stderr);
+        if !args.quiet {
+            diagnostics::print_up_to_date(&rustc_cmd.build_info(false));
+        }
+        report.cached = true;
+        report.errors = replayed.errors;
+        report.warnings = replayed.warnings;
+        report.artifacts = artifacts(rustc_cmd);
+        collect_diagnostics(args, stats, collected, recorded, fixes, rustc_cmd, replayed.diagnostics);
+        report.elapsed = start.elapsed();
+        return Ok(report);
+    }
+    
+    // 命中缓存时直接复制产物，并重放缓存的诊断
+    if let Some(cache) = cache {
+        let step = Instant::now();
+        let restored = cache.restore(rustc_cmd)?;
+        report.phases.push(("cache-restore", step.elapsed()));
+        if let Some(stderr) = restored {
+            let replayed = executor::replay(rustc_cmd, &stderr);
+            if args.verbose {
+                console::eprint(&format!("{} cache hit", "[icx-rustc]".bright_blue().bold()));
+            }
+            // 缓存的是模块本身；pkg/ 下的 wasm-bindgen 输出重新生成
+            let module = rustc_cmd.artifact();
+            for step in rustc_cmd.wasm.iter().filter(|s| module.as_ref() != Some(&s.output)) {
+                let code = cc::run_step(rustc_cmd, step)?;
+                if code != exit_code::SUCCESS {
+                    report.code = code;
+                    report.elapsed = start.elapsed();
+                    return Ok(report);
+                }
+            }
+            if !args.quiet {
+                diagnostics::print_cache_hit(start.elapsed().as_millis() as u64, &rustc_cmd.build_info(true));
+            }
+            if let Some(view) = &rustc_cmd.asm_view {
+                asm_view::show(rustc_cmd, view)?;
+            }
+            if let Some(top) = rustc_cmd.size_report {
+                size_report::print(rustc_cmd, top)?;
+            }
+            if let Some(mode) = rustc_cmd.symbols {
+                symbols::print(rustc_cmd, mode)?;
+            }
+            report.cached = true;
+            report.errors = replayed.errors;
+            report.warnings = replayed.warnings;
+            report.artifacts = artifacts(rustc_cmd);
+            collect_diagnostics(args, stats, collected, recorded, fixes, rustc_cmd, replayed.diagnostics);
+            report.elapsed = start.elapsed();
+            return Ok(report);
+        }
+    }
+    
+    // 执行
+    let step = Instant::now();
+    let compiled = executor::run(rustc_cmd)?;
+    report.phases.push(("rustc", step.elapsed()));
+    let mut code = compiled.code;
+    // /Qar：目标文件并入 rustc 生成的静态库（在存入缓存之前）
+    if let Some(archive) = rustc_cmd.archive.as_ref().filter(|_| code == exit_code::SUCCESS) {
+        let step = Instant::now();
+        code = cc::run_archive(rustc_cmd, archive)?;
+        report.phases.push(("archive", step.elapsed()));
+    }
+    // -v /Qstrip：与 bolt 之前的产物比较
+    if let Some(binary) = rustc_cmd.strip_report.as_ref().filter(|_| code == exit_code::SUCCESS) {
+        let step = Instant::now();
+        if let Err(e) = executor::report_strip(rustc_cmd, binary) {
+            console::eprint(&format!("[icx-rustc] warning: /Qstrip: savings not measured: {:#}", e));
+        }
+        report.phases.push(("strip", step.elapsed()));
+    }
+    if let Some(bolt) = rustc_cmd.bolt.as_ref().filter(|_| code == exit_code::SUCCESS) {
+        let step = Instant::now();
+        code = bolt::run(rustc_cmd, bolt)?;
+        report.phases.push(("bolt", step.elapsed()));
+    }
+    for wasm in &rustc_cmd.wasm {
+        if code != exit_code::SUCCESS {
+            break;
+        }
+        let step = Instant::now();
+        code = cc::run_step(rustc_cmd, wasm)?;
+        report.phases.push(("wasm", step.elapsed()));
+    }
+    // 摘要在后续步骤之后，报告的是最终产物的大小
+    compiled.report(rustc_cmd, code);
+    if let Some(depfile) = rustc_cmd.depfile.as_ref().filter(|_| code == exit_code::SUCCESS) {
+        depfile::rewrite(rustc_cmd, depfile)?;
+    }
+    if let Some(view) = rustc_cmd.asm_view.as_ref().filter(|_| code == exit_code::SUCCESS) {
+        asm_view::show(rustc_cmd, view)?;
+    }
+    if let Some(top) = rustc_cmd.size_report.filter(|_| code == exit_code::SUCCESS) {
+        size_report::print(rustc_cmd, top)?;
+    }
+    if let Some(mode) = rustc_cmd.symbols.filter(|_| code == exit_code::SUCCESS) {
+        symbols::print(rustc_cmd, mode)?;
+    }
+    report.code = code;
+    report.errors = compiled.errors;
+    report.warnings = compiled.warnings;
+    report.usage = compiled.usage;
+    report.passes = compiled.passes;
+    
+    if let (Some(cache), exit_code::SUCCESS) = (cache, code) {
+        let step = Instant::now();
+        if let Err(e) = cache.store(rustc_cmd, &compiled.stderr) {
+            console::eprint(&format!("[icx-rustc] warning: not cached: {}", e));
+        }
+        report.phases.push(("cache-store", step.elapsed()));
+    }
+    if args.up_to_date && code == exit_code::SUCCESS {
+        if let Err(e) = stamp::write(rustc_cmd, &compiled.stderr) {
+            console::eprint(&format!("[icx-rustc] warning: /Qup-to-date: no stamp written: {}", e));
+        }
+    }
+    
+    collect_diagnostics(args, stats, collected, recorded, fixes, rustc_cmd, compiled.diagnostics);
+    
+    if code == exit_code::SUCCESS {
+        report.artifacts = artifacts(rustc_cmd);
+    }
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+/// Hands a unit's diagnostics to the build-wide consumers (statistics,
+/// baseline, /Qfix, /Qsarif), whether compiled live or replayed
+fn collect_diagnostics(
+    args: &cli::Args,
+    stats: &Mutex<diagnostics::DiagnosticStats>,
+    collected: &Mutex<Vec<diagnostics::Diagnostic>>,
+    recorded: &Mutex<Vec<diagnostics::Diagnostic>>,
+    fixes: &Mutex<Vec<fix::Fix>>,
+    rustc_cmd: &translator::RustcCommand,
+    mut diagnostics: Vec<diagnostics::Diagnostic>,
+) {
+    // 汇总诊断统计（诊断本身已在编译时实时显示）
+    if args.optimize_diagnostics {
+        let mut stats = stats.lock().unwrap_or_else(|e| e.into_inner());
+        diagnostics::post_process(&mut stats, &diagnostics);
+    }
+    
+    // /Qdiag-baseline:record：保留 rustc 报告的原始文件名，与检查时一致
+    if args.diag_baseline == Some(cli::BaselineMode::Record) {
+        recorded.lock().unwrap_or_else(|e| e.into_inner()).extend(diagnostics.iter().cloned());
+    }
+    
+    if args.fix {
+        let suggested = fix::suggestions(&diagnostics, |path| rustc_cmd.resolve(path));
+        fixes.lock().unwrap_or_else(|e| e.into_inner()).extend(suggested);
+    }
+    
+    // /Qsarif：按 /Qdir 解析文件名后留待整个构建结束时写出
+    if args.sarif.is_some() {
+        for span in diagnostics.iter_mut().flat_map(|d| d.spans.iter_mut()) {
+            span.file_name = rustc_cmd.resolve(Path::new(&span.file_name)).display().to_string();
+        }
+        collected.lock().unwrap_or_else(|e| e.into_inner()).extend(diagnostics);
+    }
+}
+
+/// Outputs of the command that exist on disk
+fn artifacts(rustc_cmd: &translator::RustcCommand) -> Vec<PathBuf> {
+    rustc_cmd.outputs()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|out| rustc_cmd.resolve(&out))
+        .filter(|out| out.exists())
+        .collect()
+}
+
+fn print_version() {
+    println!("Intel(R) oneAPI Rust Compiler (icx-rustc)");
+    println!("Version 2025.0.0 (Rust Edition)");
+    println!("Target: x86_64-pc-windows-msvc / x86_64-unknown-linux-gnu");
+    println!("Rustc wrapper with Intel-style command interface");
+}
@@ -0,0 +1,30 @@
+use crate::cli::Args;
+use crate::stdin::{self, TempSource};
+use anyhow::{bail, Result};
+
+/// /Qeval:"<expression or statements>": runs a snippet and prints its value
+///
+/// The snippet becomes the body of a block in `fn main`, so `use`
+/// items, `let` statements and a final expression all work; a value
+/// other than `()` is printed with `{:?}`. It is compiled with the other
+/// options given (/arch:AVX2, /O3, ...) and run like /run; arguments
+/// after `--` are the program's. The summary line is left out.
+pub fn prepare(args: &mut Args) -> Result<Option<TempSource>> {
+    let Some(snippet) = args.eval.clone() else {
+        return Ok(None);
+    };
+    if !args.files.is_empty() {
+        bail!("/Qeval takes no input files; the snippet is the program");
+    }
+    let source = format!(
+        "#![allow(unused)]\nfn main() {{\n    let value = {{\n{}\n    }};\n    if std::any::type_name_of_val(&value) != \"()\" {{\n        println!(\"{{:?}}\", value);\n    }}\n}}\n",
+        snippet
+    );
+    let (guard, file) = stdin::temporary("eval", "eval", source.as_bytes())?;
+    args.files.push(file.clone());
+    let end = args.command_line.iter().position(|a| a == "--").unwrap_or(args.command_line.len());
+    args.command_line.insert(end, file.display().to_string());
+    args.run = true;
+    args.quiet = true;
+    Ok(Some(guard))
+}
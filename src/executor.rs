@@ -1,76 +1,745 @@
-﻿use crate::diagnostics::{format_diagnostic, print_summary};
-use crate::translator::RustcCommand;
-use anyhow::{Context, Result};
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
-use std::time::Instant;
-
-pub fn run(cmd: &RustcCommand) -> Result<i32> {
-    let start = Instant::now();
-    
-    let mut command = Command::new(&cmd.executable);
-    command.args(&cmd.args);
-    
-    for file in &cmd.input_files {
-        command.arg(file);
-    }
-    
-    if let Some(out) = &cmd.output {
-        command.arg("-o").arg(out);
-    }
-    
-    // 设置环境变量
-    for (key, val) in &cmd.env_vars {
-        command.env(key, val);
-    }
-    
-    // 捕获输出以便处理
-    command.stdout(Stdio::piped());
-    command.stderr(Stdio::piped());
-    
-    let mut child = command.spawn()
-        .with_context(|| format!("Failed to spawn {}", cmd.executable))?;
-    
-    let stdout = child.stdout.take().unwrap();
-    let stderr = child.stderr.take().unwrap();
-    
-    let stdout_handle = std::thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                println!("{}", line);
-            }
-        }
-    });
-    
-    let mut errors = 0;
-    let mut warnings = 0;
-    
-    let stderr_handle = std::thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let formatted = format_diagnostic(&line);
-                eprintln!("{}", formatted);
-                
-                if line.contains("error[") || line.contains("error:") {
-                    // 统计错误
-                } else if line.contains("warning:") {
-                    // 统计警告
-                }
-            }
-        }
-    });
-    
-    let status = child.wait()
-        .context("Failed to wait for rustc")?;
-    
-    stdout_handle.join().ok();
-    stderr_handle.join().ok();
-    
-    let elapsed = start.elapsed().as_millis() as u64;
-    
-    print_summary(0, 0, elapsed);
-    
-    Ok(status.code().unwrap_or(1))
-}
+use crate::baseline::Baseline;
+use crate::buildlog;
+use crate::cli::DiagFormat;
+use crate::cache;
+use crate::cmdline;
+use crate::console;
+use crate::diagfile;
+use crate::exit_code;
+use crate::hyperlink;
+use crate::i18n;
+use crate::interrupt;
+use crate::resources::{self, Usage};
+use crate::diagnostics::{parse_time_pass, print_summary, print_time_report, Diagnostic, DiagnosticReporter};
+use crate::suppress::Suppression;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Exit code and collected diagnostics of one compiler invocation
+pub struct Compiled {
+    pub code: i32,
+    pub errors: u32,
+    pub warnings: u32,
+    /// Top-level errors and warnings from the JSON stream, in emission order
+    pub diagnostics: Vec<Diagnostic>,
+    /// Peak RSS and CPU time (/Qresource-report)
+    pub usage: Option<Usage>,
+    /// `-Ztime-passes` results in seconds (/Qtime)
+    pub passes: Vec<(String, f64)>,
+    /// stderr lines as received, stored with cached and up-to-date units
+    pub stderr: Vec<String>,
+    /// Compile time in milliseconds; `None` when there is no summary left to
+    /// print (replayed, timed out or interrupted)
+    elapsed: Option<u64>,
+}
+
+impl Compiled {
+    /// Prints the summary, /Qtime and /Qresource-report, once the archive,
+    /// bolt and wasm steps ran and so the artifact sizes are final
+    pub fn report(&self, cmd: &RustcCommand, code: i32) {
+        let Some(elapsed) = self.elapsed else {
+            return;
+        };
+        if !cmd.quiet {
+            let info = (code == exit_code::SUCCESS && !cmd.condensed).then(|| cmd.build_info(false));
+            print_summary(self.errors, self.warnings, elapsed, info.as_ref());
+        }
+        if cmd.time_report {
+            print_time_report(&self.passes);
+        }
+        if let Some(usage) = &self.usage {
+            let line = usage.summary();
+            buildlog::write("", &line);
+            console::eprint(&line);
+        }
+    }
+}
+
+pub fn run(cmd: &RustcCommand) -> Result<Compiled> {
+    // 远程编译失败（连接、传输）时回退到本地
+    if let Some(host) = &cmd.remote {
+        match run_remote(cmd, host) {
+            Ok(compiled) => return Ok(compiled),
+            Err(e) => console::eprint(&format!(
+                "[icx-rustc] warning: remote compilation on {} failed, compiling locally: {:#}",
+                host, e
+            )),
+        }
+    }
+    
+    let (command, _stub) = build_command(cmd)?;
+    let outcome = spawn_and_render(cmd, command)?;
+    Ok(outcome.finish(cmd))
+}
+
+/// Result of one compiler process
+struct Outcome {
+    start: Instant,
+    /// `None` when the process was killed after /Qtimeout
+    status: Option<ExitStatus>,
+    /// Peak RSS and CPU time (/Qresource-report)
+    usage: Option<Usage>,
+    rendered: Rendered,
+}
+
+impl Outcome {
+    fn finish(self, cmd: &RustcCommand) -> Compiled {
+        let code = self.exit_code(cmd);
+        let elapsed = (self.status.is_some() && !interrupt::requested())
+            .then(|| self.start.elapsed().as_millis() as u64);
+        Compiled {
+            code,
+            errors: self.rendered.errors,
+            warnings: self.rendered.warnings,
+            diagnostics: self.rendered.diagnostics,
+            usage: self.usage,
+            passes: self.rendered.passes,
+            stderr: self.rendered.lines,
+            elapsed,
+        }
+    }
+    
+    /// Maps the result to an icx exit code; a timeout is reported here, as
+    /// nothing runs after it
+    fn exit_code(&self, cmd: &RustcCommand) -> i32 {
+        let elapsed = self.start.elapsed().as_millis() as u64;
+        
+        // Ctrl+C：删除可能只写了一半的产物，免得 make 误以为已是最新
+        if interrupt::requested() && !self.status.is_some_and(|s| s.success()) {
+            for out in cmd.outputs().unwrap_or_default() {
+                let _ = std::fs::remove_file(cmd.resolve(&out));
+            }
+            return interrupt::exit_status();
+        }
+        let Some(status) = self.status else {
+            let message = format!(
+                "{} {} compilation timed out after {} s, {} was killed",
+                i18n::t("error").bright_red().bold(),
+                "[ICX]".bright_cyan(),
+                cmd.timeout.unwrap_or_default().as_secs(),
+                cmd.executable
+            );
+            buildlog::write("", &message);
+            console::eprint(&message);
+            if let Some(path) = &cmd.diag_file {
+                diagfile::append(path, &message);
+            }
+            if !cmd.quiet {
+                print_summary(self.rendered.errors + 1, self.rendered.warnings, elapsed, None);
+            }
+            return exit_code::KILLED;
+        };
+        
+        // 报告了错误但退出码为 0（例如被包装脚本掩盖）时仍视为失败
+        let code = if self.rendered.aborted { Some(1) } else { status.code() };
+        exit_code::from_rustc(code, self.rendered.errors)
+    }
+}
+
+/// Spawns the compiler and renders its output as it arrives
+fn spawn_and_render(cmd: &RustcCommand, mut command: Command) -> Result<Outcome> {
+    let start = Instant::now();
+    
+    // 超时与 Ctrl+C 需要结束整个进程树（rustc 会再启动链接器）
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    
+    // 捕获输出以便处理
+    command.stdout(Stdio::piped());
+    command.stderr(Stdio::piped());
+    
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command.spawn()
+        .with_context(|| format!("Failed to spawn {}", program))?;
+    let _tracked = interrupt::track(&child);
+    
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    
+    let stdout_handle = std::thread::spawn(move || {
+        for line in output_lines(stdout) {
+            buildlog::write("stdout| ", &line);
+            console::print(&line);
+        }
+    });
+    
+    let stderr_handle = {
+        let rendering = Rendering::of(cmd);
+        let pid = child.id();
+        std::thread::spawn(move || render(&rendering, output_lines(stderr), || kill_tree(pid)))
+    };
+    
+    let (status, usage) = wait_child(&mut child, cmd.timeout, cmd.resource_report)?;
+    if status.is_none() {
+        kill_process_tree(&mut child);
+    }
+    
+    stdout_handle.join().ok();
+    let rendered = stderr_handle.join().unwrap_or_default();
+    
+    Ok(Outcome { start, status, usage, rendered })
+}
+
+/// How compiler output is rendered, copied out of the command for the reader thread
+struct Rendering {
+    error_limit: Option<u32>,
+    abort_at_limit: bool,
+    condensed: bool,
+    quiet: bool,
+    msvc: bool,
+    grouped: bool,
+    suppressed: Vec<Suppression>,
+    baseline: Option<Arc<Baseline>>,
+    links: Option<PathBuf>,
+    width: Option<usize>,
+    time_report: bool,
+    cargo: bool,
+    diag_file: Option<PathBuf>,
+}
+
+impl Rendering {
+    fn of(cmd: &RustcCommand) -> Self {
+        let msvc = cmd.diag_format == DiagFormat::Msvc;
+        Self {
+            error_limit: cmd.error_limit,
+            abort_at_limit: cmd.error_limit_abort,
+            condensed: cmd.condensed,
+            quiet: cmd.quiet,
+            msvc,
+            grouped: cmd.diag_group,
+            suppressed: cmd.suppress.clone(),
+            baseline: cmd.baseline.clone(),
+            // 输出转发给 --daemon 客户端时无法判断其终端
+            links: (hyperlink::enabled() && !msvc && !console::is_redirected())
+                .then(|| cmd.cwd.clone().unwrap_or_default()),
+            width: (!msvc && !console::is_redirected()).then(console::width).flatten(),
+            time_report: cmd.time_report,
+            cargo: cmd.cargo,
+            diag_file: cmd.diag_file.clone(),
+        }
+    }
+}
+
+/// The compiler's stderr after rendering
+#[derive(Default)]
+struct Rendered {
+    errors: u32,
+    warnings: u32,
+    /// `-Ztime-passes` results (/Qtime)
+    passes: Vec<(String, f64)>,
+    diagnostics: Vec<Diagnostic>,
+    /// Killed on reaching /Qdiag-error-limit
+    aborted: bool,
+    /// The lines as received, kept with cached and up-to-date units
+    lines: Vec<String>,
+}
+
+/// Renders compiler stderr: JSON diagnostics through the reporter, other
+/// output (linker messages) as text; `abort` kills the compiler at the
+/// /Qdiag-error-limit
+fn render(rendering: &Rendering, lines: impl Iterator<Item = String>, abort: impl Fn()) -> Rendered {
+    // 诊断同时写入 /Qlog 与 /Qdiag-file
+    let show = |text: &str| {
+        buildlog::write("", text);
+        console::eprint(text);
+        if let Some(path) = &rendering.diag_file {
+            diagfile::append(path, text);
+        }
+    };
+    let reporter = DiagnosticReporter::new()
+        .condensed(rendering.condensed)
+        .quiet(rendering.quiet)
+        .msvc(rendering.msvc)
+        .grouped(rendering.grouped)
+        .suppress(rendering.suppressed.clone())
+        .baseline(rendering.baseline.clone())
+        .hyperlinks(rendering.links.clone())
+        .width(rendering.width);
+    let error_limit = rendering.error_limit;
+    let mut rendered = Rendered::default();
+    let mut shown_errors = 0;
+    let mut hidden_errors = 0;
+    for line in lines {
+        buildlog::write("stderr| ", &line);
+        rendered.lines.push(line.clone());
+        
+        // -Ztime-passes 输出汇总到阶段报告中
+        if rendering.time_report {
+            if let Some(pass) = parse_time_pass(&line) {
+                rendered.passes.push(pass);
+                continue;
+            }
+        }
+        
+        // JSON 诊断结构化渲染，其余输出（链接器等）按文本处理
+        let (formatted, w, e, file, json) = match Diagnostic::parse(&line) {
+            // cargo 需要 artifact 通知（流水线编译）等原始消息
+            Some(diag) if rendering.cargo && !diag.message_type.is_empty() && diag.message_type != "diagnostic" => {
+                console::eprint(&line);
+                continue;
+            }
+            Some(diag) => {
+                let (formatted, w, e) = reporter.render(&diag);
+                let file = diag.primary_span().map(|s| s.file_name.clone());
+                if w + e > 0 {
+                    rendered.diagnostics.push(diag);
+                }
+                (formatted, w, e, file, true)
+            }
+            None => {
+                let (formatted, w, e) = reporter.format(&line);
+                (formatted, w, e, None, false)
+            }
+        };
+        
+        // 统计错误 / 警告
+        rendered.warnings += w;
+        rendered.errors += e;
+        
+        // /Qdiag-error-limit：超出上限的错误只计数，不显示
+        if e > 0 && error_limit.is_some_and(|limit| shown_errors >= limit) {
+            hidden_errors += e;
+            continue;
+        }
+        shown_errors += e;
+        // cargo 读取 JSON 诊断（--message-format=json、rust-analyzer），rendered 换成 ICX 格式
+        if rendering.cargo && json {
+            if !formatted.is_empty() {
+                buildlog::write("", &formatted);
+                if let Some(path) = &rendering.diag_file {
+                    diagfile::append(path, &formatted);
+                }
+                console::eprint(&with_rendered(&line, &formatted));
+            }
+        } else if let Some(formatted) = reporter.emit(file.as_deref(), formatted, w, e) {
+            show(&formatted);
+        }
+        if rendering.abort_at_limit && error_limit.is_some_and(|limit| shown_errors >= limit) && !rendered.aborted {
+            rendered.aborted = true;
+            abort();
+        }
+    }
+    
+    // /Qdiag-group：整个调用的诊断按文件一次输出，不与其他翻译单元交错
+    if let Some(grouped) = reporter.flush() {
+        show(&grouped);
+    }
+    if let Some(repeated) = reporter.repeated() {
+        show(&repeated);
+    }
+    
+    let limit = error_limit.unwrap_or_default();
+    let mut notes = Vec::new();
+    if hidden_errors > 0 {
+        notes.push(format!(
+            "     {} additional error(s) suppressed (/Qdiag-error-limit:{})",
+            hidden_errors, limit
+        ).bright_black().to_string());
+    }
+    if rendered.aborted {
+        notes.push(format!(
+            "{} {} compilation aborted after {} error(s)",
+            i18n::t("error").bright_red().bold(),
+            "[ICX]".bright_cyan(),
+            limit
+        ));
+    }
+    for note in notes {
+        show(&note);
+    }
+    rendered
+}
+
+/// A JSON diagnostic line with its `rendered` text replaced
+fn with_rendered(line: &str, rendered: &str) -> String {
+    let Ok(mut message) = serde_json::from_str::<serde_json::Value>(line) else {
+        return line.to_string();
+    };
+    if let Some(fields) = message.as_object_mut() {
+        fields.insert("rendered".to_string(), serde_json::Value::String(format!("{}\n", rendered)));
+    }
+    message.to_string()
+}
+
+/// Shows the compiler output recorded with a cached or up-to-date unit as
+/// if it had just compiled, so the diagnostics reach every consumer again
+pub fn replay(cmd: &RustcCommand, stderr: &[String]) -> Compiled {
+    let rendered = render(&Rendering::of(cmd), stderr.iter().cloned(), || {});
+    Compiled {
+        code: exit_code::SUCCESS,
+        errors: rendered.errors,
+        warnings: rendered.warnings,
+        diagnostics: rendered.diagnostics,
+        usage: None,
+        passes: Vec::new(),
+        stderr: rendered.lines,
+        elapsed: None,
+    }
+}
+
+/// Compiles on an SSH host: ship the sources, stream diagnostics, fetch the outputs
+///
+/// Errors mean nothing was compiled remotely and the caller may fall back to a
+/// local build; compile errors are a normal (non-zero) result.
+fn run_remote(cmd: &RustcCommand, host: &str) -> Result<Compiled> {
+    anyhow::ensure!(!cmd.input_files.is_empty(), "link steps run locally");
+    anyhow::ensure!(
+        !cmd.args.iter().any(|a| a == "-Ctarget-cpu=native"),
+        "-Ctarget-cpu=native must be resolved on the local host"
+    );
+    let outputs = cmd.outputs().context("outputs cannot be predicted")?;
+    
+    // 需要传输的文件：源文件（含模块）、extern 与链接输入，必须是相对路径
+    // /MP 的各单元并行运行，dep-info 各写各的目录
+    let dep_dir = tempfile::Builder::new().prefix("icx-rustc-remote-").tempdir()
+        .context("Failed to create a temporary directory")?;
+    let dep_file = dep_dir.path().join("dep-info.d");
+    let externs = cmd.args.iter()
+        .filter_map(|a| a.strip_prefix("--extern="))
+        .filter_map(|spec| spec.split_once('='))
+        .map(|(_, path)| PathBuf::from(path));
+    let mut files: Vec<PathBuf> = cache::source_files(cmd, &dep_file)?
+        .into_iter()
+        .chain(externs)
+        .chain(cmd.link_inputs.iter().cloned())
+        .collect();
+    files.sort();
+    files.dedup();
+    let portable = |p: &PathBuf| p.is_relative() && !p.components().any(|c| c == Component::ParentDir);
+    if let Some(file) = files.iter().chain(&outputs).find(|p| !portable(p)) {
+        anyhow::bail!("{} is not below the working directory", file.display());
+    }
+    
+    let local_dir = cmd.cwd.clone().unwrap_or_else(|| PathBuf::from("."));
+    let ssh = |script: &str| {
+        let mut command = Command::new("ssh");
+        command.args(["-o", "BatchMode=yes", host, script]);
+        command
+    };
+    
+    let output = ssh("mktemp -d").output().context("Failed to run ssh")?;
+    anyhow::ensure!(output.status.success(), "ssh {} exited with {}", host, output.status);
+    let remote_dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let remote_quoted = quote(&remote_dir);
+    let result = (|| {
+        pipe(
+            Command::new("tar").arg("-cf").arg("-").arg("-C").arg(&local_dir).args(&files),
+            &mut ssh(&format!("tar -xf - -C {}", remote_quoted)),
+        )
+        .context("Failed to upload sources")?;
+        
+        // 远程 shell 命令：与本地调用相同的参数
+        let rustc = std::env::var("ICX_REMOTE_RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let mut script = format!("cd {} &&", remote_quoted);
+        for out in &outputs {
+            if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+                script.push_str(&format!(" mkdir -p {} &&", quote(&parent.display().to_string())));
+            }
+        }
+        if !cmd.env_vars.is_empty() {
+            script.push_str(" env");
+            for (key, val) in &cmd.env_vars {
+                script.push_str(&format!(" {}", quote(&format!("{}={}", key, val))));
+            }
+        }
+        let mut words = vec![rustc];
+        words.extend(cmd.args.iter().cloned());
+        if !cmd.args.iter().any(|a| a.starts_with("--error-format")) {
+            words.push("--error-format=json".to_string());
+        }
+        words.extend(cmd.input_files.iter().map(|f| f.display().to_string()));
+        words.extend(cmd.link_inputs.iter().map(|f| format!("-Clink-arg={}", f.display())));
+        words.extend(cmd.link_libs.iter().map(|l| format!("-Clink-arg={}", l)));
+        if let Some(out) = &cmd.output {
+            words.push("-o".to_string());
+            words.push(out.display().to_string());
+        }
+        for word in &words {
+            script.push(' ');
+            script.push_str(&quote(word));
+        }
+        
+        let outcome = spawn_and_render(cmd, ssh(&script))?;
+        // ssh 自身失败（连接中断等）返回 255
+        if outcome.status.and_then(|s| s.code()) == Some(255) {
+            anyhow::bail!("connection to {} lost", host);
+        }
+        
+        if outcome.status.is_some_and(|s| s.success()) {
+            let names: Vec<String> = outputs.iter().map(|o| quote(&o.display().to_string())).collect();
+            pipe(
+                &mut ssh(&format!("cd {} && tar -cf - {}", remote_quoted, names.join(" "))),
+                Command::new("tar").arg("-xf").arg("-").arg("-C").arg(&local_dir),
+            )
+            .context("Failed to download artifacts")?;
+        }
+        Ok(outcome.finish(cmd))
+    })();
+    
+    let _ = ssh(&format!("rm -rf {}", remote_quoted)).stdout(Stdio::null()).status();
+    result
+}
+
+/// Runs `from | to`, failing unless both succeed
+fn pipe(from: &mut Command, to: &mut Command) -> Result<()> {
+    let mut producer = from.stdout(Stdio::piped()).spawn()
+        .with_context(|| format!("Failed to spawn {}", from.get_program().to_string_lossy()))?;
+    let stdout = producer.stdout.take().context("no pipe")?;
+    let status = to.stdin(stdout).stdout(Stdio::null()).status()
+        .with_context(|| format!("Failed to spawn {}", to.get_program().to_string_lossy()))?;
+    let produced = producer.wait()?;
+    anyhow::ensure!(produced.success() && status.success(), "transfer failed");
+    Ok(())
+}
+
+fn quote(word: &str) -> String {
+    shlex::try_quote(word)
+        .map(|q| q.into_owned())
+        .unwrap_or_else(|_| word.to_string())
+}
+
+/// Lines of compiler output, including ones that are not valid UTF-8
+///
+/// Linkers print localized messages in the system code page and file names
+/// as raw bytes; such lines are decoded instead of ending the stream.
+fn output_lines(reader: impl Read) -> impl Iterator<Item = String> {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    std::iter::from_fn(move || {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                if buf.ends_with(b"\n") {
+                    buf.pop();
+                    if buf.ends_with(b"\r") {
+                        buf.pop();
+                    }
+                }
+                Some(decode(&buf))
+            }
+        }
+    })
+}
+
+fn decode(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    #[cfg(windows)]
+    if let Some(text) = decode_code_page(bytes) {
+        return text;
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Decodes through the console output code page (the ANSI code page without a console)
+#[cfg(windows)]
+fn decode_code_page(bytes: &[u8]) -> Option<String> {
+    use windows_sys::Win32::Globalization::{MultiByteToWideChar, CP_ACP};
+    use windows_sys::Win32::System::Console::GetConsoleOutputCP;
+    
+    let code_page = match unsafe { GetConsoleOutputCP() } {
+        0 => CP_ACP,
+        cp => cp,
+    };
+    let len = i32::try_from(bytes.len()).ok()?;
+    // 先取所需长度，再实际转换
+    let wide_len = unsafe { MultiByteToWideChar(code_page, 0, bytes.as_ptr(), len, std::ptr::null_mut(), 0) };
+    if wide_len <= 0 {
+        return None;
+    }
+    let mut wide = vec![0u16; wide_len as usize];
+    let written = unsafe { MultiByteToWideChar(code_page, 0, bytes.as_ptr(), len, wide.as_mut_ptr(), wide_len) };
+    (written > 0).then(|| String::from_utf16_lossy(&wide[..written as usize]))
+}
+
+/// Waits for the child, returning no status once the limit has passed
+///
+/// With `measure` the child is reaped through [`resources::try_wait`] so its
+/// resource usage can be read.
+fn wait_child(child: &mut Child, limit: Option<Duration>, measure: bool) -> Result<(Option<ExitStatus>, Option<Usage>)> {
+    let deadline = limit.map(|limit| Instant::now() + limit);
+    let block = deadline.is_none();
+    loop {
+        if measure {
+            if let Some((status, usage)) = resources::try_wait(child, block).context("Failed to wait for rustc")? {
+                return Ok((Some(status), Some(usage)));
+            }
+        } else if block {
+            return Ok((Some(child.wait().context("Failed to wait for rustc")?), None));
+        } else if let Some(status) = child.try_wait().context("Failed to wait for rustc")? {
+            return Ok((Some(status), None));
+        }
+        
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok((None, None));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn kill_process_tree(child: &mut Child) {
+    kill_tree(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Kills a compiler together with the linker and other processes it started
+fn kill_tree(pid: u32) {
+    // rustc 在独立进程组中运行，连同链接器一起结束
+    #[cfg(unix)]
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", pid)])
+        .status();
+    
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .output();
+}
+
+/// A path for the rustc command line; in `\\?\` form when it is too long
+/// for the linker (deep CI checkouts), otherwise as given
+fn path_arg(cmd: &RustcCommand, path: &Path) -> PathBuf {
+    let resolved = cmd.resolve(path);
+    let long = cmdline::long_path(&resolved);
+    if long == resolved {
+        path.to_path_buf()
+    } else {
+        long
+    }
+}
+
+/// Assembles the rustc process; the returned stub must outlive the child
+fn build_command(cmd: &RustcCommand) -> Result<(Command, Option<LinkStub>)> {
+    let mut command = match &cmd.wrapper {
+        Some(wrapper) => {
+            let mut command = Command::new(wrapper);
+            command.arg(&cmd.executable);
+            command
+        }
+        None => Command::new(&cmd.executable),
+    };
+    for arg in &cmd.args {
+        match arg.strip_prefix("--out-dir=") {
+            Some(dir) => command.arg(format!("--out-dir={}", path_arg(cmd, Path::new(dir)).display())),
+            None => command.arg(arg),
+        };
+    }
+    
+    // 结构化诊断，由 DiagnosticReporter 渲染
+    if !cmd.args.iter().any(|a| a.starts_with("--error-format")) {
+        command.arg("--error-format=json");
+    }
+    
+    for file in &cmd.input_files {
+        command.arg(path_arg(cmd, file));
+    }
+    
+    // 链接阶段：没有源文件时由桩 crate 让 rustc 充当链接驱动
+    let stub = if cmd.input_files.is_empty() && !cmd.link_inputs.is_empty() {
+        let stub = LinkStub::create()?;
+        command.arg(&stub.source).arg("--crate-name=icx_link");
+        Some(stub)
+    } else {
+        None
+    };
+    
+    for obj in &cmd.link_inputs {
+        command.arg(format!("-Clink-arg={}", path_arg(cmd, obj).display()));
+    }
+    for lib in &cmd.link_libs {
+        command.arg(format!("-Clink-arg={}", lib));
+    }
+    
+    if let Some(out) = &cmd.output {
+        // rustc 不会创建 -o 的父目录（如 /Fo<dir>/、/Qoutdir）
+        if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+            let parent = cmd.resolve(parent);
+            std::fs::create_dir_all(&parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        command.arg("-o").arg(path_arg(cmd, out));
+    }
+    
+    // 设置环境变量
+    for (key, val) in &cmd.env_vars {
+        command.env(key, val);
+    }
+    
+    if let Some(dir) = &cmd.cwd {
+        command.current_dir(dir);
+    }
+    
+    Ok((command, stub))
+}
+
+/// -v /Qstrip: builds the unit once more without `-Cstrip` into a
+/// temporary directory and reports the difference to the stripped binary
+///
+/// The second build only reads the sources: named outputs (`--emit` paths,
+/// the incremental directory) are left out, and its diagnostics are dropped.
+pub fn report_strip(cmd: &RustcCommand, binary: &Path) -> Result<()> {
+    let dir = tempfile::Builder::new().prefix("icx-rustc-strip-").tempdir()
+        .context("Failed to create a temporary directory")?;
+    let mut unstripped = cmd.clone();
+    unstripped.args.retain(|a| !a.starts_with("-Cstrip=") && !a.starts_with("--emit=") && !a.starts_with("-Cincremental="));
+    unstripped.args.push("-Cstrip=none".to_string());
+    unstripped.output = Some(dir.path().join(binary.file_name().context("no file name")?));
+    unstripped.wrapper = None;
+    
+    let (mut command, _stub) = build_command(&unstripped)?;
+    let status = command.stdout(Stdio::null()).stderr(Stdio::null()).status()
+        .with_context(|| format!("Failed to run {}", cmd.executable))?;
+    anyhow::ensure!(status.success(), "{} exited with {}", cmd.executable, status);
+    
+    let size = |path: &Path| std::fs::metadata(path)
+        .map(|m| m.len())
+        .with_context(|| format!("Failed to read {}", path.display()));
+    let before = size(unstripped.output.as_deref().unwrap_or(binary))?;
+    let after = size(&cmd.resolve(binary))?;
+    if before > 0 {
+        let saved = before.saturating_sub(after);
+        console::eprint(&format!(
+            "[icx-rustc] strip: {} {} -> {} bytes (-{:.1}%)",
+            binary.display(),
+            before,
+            after,
+            saved as f64 * 100.0 / before as f64
+        ));
+    }
+    Ok(())
+}
+
+/// Empty `#![no_main]` crate root used when only objects are linked
+///
+/// The objects already contain `main`; rustc supplies std and the
+/// platform linker invocation. Each link gets its own directory, since
+/// /MP links run concurrently; it is removed again on drop.
+struct LinkStub {
+    _dir: tempfile::TempDir,
+    source: PathBuf,
+}
+
+impl LinkStub {
+    fn create() -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("icx-rustc-link-").tempdir()
+            .context("Failed to create a temporary directory")?;
+        
+        let source = dir.path().join("icx_link.rs");
+        std::fs::write(&source, "#![no_main]\n")
+            .with_context(|| format!("Failed to write {}", source.display()))?;
+        
+        Ok(Self { _dir: dir, source })
+    }
+}
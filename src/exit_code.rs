@@ -0,0 +1,33 @@
+//! Documented exit codes of icx-rustc
+//!
+//! Build systems key retry and reporting logic off these, so they are stable:
+//! rustc's own status is mapped onto them instead of being forwarded.
+//! Interrupted builds exit like an interrupted shell command instead, see
+//! [`crate::interrupt::exit_status`].
+
+/// Everything compiled
+pub const SUCCESS: i32 = 0;
+
+/// The compiler reported errors
+pub const COMPILE_ERROR: i32 = 1;
+
+/// Invalid command line or options
+pub const USAGE_ERROR: i32 = 2;
+
+/// The wrapper or the compiler itself failed (spawn failure, ICE, I/O)
+pub const INTERNAL_ERROR: i32 = 3;
+
+/// The compiler was killed (/Qtimeout or a signal)
+pub const KILLED: i32 = 4;
+
+/// Maps a rustc exit status onto the scheme above
+pub fn from_rustc(code: Option<i32>, errors: u32) -> i32 {
+    match code {
+        Some(0) if errors == 0 => SUCCESS,
+        Some(0) => COMPILE_ERROR,
+        // rustc 内部编译器错误（ICE）
+        Some(101) => INTERNAL_ERROR,
+        Some(_) => COMPILE_ERROR,
+        None => KILLED,
+    }
+}
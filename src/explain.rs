@@ -0,0 +1,162 @@
+use crate::cli::Args;
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use crate::numbering::{self, DiagId};
+use crate::translator;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// `--explain E0382`: rustc's explanation in the wrapper's colors, paged on a terminal
+///
+/// Accepts E-codes, bare numbers (0382), ICX numbers (10382, #20001) and
+/// lint names; lints have no long explanation, so their `rustc -W help`
+/// entry is shown instead.
+pub fn run(args: &Args, spec: &str) -> Result<i32> {
+    let rustc = translator::rustc_executable(args)?;
+    let spec = spec.trim();
+    let id = match spec.parse::<u32>() {
+        Ok(n) if n < 10000 => Some(DiagId::Error(format!("E{:04}", n))),
+        _ => numbering::resolve(&spec.to_ascii_uppercase())
+            .filter(|id| matches!(id, DiagId::Error(_)))
+            .or_else(|| numbering::resolve(spec)),
+    };
+    
+    let text = match id {
+        Some(DiagId::Error(code)) => explain_error(&rustc, &code)?,
+        Some(DiagId::Lint(lint)) => explain_lint(&rustc, &lint)?,
+        None => None,
+    };
+    let Some(text) = text else {
+        console::eprint(&format!(
+            "{}: {} is not a known error code, ICX number or lint",
+            i18n::t("icx-rustc error").bright_red().bold(),
+            spec
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    };
+    
+    show(&text);
+    Ok(exit_code::SUCCESS)
+}
+
+fn explain_error(rustc: &str, code: &str) -> Result<Option<String>> {
+    let output = Command::new(rustc)
+        .args(["--explain", code])
+        .output()
+        .with_context(|| format!("Failed to run {}", rustc))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    
+    let header = format!(
+        "{} {} {}",
+        i18n::t("error").bright_red().bold(),
+        "[ICX]".bright_cyan(),
+        format!("[#{}: {}]", numbering::number(code).unwrap_or_default(), code).bright_black()
+    );
+    let markdown = String::from_utf8_lossy(&output.stdout);
+    Ok(Some(format!("{}\n\n{}", header, render(&markdown))))
+}
+
+/// The lint's line from `rustc -W help`: name, default level and description
+fn explain_lint(rustc: &str, lint: &str) -> Result<Option<String>> {
+    let output = Command::new(rustc)
+        .args(["-W", "help"])
+        .output()
+        .with_context(|| format!("Failed to run {}", rustc))?;
+    let help = String::from_utf8_lossy(&output.stdout);
+    let dashed = lint.replace('_', "-");
+    
+    let entry = help.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        (words.next()? == dashed).then(|| {
+            let level = words.next().unwrap_or_default().to_string();
+            (level, words.collect::<Vec<_>>().join(" "))
+        })
+    });
+    let Some((level, description)) = entry else {
+        return Ok(None);
+    };
+    
+    let code = match numbering::number(lint) {
+        Some(number) => format!("[#{}: {}]", number, lint),
+        None => format!("[{}]", lint),
+    };
+    Ok(Some(format!(
+        "{} {} {}\n\n     {}\n\n     {} {} (/wd and /we take the name or ICX number)",
+        "lint".bright_yellow().bold(),
+        "[ICX]".bright_cyan(),
+        code.bright_black(),
+        description.bright_white(),
+        "default:".bright_blue(),
+        level
+    )))
+}
+
+/// Colors rustc's markdown: code blocks dimmed behind a gutter, `code` spans highlighted
+fn render(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            // 代码块的标记（如 compile_fail,E0382）只保留错误示例的提示
+            let info = line.trim_start().trim_start_matches('`');
+            if in_code && info.contains("compile_fail") {
+                out.push(format!("     {}", "// does not compile".bright_red()));
+            }
+            continue;
+        }
+        if in_code {
+            out.push(format!("     {} {}", "|".bright_blue(), line.bright_black()));
+        } else if let Some(title) = line.strip_prefix('#') {
+            out.push(title.trim_start_matches('#').trim().bright_white().bold().to_string());
+        } else {
+            out.push(inline_code(line));
+        }
+    }
+    out.join("\n")
+}
+
+fn inline_code(line: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in line.split('`').enumerate() {
+        if i % 2 == 1 {
+            out.push_str(&part.bright_cyan().to_string());
+        } else {
+            out.push_str(part);
+        }
+    }
+    out
+}
+
+/// Through `$PAGER` (default `less -R` / `more`) when stdout is a terminal
+fn show(text: &str) {
+    let pager = std::env::var("PAGER").ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "more" } else { "less -R" }.to_string());
+    let words = shlex::split(&pager).unwrap_or_default();
+    
+    if std::io::stdout().is_terminal() {
+        if let Some((program, rest)) = words.split_first() {
+            let spawned = Command::new(program)
+                .args(rest)
+                .env("LESS", std::env::var("LESS").unwrap_or_else(|_| "FRX".to_string()))
+                .stdin(Stdio::piped())
+                .spawn();
+            // 分页程序不存在时直接输出
+            if let Ok(mut pager) = spawned {
+                if let Some(mut stdin) = pager.stdin.take() {
+                    let _ = writeln!(stdin, "{}", text);
+                }
+                let _ = pager.wait();
+                return;
+            }
+        }
+    }
+    // 管道提前关闭（如 | head）不算错误
+    let _ = writeln!(std::io::stdout().lock(), "{}", text);
+}
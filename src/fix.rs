@@ -0,0 +1,146 @@
+use crate::console;
+use crate::diagnostics::Diagnostic;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+
+/// One machine-applicable suggestion: all of its replacements go in together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// Error code or lint that suggested it
+    pub code: String,
+    /// (byte start, byte end, replacement)
+    pub edits: Vec<(usize, usize, String)>,
+}
+
+/// Collects the suggestions rustc marked `MachineApplicable`
+///
+/// Relative file names are resolved with `resolve` (the compiler's
+/// directory); spans into files that do not exist, like the standard
+/// library's, are skipped.
+pub fn suggestions(diagnostics: &[Diagnostic], resolve: impl Fn(&Path) -> PathBuf) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    for diag in diagnostics {
+        let code = diag.code.as_ref().map(|c| c.code.clone()).unwrap_or_default();
+        for child in &diag.children {
+            let spans: Vec<_> = child.spans.iter()
+                .filter(|s| s.suggestion_applicability.as_deref() == Some("MachineApplicable"))
+                .filter_map(|s| Some((s, s.suggested_replacement.as_ref()?)))
+                .collect();
+            let Some((first, _)) = spans.first() else {
+                continue;
+            };
+            // 一个建议的所有替换必须在同一文件中
+            if spans.iter().any(|(s, _)| s.file_name != first.file_name) {
+                continue;
+            }
+            let file = resolve(Path::new(&first.file_name));
+            if !file.is_file() {
+                continue;
+            }
+            fixes.push(Fix {
+                file,
+                line: first.line_start,
+                column: first.column_start,
+                code: code.clone(),
+                edits: spans.iter().map(|(s, r)| (s.byte_start, s.byte_end, r.to_string())).collect(),
+            });
+        }
+    }
+    fixes
+}
+
+/// Applies the fixes, saving each changed file as `<file>.bak` first
+///
+/// Identical fixes reported by several compiles are applied once; a fix
+/// overlapping one already accepted is left for the next build, as
+/// `cargo fix` does. Returns how many fixes were applied.
+pub fn apply(fixes: &[Fix], quiet: bool) -> Result<usize> {
+    let mut by_file: IndexMap<&Path, Vec<&Fix>> = IndexMap::new();
+    for fix in fixes {
+        let list = by_file.entry(fix.file.as_path()).or_default();
+        if !list.contains(&fix) {
+            list.push(fix);
+        }
+    }
+    
+    let mut applied = 0;
+    let mut skipped = 0;
+    let mut changed = 0;
+    for (file, fixes) in by_file {
+        let mut source = std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        
+        // 丢弃与已接受的修复重叠的建议
+        let mut accepted: Vec<&Fix> = Vec::new();
+        for fix in fixes {
+            let overlaps = fix.edits.iter().any(|&(start, end, _)| {
+                end > source.len() || accepted.iter().flat_map(|f| &f.edits).any(|&(s, e, _)| {
+                    (start < e && s < end) || (start == end && start == s && s == e)
+                })
+            });
+            if overlaps {
+                skipped += 1;
+            } else {
+                accepted.push(fix);
+            }
+        }
+        if accepted.is_empty() {
+            continue;
+        }
+        
+        // 从后往前替换，前面的字节偏移保持有效
+        let mut edits: Vec<&(usize, usize, String)> = accepted.iter().flat_map(|f| &f.edits).collect();
+        edits.sort_by_key(|&&(start, end, _)| std::cmp::Reverse((start, end)));
+        let original = source.clone();
+        for (start, end, replacement) in edits {
+            source.splice(*start..*end, replacement.bytes());
+        }
+        if source == original {
+            continue;
+        }
+        
+        let backup = PathBuf::from(format!("{}.bak", file.display()));
+        std::fs::write(&backup, &original).with_context(|| format!("Failed to write {}", backup.display()))?;
+        std::fs::write(file, &source).with_context(|| format!("Failed to write {}", file.display()))?;
+        changed += 1;
+        applied += accepted.len();
+        
+        if !quiet {
+            for fix in &accepted {
+                let replacement = fix.edits.iter().map(|(_, _, r)| r.as_str()).collect::<Vec<_>>().join("…");
+                let change = if replacement.is_empty() {
+                    "(removed)".to_string()
+                } else {
+                    format!("`{}`", replacement)
+                };
+                console::eprint(&format!(
+                    "{} fixed {}:{}:{} {} {}",
+                    "[icx-rustc]".bright_blue().bold(),
+                    file.display(),
+                    fix.line,
+                    fix.column,
+                    fix.code.bright_black(),
+                    change.bright_green()
+                ));
+            }
+        }
+    }
+    
+    if !quiet && (applied > 0 || skipped > 0) {
+        let mut message = format!(
+            "{} applied {} fix(es) to {} file(s) (originals saved as <file>.bak)",
+            "[icx-rustc]".bright_blue().bold(),
+            applied,
+            changed
+        );
+        if skipped > 0 {
+            message.push_str(&format!("; {} overlapping fix(es) left for the next build", skipped));
+        }
+        console::eprint(&message);
+    }
+    Ok(applied)
+}
@@ -0,0 +1,93 @@
+use crate::cli::Args;
+use crate::translator::{self, RustcCommand};
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The instrumentation cargo-fuzz uses: 8-bit edge counters, the PC table
+/// and comparison tracing, which libFuzzer's mutator feeds on
+const INSTRUMENTATION: &[&str] = &[
+    "-Cpasses=sancov-module",
+    "-Cllvm-args=-sanitizer-coverage-level=4",
+    "-Cllvm-args=-sanitizer-coverage-inline-8bit-counters",
+    "-Cllvm-args=-sanitizer-coverage-pc-table",
+    "-Cllvm-args=-sanitizer-coverage-trace-compares",
+];
+
+/// /Qfuzz[:address]: builds the input as a libFuzzer target
+///
+/// The source is `#![no_main]` and defines the entry point libFuzzer's
+/// `main` calls:
+///
+/// ```text
+/// #[no_mangle]
+/// pub extern "C" fn LLVMFuzzerTestOneInput(data: *const u8, size: usize) -> i32
+/// ```
+///
+/// The code is compiled with sanitizer coverage, `cfg(fuzzing)`, debug
+/// assertions and overflow checks (unless turned off with their `-`
+/// options), and linked with the libFuzzer runtime: ICX_LIBFUZZER, or
+/// the `libclang_rt.fuzzer` of the clang on PATH. `address` adds
+/// AddressSanitizer, which needs a nightly toolchain.
+pub fn translate(cmd: &mut RustcCommand, args: &Args, target: &str) -> Result<()> {
+    let Some(spec) = &args.fuzz else {
+        return Ok(());
+    };
+    let sanitizer = match spec.to_ascii_lowercase().as_str() {
+        "" | "libfuzzer" => None,
+        "address" | "asan" => Some("address"),
+        _ => bail!("Unknown /Qfuzz sanitizer '{}' (expected address)", spec),
+    };
+    
+    cmd.args.extend(INSTRUMENTATION.iter().map(|f| f.to_string()));
+    cmd.args.push("--cfg=fuzzing".to_string());
+    // --check-cfg 自 1.80 起稳定，更早的版本也不会对未知 cfg 发出警告
+    if translator::supports(&cmd.executable, 80) {
+        cmd.args.push("--check-cfg=cfg(fuzzing)".to_string());
+    }
+    if !args.no_debug_assertions && !args.debug_assertions {
+        cmd.args.push("-Cdebug-assertions=on".to_string());
+    }
+    if !args.no_overflow_checks && !args.overflow_checks {
+        cmd.args.push("-Coverflow-checks=on".to_string());
+    }
+    if let Some(sanitizer) = sanitizer {
+        translator::require_nightly(cmd, &format!("/Qfuzz:{}", spec))?;
+        cmd.args.push(format!("-Zsanitizer={}", sanitizer));
+    }
+    
+    // 只有链接可执行文件时才需要运行时（它提供 main）
+    if args.compile_only || !matches!(translator::crate_type(cmd), None | Some("bin")) {
+        return Ok(());
+    }
+    cmd.link_inputs.push(runtime(target)?);
+    if !target.contains("-msvc") {
+        let cpp = if target.contains("-apple-") || target.contains("-freebsd") { "-lc++" } else { "-lstdc++" };
+        cmd.link_libs.push(cpp.to_string());
+    }
+    Ok(())
+}
+
+/// The libFuzzer runtime library for the target
+fn runtime(target: &str) -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("ICX_LIBFUZZER") {
+        return Ok(PathBuf::from(path));
+    }
+    let arch = match target.split('-').next().unwrap_or_default() {
+        "i586" | "i686" => "i386",
+        arch => arch,
+    };
+    let names = if target.contains("-msvc") {
+        vec![format!("clang_rt.fuzzer-{}.lib", arch), "clang_rt.fuzzer.lib".to_string()]
+    } else {
+        vec![format!("libclang_rt.fuzzer-{}.a", arch), "libclang_rt.fuzzer.a".to_string()]
+    };
+    let clang = which::which("clang").ok();
+    // -print-file-name 找不到时原样返回文件名
+    let found = clang.as_ref().and_then(|clang| names.iter().find_map(|name| {
+        let output = Command::new(clang).arg(format!("-print-file-name={}", name)).output().ok()?;
+        let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+        (path.is_absolute() && path.is_file()).then_some(path)
+    }));
+    found.context("/Qfuzz needs the libFuzzer runtime: install clang with compiler-rt, or set ICX_LIBFUZZER to libclang_rt.fuzzer")
+}
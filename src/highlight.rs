@@ -0,0 +1,163 @@
+use colored::Colorize;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "static", "struct", "super", "trait", "type", "union", "unsafe",
+    "use", "where", "while", "yield",
+];
+
+const LITERALS: &[&str] = &["true", "false", "self", "Self"];
+
+/// Colors one line of Rust source for the code context of a diagnostic
+///
+/// A small lexer rather than a full grammar: keywords, literals, strings,
+/// chars, lifetimes, macros, types, numbers and comments. Only colors are
+/// added, so the visible width is unchanged and the caret lines stay
+/// aligned. Lines are colored independently; a string or block comment
+/// spanning several lines is only recognized on the line it starts.
+pub fn rust(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = |from: usize, to: usize| chars[from..to.min(chars.len())].iter().collect::<String>();
+        
+        // 注释
+        if c == '/' && chars.get(i + 1) == Some(&'/') {
+            out.push_str(&rest(i, chars.len()).bright_black().italic().to_string());
+            break;
+        }
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let end = find(&chars, i + 2, "*/").map(|e| e + 2).unwrap_or(chars.len());
+            out.push_str(&rest(i, end).bright_black().italic().to_string());
+            i = end;
+            continue;
+        }
+        
+        // 字符串（含 b"..."、r#"..."#）
+        if let Some(end) = string_end(&chars, i) {
+            out.push_str(&rest(i, end).green().to_string());
+            i = end;
+            continue;
+        }
+        
+        // 字符字面量与生命周期
+        if c == '\'' {
+            if let Some(end) = char_end(&chars, i) {
+                out.push_str(&rest(i, end).green().to_string());
+                i = end;
+                continue;
+            }
+            let end = word_end(&chars, i + 1);
+            out.push_str(&rest(i, end).bright_magenta().to_string());
+            i = end.max(i + 1);
+            continue;
+        }
+        
+        if c.is_ascii_digit() {
+            let end = word_end(&chars, i);
+            out.push_str(&rest(i, end).bright_cyan().to_string());
+            i = end;
+            continue;
+        }
+        
+        if c.is_alphabetic() || c == '_' {
+            let end = word_end(&chars, i);
+            let word = rest(i, end);
+            // 宏调用带上感叹号
+            if chars.get(end) == Some(&'!') && chars.get(end + 1) != Some(&'=') {
+                out.push_str(&format!("{}!", word).bright_blue().to_string());
+                i = end + 1;
+                continue;
+            }
+            let colored = if KEYWORDS.contains(&word.as_str()) {
+                word.magenta().bold().to_string()
+            } else if LITERALS.contains(&word.as_str()) {
+                word.bright_cyan().to_string()
+            } else if word.starts_with(|c: char| c.is_uppercase()) {
+                word.yellow().to_string()
+            } else if chars.get(end) == Some(&'(') {
+                word.bright_blue().to_string()
+            } else {
+                word
+            };
+            out.push_str(&colored);
+            i = end;
+            continue;
+        }
+        
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// End of an identifier or number starting at `from`
+fn word_end(chars: &[char], from: usize) -> usize {
+    let mut end = from;
+    while end < chars.len() && (chars[end].is_alphanumeric() || chars[end] == '_') {
+        end += 1;
+    }
+    // 浮点数的小数部分（1.5，但不是 1..2 或 x.0.method）
+    if end > from && chars[from].is_ascii_digit()
+        && chars.get(end) == Some(&'.')
+        && chars.get(end + 1).is_some_and(|c| c.is_ascii_digit())
+    {
+        return word_end(chars, end + 1);
+    }
+    end
+}
+
+fn find(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&needle))
+}
+
+/// End of a string literal starting at `start`, if one starts there
+fn string_end(chars: &[char], start: usize) -> Option<usize> {
+    // 前缀不能是标识符的一部分（如 attr"）
+    if start > 0 && (chars[start - 1].is_alphanumeric() || chars[start - 1] == '_') {
+        return None;
+    }
+    let mut i = start;
+    if matches!(chars.get(i), Some('b') | Some('c')) {
+        i += 1;
+    }
+    if chars.get(i) == Some(&'r') {
+        i += 1;
+        let hashes = chars[i..].iter().take_while(|&&c| c == '#').count();
+        i += hashes;
+        if chars.get(i) != Some(&'"') {
+            return None;
+        }
+        let close: String = std::iter::once('"').chain(std::iter::repeat_n('#', hashes)).collect();
+        return Some(find(chars, i + 1, &close).map(|e| e + close.len()).unwrap_or(chars.len()));
+    }
+    if chars.get(i) != Some(&'"') {
+        return None;
+    }
+    i += 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    Some(chars.len())
+}
+
+/// End of a char literal ('a', '\n', '\u{1F600}') starting at `start`
+fn char_end(chars: &[char], start: usize) -> Option<usize> {
+    match chars.get(start + 1)? {
+        '\\' => {
+            let close = (start + 2..chars.len()).find(|&i| chars[i] == '\'')?;
+            Some(close + 1)
+        }
+        _ if chars.get(start + 2) == Some(&'\'') => Some(start + 3),
+        _ => None,
+    }
+}
@@ -0,0 +1,191 @@
+use crate::cli::Args;
+use crate::cmdline;
+use crate::config::Config;
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use crate::suggest::did_you_mean;
+use crate::translator::RustcCommand;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Points of a build where hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Once per invocation, before the options are translated
+    PreTranslate,
+    /// Before each compile (C sources included)
+    PreCompile,
+    /// After each compile, whether it succeeded or not
+    PostCompile,
+    /// After each successful compile that linked an executable or shared library
+    PostLink,
+}
+
+const STAGES: [(&str, Stage); 4] = [
+    ("pre-translate", Stage::PreTranslate),
+    ("pre-compile", Stage::PreCompile),
+    ("post-compile", Stage::PostCompile),
+    ("post-link", Stage::PostLink),
+];
+
+impl Stage {
+    fn name(self) -> &'static str {
+        STAGES.iter().find(|(_, s)| *s == self).map_or("", |(name, _)| name)
+    }
+    
+    fn parse(name: &str) -> Result<Stage> {
+        if let Some((_, stage)) = STAGES.iter().find(|(n, _)| *n == name) {
+            return Ok(*stage);
+        }
+        match did_you_mean(name, STAGES.iter().map(|(n, _)| *n)) {
+            Some(close) => bail!("Unknown hook stage '{}'; did you mean '{}'?", name, close),
+            None => bail!("Unknown hook stage '{}' (pre-translate, pre-compile, post-compile, post-link)", name),
+        }
+    }
+}
+
+/// Commands run around the build (`[hooks]` in icx-rustc.toml and /Qhook)
+///
+/// Each command goes through the shell (`sh -c`, `cmd /C` on Windows) in
+/// the current directory, with its output on stderr. What the build is
+/// doing is passed in the environment:
+///
+/// * `ICX_HOOK`: the stage
+/// * `ICX_COMMAND_LINE`: the icx-rustc options
+/// * `ICX_RUSTC_COMMAND`: the translated rustc command (not for pre-translate)
+/// * `ICX_INPUTS`, `ICX_ARTIFACTS`: input and output files as a path list
+/// * `ICX_OUTPUT`: the main output, when it can be predicted
+/// * `ICX_EXIT_CODE`: the compile's exit code (post-compile, post-link)
+///
+/// A failing pre hook stops the build, a failing post hook fails it.
+#[derive(Debug)]
+pub struct Hooks {
+    commands: Vec<(Stage, String)>,
+}
+
+impl Hooks {
+    /// The configured hooks, then those of /Qhook; `None` when there are none
+    pub fn new(config: &Config, flags: &[String]) -> Result<Option<Hooks>> {
+        let mut commands = Vec::new();
+        for (name, list) in &config.hooks {
+            let stage = Stage::parse(name).with_context(|| match &config.path {
+                Some(path) => format!("In {}", path.display()),
+                None => "In the configuration".to_string(),
+            })?;
+            commands.extend(list.list().iter().map(|c| (stage, c.clone())));
+        }
+        for flag in flags {
+            let Some((name, command)) = flag.split_once('=') else {
+                bail!("/Qhook needs <stage>=<command>, got '{}'", flag);
+            };
+            commands.push((Stage::parse(name.trim())?, command.to_string()));
+        }
+        Ok((!commands.is_empty()).then_some(Hooks { commands }))
+    }
+    
+    pub fn has(&self, stage: Stage) -> bool {
+        self.commands.iter().any(|(s, _)| *s == stage)
+    }
+    
+    /// Runs the stage's commands in order; the first failure stops them
+    ///
+    /// Returns [`exit_code::COMPILE_ERROR`] when a command failed, after
+    /// reporting it.
+    pub fn run(&self, stage: Stage, args: &Args, cmd: Option<&RustcCommand>, code: Option<i32>) -> Result<i32> {
+        if !self.has(stage) {
+            return Ok(exit_code::SUCCESS);
+        }
+        let env = environment(stage, args, cmd, code);
+        for (_, command) in self.commands.iter().filter(|(s, _)| *s == stage) {
+            if args.verbose {
+                console::eprint(&format!(
+                    "{} {} {}",
+                    "[icx-rustc]".bright_blue().bold(),
+                    format!("{} hook:", stage.name()).dimmed(),
+                    command.dimmed()
+                ));
+            }
+            let status = shell(command)
+                .envs(env.iter().map(|(k, v)| (k, v)))
+                .stdout(Stdio::from(std::io::stderr()))
+                .status()
+                .with_context(|| format!("Failed to run the {} hook '{}'", stage.name(), command))?;
+            if !status.success() {
+                let status = status.code().map_or("killed".to_string(), |c| format!("exit code {}", c));
+                console::eprint(&format!(
+                    "{}: {} hook failed ({}): {}",
+                    i18n::t("icx-rustc error").bright_red().bold(),
+                    stage.name(),
+                    status,
+                    command
+                ));
+                return Ok(exit_code::COMPILE_ERROR);
+            }
+        }
+        Ok(exit_code::SUCCESS)
+    }
+}
+
+fn environment(stage: Stage, args: &Args, cmd: Option<&RustcCommand>, code: Option<i32>) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("ICX_HOOK".to_string(), stage.name().to_string()),
+        ("ICX_COMMAND_LINE".to_string(), join(args.command_line.iter().skip(1).map(String::as_str))),
+    ];
+    let inputs: Vec<PathBuf> = match cmd {
+        Some(cmd) => cmd.input_files.iter().chain(cmd.c_steps.iter().map(|s| &s.source)).cloned().collect(),
+        None => args.files.clone(),
+    };
+    env.push(("ICX_INPUTS".to_string(), paths(&inputs)));
+    if let Some(cmd) = cmd {
+        env.push(("ICX_RUSTC_COMMAND".to_string(), join(cmd.arguments().iter().map(String::as_str))));
+        let artifacts: Vec<PathBuf> = cmd.artifacts().unwrap_or_default().iter().map(|a| cmd.resolve(a)).collect();
+        env.push(("ICX_ARTIFACTS".to_string(), paths(&artifacts)));
+        let output = match stage {
+            Stage::PostLink => cmd.artifact_of(&["bin", "cdylib", "dylib"]),
+            _ => cmd.artifact(),
+        };
+        if let Some(output) = output {
+            env.push(("ICX_OUTPUT".to_string(), cmd.resolve(&output).display().to_string()));
+        }
+    }
+    if let Some(code) = code {
+        env.push(("ICX_EXIT_CODE".to_string(), code.to_string()));
+    }
+    env
+}
+
+/// Files as a PATH-style list (`:` separated, `;` on Windows)
+fn paths(files: &[PathBuf]) -> String {
+    std::env::join_paths(files)
+        .map(|list| list.to_string_lossy().into_owned())
+        // 文件名含分隔符时退回逐行列出
+        .unwrap_or_else(|_| files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join("\n"))
+}
+
+/// A command line the hook's shell splits back into the same words
+fn join<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    if cfg!(windows) {
+        words.map(cmdline::quote_cmd).collect::<Vec<_>>().join(" ")
+    } else {
+        cmdline::join(words)
+    }
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut shell = Command::new("cmd");
+    // 原样传给 cmd，引号由它自己解析
+    shell.arg("/C").raw_arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
@@ -0,0 +1,79 @@
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Whether `--> file:line:col` locations are printed as OSC 8 hyperlinks
+///
+/// `ICX_HYPERLINKS=1` / `0` forces the choice; otherwise links are used
+/// when stderr is a terminal known to support them.
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| match std::env::var("ICX_HYPERLINKS").as_deref() {
+        Ok("1") | Ok("always") => true,
+        Ok("0") | Ok("never") => false,
+        _ => std::io::stderr().is_terminal() && terminal_supports_links(),
+    })
+}
+
+fn terminal_supports_links() -> bool {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    if var("TERM") == "dumb" {
+        return false;
+    }
+    
+    // Windows Terminal、VS Code、iTerm2、WezTerm、kitty、VTE 0.50+（GNOME Terminal 等）
+    !var("WT_SESSION").is_empty()
+        || !var("KITTY_WINDOW_ID").is_empty()
+        || !var("WEZTERM_EXECUTABLE").is_empty()
+        || matches!(var("TERM_PROGRAM").as_str(), "vscode" | "iTerm.app" | "WezTerm" | "ghostty")
+        || var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000)
+        || var("TERM").starts_with("xterm-kitty")
+        || var("TERM").starts_with("foot")
+}
+
+/// Wraps `text` in a link to `file` at `line:col`
+///
+/// The target is a `file://` URL, or `ICX_EDITOR_URL` with `{path}`,
+/// `{line}` and `{col}` filled in (e.g. `vscode://file/{path}:{line}:{col}`).
+/// Relative names are resolved against `base`, the compiler's directory.
+pub fn link(text: &str, file: &str, line: usize, col: usize, base: &Path) -> String {
+    let path = absolute(file, base);
+    // 标准库等不存在的路径不加链接
+    if !path.is_file() {
+        return text.to_string();
+    }
+    let path = path.display().to_string().replace('\\', "/");
+    let url = match std::env::var("ICX_EDITOR_URL") {
+        Ok(template) if !template.is_empty() => template
+            .replace("{path}", &path)
+            .replace("{line}", &line.to_string())
+            .replace("{col}", &col.to_string()),
+        _ => format!("file://{}{}", if path.starts_with('/') { "" } else { "/" }, escape(&path)),
+    };
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+fn absolute(file: &str, base: &Path) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let base = if base.is_absolute() {
+        base.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(base)
+    };
+    base.join(path)
+}
+
+/// Percent-encodes the characters that end or confuse a URL
+fn escape(path: &str) -> String {
+    let mut out = String::new();
+    for c in path.chars() {
+        match c {
+            ' ' | '#' | '?' | '%' | '"' | '<' | '>' => out.push_str(&format!("%{:02X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,351 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of the wrapper's own messages (/Qlocale)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    EnUs,
+    ZhCn,
+}
+
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::EnUs as u8);
+
+/// Selects the locale: `/Qlocale`, else LC_ALL / LC_MESSAGES / LANG, else
+/// (on Windows) the user's UI locale
+///
+/// Only the wrapper's labels, summary lines and help are translated;
+/// rustc's messages and the machine-readable formats (MSVC lines, JSON,
+/// SARIF) stay in English.
+pub fn init(choice: Option<&str>) {
+    let locale = match choice {
+        Some(name) => parse(name).unwrap_or_else(|| {
+            crate::console::eprint(&format!(
+                "[icx-rustc] warning: unsupported locale '{}', using en-US (available: en-US, zh-CN)",
+                name
+            ));
+            Locale::EnUs
+        }),
+        None => system_locale().and_then(|name| parse(&name)).unwrap_or(Locale::EnUs),
+    };
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    match LOCALE.load(Ordering::Relaxed) {
+        l if l == Locale::ZhCn as u8 => Locale::ZhCn,
+        _ => Locale::EnUs,
+    }
+}
+
+fn parse(name: &str) -> Option<Locale> {
+    let name = name.to_ascii_lowercase().replace('_', "-");
+    if name.starts_with("zh") {
+        Some(Locale::ZhCn)
+    } else if name.starts_with("en") || name == "c" || name == "posix" || name.starts_with("c.") {
+        Some(Locale::EnUs)
+    } else {
+        None
+    }
+}
+
+fn system_locale() -> Option<String> {
+    let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()));
+    if from_env.is_some() {
+        return from_env;
+    }
+    
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+        // LOCALE_NAME_MAX_LENGTH
+        let mut buffer = [0u16; 85];
+        let len = unsafe { GetUserDefaultLocaleName(buffer.as_mut_ptr(), buffer.len() as i32) };
+        if len > 1 {
+            return Some(String::from_utf16_lossy(&buffer[..len as usize - 1]));
+        }
+    }
+    None
+}
+
+/// `text` in the current locale; untranslated text is returned unchanged
+pub fn t(text: &str) -> &str {
+    #[cfg(test)]
+    tests::SEEN.with(|seen| seen.borrow_mut().push(text.to_string()));
+    if locale() == Locale::EnUs {
+        return text;
+    }
+    ZH_CN.iter()
+        .find(|(en, _)| *en == text)
+        .map_or(text, |(_, zh)| zh)
+}
+
+/// Translates a `{}` template and fills in the arguments in order
+pub fn fill(template: &'static str, args: &[&dyn Display]) -> String {
+    let mut parts = t(template).split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for (part, arg) in parts.zip(args.iter().map(|a| a.to_string()).chain(std::iter::repeat(String::new()))) {
+        out.push_str(&arg);
+        out.push_str(part);
+    }
+    out
+}
+
+/// zh-CN translations, keyed by the English text
+///
+/// The tests below fail when /help or a literal passed to `t` / `fill` has no
+/// entry, so a reworded English text cannot silently fall back to English.
+const ZH_CN: &[(&str, &str)] = &[
+    // 诊断标签与摘要
+    ("error", "错误"),
+    ("warning", "警告"),
+    ("note:", "注意:"),
+    ("output:", "产物:"),
+    ("target:", "目标:"),
+    ("help:", "帮助:"),
+    ("failed", "失败"),
+    ("finished", "完成"),
+    ("build failed", "构建失败"),
+    ("build finished", "构建完成"),
+    ("icx-rustc error", "icx-rustc 错误"),
+    ("{} error(s), {} warning(s)", "{} 个错误，{} 个警告"),
+    ("restored from cache in {} ms", "从缓存恢复，用时 {} ms"),
+    ("up to date", "已是最新"),
+    ("{} (repeated {} times)", "{}（重复 {} 次）"),
+    ("{} at {} (repeated {} times)", "{}，位于 {}（重复 {} 次）"),
+    ("{} error(s), {} warning(s) in {} ms", "{} 个错误，{} 个警告，用时 {} ms"),
+    ("{} error(s), {} warning(s) in {} unit(s), {} ms", "{} 个错误，{} 个警告，共 {} 个翻译单元，用时 {} ms"),
+    ("diagnostic statistics (top {} of {} codes)", "诊断统计（前 {} 个，共 {} 个代码）"),
+    ("diagnostic statistics ({} codes)", "诊断统计（{} 个代码）"),
+    ("count", "次数"),
+    ("files", "文件数"),
+    ("level", "级别"),
+    ("code", "代码"),
+    
+    // 帮助
+    ("Usage: icx-rustc [options] <input files>", "用法: icx-rustc [选项] <输入文件>"),
+    ("Options are accepted as /name[:value] (Intel/MSVC) or --name[=value].", "选项可写作 /name[:value]（Intel/MSVC 风格）或 --name[=value]。"),
+    
+    ("Input files:", "输入文件:"),
+    ("Rust sources; those the others name as crates (use b::..) are built as lib<name>.rlib first", "Rust 源文件；被其他源文件作为 crate 引用的（use b::..）先编译为 lib<name>.rlib"),
+    ("Read the Rust source from stdin", "从标准输入读取 Rust 源代码"),
+    ("Link previously compiled objects (with /c output)", "链接先前编译的目标文件（/c 的输出）"),
+    ("Link rlibs, or use them as --extern when compiling sources", "链接 rlib，编译源文件时作为 --extern 传入"),
+    ("Compile with the C/C++ compiler (CC, CXX or the one on PATH) and link the objects", "用 C/C++ 编译器（CC、CXX 或 PATH 中的编译器）编译并链接目标文件"),
+    ("Compile resources with rc / llvm-rc (windres for MinGW) and link them; .res files link as is", "用 rc / llvm-rc（MinGW 为 windres）编译资源并链接；.res 文件直接链接"),
+    ("Read further options and inputs from a response file (UTF-8, or UTF-16 with a byte order mark)", "从响应文件读取更多选项与输入（UTF-8，或带字节顺序标记的 UTF-16）"),
+    ("Use a rustup toolchain (first argument only)", "使用 rustup 工具链（仅限第一个参数）"),
+    ("Pass the rest to rustc unchanged (to the program with /run)", "其余参数原样传给 rustc（/run 时传给程序）"),
+    
+    ("Optimization Options:", "优化选项:"),
+    ("Optimization level (O0, O1, O2, O3, Ox)", "优化级别（O0、O1、O2、O3、Ox）"),
+    ("Optimization level, MSVC style: /O0 to /O3, /Ox (default /O2)", "MSVC 风格的优化级别：/O0 至 /O3、/Ox（默认 /O2）"),
+    ("Target a specific architecture (AVX2, AVX512, ...)", "针对特定架构（AVX2、AVX512 等）"),
+    ("Optimize for the host architecture", "针对本机架构优化"),
+    ("Optimize the linked binary with llvm-bolt using this profile (.fdata or YAML)", "用 llvm-bolt 按此性能数据（.fdata 或 YAML）优化链接后的程序"),
+    ("Compile the /Qoffload-kernel sources for a GPU (nvptx64[:sm_XX], spirv)", "为 GPU 编译 /Qoffload-kernel 源文件（nvptx64[:sm_XX]、spirv）"),
+    ("Kernel sources compiled for the /Qoffload device", "为 /Qoffload 设备编译的内核源文件"),
+    ("Enable overflow checks regardless of optimization level", "无论优化级别如何都启用溢出检查"),
+    ("Disable overflow checks", "禁用溢出检查"),
+    ("Enable debug assertions regardless of optimization level", "无论优化级别如何都启用调试断言"),
+    ("Disable debug assertions", "禁用调试断言"),
+    ("Release mode: /O3 unless an optimization level is given", "发布模式：未指定优化级别时为 /O3"),
+    
+    ("Code Generation:", "代码生成:"),
+    ("Compile and run a snippet, printing its value", "编译并运行代码片段，打印其值"),
+    ("Crate name of a source read from stdin ('-')", "从标准输入（'-'）读取的源文件的 crate 名"),
+    ("Compile only, do not link", "只编译，不链接"),
+    ("Output file name", "输出文件名"),
+    ("Output directory for all artifacts", "所有产物的输出目录"),
+    ("MSVC-style object output", "MSVC 风格的目标文件输出"),
+    ("MSVC-style executable output", "MSVC 风格的可执行文件输出"),
+    ("Output kinds to emit (obj, asm, llvm-ir, llvm-bc, mir, metadata, dep-info, link)", "要生成的输出类型（obj、asm、llvm-ir、llvm-bc、mir、metadata、dep-info、link）"),
+    ("Compile translation units in parallel (0 = one per core)", "并行编译翻译单元（0 = 每个核心一个）"),
+    ("Compilation cache backend: builtin, or an external wrapper such as sccache", "编译缓存后端：builtin，或 sccache 等外部包装器"),
+    ("Remove cache and incremental entries unused for this many days (default 30)", "删除此天数内未使用的缓存与增量编译条目（默认 30）"),
+    ("--gc-cache, also removing the oldest entries until the rest fits (e.g. 10G)", "同 --gc-cache，并删除最旧的条目直到其余部分不超过此大小（如 10G）"),
+    ("Skip compiles whose sources, flags and compiler match the last successful one", "源文件、选项与编译器都与上次成功编译相同时跳过编译"),
+    ("Build the units described by a project file (TOML or JSON)", "构建项目文件（TOML 或 JSON）描述的翻译单元"),
+    ("Split debug info: packed, unpacked or off (platform default if no value)", "拆分调试信息：packed、unpacked 或 off（无值时为平台默认）"),
+    ("Strip symbols or debug info from the output (default: symbols)", "从输出中剥离符号或调试信息（默认：符号）"),
+    ("Write a make/ninja depfile for the output (default: <output>.d)", "为输出写 make/ninja 依赖文件（默认：<output>.d）"),
+    ("Record the compile commands in a compilation database (default: compile_commands.json)", "将编译命令记录到编译数据库（默认：compile_commands.json）"),
+    ("Write a CMake fragment that builds Rust targets with icx-rustc (default: icx-rustc.cmake)", "写出用 icx-rustc 构建 Rust 目标的 CMake 片段（默认：icx-rustc.cmake）"),
+    ("Write icx-rustc.props and icx-rustc.targets for Visual Studio projects (default: current directory)", "为 Visual Studio 项目写出 icx-rustc.props 与 icx-rustc.targets（默认：当前目录）"),
+    ("Write a build.ninja for the project or inputs instead of building (default: build.ninja)", "为项目或输入写出 build.ninja 而不构建（默认：build.ninja）"),
+    ("Reproducible build (remap paths, stable metadata, deterministic link)", "可复现构建（重映射路径、稳定元数据、确定性链接）"),
+    ("Per-file extra options: <file>=\"<options>\"", "按文件附加选项：<file>=\"<options>\""),
+    ("Compile, then run the executable with the arguments after `--`", "编译后运行可执行文件，参数为 `--` 之后的部分"),
+    
+    ("Preprocessor:", "预处理器:"),
+    ("Define macro", "定义宏"),
+    ("Undefine macro", "取消定义宏"),
+    ("Include directory", "包含目录"),
+    ("Generate Rust bindings for a C header into OUT_DIR before compiling", "编译前为 C 头文件生成 Rust 绑定到 OUT_DIR"),
+    
+    ("Linking:", "链接:"),
+    ("Embed an application manifest in the Windows executable (EMBED, NO)", "在 Windows 可执行文件中嵌入应用程序清单（EMBED、NO）"),
+    ("Manifest file to embed (implies /MANIFEST)", "要嵌入的清单文件（隐含 /MANIFEST）"),
+    ("Requested execution level: asInvoker, highestAvailable, requireAdministrator", "请求的执行级别：asInvoker、highestAvailable、requireAdministrator"),
+    ("DPI awareness in the manifest: unaware, system, permonitor, permonitorv2", "清单中的 DPI 感知：unaware、system、permonitor、permonitorv2"),
+    ("Version fields stamped into the binary (1.2.3,product=App,company=Acme)", "写入二进制文件的版本字段（1.2.3,product=App,company=Acme）"),
+    ("Import library for a Windows DLL (default: <name>.lib next to it)", "Windows DLL 的导入库（默认：同目录下的 <name>.lib）"),
+    ("Linker arguments (MSVC style)", "链接器参数（MSVC 风格）"),
+    ("Linker to use: lld, mold, gold, bfd or link (MSVC)", "使用的链接器：lld、mold、gold、bfd 或 link（MSVC）"),
+    ("Build a static library that also contains the object inputs", "生成同时包含目标文件输入的静态库"),
+    
+    ("Diagnostics:", "诊断:"),
+    ("Warning level: /W0 to /W4, /Wall", "警告级别：/W0 至 /W4、/Wall"),
+    ("Warnings as errors", "将警告视为错误"),
+    ("Disable warnings by ICX number or lint name (/wd20001, /Qdiag-disable:dead_code)", "按 ICX 编号或 lint 名禁用警告（/wd20001、/Qdiag-disable:dead_code）"),
+    ("File listing warnings to silence, optionally per file pattern", "列出要屏蔽的警告的文件，可按文件模式区分"),
+    ("Report warnings as errors by ICX number or lint name (/we20001)", "按 ICX 编号或 lint 名将警告报告为错误（/we20001）"),
+    ("Working directory for the spawned compiler", "所启动编译器的工作目录"),
+    ("Kill the compiler after this many seconds", "编译器运行超过此秒数后将其结束"),
+    ("Print a compile phase timing breakdown (nightly rustc)", "打印编译各阶段的耗时（nightly rustc）"),
+    ("Unstable rustc option, passed as -Z<flag> (nightly rustc)", "不稳定的 rustc 选项，以 -Z<flag> 传入（nightly rustc）"),
+    ("Let a stable or beta rustc accept -Z options (RUSTC_BOOTSTRAP=1)", "让 stable 或 beta 版 rustc 接受 -Z 选项（RUSTC_BOOTSTRAP=1）"),
+    ("Report peak memory and CPU time of the compiler", "报告编译器的峰值内存与 CPU 时间"),
+    ("Compile on a remote host over SSH, falling back to local on failure", "通过 SSH 在远程主机上编译，失败时回退到本地"),
+    ("Colored output: auto (terminal, honoring NO_COLOR), always or never", "彩色输出：auto（终端，遵循 NO_COLOR）、always 或 never"),
+    ("Language of the wrapper's own messages: en-US or zh-CN (default: from LANG)", "包装器自身消息的语言：en-US 或 zh-CN（默认：取自 LANG）"),
+    ("Diagnostic layout: icx (default) or msvc for IDE and MSBuild problem matchers", "诊断格式：icx（默认）或 msvc（供 IDE 与 MSBuild 问题匹配器使用）"),
+    ("Show at most N errors per compile, counting the rest (0 = no limit)", "每次编译最多显示 N 个错误，其余只计数（0 = 不限）"),
+    ("Kill the compiler once the error limit is reached", "达到错误上限后结束编译器"),
+    ("Also write the diagnostics as plain text to FILE (default: <source>.diag)", "同时将诊断以纯文本写入 FILE（默认：<source>.diag）"),
+    ("Print the N most frequent diagnostic codes at the end of the build (0 = all)", "构建结束时打印最常见的 N 个诊断代码（0 = 全部）"),
+    ("Record the build's warnings as a baseline, or check against it", "将本次构建的警告记录为基线，或与基线比较"),
+    ("Baseline file for /Qdiag-baseline", "/Qdiag-baseline 的基线文件"),
+    ("Apply rustc's machine-applicable suggestions to the sources", "将 rustc 可自动应用的建议应用到源文件"),
+    ("Print each compile's diagnostics grouped under per-file headers", "将每次编译的诊断按文件分组打印"),
+    ("End-of-build summary format: text, or json for CI", "构建结束摘要的格式：text，或供 CI 使用的 json"),
+    ("Write the JSON summary to this file instead of stdout (implies --summary-format=json)", "将 JSON 摘要写入此文件而非标准输出（隐含 --summary-format=json）"),
+    ("Record the invocation, timings, diagnostics and artifact hashes as JSON", "以 JSON 记录调用、耗时、诊断与产物哈希"),
+    ("Write all diagnostics of the build to a SARIF 2.1.0 log", "将本次构建的全部诊断写入 SARIF 2.1.0 日志"),
+    ("Tee the command, compiler output and diagnostics to a log file", "将命令、编译器输出与诊断同时写入日志文件"),
+    ("Append to the /Qlog file instead of overwriting it", "追加到 /Qlog 文件而不是覆盖"),
+    ("Run as a compile server for later invocations", "作为编译服务器运行，供之后的调用使用"),
+    ("Recompile whenever an input or module file changes", "输入或模块文件变化时重新编译"),
+    ("Verbose: echo the commands and report each step", "详细模式：回显命令并报告每个步骤"),
+    ("Print only diagnostics: no command echo, progress or summary line", "只打印诊断：不回显命令，不显示进度与摘要行"),
+    ("Dry run; with a file name, also write the commands as a .sh / .bat script", "试运行；带文件名时还将命令写成 .sh / .bat 脚本"),
+    ("Treat unknown options as errors instead of ignoring them", "将未知选项视为错误而不是忽略"),
+    ("Show version", "显示版本"),
+    ("Show help", "显示帮助"),
+    ("List every option with its description and rustc translation", "列出所有选项及其说明与对应的 rustc 参数"),
+    ("Print the option reference as a roff man page", "以 roff 手册页格式打印选项参考"),
+    ("Print a completion script for the shell, with both option spellings", "打印 shell 补全脚本，包含两种选项写法"),
+    ("Explain an error code, ICX number or lint (like rustc --explain)", "解释错误代码、ICX 编号或 lint（同 rustc --explain）"),
+    ("Show the generated assembly interleaved with source lines (optionally one function)", "显示与源代码行交错的汇编（可只显示一个函数）"),
+    ("Write the /Qasm-view listing to FILE instead of printing it", "将 /Qasm-view 的列表写入 FILE 而不是打印"),
+    ("Report section and symbol sizes of the linked binary (the N largest, default 10)", "报告链接后二进制文件的段与符号大小（最大的 N 个，默认 10）"),
+    ("List the exported and undefined symbols of the outputs, demangled", "列出输出文件导出与未定义的符号（已还原名称）"),
+    ("Build a libFuzzer target, optionally with a sanitizer (address)", "构建 libFuzzer 目标，可选配 sanitizer（address）"),
+    ("Instrument the code for source-based coverage (-Cinstrument-coverage)", "为基于源代码的覆盖率插桩（-Cinstrument-coverage）"),
+    ("Coverage report of instrumented binaries that have run (summary, html, lcov)", "已运行的插桩程序的覆盖率报告（summary、html、lcov）"),
+    ("Optimize diagnostics output", "优化诊断输出"),
+    
+    ("Rust-specific:", "Rust 专用:"),
+    ("Rust edition (2015, 2018, 2021, 2024)", "Rust 版本（2015、2018、2021、2024）"),
+    ("Crate type, or a comma list of them", "crate 类型，或以逗号分隔的多个类型"),
+    ("Crate name (default: the sanitized file stem)", "crate 名（默认：规范化后的文件名）"),
+    ("Target triple or alias (win64, win32, linux-x64, linux-arm64, macos-arm64, musl, wasm, wasi)", "目标三元组或别名（win64、win32、linux-x64、linux-arm64、macos-arm64、musl、wasm、wasi）"),
+    ("Build a WebAssembly module (wasi, bindgen, opt)", "构建 WebAssembly 模块（wasi、bindgen、opt）"),
+    ("Custom sysroot (embedded targets, vendored std)", "自定义 sysroot（嵌入式目标、自带的 std）"),
+    ("rustup toolchain to use (also accepted as a leading +<toolchain>)", "使用的 rustup 工具链（也可写作开头的 +<toolchain>）"),
+    ("Path to a custom rustc (overrides ICX_RUSTC)", "自定义 rustc 的路径（优先于 ICX_RUSTC）"),
+    ("Build the vendored crates the sources use, from a `cargo vendor` directory", "从 `cargo vendor` 目录构建源文件用到的 crate"),
+    ("Enable features of vendored crates (crate/feature, comma separated)", "启用 vendor 中 crate 的特性（crate/feature，逗号分隔）"),
+    
+    ("Cargo:", "Cargo:"),
+    ("icx-cargo build /O3 /arch:AVX2 --release runs cargo with the Intel-style options applied (also 'icx-rustc cargo ...'); the other arguments go to cargo.", "icx-cargo build /O3 /arch:AVX2 --release 以应用后的 Intel 风格选项运行 cargo（也可写作 'icx-rustc cargo ...'）；其余参数传给 cargo。"),
+    ("RUSTC_WRAPPER=icx-rustc cargo build compiles every crate through icx-rustc; ICX_FLAGS adds options such as \"/arch:AVX2 /W4\" (cargo's profile keeps LTO).", "RUSTC_WRAPPER=icx-rustc cargo build 让每个 crate 都经 icx-rustc 编译；ICX_FLAGS 可添加 \"/arch:AVX2 /W4\" 等选项（cargo 的 profile 保留 LTO）。"),
+    
+    ("Configuration:", "配置:"),
+    ("Print the effective settings and where each one came from", "显示生效的设置及各自的来源"),
+    ("Options apply in this order, later ones winning: built-in defaults, options = \"...\" in icx-rustc.toml, ICX_OPTIONS, then the command line.", "选项按以下顺序生效，后者覆盖前者：内置默认值、icx-rustc.toml 中的 options = \"...\"、ICX_OPTIONS、命令行。"),
+    
+    ("Build hooks:", "构建钩子:"),
+    ("Run a shell command at a build stage (after the icx-rustc.toml hooks)", "在构建阶段运行 shell 命令（在 icx-rustc.toml 的钩子之后）"),
+    ("[hooks] of the nearest icx-rustc.toml up from the current directory (or ICX_CONFIG), e.g. post-link = \"sign.sh\", run before /Qhook.", "从当前目录向上最近的 icx-rustc.toml（或 ICX_CONFIG）中的 [hooks]，如 post-link = \"sign.sh\"，先于 /Qhook 运行。"),
+    ("Hooks get ICX_HOOK, ICX_COMMAND_LINE, ICX_RUSTC_COMMAND, ICX_INPUTS, ICX_ARTIFACTS, ICX_OUTPUT and ICX_EXIT_CODE.", "钩子可读取 ICX_HOOK、ICX_COMMAND_LINE、ICX_RUSTC_COMMAND、ICX_INPUTS、ICX_ARTIFACTS、ICX_OUTPUT 和 ICX_EXIT_CODE。"),
+    
+    ("Environment:", "环境变量:"),
+    ("Options applied after those of icx-rustc.toml and before the command line", "在 icx-rustc.toml 之后、命令行之前应用的选项"),
+    ("rustc to run instead of the one on PATH (--rustc overrides it)", "代替 PATH 中 rustc 运行的 rustc（--rustc 优先）"),
+    ("Extra icx options in cargo wrapper mode (icx-cargo)", "cargo 包装模式（icx-cargo）下附加的 icx 选项"),
+    ("Configuration file to use instead of the nearest icx-rustc.toml", "代替最近的 icx-rustc.toml 使用的配置文件"),
+    ("Append one JSON line per compile to this file", "每次编译向此文件追加一行 JSON"),
+    ("Directory of the builtin compilation cache and of stored rustc queries", "内置编译缓存与 rustc 查询结果的存放目录"),
+    ("External cache wrapper (sccache) when /Qcache names none", "/Qcache 未指定时使用的外部缓存包装器（sccache）"),
+    ("SSH host for /Qremote", "/Qremote 的 SSH 主机"),
+    ("rustc on the /Qremote host", "/Qremote 主机上的 rustc"),
+    ("Do not hand the compile to a running --daemon", "不将编译交给正在运行的 --daemon"),
+    ("Make diagnostic locations clickable (1) or not (0)", "诊断位置可点击（1）或不可点击（0）"),
+    ("Link target of the locations, e.g. vscode://file/{path}:{line}:{col}", "位置的链接目标，如 vscode://file/{path}:{line}:{col}"),
+    ("bindgen executable for /Qbindgen", "/Qbindgen 使用的 bindgen 程序"),
+    ("llvm-bolt executable for /Qbolt", "/Qbolt 使用的 llvm-bolt 程序"),
+    ("libFuzzer runtime for /Qfuzz", "/Qfuzz 使用的 libFuzzer 运行时"),
+    ("rust-gpu codegen backend for /Qoffload:spirv", "/Qoffload:spirv 使用的 rust-gpu 代码生成后端"),
+    ("Terminal width diagnostics are wrapped to (detected by default)", "诊断换行使用的终端宽度（默认自动检测）"),
+    ("Disable colors unless /Qcolor:always is given", "除非指定 /Qcolor:always，否则禁用颜色"),
+    
+    ("Exit codes:", "退出码:"),
+    ("Everything compiled", "全部编译成功"),
+    ("The compiler reported errors", "编译器报告了错误"),
+    ("Invalid command line or options", "命令行或选项无效"),
+    ("The wrapper or the compiler itself failed (spawn failure, ICE, I/O)", "包装器或编译器本身失败（无法启动、ICE、I/O）"),
+    ("The compiler was killed (/Qtimeout or a signal)", "编译器被结束（/Qtimeout 或信号）"),
+    ("Interrupted by Ctrl+C (STATUS_CONTROL_C_EXIT on Windows)", "被 Ctrl+C 中断（Windows 上为 STATUS_CONTROL_C_EXIT）"),
+    
+    ("Examples:", "示例:"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::ZH_CN;
+    use std::cell::RefCell;
+    
+    thread_local! {
+        /// Every text passed to `t` on this thread
+        pub(super) static SEEN: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+    
+    fn untranslated(texts: impl IntoIterator<Item = String>) -> Vec<String> {
+        texts.into_iter().filter(|text| !ZH_CN.iter().any(|(en, _)| en == text)).collect()
+    }
+    
+    #[test]
+    fn help_is_translated() {
+        crate::manual::help_text();
+        let seen = SEEN.with(|seen| seen.take());
+        assert!(seen.len() > 100);
+        assert_eq!(untranslated(seen), Vec::<String>::new());
+    }
+    
+    #[test]
+    fn message_literals_are_translated() {
+        // 其余调用以字面量传入英文文本，从源码中找出来
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut texts = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "rs") || path.ends_with("i18n.rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            for call in ["i18n::t(", "i18n::fill("] {
+                for (at, _) in source.match_indices(call) {
+                    let rest = source[at + call.len()..].trim_start();
+                    let Some(literal) = rest.strip_prefix('"') else {
+                        continue;
+                    };
+                    let end = literal.find('"').unwrap();
+                    texts.push(literal[..end].to_string());
+                }
+            }
+        }
+        assert!(texts.len() > 40);
+        assert_eq!(untranslated(texts), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,169 @@
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+pub use platform::Tracked;
+
+/// Signal that interrupted the build (0: not interrupted)
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Exit right away instead of letting the build wind down (--daemon)
+static EXIT_AT_ONCE: AtomicBool = AtomicBool::new(false);
+
+/// Installs the Ctrl+C / SIGINT / SIGTERM handler
+///
+/// The handler kills every tracked compiler together with the linker and
+/// other processes it started, then lets the build unwind so temporary
+/// files are removed and [`exit_status`] is returned. A second Ctrl+C, or
+/// the first one with `exit_at_once`, exits immediately.
+pub fn install(exit_at_once: bool) {
+    EXIT_AT_ONCE.store(exit_at_once, Ordering::SeqCst);
+    platform::install();
+}
+
+/// Whether the build was interrupted
+pub fn requested() -> bool {
+    SIGNAL.load(Ordering::SeqCst) != 0
+}
+
+/// Exit code of an interrupted build, as shells report it
+pub fn exit_status() -> i32 {
+    platform::exit_status(SIGNAL.load(Ordering::SeqCst))
+}
+
+/// Kills the child and everything it spawns when icx-rustc is interrupted
+///
+/// On Unix the child must have been started in its own process group.
+pub fn track(child: &Child) -> Tracked {
+    platform::track(child)
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::{EXIT_AT_ONCE, SIGNAL};
+    use std::process::Child;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    
+    /// Process groups of the running compilers (0: free slot)
+    static GROUPS: [AtomicI32; 256] = [const { AtomicI32::new(0) }; 256];
+    
+    pub struct Tracked(Option<usize>);
+    
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            if let Some(slot) = self.0 {
+                GROUPS[slot].store(0, Ordering::SeqCst);
+            }
+        }
+    }
+    
+    pub fn install() {
+        // 安全性：sigaction 全零即合法初值；处理函数只调用异步信号安全的函数
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+                libc::sigaction(signal, &action, std::ptr::null_mut());
+            }
+        }
+    }
+    
+    extern "C" fn handle(signal: libc::c_int) {
+        for group in &GROUPS {
+            let pid = group.load(Ordering::SeqCst);
+            if pid != 0 {
+                unsafe { libc::kill(-pid, libc::SIGKILL) };
+            }
+        }
+        
+        let first = SIGNAL.compare_exchange(0, signal, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+        if !first || EXIT_AT_ONCE.load(Ordering::SeqCst) {
+            unsafe { libc::_exit(exit_status(signal)) };
+        }
+    }
+    
+    pub fn exit_status(signal: i32) -> i32 {
+        128 + signal
+    }
+    
+    pub fn track(child: &Child) -> Tracked {
+        let pid = child.id() as i32;
+        let slot = GROUPS.iter()
+            .position(|g| g.compare_exchange(0, pid, Ordering::SeqCst, Ordering::SeqCst).is_ok());
+        
+        // 注册前已收到信号
+        if SIGNAL.load(Ordering::SeqCst) != 0 {
+            unsafe { libc::kill(-pid, libc::SIGKILL) };
+        }
+        Tracked(slot)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::{EXIT_AT_ONCE, SIGNAL};
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::sync::atomic::Ordering;
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{BOOL, HANDLE};
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    
+    /// STATUS_CONTROL_C_EXIT, the code cmd.exe reports for Ctrl+C
+    const CONTROL_C_EXIT: i32 = 0xC000013A_u32 as i32;
+    
+    /// Job object holding every compiler and its children (the handle is kept as usize)
+    static JOB: OnceLock<usize> = OnceLock::new();
+    
+    pub struct Tracked;
+    
+    fn job() -> HANDLE {
+        *JOB.get_or_init(|| unsafe {
+            // 作业句柄随 icx-rustc 进程关闭时也结束其中的进程
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            job as usize
+        }) as HANDLE
+    }
+    
+    pub fn install() {
+        job();
+        unsafe { SetConsoleCtrlHandler(Some(handle), 1) };
+    }
+    
+    unsafe extern "system" fn handle(_event: u32) -> BOOL {
+        TerminateJobObject(job(), CONTROL_C_EXIT as u32);
+        
+        // 返回 FALSE 交给默认处理（结束进程）
+        let first = SIGNAL.compare_exchange(0, 1, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+        if !first || EXIT_AT_ONCE.load(Ordering::SeqCst) {
+            return 0;
+        }
+        1
+    }
+    
+    pub fn exit_status(_signal: i32) -> i32 {
+        CONTROL_C_EXIT
+    }
+    
+    pub fn track(child: &Child) -> Tracked {
+        unsafe { AssignProcessToJobObject(job(), child.as_raw_handle() as HANDLE) };
+        if SIGNAL.load(Ordering::SeqCst) != 0 {
+            unsafe { TerminateJobObject(job(), CONTROL_C_EXIT as u32) };
+        }
+        Tracked
+    }
+}
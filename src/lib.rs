@@ -0,0 +1,140 @@
+//! icx-rustc as a library: Intel-style options translated to rustc
+//!
+//! [`cli`], [`translator`] and [`diagnostics`] are the public API, for
+//! IDE plugins, build systems and tests that need the translation
+//! without spawning the binary:
+//!
+//! ```no_run
+//! let args = icx_rustc::cli::parse_options(vec!["/O2".into(), "main.rs".into()])?;
+//! let cmd = icx_rustc::translate(&args)?;
+//! println!("{} {}", cmd.executable, cmd.args.join(" "));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The other modules implement the icx-rustc command itself and may
+//! change between releases.
+
+pub mod cli;
+pub mod diagnostics;
+pub mod translator;
+
+pub use cli::Args;
+pub use diagnostics::DiagnosticReporter;
+pub use translator::{translate, RustcCommand};
+
+#[doc(hidden)]
+pub mod asm_view;
+#[doc(hidden)]
+pub mod audit;
+#[doc(hidden)]
+pub mod baseline;
+#[doc(hidden)]
+pub mod bolt;
+#[doc(hidden)]
+pub mod buildlog;
+#[doc(hidden)]
+pub mod cache;
+#[doc(hidden)]
+pub mod cargo;
+#[doc(hidden)]
+pub mod cc;
+#[doc(hidden)]
+pub mod cmake;
+#[doc(hidden)]
+pub mod cmdline;
+#[doc(hidden)]
+pub mod compdb;
+#[doc(hidden)]
+pub mod completions;
+#[doc(hidden)]
+pub mod config;
+#[doc(hidden)]
+pub mod console;
+#[doc(hidden)]
+pub mod coverage;
+#[doc(hidden)]
+pub mod daemon;
+#[doc(hidden)]
+pub mod demangle;
+#[doc(hidden)]
+pub mod depfile;
+#[doc(hidden)]
+pub mod deps;
+#[doc(hidden)]
+pub mod diagfile;
+#[doc(hidden)]
+pub mod driver;
+#[doc(hidden)]
+pub mod eval;
+#[doc(hidden)]
+pub mod executor;
+#[doc(hidden)]
+pub mod exit_code;
+#[doc(hidden)]
+pub mod explain;
+#[doc(hidden)]
+pub mod fix;
+#[doc(hidden)]
+pub mod fuzz;
+#[doc(hidden)]
+pub mod highlight;
+#[doc(hidden)]
+pub mod hooks;
+#[doc(hidden)]
+pub mod hyperlink;
+#[doc(hidden)]
+pub mod i18n;
+#[doc(hidden)]
+pub mod interrupt;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod manual;
+#[doc(hidden)]
+pub mod msbuild;
+#[doc(hidden)]
+pub mod ninja;
+#[doc(hidden)]
+pub mod numbering;
+#[doc(hidden)]
+pub mod object;
+#[doc(hidden)]
+pub mod offload;
+#[doc(hidden)]
+pub mod parallel;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod resources;
+#[doc(hidden)]
+pub mod runner;
+#[doc(hidden)]
+pub mod sarif;
+#[doc(hidden)]
+pub mod script;
+#[doc(hidden)]
+pub mod size_report;
+#[doc(hidden)]
+pub mod stamp;
+#[doc(hidden)]
+pub mod stdin;
+#[doc(hidden)]
+pub mod suggest;
+#[doc(hidden)]
+pub mod summary;
+#[doc(hidden)]
+pub mod suppress;
+#[doc(hidden)]
+pub mod symbols;
+#[doc(hidden)]
+pub mod toml;
+#[doc(hidden)]
+pub mod version_info;
+#[doc(hidden)]
+pub mod wasm;
+#[doc(hidden)]
+pub mod watch;
+#[doc(hidden)]
+pub mod wrapper;
@@ -0,0 +1,119 @@
+use crate::cache;
+use crate::cc;
+use crate::cli::Args;
+use crate::console;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+
+const LEVELS: &[&str] = &["asInvoker", "highestAvailable", "requireAdministrator"];
+
+/// (option value, `<dpiAware>`, `<dpiAwareness>`)
+const DPI_MODES: &[(&str, &str, &str)] = &[
+    ("unaware", "false", "unaware"),
+    ("system", "true", "system"),
+    ("permonitor", "true/pm", "PerMonitor"),
+    ("permonitorv2", "true/pm", "PerMonitorV2, PerMonitor"),
+];
+
+/// /MANIFEST, /MANIFESTFILE, /MANIFESTUAC and /Qmanifest-dpi: embeds an
+/// application manifest in a Windows executable or DLL
+///
+/// link.exe embeds it itself (`/MANIFEST:EMBED`, the UAC level given
+/// directly, DPI awareness as a manifest fragment to merge). The GNU
+/// linker has no manifest support, so for MinGW the complete manifest
+/// becomes resource 1 of type RT_MANIFEST, compiled like a `.rc` input.
+pub fn translate(cmd: &mut RustcCommand, args: &Args, target: &str) -> Result<()> {
+    let requested = args.manifest.as_deref().is_some_and(|m| !m.eq_ignore_ascii_case("NO"))
+        || args.manifest_file.is_some()
+        || args.manifest_uac.is_some()
+        || args.manifest_dpi.is_some();
+    if !requested || args.compile_only {
+        return Ok(());
+    }
+    if !target.contains("-windows") {
+        console::eprint("[icx-rustc] warning: application manifests need a Windows target, ignored");
+        return Ok(());
+    }
+    if let Some(mode) = args.manifest.as_deref().filter(|m| !m.eq_ignore_ascii_case("EMBED") && !m.eq_ignore_ascii_case("NO")) {
+        anyhow::bail!("Unknown /MANIFEST mode '{}' (expected EMBED or NO)", mode);
+    }
+    
+    let level = match &args.manifest_uac {
+        Some(level) => Some(*LEVELS.iter()
+            .find(|l| l.eq_ignore_ascii_case(level))
+            .with_context(|| format!("Unknown execution level '{}' (expected one of: {})", level, LEVELS.join(", ")))?),
+        None => None,
+    };
+    let dpi = match &args.manifest_dpi {
+        Some(mode) => Some(DPI_MODES.iter()
+            .find(|(m, _, _)| m.eq_ignore_ascii_case(mode))
+            .with_context(|| format!(
+                "Unknown DPI awareness '{}' (expected one of: {})",
+                mode,
+                DPI_MODES.iter().map(|(m, _, _)| *m).collect::<Vec<_>>().join(", ")
+            ))?),
+        None => None,
+    };
+    
+    if target.contains("-msvc") {
+        cmd.args.push("-Clink-arg=/MANIFEST:EMBED".to_string());
+        match level {
+            Some(level) => cmd.args.push(format!("-Clink-arg=/MANIFESTUAC:level='{}' uiAccess='false'", level)),
+            // 自带的清单通常已有 trustInfo，与 link 生成的会冲突
+            None if args.manifest_file.is_some() => cmd.args.push("-Clink-arg=/MANIFESTUAC:NO".to_string()),
+            None => {}
+        }
+        if let Some(file) = &args.manifest_file {
+            cmd.args.push(format!("-Clink-arg=/MANIFESTINPUT:{}", file.display()));
+        }
+        if let Some(&(_, aware, awareness)) = dpi {
+            let fragment = cache::generated_file("manifest", &document(None, Some((aware, awareness))), "dpi.manifest")?;
+            cmd.args.push(format!("-Clink-arg=/MANIFESTINPUT:{}", fragment.display()));
+        }
+        return Ok(());
+    }
+    
+    let manifest = match &args.manifest_file {
+        Some(file) => std::path::absolute(file).with_context(|| format!("Failed to resolve {}", file.display()))?,
+        None => cache::generated_file("manifest", &document(Some(level.unwrap_or("asInvoker")), dpi.map(|&(_, a, b)| (a, b))), "app.manifest")?,
+    };
+    if args.manifest_file.is_some() && (level.is_some() || dpi.is_some()) {
+        console::eprint("[icx-rustc] warning: /MANIFESTUAC and /Qmanifest-dpi are not merged into /MANIFESTFILE for MinGW targets");
+    }
+    // 1 = CREATEPROCESS_MANIFEST_RESOURCE_ID，24 = RT_MANIFEST
+    let path = manifest.display().to_string().replace('\\', "/");
+    // 资源对象以程序命名：<name>-manifest.res.o
+    let name = cmd.artifact_of(&["bin", "cdylib", "dylib"])
+        .and_then(|a| a.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "app".to_string());
+    let script = cache::generated_file("manifest", &format!("1 24 \"{}\"\n", path), &format!("{}-manifest.rc", name))?;
+    cc::resources(cmd, args, &[script], target)
+}
+
+fn document(level: Option<&str>, dpi: Option<(&str, &str)>) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+        "<assembly xmlns=\"urn:schemas-microsoft-com:asm.v1\" manifestVersion=\"1.0\">\n",
+    ));
+    if let Some(level) = level {
+        xml.push_str(&format!(concat!(
+            "  <trustInfo xmlns=\"urn:schemas-microsoft-com:asm.v3\">\n",
+            "    <security><requestedPrivileges>\n",
+            "      <requestedExecutionLevel level=\"{}\" uiAccess=\"false\"/>\n",
+            "    </requestedPrivileges></security>\n",
+            "  </trustInfo>\n",
+        ), level));
+    }
+    if let Some((aware, awareness)) = dpi {
+        xml.push_str(&format!(concat!(
+            "  <application xmlns=\"urn:schemas-microsoft-com:asm.v3\">\n",
+            "    <windowsSettings>\n",
+            "      <dpiAware xmlns=\"http://schemas.microsoft.com/SMI/2005/WindowsSettings\">{}</dpiAware>\n",
+            "      <dpiAwareness xmlns=\"http://schemas.microsoft.com/SMI/2016/WindowsSettings\">{}</dpiAwareness>\n",
+            "    </windowsSettings>\n",
+            "  </application>\n",
+        ), aware, awareness));
+    }
+    xml.push_str("</assembly>\n");
+    xml
+}
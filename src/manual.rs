@@ -0,0 +1,477 @@
+use crate::cli::{self, Args};
+use crate::completions::ATTACHED;
+use crate::{console, exit_code, i18n, translator};
+use anyhow::Result;
+use clap::builder::PossibleValue;
+use clap::{Arg, CommandFactory};
+use colored::Colorize;
+
+/// Source file name the sample translations are made for
+const SAMPLE_INPUT: &str = "main.rs";
+
+/// Sections of /help, in order; options of any other heading follow them
+const HEADINGS: [&str; 9] = [
+    "Optimization Options",
+    "Code Generation",
+    "Preprocessor",
+    "Linking",
+    "Diagnostics",
+    "Rust-specific",
+    "Cargo",
+    "Configuration",
+    "Build hooks",
+];
+
+/// What the command line accepts besides options, for /help
+const INPUTS: [(&str, &str); 9] = [
+    ("<file>.rs", "Rust sources; those the others name as crates (use b::..) are built as lib<name>.rlib first"),
+    ("-", "Read the Rust source from stdin"),
+    ("<file>.o/.obj", "Link previously compiled objects (with /c output)"),
+    ("<file>.rlib", "Link rlibs, or use them as --extern when compiling sources"),
+    ("<file>.c/.cpp", "Compile with the C/C++ compiler (CC, CXX or the one on PATH) and link the objects"),
+    ("<file>.rc", "Compile resources with rc / llvm-rc (windres for MinGW) and link them; .res files link as is"),
+    ("@<file>", "Read further options and inputs from a response file (UTF-8, or UTF-16 with a byte order mark)"),
+    ("+<toolchain>", "Use a rustup toolchain (first argument only)"),
+    ("-- <args>", "Pass the rest to rustc unchanged (to the program with /run)"),
+];
+
+/// Topics of /help that are not single options: (heading, paragraphs),
+/// printed after the options of that heading
+const TOPICS: [(&str, &[&str]); 3] = [
+    ("Cargo", &[
+        "icx-cargo build /O3 /arch:AVX2 --release runs cargo with the Intel-style options applied (also 'icx-rustc cargo ...'); the other arguments go to cargo.",
+        "RUSTC_WRAPPER=icx-rustc cargo build compiles every crate through icx-rustc; ICX_FLAGS adds options such as \"/arch:AVX2 /W4\" (cargo's profile keeps LTO).",
+    ]),
+    ("Configuration", &[
+        "Options apply in this order, later ones winning: built-in defaults, options = \"...\" in icx-rustc.toml, ICX_OPTIONS, then the command line.",
+    ]),
+    ("Build hooks", &[
+        "[hooks] of the nearest icx-rustc.toml up from the current directory (or ICX_CONFIG), e.g. post-link = \"sign.sh\", run before /Qhook.",
+        "Hooks get ICX_HOOK, ICX_COMMAND_LINE, ICX_RUSTC_COMMAND, ICX_INPUTS, ICX_ARTIFACTS, ICX_OUTPUT and ICX_EXIT_CODE.",
+    ]),
+];
+
+const EXAMPLES: [&str; 4] = [
+    "icx-rustc main.rs",
+    "icx-rustc /O3 /arch:AVX2 program.rs -o program.exe",
+    "icx-rustc /c /Fooutput.o lib.rs",
+    "icx-rustc /run main.rs -- input.txt",
+];
+
+/// Width /help is wrapped to, and the column descriptions start at
+const HELP_WIDTH: usize = 80;
+const HELP_COLUMN: usize = 20;
+
+/// Environment variables icx-rustc reads, for /help and the man page
+const ENVIRONMENT: [(&str, &str); 18] = [
+    ("ICX_OPTIONS", "Options applied after those of icx-rustc.toml and before the command line"),
+    ("ICX_RUSTC", "rustc to run instead of the one on PATH (--rustc overrides it)"),
+    ("ICX_FLAGS", "Extra icx options in cargo wrapper mode (icx-cargo)"),
+    ("ICX_CONFIG", "Configuration file to use instead of the nearest icx-rustc.toml"),
+    ("ICX_AUDIT_LOG", "Append one JSON line per compile to this file"),
+    ("ICX_CACHE_DIR", "Directory of the builtin compilation cache and of stored rustc queries"),
+    ("ICX_CACHE_WRAPPER", "External cache wrapper (sccache) when /Qcache names none"),
+    ("ICX_REMOTE", "SSH host for /Qremote"),
+    ("ICX_REMOTE_RUSTC", "rustc on the /Qremote host"),
+    ("ICX_NO_DAEMON", "Do not hand the compile to a running --daemon"),
+    ("ICX_HYPERLINKS", "Make diagnostic locations clickable (1) or not (0)"),
+    ("ICX_EDITOR_URL", "Link target of the locations, e.g. vscode://file/{path}:{line}:{col}"),
+    ("ICX_BINDGEN", "bindgen executable for /Qbindgen"),
+    ("ICX_BOLT", "llvm-bolt executable for /Qbolt"),
+    ("ICX_LIBFUZZER", "libFuzzer runtime for /Qfuzz"),
+    ("ICX_SPIRV_BACKEND", "rust-gpu codegen backend for /Qoffload:spirv"),
+    ("COLUMNS", "Terminal width diagnostics are wrapped to (detected by default)"),
+    ("NO_COLOR", "Disable colors unless /Qcolor:always is given"),
+];
+
+/// Exit codes, as in [`crate::exit_code`], and that of an interrupted build
+const EXIT_STATUS: [(i32, &str); 6] = [
+    (exit_code::SUCCESS, "Everything compiled"),
+    (exit_code::COMPILE_ERROR, "The compiler reported errors"),
+    (exit_code::USAGE_ERROR, "Invalid command line or options"),
+    (exit_code::INTERNAL_ERROR, "The wrapper or the compiler itself failed (spawn failure, ICE, I/O)"),
+    (exit_code::KILLED, "The compiler was killed (/Qtimeout or a signal)"),
+    (130, "Interrupted by Ctrl+C (STATUS_CONTROL_C_EXIT on Windows)"),
+];
+
+/// One option of the table, in both spellings
+struct Entry {
+    /// Intel/MSVC spelling, `/Qcolor:<WHEN>`
+    slash: String,
+    /// GNU spelling, `--Qcolor=<WHEN>`
+    dash: String,
+    aliases: Vec<String>,
+    help: String,
+    values: Vec<(String, String)>,
+    /// rustc arguments of each sample use, `None` when it needs real input
+    rustc: Vec<(String, Option<String>)>,
+}
+
+/// /help: the options by section, each with its one-line description
+///
+/// Generated from the option table like --help-long, in the current
+/// locale and without the translations.
+pub fn print_help() -> Result<i32> {
+    console::print_document(&help_text())?;
+    Ok(exit_code::SUCCESS)
+}
+
+/// The text of /help
+pub fn help_text() -> String {
+    let mut out = String::new();
+    out.push_str(&format!("{}\n", "Intel(R) oneAPI Rust Compiler".bright_blue().bold()));
+    out.push_str(&format!("{}\n", i18n::t("Usage: icx-rustc [options] <input files>")));
+    out.push_str(&format!("{}\n", i18n::t("Options are accepted as /name[:value] (Intel/MSVC) or --name[=value].")));
+    
+    let command = Args::command();
+    let options: Vec<&Arg> = command.get_arguments().filter(|a| !a.is_hide_set() && !a.is_positional()).collect();
+    let mut headings: Vec<&str> = HEADINGS.to_vec();
+    for arg in &options {
+        let heading = arg.get_help_heading().unwrap_or("Options");
+        if !headings.contains(&heading) {
+            headings.push(heading);
+        }
+    }
+    heading(&mut out, "Input files");
+    for (name, help) in INPUTS {
+        help_row(&mut out, name, i18n::t(help));
+    }
+    for section in headings {
+        heading(&mut out, section);
+        for arg in options.iter().filter(|a| a.get_help_heading().unwrap_or("Options") == section) {
+            let mut names = vec![spellings(arg).0];
+            names.extend(arg.get_all_aliases().unwrap_or_default().into_iter().map(|a| format!("/{}", a)));
+            let help = arg.get_help().map(|h| h.to_string()).unwrap_or_default();
+            help_row(&mut out, &names.join(", "), i18n::t(&help));
+        }
+        for (_, paragraphs) in TOPICS.iter().filter(|(topic, _)| *topic == section) {
+            paragraphs.iter().for_each(|text| paragraph(&mut out, text));
+        }
+    }
+    heading(&mut out, "Environment");
+    for (name, help) in ENVIRONMENT {
+        help_row(&mut out, name, i18n::t(help));
+    }
+    heading(&mut out, "Exit codes");
+    for (code, help) in EXIT_STATUS {
+        help_row(&mut out, &code.to_string(), i18n::t(help));
+    }
+    heading(&mut out, "Examples");
+    for example in EXAMPLES {
+        out.push_str(&format!("  {}\n", example));
+    }
+    out
+}
+
+fn heading(out: &mut String, text: &str) {
+    out.push('\n');
+    out.push_str(&format!("{}\n", i18n::t(&format!("{}:", text)).yellow().bold()));
+}
+
+/// `  name              description`, wrapped; long names get a line of their own
+fn help_row(out: &mut String, name: &str, help: &str) {
+    let mut lines = wrap(help, HELP_WIDTH - HELP_COLUMN).into_iter();
+    if name.len() + 3 > HELP_COLUMN {
+        out.push_str(&format!("  {}\n", name));
+    } else {
+        out.push_str(&format!("  {:<width$}{}\n", name, lines.next().unwrap_or_default(), width = HELP_COLUMN - 2));
+    }
+    for line in lines {
+        out.push_str(&format!("{:width$}{}\n", "", line, width = HELP_COLUMN));
+    }
+}
+
+fn paragraph(out: &mut String, text: &str) {
+    for line in wrap(i18n::t(text), HELP_WIDTH - 2) {
+        out.push_str(&format!("  {}\n", line));
+    }
+}
+
+/// Lines of at most `width` columns, broken at spaces or between CJK
+/// characters, which take two columns
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let wide = |c: char| matches!(c, '\u{1100}'..='\u{115f}' | '\u{2e80}'..='\u{a4cf}' | '\u{ac00}'..='\u{d7a3}' | '\u{f900}'..='\u{faff}' | '\u{fe30}'..='\u{fe4f}' | '\u{ff00}'..='\u{ff60}' | '\u{ffe0}'..='\u{ffe6}');
+    let columns = |s: &str| s.chars().map(|c| if wide(c) { 2 } else { 1 }).sum::<usize>();
+    // 不在开括号之后、闭合标点之前断行
+    let breaks = |prev: char, c: char| (wide(prev) || wide(c)) && !"（「『".contains(prev) && !"）」』，、。；：！？".contains(c);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ').filter(|w| !w.is_empty()) {
+        // 中文没有空格，逐字断开；其余的词保持完整
+        let mut pieces = Vec::new();
+        let mut rest = word;
+        while let Some(at) = rest.char_indices().skip(1).find(|&(i, c)| rest[..i].chars().last().is_some_and(|prev| breaks(prev, c))).map(|(i, _)| i) {
+            pieces.push(&rest[..at]);
+            rest = &rest[at..];
+        }
+        pieces.push(rest);
+        for (n, piece) in pieces.into_iter().enumerate() {
+            let space = if n == 0 && !line.is_empty() { " " } else { "" };
+            if !line.is_empty() && columns(&line) + space.len() + columns(piece) > width {
+                lines.push(std::mem::take(&mut line));
+            } else {
+                line.push_str(space);
+            }
+            line.push_str(piece);
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// --help-long: every option with its description and rustc translation
+///
+/// Like /help, this is generated from the option table, so it lists
+/// exactly what the command line accepts. The translation column
+/// comes from translating a sample invocation with and without the option
+/// and comparing the rustc arguments.
+pub fn print_long() -> Result<i32> {
+    let mut out = String::new();
+    out.push_str("Usage: icx-rustc [options] <input files>\n");
+    out.push('\n');
+    out.push_str("Options are accepted as /name[:value] (Intel/MSVC) or --name[=value].\n");
+    out.push_str(&format!("\"rustc:\" shows what each one adds to the rustc command line for {};\n", SAMPLE_INPUT));
+    out.push_str("options without it are handled by icx-rustc itself.\n");
+    for entry in entries() {
+        out.push('\n');
+        let mut names = vec![entry.slash.clone(), entry.dash.clone()];
+        names.extend(entry.aliases.iter().cloned());
+        out.push_str(&format!("  {}\n", names.join(", ")));
+        if !entry.help.is_empty() {
+            out.push_str(&format!("      {}\n", entry.help));
+        }
+        for (value, help) in &entry.values {
+            if help.is_empty() {
+                out.push_str(&format!("        {}\n", value));
+            } else {
+                out.push_str(&format!("        {:<12} {}\n", value, help));
+            }
+        }
+        for line in translation_lines(&entry) {
+            out.push_str(&format!("      {}\n", line));
+        }
+    }
+    console::print_document(&out)?;
+    Ok(exit_code::SUCCESS)
+}
+
+/// --man: the same reference as a roff man page (`icx-rustc --man > icx-rustc.1`)
+pub fn print_man() -> Result<i32> {
+    let mut page = String::new();
+    page.push_str(&format!(".TH ICX-RUSTC 1 \"\" \"icx-rustc {}\" \"User Commands\"\n", env!("CARGO_PKG_VERSION")));
+    page.push_str(".SH NAME\nicx\\-rustc \\- Intel(R) oneAPI Rust Compiler\n");
+    page.push_str(".SH SYNOPSIS\n.B icx\\-rustc\n[\\fIoptions\\fR] \\fIinput files\\fR... [\\fB\\-\\-\\fR \\fIrustc arguments\\fR]\n");
+    page.push_str(".SH DESCRIPTION\n");
+    page.push_str(&roff(
+        "icx-rustc accepts the options of the Intel and MSVC compiler drivers, translates them \
+         into a rustc command line, runs rustc and reports its diagnostics in the Intel style. \
+         Every option is accepted as /name[:value] and as --name[=value]; arguments after -- \
+         are passed to rustc unchanged.",
+    ));
+    page.push('\n');
+    page.push_str(".SH OPTIONS\n");
+    for entry in entries() {
+        let mut names = vec![bold(&entry.slash), bold(&entry.dash)];
+        names.extend(entry.aliases.iter().map(|a| bold(a)));
+        page.push_str(&format!(".TP\n{}\n", names.join(", ")));
+        if !entry.help.is_empty() {
+            page.push_str(&roff(&entry.help));
+            page.push('\n');
+        }
+        if !entry.values.is_empty() {
+            page.push_str(".RS\n");
+            for (value, help) in &entry.values {
+                page.push_str(&format!(".TP\n{}\n", bold(value)));
+                if !help.is_empty() {
+                    page.push_str(&format!("{}\n", roff(help)));
+                }
+            }
+            page.push_str(".RE\n");
+        }
+        for line in translation_lines(&entry) {
+            page.push_str(&format!(".br\n{}\n", roff(&line)));
+        }
+    }
+    page.push_str(".SH ENVIRONMENT\n");
+    for (name, help) in ENVIRONMENT {
+        page.push_str(&format!(".TP\n{}\n{}\n", bold(name), roff(help)));
+    }
+    page.push_str(&format!(
+        ".SH FILES\n.TP\n{}\n{}\n",
+        bold(crate::config::FILE_NAME),
+        roff("Options, build hooks and settings, looked up from the working directory upward (or ICX_CONFIG)")
+    ));
+    page.push_str(".SH EXIT STATUS\n");
+    for (code, help) in EXIT_STATUS {
+        page.push_str(&format!(".TP\n{}\n{}\n", bold(&code.to_string()), roff(help)));
+    }
+    page.push_str(".SH SEE ALSO\n.BR rustc (1),\n.BR cargo (1)\n");
+    console::print_document(&page)?;
+    Ok(exit_code::SUCCESS)
+}
+
+fn entries() -> Vec<Entry> {
+    let command = Args::command();
+    let arguments: Vec<&Arg> = command.get_arguments()
+        .filter(|a| !a.is_hide_set() && !a.is_positional())
+        .collect();
+    
+    // 示例翻译期间的警告（"unknown arch" 等）不输出
+    console::set_sink(Some(Box::new(|_, _| {})));
+    let base = sample(&[]);
+    let entries = arguments.into_iter()
+        .map(|arg| {
+            let (slash, dash) = spellings(arg);
+            let aliases = arg.get_all_aliases().unwrap_or_default().into_iter().map(|a| format!("/{}", a)).collect();
+            let help = arg.get_long_help().or(arg.get_help()).map(|h| h.to_string()).unwrap_or_default();
+            let values = possible_values(arg).into_iter()
+                .map(|v| (v.get_name().to_string(), v.get_help().map(|h| h.to_string()).unwrap_or_default()))
+                .collect();
+            let rustc = samples(arg).into_iter()
+                .map(|(shown, words)| (shown, base.as_ref().and_then(|base| difference(base, sample(&words).as_ref()?))))
+                .collect();
+            Entry { slash, dash, aliases, help, values, rustc }
+        })
+        .collect();
+    console::set_sink(None);
+    entries
+}
+
+/// `(/spelling, --spelling)` with a `<VALUE>` placeholder
+fn spellings(arg: &Arg) -> (String, String) {
+    let value = placeholder(arg);
+    let optional = arg.get_num_args().is_some_and(|n| n.min_values() == 0);
+    let takes_value = arg.get_action().takes_values();
+    match (arg.get_long(), arg.get_short()) {
+        // 单字母选项（/D、/I、/c）按 MSVC 的写法直接带值
+        (Some(long), Some(short)) if long.len() == 1 && long.starts_with(short) => {
+            if takes_value {
+                (format!("/{}{}", short, value), format!("-{} {}", short, value))
+            } else {
+                (format!("/{}", short), format!("-{}", short))
+            }
+        }
+        (Some(long), _) if !takes_value => (format!("/{}", long), format!("--{}", long)),
+        (Some(long), _) if optional => (format!("/{}[:{}]", long, value), format!("--{}[={}]", long, value)),
+        (Some(long), _) => (format!("/{}:{}", long, value), format!("--{}={}", long, value)),
+        (None, Some(short)) if takes_value => (format!("/{}{}", short, value), format!("-{}{}", short, value)),
+        (None, Some(short)) => (format!("/{}", short), format!("-{}", short)),
+        (None, None) => (String::new(), String::new()),
+    }
+}
+
+/// The listed values of an option; flags have none (not `true`/`false`)
+fn possible_values(arg: &Arg) -> Vec<PossibleValue> {
+    if !arg.get_action().takes_values() {
+        return Vec::new();
+    }
+    arg.get_possible_values().into_iter().filter(|v| !v.is_hide_set()).collect()
+}
+
+fn placeholder(arg: &Arg) -> String {
+    let name = arg.get_value_names()
+        .and_then(|names| names.first().map(|n| n.to_string()))
+        .unwrap_or_else(|| arg.get_id().to_string().to_uppercase());
+    format!("<{}>", name)
+}
+
+/// The uses of an option whose translation is shown: `(shown as, words)`
+fn samples(arg: &Arg) -> Vec<(String, Vec<String>)> {
+    let values: Vec<String> = possible_values(arg).iter().map(|v| v.get_name().to_string()).collect();
+    // /O2、/W4：值直接跟在短选项之后
+    let attached: Vec<(String, Vec<String>)> = ATTACHED.iter()
+        .filter(|(word, _)| arg.get_action().takes_values() && arg.get_short().is_some_and(|s| word[1..].starts_with(s)))
+        .map(|(word, _)| (word.to_string(), vec![format!("-{}", &word[1..])]))
+        .collect();
+    if !attached.is_empty() {
+        return attached;
+    }
+    match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) if !values.is_empty() => values.iter()
+            .map(|v| (format!("/{}:{}", long, v), vec![format!("--{}={}", long, v)]))
+            .collect(),
+        (Some(long), _) if arg.get_action().takes_values() && arg.get_num_args().is_none_or(|n| n.min_values() > 0) => {
+            let value = placeholder(arg);
+            vec![(format!("/{}:{}", long, value), vec![format!("--{}={}", long, value)])]
+        }
+        (Some(long), _) => vec![(format!("/{}", long), vec![format!("--{}", long)])],
+        (None, Some(short)) => vec![(format!("/{}", short), vec![format!("-{}", short)])],
+        (None, None) => Vec::new(),
+    }
+}
+
+/// The rustc command for the sample input with these options, if it translates
+fn sample(words: &[String]) -> Option<translator::RustcCommand> {
+    let words = words.iter().cloned().chain([SAMPLE_INPUT.to_string()]).collect();
+    translator::translate(&cli::parse_options(words).ok()?).ok()
+}
+
+/// What the option changes in the rustc command: added arguments and
+/// environment, then the arguments it replaces
+fn difference(base: &translator::RustcCommand, with: &translator::RustcCommand) -> Option<String> {
+    let mut removed = base.arguments().split_off(1);
+    let mut added = Vec::new();
+    for arg in &with.arguments()[1..] {
+        match removed.iter().position(|a| a == arg) {
+            Some(at) => {
+                removed.remove(at);
+            }
+            None => added.push(arg.clone()),
+        }
+    }
+    added.extend(with.env_vars.iter()
+        .filter(|var| !base.env_vars.contains(var))
+        .map(|(name, value)| format!("{}={}", name, value)));
+    if with.executable != base.executable {
+        added.insert(0, format!("(runs {})", with.executable));
+    }
+    if added.is_empty() && removed.is_empty() {
+        return Some(String::new());
+    }
+    let mut text = added.join(" ");
+    if !removed.is_empty() {
+        text.push_str(&format!(" (instead of {})", removed.join(" ")));
+    }
+    Some(text.trim_start().to_string())
+}
+
+/// `rustc: ...` lines of an entry; none if no sample could be translated
+fn translation_lines(entry: &Entry) -> Vec<String> {
+    let translated: Vec<(&str, &str)> = entry.rustc.iter()
+        .filter_map(|(shown, rustc)| Some((shown.as_str(), rustc.as_deref()?)))
+        .collect();
+    if translated.iter().all(|(_, rustc)| rustc.is_empty()) {
+        return Vec::new();
+    }
+    match &translated[..] {
+        [(_, rustc)] if entry.rustc.len() == 1 => vec![format!("rustc: {}", rustc)],
+        _ => translated.iter()
+            .map(|(shown, rustc)| format!("rustc: {:<12} {}", shown, if rustc.is_empty() { "(unchanged)" } else { rustc }))
+            .collect(),
+    }
+}
+
+fn bold(text: &str) -> String {
+    format!("\\fB{}\\fR", escape(text))
+}
+
+/// A text line for roff, with no request or control character at its start
+fn roff(text: &str) -> String {
+    let lines: Vec<String> = text.lines()
+        .map(|line| {
+            let line = escape(line);
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
@@ -0,0 +1,190 @@
+use crate::cli::Args;
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::Path;
+
+/// --emit-msbuild[=dir]: `icx-rustc.props` and `icx-rustc.targets` for .vcxproj files
+///
+/// `RustCompile` items are crate roots built by icx-rustc before the link
+/// step. Their Optimization, RuntimeLibrary, PreprocessorDefinitions,
+/// WarningLevel, TreatWarningAsError and DebugInformationFormat default
+/// to the project's ClCompile settings, the target to the Platform.
+/// Options given together with --emit-msbuild become `IcxRustcFlags`.
+pub fn write(args: &Args, dir: &Path) -> Result<i32> {
+    if !args.files.is_empty() {
+        console::eprint(&format!(
+            "{}: --emit-msbuild takes options only; the sources are RustCompile items of the project",
+            i18n::t("icx-rustc error").bright_red().bold()
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    }
+    let exe = std::env::current_exe().context("Failed to locate icx-rustc")?;
+    let flags: Vec<String> = args.command_line.iter().skip(1)
+        .filter(|a| !a.starts_with("--emit-msbuild"))
+        .map(|a| if a.contains(char::is_whitespace) { format!("\"{}\"", a) } else { a.clone() })
+        .collect();
+    
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let props = PROPS
+        .replace("@VERSION@", env!("CARGO_PKG_VERSION"))
+        .replace("@ICX_RUSTC@", &escape(&exe.display().to_string()))
+        .replace("@FLAGS@", &escape(&flags.join(" ")));
+    for (name, text) in [("icx-rustc.props", props.as_str()), ("icx-rustc.targets", TARGETS)] {
+        let path = dir.join(name);
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    console::eprint(&format!(
+        "[icx-rustc] wrote icx-rustc.props and icx-rustc.targets to {}; import them in the .vcxproj",
+        dir.display()
+    ));
+    Ok(exit_code::SUCCESS)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const PROPS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!--
+  icx-rustc integration for MSBuild, generated by icx-rustc @VERSION@
+  
+  Import this file after Microsoft.Cpp.Default.props and icx-rustc.targets
+  after Microsoft.Cpp.targets, then add crate roots to the project:
+    
+    <RustCompile Include="src\lib.rs" />
+  
+  Each is built as a static library (CrateType) and linked into the project's
+  output. Optimization, RuntimeLibrary, PreprocessorDefinitions, WarningLevel,
+  TreatWarningAsError and DebugInformationFormat follow the project's ClCompile
+  settings unless the item sets its own; AdditionalOptions are passed as they are.
+  Modules of the crate are not tracked: list them in AdditionalInputs.
+-->
+<Project xmlns="http://schemas.microsoft.com/developer/msbuild/2003">
+  <PropertyGroup>
+    <IcxRustc Condition="'$(IcxRustc)' == ''">@ICX_RUSTC@</IcxRustc>
+    <IcxRustcFlags Condition="'$(IcxRustcFlags)' == ''">@FLAGS@</IcxRustcFlags>
+    <!-- What the standard library of a Rust static library needs from Windows -->
+    <IcxRustNativeLibraries Condition="'$(IcxRustNativeLibraries)' == ''">kernel32.lib;advapi32.lib;bcrypt.lib;ntdll.lib;userenv.lib;ws2_32.lib;dbghelp.lib</IcxRustNativeLibraries>
+  </PropertyGroup>
+  <PropertyGroup Condition="'$(IcxRustTarget)' == ''">
+    <IcxRustTarget Condition="'$(Platform)' == 'Win32'">i686-pc-windows-msvc</IcxRustTarget>
+    <IcxRustTarget Condition="'$(Platform)' == 'x64'">x86_64-pc-windows-msvc</IcxRustTarget>
+    <IcxRustTarget Condition="'$(Platform)' == 'ARM64'">aarch64-pc-windows-msvc</IcxRustTarget>
+  </PropertyGroup>
+  <ItemGroup>
+    <AvailableItemName Include="RustCompile" />
+  </ItemGroup>
+  <ItemDefinitionGroup>
+    <RustCompile>
+      <CrateType>staticlib</CrateType>
+      <AdditionalOptions></AdditionalOptions>
+      <AdditionalInputs></AdditionalInputs>
+    </RustCompile>
+  </ItemDefinitionGroup>
+</Project>
+"#;
+
+const TARGETS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- icx-rustc integration for MSBuild; see icx-rustc.props -->
+<Project xmlns="http://schemas.microsoft.com/developer/msbuild/2003">
+  <ItemDefinitionGroup>
+    <Link>
+      <AdditionalDependencies>%(AdditionalDependencies);$(IcxRustNativeLibraries)</AdditionalDependencies>
+    </Link>
+  </ItemDefinitionGroup>
+  
+  <!-- Fills in the ClCompile settings and translates them to icx-rustc options -->
+  <Target Name="_IcxRustSettings" Condition="'@(RustCompile)' != ''">
+    <!-- An item created here receives the project's ClCompile definitions -->
+    <ItemGroup>
+      <ClCompile Include="$(MSBuildThisFileDirectory)icx-rustc.defaults" />
+      <_IcxClDefaults Include="@(ClCompile->WithMetadataValue('Identity', '$(MSBuildThisFileDirectory)icx-rustc.defaults'))" />
+      <ClCompile Remove="$(MSBuildThisFileDirectory)icx-rustc.defaults" />
+    </ItemGroup>
+    <PropertyGroup>
+      <_IcxOptimization>@(_IcxClDefaults->'%(Optimization)')</_IcxOptimization>
+      <_IcxRuntimeLibrary>@(_IcxClDefaults->'%(RuntimeLibrary)')</_IcxRuntimeLibrary>
+      <_IcxPreprocessorDefinitions>@(_IcxClDefaults->'%(PreprocessorDefinitions)')</_IcxPreprocessorDefinitions>
+      <_IcxWarningLevel>@(_IcxClDefaults->'%(WarningLevel)')</_IcxWarningLevel>
+      <_IcxTreatWarningAsError>@(_IcxClDefaults->'%(TreatWarningAsError)')</_IcxTreatWarningAsError>
+      <_IcxDebugInformationFormat>@(_IcxClDefaults->'%(DebugInformationFormat)')</_IcxDebugInformationFormat>
+    </PropertyGroup>
+    <ItemGroup>
+      <RustCompile>
+        <Optimization Condition="'%(RustCompile.Optimization)' == ''">$(_IcxOptimization)</Optimization>
+        <RuntimeLibrary Condition="'%(RustCompile.RuntimeLibrary)' == ''">$(_IcxRuntimeLibrary)</RuntimeLibrary>
+        <PreprocessorDefinitions Condition="'%(RustCompile.PreprocessorDefinitions)' == ''">$(_IcxPreprocessorDefinitions)</PreprocessorDefinitions>
+        <WarningLevel Condition="'%(RustCompile.WarningLevel)' == ''">$(_IcxWarningLevel)</WarningLevel>
+        <TreatWarningAsError Condition="'%(RustCompile.TreatWarningAsError)' == ''">$(_IcxTreatWarningAsError)</TreatWarningAsError>
+        <DebugInformationFormat Condition="'%(RustCompile.DebugInformationFormat)' == ''">$(_IcxDebugInformationFormat)</DebugInformationFormat>
+      </RustCompile>
+      <RustCompile>
+        <OutputFile Condition="'%(RustCompile.OutputFile)' == '' and '%(RustCompile.CrateType)' == 'staticlib'">$(IntDir)%(Filename).lib</OutputFile>
+        <OutputFile Condition="'%(RustCompile.OutputFile)' == '' and '%(RustCompile.CrateType)' == 'cdylib'">$(OutDir)%(Filename).dll</OutputFile>
+        <OutputFile Condition="'%(RustCompile.OutputFile)' == '' and '%(RustCompile.CrateType)' == 'bin'">$(OutDir)%(Filename).exe</OutputFile>
+        <_IcxOptions Condition="'%(RustCompile.Optimization)' == 'Disabled'">/Od</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.Optimization)' == 'MinSpace'">/O1</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.Optimization)' == 'MaxSpeed'">/O2</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.Optimization)' == 'Full'">/Ox</_IcxOptions>
+      </RustCompile>
+      <RustCompile>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'TurnOffAllWarnings'">%(_IcxOptions) /W0</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'Level1'">%(_IcxOptions) /W1</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'Level2'">%(_IcxOptions) /W2</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'Level3'">%(_IcxOptions) /W3</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'Level4'">%(_IcxOptions) /W4</_IcxOptions>
+        <_IcxOptions Condition="'%(RustCompile.WarningLevel)' == 'EnableAllWarnings'">%(_IcxOptions) /Wall</_IcxOptions>
+      </RustCompile>
+      <RustCompile>
+        <_IcxOptions Condition="'%(RustCompile.TreatWarningAsError)' == 'true'">%(_IcxOptions) /WX</_IcxOptions>
+      </RustCompile>
+      <RustCompile>
+        <_IcxOptions Condition="'%(RustCompile.DebugInformationFormat)' == 'ProgramDatabase' or '%(RustCompile.DebugInformationFormat)' == 'EditAndContinue'">%(_IcxOptions) /Qsplit-debug</_IcxOptions>
+      </RustCompile>
+      <!-- WIN32;_DEBUG;%(PreprocessorDefinitions) -> /DWIN32 /D_DEBUG -->
+      <RustCompile>
+        <_IcxDefines>$([System.String]::Copy('%(RustCompile.PreprocessorDefinitions)').Replace(';;', ';').Trim(';'))</_IcxDefines>
+      </RustCompile>
+      <RustCompile>
+        <_IcxOptions Condition="'%(RustCompile._IcxDefines)' != ''">%(_IcxOptions) /D$([System.String]::Copy('%(RustCompile._IcxDefines)').Replace(';', ' /D'))</_IcxOptions>
+      </RustCompile>
+      <!-- /MT and /MTd link the C runtime statically; Rust only has the release CRT -->
+      <RustCompile>
+        <_IcxRustcOptions Condition="'%(RustCompile.RuntimeLibrary)' == 'MultiThreaded' or '%(RustCompile.RuntimeLibrary)' == 'MultiThreadedDebug'">-Ctarget-feature=+crt-static</_IcxRustcOptions>
+      </RustCompile>
+    </ItemGroup>
+  </Target>
+  
+  <!-- One icx-rustc run per crate root, skipped while its output is newer than the inputs -->
+  <Target Name="IcxRustCompile"
+          AfterTargets="ClCompile"
+          BeforeTargets="Link;Lib"
+          DependsOnTargets="_IcxRustSettings"
+          Condition="'@(RustCompile)' != ''"
+          Inputs="%(RustCompile.FullPath);%(RustCompile.AdditionalInputs);$(MSBuildProjectFullPath)"
+          Outputs="%(RustCompile.OutputFile)">
+    <MakeDir Directories="$(IntDir);$([System.IO.Path]::GetDirectoryName('%(RustCompile.OutputFile)'))" />
+    <Exec Command="&quot;$(IcxRustc)&quot; /Qdiag-format:msvc $(IcxRustcFlags) --target=$(IcxRustTarget) --crate-type=%(RustCompile.CrateType) %(RustCompile._IcxOptions) %(RustCompile.AdditionalOptions) &quot;%(RustCompile.FullPath)&quot; -o &quot;%(RustCompile.OutputFile)&quot; -- %(RustCompile._IcxRustcOptions)"
+          WorkingDirectory="$(IntDir)" />
+  </Target>
+  
+  <!-- Static libraries are linked (or archived) with the project's objects -->
+  <Target Name="_IcxRustLinkInputs"
+          AfterTargets="IcxRustCompile"
+          BeforeTargets="Link;Lib"
+          DependsOnTargets="_IcxRustSettings"
+          Condition="'@(RustCompile)' != ''">
+    <ItemGroup>
+      <Link Include="@(RustCompile->'%(OutputFile)')" Condition="'%(RustCompile.CrateType)' == 'staticlib' and '$(ConfigurationType)' != 'StaticLibrary'" />
+      <Lib Include="@(RustCompile->'%(OutputFile)')" Condition="'%(RustCompile.CrateType)' == 'staticlib' and '$(ConfigurationType)' == 'StaticLibrary'" />
+    </ItemGroup>
+  </Target>
+  
+  <Target Name="_IcxRustClean" AfterTargets="Clean" DependsOnTargets="_IcxRustSettings" Condition="'@(RustCompile)' != ''">
+    <Delete Files="@(RustCompile->'%(OutputFile)')" />
+  </Target>
+</Project>
+"#;
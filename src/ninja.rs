@@ -0,0 +1,166 @@
+use crate::cc;
+use crate::cli::Args;
+use crate::cmdline;
+use crate::console;
+use crate::exit_code;
+use crate::translator;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+/// One icx-rustc run in the generated build.ninja
+pub struct Edge {
+    pub output: PathBuf,
+    /// Files the output is rebuilt from (besides those in its depfile)
+    pub inputs: Vec<PathBuf>,
+    /// The icx-rustc command line without the program; rustc flags after `--`
+    pub words: Vec<String>,
+}
+
+/// Options that name inputs or outputs; every edge gets its own
+const OUTPUT_OPTIONS: &[&str] = &["-o", "--o", "--Fe", "--Fo", "--Qoutdir", "--out-dir"];
+
+/// /Qninja[:file] without /project: an edge per C/C++ source and the final link
+///
+/// The C and C++ sources become `/c` edges, so ninja compiles them in
+/// parallel and only when they change; the Rust sources, the other inputs
+/// and the objects are linked by one more edge. With /c, every input is
+/// an edge of its own.
+pub fn from_inputs(args: &Args, ninja_file: &Path) -> Result<i32> {
+    let commands = translator::translate_units(args)?;
+    let (base, raw) = base_options(args);
+    let mut edges = Vec::new();
+    for (index, cmd) in commands.iter().enumerate() {
+        let mut objects = Vec::new();
+        for step in cmd.c_steps.iter().filter(|s| cc::is_source(&s.source)) {
+            let mut words = base.clone();
+            if !args.compile_only {
+                words.push("/c".to_string());
+            }
+            words.extend([step.source.display().to_string(), "-o".to_string(), step.output.display().to_string()]);
+            edges.push(Edge { output: step.output.clone(), inputs: vec![step.source.clone()], words });
+            objects.push(step.output.clone());
+        }
+        if cmd.input_files.is_empty() && cmd.link_inputs.is_empty() {
+            continue;
+        }
+        
+        let output = cmd.artifact().context("/Qninja: the output name cannot be predicted with --emit")?;
+        // 链接边：Rust 源文件、其余输入（目标文件、rlib、资源）与 C/C++ 目标文件
+        let library = commands.iter().any(|c| c.needs.contains(&index));
+        let mut inputs = cmd.input_files.clone();
+        inputs.extend(cmd.needs.iter().filter_map(|&j| commands[j].artifact()));
+        if !args.compile_only && !library {
+            inputs.extend(args.files.iter()
+                .filter(|f| !cc::is_source(f) && !cmd.input_files.contains(f) && f.extension().is_none_or(|e| e != "rs"))
+                .cloned());
+        }
+        inputs.extend(objects);
+        let mut words = base.clone();
+        // 被其他源文件当作 crate 使用的源文件单独编译为 rlib
+        if library {
+            words.push("--crate-type=rlib".to_string());
+        }
+        words.extend(inputs.iter().map(|p| p.display().to_string()));
+        words.extend(["-o".to_string(), output.display().to_string(), "--".to_string()]);
+        words.extend(raw.iter().cloned());
+        if library || !cmd.needs.is_empty() {
+            words.extend(cmd.args.iter().filter(|a| a.starts_with("-Ldependency=")).cloned());
+        }
+        edges.push(Edge { output, inputs, words });
+    }
+    
+    write(ninja_file, &edges, &[], &args.command_line)?;
+    if !args.quiet {
+        console::eprint(&format!(
+            "{} wrote {} ({} edge(s)); build with ninja -f {}",
+            "[icx-rustc]".bright_blue().bold(),
+            ninja_file.display(),
+            edges.len(),
+            ninja_file.display()
+        ));
+    }
+    Ok(exit_code::SUCCESS)
+}
+
+/// The options without inputs, outputs and /Qninja, and the rustc flags after `--`
+fn base_options(args: &Args) -> (Vec<String>, Vec<String>) {
+    let inputs: Vec<String> = args.files.iter().map(|f| f.display().to_string()).collect();
+    let mut words = Vec::new();
+    let mut iter = args.command_line.iter().skip(1);
+    while let Some(word) = iter.next() {
+        if word == "--" {
+            break;
+        }
+        if OUTPUT_OPTIONS.contains(&word.as_str()) {
+            iter.next();
+        } else if !OUTPUT_OPTIONS.iter().any(|o| word.starts_with(&format!("{}=", o)))
+            && !word.starts_with("--Qninja")
+            && !inputs.contains(word)
+        {
+            words.push(word.clone());
+        }
+    }
+    (words, iter.cloned().collect())
+}
+
+/// Writes the edges as a build.ninja with a single `icx` rule
+///
+/// Each edge writes a /QMMD depfile that ninja reads into its own
+/// database (`deps = gcc`). When `sources` is not empty, the file is
+/// regenerated by the command line that wrote it whenever one of them
+/// changes. Paths are relative to the current directory, which is where
+/// ninja has to run.
+pub fn write(path: &Path, edges: &[Edge], sources: &[PathBuf], command_line: &[String]) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate icx-rustc")?;
+    let mut out = String::new();
+    out.push_str(&format!("# Generated by icx-rustc {} (/Qninja); run ninja -f {} from this directory\n\n", env!("CARGO_PKG_VERSION"), path.display()));
+    // deps = gcc 需要 ninja 1.3
+    out.push_str("ninja_required_version = 1.3\n\n");
+    out.push_str(&format!("icx = {}\n\n", value(&quote(&exe.display().to_string()))));
+    out.push_str("rule icx\n  command = $icx $args\n  depfile = $out.d\n  deps = gcc\n  description = ICX $out\n\n");
+    
+    for edge in edges {
+        let mut words = edge.words.clone();
+        // 依赖文件放在 rustc 选项之前
+        let at = words.iter().position(|w| w == "--").unwrap_or(words.len());
+        words.insert(at, format!("--QMMD={}.d", edge.output.display()));
+        let inputs: Vec<String> = edge.inputs.iter().map(|p| path_word(p)).collect();
+        out.push_str(&format!("build {}: icx {}\n", path_word(&edge.output), inputs.join(" ")));
+        let args: Vec<String> = words.iter().map(|w| quote(w)).collect();
+        out.push_str(&format!("  args = {}\n\n", value(&args.join(" "))));
+    }
+    
+    if !sources.is_empty() {
+        out.push_str("rule regen\n  command = $icx $args\n  generator = 1\n  description = Regenerating $out\n\n");
+        let sources: Vec<String> = sources.iter().map(|p| path_word(p)).collect();
+        out.push_str(&format!("build {}: regen {}\n", path_word(path), sources.join(" ")));
+        let args: Vec<String> = command_line.iter().skip(1).map(|w| quote(w)).collect();
+        out.push_str(&format!("  args = {}\n", value(&args.join(" "))));
+    }
+    std::fs::write(path, out).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Quotes a word for the shell ninja runs commands with (CreateProcess on Windows)
+fn quote(word: &str) -> String {
+    if cfg!(windows) {
+        if word.is_empty() || word.contains([' ', '\t', '"']) {
+            return cmdline::quote_windows(word);
+        }
+        return word.to_string();
+    }
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c)) {
+        return word.to_string();
+    }
+    shlex::try_quote(word).map(|q| q.into_owned()).unwrap_or_else(|_| word.to_string())
+}
+
+/// A variable value: only `$` is special
+fn value(text: &str) -> String {
+    text.replace('$', "$$")
+}
+
+/// A path in a `build` line, where spaces and colons separate
+fn path_word(path: &Path) -> String {
+    path.display().to_string().replace('$', "$$").replace(' ', "$ ").replace(':', "$:")
+}
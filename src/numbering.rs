@@ -0,0 +1,98 @@
+/// ICX diagnostic numbers for rustc lints
+///
+/// Append only: build scripts refer to these in /wd and /we, so a number
+/// is never reused or changed.
+const LINTS: &[(&str, u32)] = &[
+    ("unused_variables", 20001),
+    ("unused_imports", 20002),
+    ("dead_code", 20003),
+    ("unused_mut", 20004),
+    ("unused_assignments", 20005),
+    ("unused_must_use", 20006),
+    ("unreachable_code", 20007),
+    ("unreachable_patterns", 20008),
+    ("unused_parens", 20009),
+    ("unused_braces", 20010),
+    ("unused_unsafe", 20011),
+    ("unused_macros", 20012),
+    ("unused_labels", 20013),
+    ("unused_doc_comments", 20014),
+    ("unused_attributes", 20015),
+    ("path_statements", 20016),
+    ("non_snake_case", 20017),
+    ("non_camel_case_types", 20018),
+    ("non_upper_case_globals", 20019),
+    ("deprecated", 20020),
+    ("while_true", 20021),
+    ("irrefutable_let_patterns", 20022),
+    ("unconditional_recursion", 20023),
+    ("improper_ctypes", 20024),
+    ("improper_ctypes_definitions", 20025),
+    ("overflowing_literals", 20026),
+    ("unused_comparisons", 20027),
+    ("unknown_lints", 20028),
+    ("renamed_and_removed_lints", 20029),
+    ("stable_features", 20030),
+    ("unexpected_cfgs", 20031),
+    ("static_mut_refs", 20032),
+    ("unsafe_op_in_unsafe_fn", 20033),
+    ("non_local_definitions", 20034),
+    ("private_interfaces", 20035),
+    ("private_bounds", 20036),
+    ("ambiguous_glob_reexports", 20037),
+    ("dropping_references", 20038),
+    ("forgetting_references", 20039),
+    ("invalid_value", 20040),
+    ("unused_allocation", 20041),
+    ("unused_extern_crates", 20042),
+    ("unused_qualifications", 20043),
+    ("unused_results", 20044),
+    ("missing_docs", 20045),
+    ("missing_debug_implementations", 20046),
+    ("trivial_casts", 20047),
+    ("trivial_numeric_casts", 20048),
+    ("unsafe_code", 20049),
+    ("elided_lifetimes_in_paths", 20050),
+];
+
+/// rustc error codes map onto 10000 + their number (E0308 -> #10308)
+const ERROR_BASE: u32 = 10000;
+
+/// ICX number of a rustc error code or lint name
+pub fn number(code: &str) -> Option<u32> {
+    if let Some(digits) = code.strip_prefix('E').filter(|d| !d.is_empty() && d.chars().all(|c| c.is_ascii_digit())) {
+        return digits.parse::<u32>().ok().map(|n| ERROR_BASE + n);
+    }
+    LINTS.iter().find(|(lint, _)| *lint == code).map(|(_, n)| *n)
+}
+
+/// What a /wd or /we argument refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagId {
+    Lint(String),
+    /// A hard error (E-code), which cannot be disabled
+    Error(String),
+}
+
+/// Resolves `20001`, `#20001`, `unused_variables` or `E0308`
+pub fn resolve(spec: &str) -> Option<DiagId> {
+    let spec = spec.trim().trim_start_matches('#');
+    if let Ok(n) = spec.parse::<u32>() {
+        if (ERROR_BASE..ERROR_BASE + 10000).contains(&n) {
+            return Some(DiagId::Error(format!("E{:04}", n - ERROR_BASE)));
+        }
+        return LINTS.iter()
+            .find(|(_, number)| *number == n)
+            .map(|(lint, _)| DiagId::Lint(lint.to_string()));
+    }
+    if number(spec).is_some() && spec.starts_with('E') {
+        return Some(DiagId::Error(spec.to_string()));
+    }
+    
+    // 表外的 lint 名称（含 clippy::）也直接交给 rustc
+    let lint = spec.replace('-', "_");
+    lint.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+        .then_some(DiagId::Lint(lint))
+        .filter(|_| !spec.is_empty())
+}
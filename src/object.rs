@@ -0,0 +1,498 @@
+use indexmap::IndexMap;
+use std::ops::Range;
+
+/// A section of an object file or image
+pub struct Section {
+    pub name: String,
+    pub size: u64,
+    /// Loaded into memory (not debug info or other metadata)
+    pub alloc: bool,
+}
+
+/// A symbol table entry; `name` is still mangled
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub kind: SymbolKind,
+    pub defined: bool,
+    /// Visible to other objects (global or weak binding, COFF external)
+    pub global: bool,
+    pub weak: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Data,
+    Other,
+}
+
+/// Sections and symbols of one ELF, Mach-O or COFF file
+pub struct Object {
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    /// The PDB a PE image names in its CodeView debug entry, which holds
+    /// the symbols link.exe leaves out of the image
+    pub pdb: Option<String>,
+}
+
+/// COFF machine types of object files without a PE header (x86, x64, ARM64, ARM)
+const COFF_MACHINES: &[u64] = &[0x14c, 0x8664, 0xaa64, 0x1c4];
+
+/// Reads an ELF or Mach-O file (object, shared library or executable),
+/// a PE image or a COFF object
+///
+/// Only what the reports need: the section table and the symbol table
+/// (`.dynsym` when an ELF file has been stripped). COFF and Mach-O
+/// symbols carry no size; it is the distance to the next symbol of the
+/// section. Universal Mach-O binaries are read for their first slice.
+pub fn parse(data: &[u8]) -> Option<Object> {
+    if data.starts_with(b"\x7fELF") {
+        return elf(data);
+    }
+    let r = Reader { data, big: false };
+    match r.u32(0)? {
+        0xfeed_face | 0xfeed_facf => return macho(data),
+        // fat_header 为大端；Java 的 class 文件也以 cafebabe 开头，但其后的数字很大
+        0xbeba_feca if (1..=16).contains(&Reader { data, big: true }.u32(4)?) => {
+            let fat = Reader { data, big: true };
+            let (offset, size) = (fat.u32(16)? as usize, fat.u32(20)? as usize);
+            return macho(data.get(offset..offset.checked_add(size)?)?);
+        }
+        _ => {}
+    }
+    if data.starts_with(b"MZ") {
+        let pe = r.u32(0x3c)? as usize;
+        if r.bytes::<4>(pe)? != *b"PE\0\0" {
+            return None;
+        }
+        return coff(data, pe.checked_add(4)?);
+    }
+    if COFF_MACHINES.contains(&r.u16(0)?) {
+        return coff(data, 0);
+    }
+    None
+}
+
+/// The members of an `ar` archive (`.a`, `.lib`, `.rlib`) by name
+///
+/// GNU and BSD long names as well as the MSVC import library layout are
+/// understood; the archive's own symbol tables are skipped.
+pub fn members(data: &[u8]) -> Option<Vec<(String, &[u8])>> {
+    let mut rest = data.strip_prefix(b"!<arch>\n")?;
+    let mut long_names: &[u8] = &[];
+    let mut members = Vec::new();
+    while rest.len() >= 60 {
+        let header = &rest[..60];
+        let field = |from: usize, to: usize| String::from_utf8_lossy(&header[from..to]).trim_end().to_string();
+        let size: usize = field(48, 58).parse().ok()?;
+        let mut body = rest.get(60..size.checked_add(60)?)?;
+        let mut name = field(0, 16);
+        // 成员按 2 字节对齐
+        rest = rest.get(60 + size + size % 2..).unwrap_or_default();
+        
+        if name == "/" || name == "/SYM64/" || name == "__.SYMDEF" || name == "__.SYMDEF SORTED" {
+            continue;
+        }
+        if name == "//" {
+            long_names = body;
+            continue;
+        }
+        if let Some(offset) = name.strip_prefix('/').and_then(|o| o.parse::<usize>().ok()) {
+            let tail = long_names.get(offset..).unwrap_or_default();
+            let end = tail.iter().position(|&b| b == b'\n' || b == 0).unwrap_or(tail.len());
+            name = String::from_utf8_lossy(&tail[..end]).trim_end_matches('/').to_string();
+        } else if let Some(len) = name.strip_prefix("#1/").and_then(|l| l.parse::<usize>().ok()) {
+            // BSD：名称在数据之前
+            let raw = body.get(..len)?;
+            name = String::from_utf8_lossy(raw).trim_end_matches('\0').to_string();
+            body = &body[len..];
+        } else {
+            name = name.trim_end_matches('/').to_string();
+        }
+        members.push((name, body));
+    }
+    Some(members)
+}
+
+/// Reads little- or big-endian integers at an offset
+struct Reader<'a> {
+    data: &'a [u8],
+    big: bool,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, at: usize) -> Option<[u8; N]> {
+        self.data.get(at..at.checked_add(N)?)?.try_into().ok()
+    }
+    
+    fn u8(&self, at: usize) -> Option<u64> {
+        self.data.get(at).map(|&b| b as u64)
+    }
+    
+    fn u16(&self, at: usize) -> Option<u64> {
+        let b = self.bytes::<2>(at)?;
+        Some(if self.big { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) } as u64)
+    }
+    
+    fn u32(&self, at: usize) -> Option<u64> {
+        let b = self.bytes::<4>(at)?;
+        Some(if self.big { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) } as u64)
+    }
+    
+    fn u64(&self, at: usize) -> Option<u64> {
+        let b = self.bytes::<8>(at)?;
+        Some(if self.big { u64::from_be_bytes(b) } else { u64::from_le_bytes(b) })
+    }
+    
+    /// `base + extra` as an offset into the data, if it is one (or the end)
+    ///
+    /// Offsets read from the file are checked here, so fields at small
+    /// distances from the result cannot overflow.
+    fn offset(&self, base: u64, extra: u64) -> Option<usize> {
+        let at = usize::try_from(base.checked_add(extra)?).ok()?;
+        (at <= self.data.len()).then_some(at)
+    }
+    
+    /// NUL-terminated string at `at`
+    fn str(&self, at: usize) -> Option<String> {
+        let rest = self.data.get(at..)?;
+        let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+        Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+    
+    /// String in a fixed-size field, NUL-padded unless it fills the field
+    fn name<const N: usize>(&self, at: usize) -> Option<String> {
+        let raw = self.bytes::<N>(at)?;
+        let end = raw.iter().position(|&b| b == 0).unwrap_or(N);
+        Some(String::from_utf8_lossy(&raw[..end]).into_owned())
+    }
+}
+
+fn elf(data: &[u8]) -> Option<Object> {
+    let wide = *data.get(4)? == 2;
+    let r = Reader { data, big: *data.get(5)? == 2 };
+    let (shoff, shentsize, shnum, shstrndx) = if wide {
+        (r.u64(0x28)?, r.u16(0x3a)?, r.u16(0x3c)?, r.u16(0x3e)?)
+    } else {
+        (r.u32(0x20)?, r.u16(0x2e)?, r.u16(0x30)?, r.u16(0x32)?)
+    };
+    
+    // (名称偏移, 类型, 标志, 文件偏移, 大小, link)
+    let header = |index: u64| -> Option<(u64, u64, u64, u64, u64, u64)> {
+        let at = r.offset(shoff, index.checked_mul(shentsize)?)?;
+        if wide {
+            Some((r.u32(at)?, r.u32(at + 4)?, r.u64(at + 8)?, r.u64(at + 24)?, r.u64(at + 32)?, r.u32(at + 40)?))
+        } else {
+            Some((r.u32(at)?, r.u32(at + 4)?, r.u32(at + 8)?, r.u32(at + 16)?, r.u32(at + 20)?, r.u32(at + 24)?))
+        }
+    };
+    let names = header(shstrndx)?.3;
+    
+    let mut sections = Vec::new();
+    let mut symtab = None;
+    let mut dynsym = None;
+    for index in 0..shnum {
+        let (name, kind, flags, offset, size, link) = header(index)?;
+        // SHF_ALLOC：装入内存的段
+        sections.push(Section { name: r.str(r.offset(names, name)?)?, size, alloc: flags & 2 != 0 });
+        match kind {
+            2 => symtab = Some((offset, size, link)),
+            11 => dynsym = Some((offset, size, link)),
+            _ => {}
+        }
+    }
+    
+    let mut symbols = Vec::new();
+    if let Some((offset, size, link)) = symtab.or(dynsym) {
+        let strings = header(link)?.3;
+        let entry = if wide { 24 } else { 16 };
+        for at in (offset..offset.checked_add(size)?).step_by(entry).skip(1) {
+            let at = r.offset(at, 0)?;
+            let (name, info, shndx, address, size) = if wide {
+                (r.u32(at)?, r.u8(at + 4)?, r.u16(at + 6)?, r.u64(at + 8)?, r.u64(at + 16)?)
+            } else {
+                (r.u32(at)?, r.u8(at + 12)?, r.u16(at + 14)?, r.u32(at + 4)?, r.u32(at + 8)?)
+            };
+            let name = r.str(r.offset(strings, name)?)?;
+            // STT_SECTION / STT_FILE 不是代码或数据
+            if name.is_empty() || matches!(info & 0xf, 3 | 4) {
+                continue;
+            }
+            let kind = match info & 0xf {
+                2 => SymbolKind::Function,
+                1 => SymbolKind::Data,
+                _ => SymbolKind::Other,
+            };
+            let bind = info >> 4;
+            symbols.push(Symbol {
+                name,
+                address,
+                size,
+                kind,
+                defined: shndx != 0,
+                global: bind == 1 || bind == 2,
+                weak: bind == 2,
+            });
+        }
+    }
+    Some(Object { sections, symbols, pdb: None })
+}
+
+/// A thin Mach-O file; symbol values are addresses, as in a linked image
+fn macho(data: &[u8]) -> Option<Object> {
+    let r = Reader { data, big: false };
+    let wide = r.u32(0)? == 0xfeed_facf;
+    let commands = r.u32(16)?;
+    let mut at = if wide { 32 } else { 28 };
+    
+    let mut sections = Vec::new();
+    // 每个段的地址范围，以及是否为代码段（S_ATTR_PURE_INSTRUCTIONS / S_ATTR_SOME_INSTRUCTIONS）
+    let mut ranges = Vec::new();
+    let mut code = Vec::new();
+    let mut symtab = None;
+    for _ in 0..commands {
+        let (command, size) = (r.u32(at)?, r.u32(at + 4)?);
+        match command {
+            // LC_SEGMENT / LC_SEGMENT_64，其后是各段的 section 结构
+            0x1 | 0x19 => {
+                let (count, first, entry) = if wide { (r.u32(at + 64)?, at + 72, 80) } else { (r.u32(at + 48)?, at + 56, 68) };
+                for index in 0..count {
+                    let s = r.offset(first as u64, index * entry)?;
+                    let (section, segment) = (r.name::<16>(s)?, r.name::<16>(s + 16)?);
+                    let (address, size, flags) = if wide {
+                        (r.u64(s + 32)?, r.u64(s + 40)?, r.u32(s + 64)?)
+                    } else {
+                        (r.u32(s + 32)?, r.u32(s + 36)?, r.u32(s + 56)?)
+                    };
+                    ranges.push(address..address.checked_add(size)?);
+                    code.push(flags & 0x8000_0400 != 0);
+                    sections.push(Section { alloc: segment != "__DWARF", name: format!("{},{}", segment, section), size });
+                }
+            }
+            // LC_SYMTAB：符号表偏移、数量与字符串表偏移
+            0x2 => symtab = Some((r.u32(at + 8)?, r.u32(at + 12)?, r.u32(at + 16)?)),
+            _ => {}
+        }
+        // cmdsize 至少覆盖 cmd 与 cmdsize 本身，否则会原地打转
+        at = r.offset(at as u64, size.max(8))?;
+    }
+    
+    let mut symbols = Vec::new();
+    let mut owners = Vec::new();
+    if let Some((offset, count, strings)) = symtab {
+        let entry = if wide { 16 } else { 12 };
+        for index in 0..count {
+            let at = r.offset(offset, index * entry)?;
+            let (name, kind, section, desc) = (r.u32(at)?, r.u8(at + 4)?, r.u8(at + 5)?, r.u16(at + 6)?);
+            let address = if wide { r.u64(at + 8)? } else { r.u32(at + 8)? };
+            let name = r.str(r.offset(strings, name)?)?;
+            // N_STAB：调试记录
+            if name.is_empty() || kind & 0xe0 != 0 {
+                continue;
+            }
+            // N_SECT：定义在 n_sect 段中（从 1 开始）
+            let in_section = kind & 0x0e == 0x0e && (1..=sections.len() as u64).contains(&section);
+            symbols.push(Symbol {
+                // C 符号带下划线前缀，Rust 的 __ZN 也是如此
+                name: name.strip_prefix('_').map(str::to_string).unwrap_or(name),
+                address,
+                size: 0,
+                kind: match in_section {
+                    true if code[section as usize - 1] => SymbolKind::Function,
+                    true => SymbolKind::Data,
+                    false => SymbolKind::Other,
+                },
+                // N_UNDF 且值非 0 为 common 符号
+                defined: kind & 0x0e != 0 || address != 0,
+                global: kind & 0x01 != 0,
+                // N_WEAK_REF / N_WEAK_DEF
+                weak: desc & 0xc0 != 0,
+            });
+            owners.push(if in_section { section } else { 0 });
+        }
+    }
+    distance_sizes(&mut symbols, &owners, &ranges);
+    Some(Object { sections, symbols, pdb: None })
+}
+
+/// A COFF header at `coff`: the PE one, or the start of an object file
+fn coff(data: &[u8], coff: usize) -> Option<Object> {
+    let r = Reader { data, big: false };
+    let count = r.u16(coff + 2)? as usize;
+    let symbol_table = r.u32(coff + 8)?;
+    let symbol_count = r.u32(coff + 12)?;
+    let optional = r.u16(coff + 16)? as usize;
+    let table = coff + 20 + optional;
+    let string_table = symbol_table + symbol_count * 18;
+    
+    // 长名称：/4（段）或前 4 字节为 0（符号）时指向字符串表
+    let short_name = |at: usize| r.name::<8>(at);
+    
+    let mut sections = Vec::new();
+    for index in 0..count {
+        let at = table + index * 40;
+        let mut name = short_name(at)?;
+        if let Some(offset) = name.strip_prefix('/').and_then(|o| o.parse::<u64>().ok()) {
+            name = r.str(r.offset(string_table, offset)?)?;
+        }
+        // 目标文件中 VirtualSize 为 0
+        let size = r.u32(at + 8)?.max(r.u32(at + 16)?);
+        // IMAGE_SCN_MEM_DISCARDABLE：调试段等
+        let alloc = r.u32(at + 36)? & 0x0200_0000 == 0 && !name.starts_with(".debug");
+        sections.push(Section { name, size, alloc });
+    }
+    
+    let (pdb, exports) = match optional {
+        0 => (None, Vec::new()),
+        _ => (pdb(&r, coff + 20, table, count), exports(&r, coff + 20, table, count).unwrap_or_default()),
+    };
+    
+    let mut symbols = Vec::new();
+    if symbol_table == 0 {
+        return Some(Object { sections, symbols: exports, pdb });
+    }
+    // 每个符号所在的段号，用于计算大小
+    let mut owners: Vec<u64> = Vec::new();
+    let mut index = 0;
+    while index < symbol_count {
+        let at = r.offset(symbol_table, index * 18)?;
+        let name = match r.u32(at)? {
+            0 => r.str(r.offset(string_table, r.u32(at + 4)?)?)?,
+            _ => short_name(at)?,
+        };
+        let value = r.u32(at + 8)?;
+        let section = r.u16(at + 12)?;
+        let kind = r.u16(at + 14)?;
+        let class = r.u8(at + 16)?;
+        let aux = r.u8(at + 17)?;
+        index += 1 + aux;
+        // IMAGE_SYM_CLASS_EXTERNAL / STATIC / WEAK_EXTERNAL，不含段符号（带辅助记录）
+        if !matches!(class, 2 | 3 | 105) || class == 3 && (aux > 0 || name.starts_with('.')) {
+            continue;
+        }
+        let in_section = (1..=count as u64).contains(&section);
+        symbols.push(Symbol {
+            name,
+            address: value,
+            size: 0,
+            kind: match kind & 0x30 {
+                0x20 => SymbolKind::Function,
+                _ if in_section => SymbolKind::Data,
+                _ => SymbolKind::Other,
+            },
+            // 段号 0：未定义（值非 0 时为 common 符号）；0xffff 之类为绝对符号
+            defined: section != 0 || value != 0,
+            global: class != 3,
+            weak: class == 105,
+        });
+        owners.push(if in_section { section } else { 0 });
+    }
+    
+    let ranges: Vec<Range<u64>> = sections.iter().map(|s| 0..s.size).collect();
+    distance_sizes(&mut symbols, &owners, &ranges);
+    symbols.extend(exports);
+    Some(Object { sections, symbols, pdb })
+}
+
+/// The PDB path of a PE image's CodeView (`RSDS`) debug directory entry
+fn pdb(r: &Reader, optional: usize, table: usize, count: usize) -> Option<String> {
+    let (rva, size) = directory(r, optional, 6)?;
+    let (_, start) = locate(r, table, count, rva)?;
+    for entry in (start..r.offset(start as u64, size)?).step_by(28) {
+        // IMAGE_DEBUG_TYPE_CODEVIEW；PointerToRawData 已是文件偏移
+        let data = r.offset(r.u32(entry + 24)?, 0)?;
+        if r.u32(entry + 12)? == 2 && r.bytes::<4>(data)? == *b"RSDS" {
+            return r.str(data + 24).filter(|path| !path.is_empty());
+        }
+    }
+    None
+}
+
+/// The named exports of a PE image (a DLL, or an executable exporting
+/// symbols), from its export directory
+///
+/// RVAs stand in for addresses. Exports forwarded to another DLL, whose
+/// RVA points into the directory itself, are undefined.
+fn exports(r: &Reader, optional: usize, table: usize, count: usize) -> Option<Vec<Symbol>> {
+    let (rva, size) = directory(r, optional, 0)?;
+    let (_, at) = locate(r, table, count, rva)?;
+    let names = r.u32(at + 24)?;
+    let located = |field: usize| Some(locate(r, table, count, r.u32(at + field)?)?.1 as u64);
+    let (functions, name_rvas, ordinals) = (located(28)?, located(32)?, located(36)?);
+    let mut symbols = Vec::new();
+    for index in 0..names {
+        let name = r.str(locate(r, table, count, r.u32(r.offset(name_rvas, index * 4)?)?)?.1)?;
+        let ordinal = r.u16(r.offset(ordinals, index * 2)?)?;
+        let address = r.u32(r.offset(functions, ordinal * 4)?)?;
+        let forwarded = (rva..rva + size).contains(&address);
+        // 所在段带 IMAGE_SCN_MEM_EXECUTE
+        let code = locate(r, table, count, address)
+            .and_then(|(section, _)| r.u32(table + section * 40 + 36))
+            .is_some_and(|flags| flags & 0x2000_0000 != 0);
+        symbols.push(Symbol {
+            name,
+            address,
+            size: 0,
+            kind: match (forwarded, code) {
+                (true, _) => SymbolKind::Other,
+                (false, true) => SymbolKind::Function,
+                (false, false) => SymbolKind::Data,
+            },
+            defined: !forwarded,
+            global: true,
+            weak: false,
+        });
+    }
+    Some(symbols)
+}
+
+/// Data directory `index` of a PE optional header at `optional`: (RVA, size), if present
+fn directory(r: &Reader, optional: usize, index: usize) -> Option<(u64, u64)> {
+    // PE32+ 的数据目录从可选头 112 字节处开始，PE32 从 96 处；其前 4 字节为目录数
+    let start = optional + if r.u16(optional)? == 0x20b { 112 } else { 96 };
+    if index as u64 >= r.u32(start - 4)? {
+        return None;
+    }
+    let at = start + index * 8;
+    Some((r.u32(at)?, r.u32(at + 4)?)).filter(|&(rva, size)| rva != 0 && size != 0)
+}
+
+/// The section of a PE image that holds `rva`, and where that byte is in the file
+fn locate(r: &Reader, table: usize, count: usize, rva: u64) -> Option<(usize, usize)> {
+    (0..count).find_map(|index| {
+        let at = table + index * 40;
+        let (size, address, raw) = (r.u32(at + 8)?.max(r.u32(at + 16)?), r.u32(at + 12)?, r.u32(at + 20)?);
+        if !(address..address + size).contains(&rva) {
+            return None;
+        }
+        Some((index, r.offset(raw, rva - address)?))
+    })
+}
+
+/// Sizes symbols as the distance to the next symbol of their section
+///
+/// `owners` gives the (1-based) section of each symbol, 0 for none, and
+/// `ranges` where each section is, in the units of the symbol values.
+/// Symbols outside their section (Mach-O's `__mh_execute_header`) get none.
+fn distance_sizes(symbols: &mut [Symbol], owners: &[u64], ranges: &[Range<u64>]) {
+    let mut by_section: IndexMap<u64, Vec<usize>> = IndexMap::new();
+    for (i, &section) in owners.iter().enumerate().filter(|(_, &s)| s > 0) {
+        by_section.entry(section).or_default().push(i);
+    }
+    for (section, mut list) in by_section {
+        let Some(range) = ranges.get(section as usize - 1) else {
+            continue;
+        };
+        list.sort_by_key(|&i| symbols[i].address);
+        for (n, &i) in list.iter().enumerate() {
+            let address = symbols[i].address;
+            if !range.contains(&address) {
+                continue;
+            }
+            let next = list[n + 1..].iter().map(|&j| symbols[j].address).find(|&a| a > address).unwrap_or(range.end);
+            symbols[i].size = next.saturating_sub(address);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::cc::CStep;
+use crate::cli::Args;
+use crate::translator::{self, sanitize_crate_name, RustcCommand};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// /Qoffload:nvptx64[:sm_XX] or /Qoffload:spirv: builds the GPU kernels
+///
+/// Every /Qoffload-kernel source is compiled once more for the device,
+/// before the host: PTX assembly for `nvptx64-nvidia-cuda` (rustup
+/// target add nvptx64-nvidia-cuda), a SPIR-V module for
+/// `spirv-unknown-vulkan1.2` through the rust-gpu codegen backend named
+/// by ICX_SPIRV_BACKEND. The device code lands beside the host binary as
+/// `<kernel>.ptx` / `<kernel>.spv`; the host is compiled with
+/// `cfg(offload)` and `ICX_OFFLOAD_<KERNEL>` naming the file, so
+/// `include_bytes!(env!("ICX_OFFLOAD_KERNEL"))` embeds it.
+pub fn translate(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(spec) = &args.offload else {
+        return Ok(());
+    };
+    let (device, cpu) = spec.split_once(':').map_or((spec.as_str(), None), |(d, c)| (d, Some(c)));
+    let device = device.to_ascii_lowercase();
+    if args.offload_kernels.is_empty() {
+        bail!("/Qoffload:{} needs the kernel sources (/Qoffload-kernel:<file.rs>)", spec);
+    }
+    
+    let mut words: Vec<String> = match device.as_str() {
+        "nvptx64" | "nvptx" | "cuda" => {
+            let mut words = vec![
+                "--target=nvptx64-nvidia-cuda".to_string(),
+                "--crate-type=lib".to_string(),
+                "--emit=asm".to_string(),
+                // 单个代码生成单元才能得到一个 .ptx 文件
+                "-Ccodegen-units=1".to_string(),
+                "-Copt-level=3".to_string(),
+            ];
+            if let Some(cpu) = cpu {
+                words.push(format!("-Ctarget-cpu={}", cpu));
+            }
+            words
+        }
+        "spirv" | "spir-v" => {
+            let backend = std::env::var_os("ICX_SPIRV_BACKEND")
+                .context("/Qoffload:spirv needs the rust-gpu codegen backend (librustc_codegen_spirv) in ICX_SPIRV_BACKEND")?;
+            translator::require_nightly(cmd, "/Qoffload:spirv")?;
+            vec![
+                format!("--target=spirv-unknown-{}", cpu.unwrap_or("vulkan1.2")),
+                "--crate-type=dylib".to_string(),
+                format!("-Zcodegen-backend={}", PathBuf::from(backend).display()),
+                "-Coverflow-checks=off".to_string(),
+                "-Copt-level=3".to_string(),
+            ]
+        }
+        _ => bail!("Unknown /Qoffload target '{}' (expected nvptx64[:sm_XX] or spirv)", spec),
+    };
+    let (name, extension) = if device.starts_with("spir") { ("spirv", "spv") } else { ("nvptx64", "ptx") };
+    words.extend(args.defines.iter().map(|d| match d.split_once('=') {
+        Some((name, value)) => format!("--cfg={}=\"{}\"", name, value.trim_matches('"')),
+        None => format!("--cfg={}", d),
+    }));
+    
+    // 设备代码放在主机程序旁边
+    let dir = match cmd.artifact() {
+        Some(artifact) => artifact.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => args.out_dir.clone().unwrap_or_default(),
+    };
+    for kernel in &args.offload_kernels {
+        let stem = kernel.file_stem()
+            .with_context(|| format!("Invalid kernel name '{}'", kernel.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let output = dir.join(format!("{}.{}", stem, extension));
+        let mut args = words.clone();
+        args.extend([
+            format!("--crate-name={}", sanitize_crate_name(&stem)),
+            kernel.display().to_string(),
+            "-o".to_string(),
+            output.display().to_string(),
+        ]);
+        let absolute = std::path::absolute(cmd.resolve(&output))
+            .with_context(|| format!("Failed to resolve {}", output.display()))?;
+        cmd.env_vars.push((format!("ICX_OFFLOAD_{}", sanitize_crate_name(&stem).to_ascii_uppercase()), absolute.display().to_string()));
+        cmd.c_steps.push(CStep { program: PathBuf::from(&cmd.executable), args, source: kernel.clone(), output });
+    }
+    
+    cmd.args.push("--cfg=offload".to_string());
+    cmd.args.push(format!("--cfg=offload=\"{}\"", name));
+    cmd.args.push("--check-cfg=cfg(offload,values(none(),\"nvptx64\",\"spirv\"))".to_string());
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::console;
+use anyhow::Result;
+use std::sync::mpsc;
+
+enum Event {
+    Token(std::io::Result<jobserver::Acquired>),
+    Done(usize, Result<i32>, Option<jobserver::Acquired>),
+}
+
+/// Runs `count` jobs with at most `limit` of them at once (/MP)
+///
+/// When started by `make -jN` (a jobserver in MAKEFLAGS) the first job runs
+/// on the token make already gave this process and every further concurrent
+/// job waits for a token from the jobserver, so the whole build stays
+/// within N processes. Results are returned in job order.
+pub fn run_jobs<F>(count: usize, limit: usize, job: F) -> Vec<Result<i32>>
+where
+    F: Fn(usize) -> Result<i32> + Sync,
+{
+    if limit <= 1 || count <= 1 {
+        return (0..count).map(&job).collect();
+    }
+
+    let (tx, rx) = mpsc::channel();
+
+    // 安全性：在启动其他线程之前调用，描述符来自父进程 make
+    let client = unsafe { jobserver::Client::from_env() };
+    let helper = client.and_then(|client| {
+        let tx = tx.clone();
+        client.into_helper_thread(move |token| {
+            let _ = tx.send(Event::Token(token));
+        })
+        .map_err(|e| console::eprint(&format!("[icx-rustc] warning: ignoring make jobserver: {}", e)))
+        .ok()
+    });
+
+    let mut results: Vec<Option<Result<i32>>> = (0..count).map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let job = &job;
+        let mut next = 0;
+        let mut running = 0;
+        let mut implicit_free = true;
+        let mut tokens = Vec::new();
+        let mut requested = false;
+        let mut limit = limit;
+
+        loop {
+            // 自带的隐式令牌优先，其余任务各需一个 jobserver 令牌
+            while next < count && running < limit {
+                let token = if implicit_free {
+                    implicit_free = false;
+                    None
+                } else if let Some(helper) = &helper {
+                    match tokens.pop() {
+                        Some(token) => Some(token),
+                        None => {
+                            if !requested {
+                                helper.request_token();
+                                requested = true;
+                            }
+                            break;
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                let tx = tx.clone();
+                let index = next;
+                scope.spawn(move || {
+                    let _ = tx.send(Event::Done(index, job(index), token));
+                });
+                next += 1;
+                running += 1;
+            }
+
+            // 没有待启动的任务时立即归还多余令牌
+            if next >= count {
+                tokens.clear();
+            }
+            if running == 0 && next >= count {
+                break;
+            }
+
+            match rx.recv() {
+                Ok(Event::Token(Ok(token))) => {
+                    requested = false;
+                    tokens.push(token);
+                }
+                Ok(Event::Token(Err(e))) => {
+                    console::eprint(&format!("[icx-rustc] warning: make jobserver failed, compiling serially: {}", e));
+                    requested = false;
+                    limit = 1;
+                }
+                Ok(Event::Done(index, result, token)) => {
+                    running -= 1;
+                    results[index] = Some(result);
+                    match token {
+                        Some(token) => drop(token),
+                        None => implicit_free = true,
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+    drop(helper);
+
+    results.into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("Compilation job did not finish"))))
+        .collect()
+}
@@ -0,0 +1,71 @@
+use crate::console;
+use colored::Colorize;
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `[12/48] kernels/fft.rs` progress for builds with several translation units
+///
+/// On a terminal the units in flight are shown on a live status line; in
+/// logs (no TTY, --daemon clients) only one plain line per finished unit
+/// is printed.
+pub struct Progress {
+    total: usize,
+    live: bool,
+    state: Mutex<State>,
+}
+
+struct State {
+    done: usize,
+    running: Vec<String>,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            live: std::io::stdout().is_terminal()
+                && std::io::stderr().is_terminal()
+                && !console::is_redirected(),
+            state: Mutex::new(State { done: 0, running: Vec::new() }),
+        }
+    }
+    
+    pub fn start(&self, unit: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running.push(unit.to_string());
+        self.redraw(&state);
+    }
+    
+    pub fn finish(&self, unit: &str, elapsed: Duration, ok: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.done += 1;
+        if let Some(i) = state.running.iter().position(|u| u == unit) {
+            state.running.remove(i);
+        }
+        
+        let counter = format!("[{}/{}]", state.done, self.total);
+        let counter = if ok { counter.bright_green() } else { counter.bright_red() };
+        console::eprint(&format!("{} {} ({} ms)", counter.bold(), unit, elapsed.as_millis()));
+        self.redraw(&state);
+    }
+    
+    fn redraw(&self, state: &State) {
+        if !self.live {
+            return;
+        }
+        // 只显示一个文件名，避免状态行折行
+        let status = match state.running.as_slice() {
+            [] => None,
+            [unit] => Some(format!("[{}/{}] {}", state.done, self.total, unit)),
+            [unit, rest @ ..] => Some(format!(
+                "[{}/{}] {} (+{} more)",
+                state.done,
+                self.total,
+                unit,
+                rest.len()
+            )),
+        };
+        console::set_status(status);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::buildlog;
+use crate::cli::{self, Args};
+use crate::console;
+use crate::deps;
+use crate::exit_code;
+use crate::ninja;
+use crate::parallel;
+use crate::toml;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A `/project:build.icxproj` file (TOML, or JSON when it starts with `{`)
+///
+/// ```toml
+/// name = "app"
+/// options = ["/O2", "/W4"]
+/// link = ["third_party/libz.a"]
+///
+/// [[unit]]
+/// source = "src/util.rs"
+/// kind = "rlib"
+///
+/// [[unit]]
+/// source = "src/main.rs"
+/// deps = ["util"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Project {
+    /// Name of the final executable (default: the project file's stem)
+    name: Option<String>,
+    /// Options for every unit
+    #[serde(default)]
+    options: Vec<String>,
+    /// Where objects and libraries go, relative to the project file
+    #[serde(default = "default_out_dir", rename = "out-dir", alias = "out_dir")]
+    out_dir: PathBuf,
+    /// Final executable (default: `<out-dir>/<name>`)
+    output: Option<PathBuf>,
+    /// Extra inputs for the final link (objects, static libraries)
+    #[serde(default)]
+    link: Vec<PathBuf>,
+    #[serde(default, rename = "unit", alias = "units")]
+    units: Vec<Unit>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Unit {
+    source: PathBuf,
+    /// Crate name other units refer to in `deps` (default: the file stem)
+    name: Option<String>,
+    #[serde(default)]
+    kind: Kind,
+    /// Options for this unit, after the project's
+    #[serde(default)]
+    options: Vec<String>,
+    /// Units built before this one; rlibs become `--extern` crates. Rlib
+    /// units the source names in `use` or `extern crate` are added.
+    #[serde(default)]
+    deps: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    /// The crate that is linked into the final executable
+    #[default]
+    Bin,
+    Rlib,
+    Staticlib,
+    /// An object file (`/c`) for the final link
+    Obj,
+}
+
+fn default_out_dir() -> PathBuf {
+    PathBuf::from("build")
+}
+
+/// A loaded project: what the unit command lines are made of
+struct Plan {
+    project: Project,
+    /// Directory of the project file; paths in it are relative to this
+    dir: PathBuf,
+    out_dir: PathBuf,
+    names: Vec<String>,
+    outputs: Vec<PathBuf>,
+    levels: Vec<Vec<usize>>,
+    bins: usize,
+    /// Options given next to `/project` and the rustc flags after `--`
+    base: Vec<String>,
+    raw: Vec<String>,
+}
+
+impl Plan {
+    fn load(args: &Args, path: &Path) -> Result<Plan> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read project {}", path.display()))?;
+        let value = if text.trim_start().starts_with('{') {
+            serde_json::from_str(&text).map_err(|e| anyhow::anyhow!("{} is not valid JSON: {}", path.display(), e))?
+        } else {
+            toml::parse(&text).map_err(|e| anyhow::anyhow!("{} is not valid TOML: {}", path.display(), e))?
+        };
+        let mut project: Project = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("Invalid project {}: {}", path.display(), e))?;
+        anyhow::ensure!(!project.units.is_empty(), "Project {} has no units", path.display());
+        
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let out_dir = dir.join(&project.out_dir);
+        let names: Vec<String> = project.units.iter().map(unit_name).collect();
+        infer_deps(&mut project, &names, &dir);
+        let bins = project.units.iter().filter(|u| u.kind == Kind::Bin).count();
+        anyhow::ensure!(bins <= 1, "Project {} has {} bin units; at most one is linked", path.display(), bins);
+        let levels = levels(&project.units, &names)?;
+        let outputs: Vec<PathBuf> = project.units.iter().zip(&names)
+            .map(|(unit, name)| output_of(unit, name, &project, &dir, &out_dir))
+            .collect();
+        let (base, raw) = base_options(args);
+        Ok(Plan { project, dir, out_dir, names, outputs, levels, bins, base, raw })
+    }
+    
+    /// The command line of unit `i`
+    fn unit_words(&self, i: usize) -> Vec<String> {
+        let project = &self.project;
+        let unit = &project.units[i];
+        let mut words = self.base.clone();
+        // 种类对应的选项在前，单元选项可以覆盖（如含 main 的目标文件用 --crate-type=bin）
+        match unit.kind {
+            Kind::Obj => words.extend(["/c".to_string(), "--crate-type=lib".to_string()]),
+            Kind::Rlib => words.push("--crate-type=rlib".to_string()),
+            Kind::Staticlib => words.push("--crate-type=staticlib".to_string()),
+            Kind::Bin => {}
+        }
+        words.extend(project.options.iter().cloned());
+        words.extend(unit.options.iter().cloned());
+        words.push(self.dir.join(&unit.source).display().to_string());
+        words.push(format!("--crate-name={}", self.names[i]));
+        words.extend(self.link_inputs(i).iter().map(|p| p.display().to_string()));
+        words.push("-o".to_string());
+        words.push(self.outputs[i].display().to_string());
+        // 依赖的依赖由 rustc 在输出目录中查找
+        words.push("--".to_string());
+        words.extend(self.raw.iter().cloned());
+        words.push(format!("-Ldependency={}", self.out_dir.display()));
+        words
+    }
+    
+    /// Outputs of other units and project inputs named on the command line of unit `i`
+    fn link_inputs(&self, i: usize) -> Vec<PathBuf> {
+        let project = &self.project;
+        let mut inputs = Vec::new();
+        if project.units[i].kind == Kind::Bin {
+            // 最终链接：所有目标文件、静态库与项目的附加输入
+            inputs.extend(self.objects());
+        }
+        for dep in &project.units[i].deps {
+            let j = self.names.iter().position(|n| n == dep).unwrap_or_default();
+            if project.units[j].kind == Kind::Rlib {
+                inputs.push(self.outputs[j].clone());
+            }
+        }
+        inputs
+    }
+    
+    /// What unit `i` is built from: its source and the outputs it needs
+    fn unit_inputs(&self, i: usize) -> Vec<PathBuf> {
+        let unit = &self.project.units[i];
+        let mut inputs = vec![self.dir.join(&unit.source)];
+        inputs.extend(self.link_inputs(i));
+        for dep in &unit.deps {
+            let j = self.names.iter().position(|n| n == dep).unwrap_or_default();
+            if !inputs.contains(&self.outputs[j]) {
+                inputs.push(self.outputs[j].clone());
+            }
+        }
+        inputs
+    }
+    
+    /// Object files, static libraries and the project's extra link inputs
+    fn objects(&self) -> Vec<PathBuf> {
+        self.project.units.iter().zip(&self.outputs)
+            .filter(|(u, _)| matches!(u.kind, Kind::Obj | Kind::Staticlib))
+            .map(|(_, out)| out.clone())
+            .chain(self.project.link.iter().map(|l| self.dir.join(l)))
+            .collect()
+    }
+    
+    /// The link of a project without a bin unit, made by rustc from the objects
+    fn final_link(&self, path: &Path) -> Option<(PathBuf, Vec<String>)> {
+        if self.bins > 0 || !self.project.units.iter().any(|u| u.kind == Kind::Obj) {
+            return None;
+        }
+        let output = final_output(&self.project, path, &self.dir, &self.out_dir);
+        let mut words = self.base.clone();
+        words.extend(self.project.options.iter().cloned());
+        words.extend(self.objects().iter().map(|o| o.display().to_string()));
+        words.push("-o".to_string());
+        words.push(output.display().to_string());
+        words.push("--".to_string());
+        words.extend(self.raw.iter().cloned());
+        Some((output, words))
+    }
+}
+
+/// Builds a project: units in dependency order, each level with /MP
+/// parallelism, then the final link
+///
+/// `compile` runs one translated command line; options given next to
+/// `/project` apply to every unit.
+pub fn run<F>(args: &Args, path: &Path, compile: F) -> Result<i32>
+where
+    F: Fn(&Args) -> Result<i32> + Sync,
+{
+    let start = Instant::now();
+    let plan = Plan::load(args, path)?;
+    let project = &plan.project;
+    std::fs::create_dir_all(&plan.out_dir).with_context(|| format!("Failed to create {}", plan.out_dir.display()))?;
+    
+    // 同一日志在整个项目中只截断一次
+    if let Some(log) = &args.log {
+        buildlog::init(log, args.log_append)?;
+    }
+    let jobs = match args.parallel {
+        Some(0) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        Some(n) => n,
+        None => 1,
+    };
+    
+    let mut status = exit_code::SUCCESS;
+    let mut built = 0;
+    for level in &plan.levels {
+        let mut units = Vec::new();
+        for &i in level {
+            let mut unit_args = cli::parse_options(plan.unit_words(i))
+                .with_context(|| format!("Invalid options for {}", project.units[i].source.display()))?;
+            unit_args.log = None;
+            units.push(unit_args);
+        }
+        
+        let results = parallel::run_jobs(units.len(), jobs, |i| compile(&units[i]));
+        for code in results {
+            let code = code?;
+            built += 1;
+            if status == exit_code::SUCCESS {
+                status = code;
+            }
+        }
+        // 依赖失败时其余层次无法构建
+        if status != exit_code::SUCCESS {
+            break;
+        }
+    }
+    
+    // 只有目标文件时由 rustc 直接链接
+    if let Some((_, words)) = plan.final_link(path).filter(|_| status == exit_code::SUCCESS) {
+        status = compile(&cli::parse_options(words)?)?;
+    }
+    
+    let skipped = project.units.len() - built;
+    if !args.quiet {
+        let name = project.name.clone().unwrap_or_else(|| stem(path));
+        let mut message = format!(
+            "{} project {}: {}/{} unit(s) built in {} ms",
+            "[icx-rustc]".bright_blue().bold(),
+            name,
+            built,
+            project.units.len(),
+            start.elapsed().as_millis()
+        );
+        if skipped > 0 {
+            message.push_str(&format!(", {} skipped after a failed dependency", skipped));
+        }
+        console::eprint(&message);
+    }
+    Ok(status)
+}
+
+/// /Qninja with /project: a build.ninja with one edge per unit and the final link
+///
+/// Every edge runs icx-rustc with the unit's command line and a /QMMD
+/// depfile, so ninja schedules the units and tracks the sources, and the
+/// file regenerates itself when the project file changes.
+pub fn write_ninja(args: &Args, path: &Path, ninja_file: &Path) -> Result<i32> {
+    let plan = Plan::load(args, path)?;
+    let mut edges = Vec::new();
+    for level in &plan.levels {
+        for &i in level {
+            edges.push(ninja::Edge {
+                output: plan.outputs[i].clone(),
+                inputs: plan.unit_inputs(i),
+                words: plan.unit_words(i),
+            });
+        }
+    }
+    if let Some((output, words)) = plan.final_link(path) {
+        edges.push(ninja::Edge { output, inputs: plan.objects(), words });
+    }
+    ninja::write(ninja_file, &edges, &[path.to_path_buf()], &args.command_line)?;
+    if !args.quiet {
+        console::eprint(&format!(
+            "{} wrote {} ({} edge(s)); build with ninja -f {}",
+            "[icx-rustc]".bright_blue().bold(),
+            ninja_file.display(),
+            edges.len(),
+            ninja_file.display()
+        ));
+    }
+    Ok(exit_code::SUCCESS)
+}
+
+/// The command line options that apply to every unit, without `/project` and `/Qninja`,
+/// and the rustc flags after `--`
+fn base_options(args: &Args) -> (Vec<String>, Vec<String>) {
+    let mut words = Vec::new();
+    let mut iter = args.command_line.iter().skip(1);
+    while let Some(word) = iter.next() {
+        if word == "--" {
+            break;
+        }
+        if word == "--project" {
+            iter.next();
+        } else if !word.starts_with("--project=") && !word.starts_with("--Qninja") {
+            words.push(word.clone());
+        }
+    }
+    (words, iter.cloned().collect())
+}
+
+/// Adds the rlib units a source names in `use` or `extern crate` to its `deps`
+fn infer_deps(project: &mut Project, names: &[String], dir: &Path) {
+    for i in 0..project.units.len() {
+        let used = deps::used_crates(&[dir.join(&project.units[i].source)]);
+        for (j, name) in names.iter().enumerate() {
+            let unit = &project.units[i];
+            if j != i && project.units[j].kind == Kind::Rlib && used.contains(name) && !unit.deps.contains(name) {
+                project.units[i].deps.push(name.clone());
+            }
+        }
+    }
+}
+
+fn unit_name(unit: &Unit) -> String {
+    unit.name.clone().unwrap_or_else(|| {
+        stem(&unit.source).chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+            .collect()
+    })
+}
+
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+fn output_of(unit: &Unit, name: &str, project: &Project, dir: &Path, out_dir: &Path) -> PathBuf {
+    match unit.kind {
+        Kind::Obj => out_dir.join(format!("{}.{}", name, if cfg!(windows) { "obj" } else { "o" })),
+        Kind::Rlib => out_dir.join(format!("lib{}.rlib", name)),
+        Kind::Staticlib if cfg!(windows) => out_dir.join(format!("{}.lib", name)),
+        Kind::Staticlib => out_dir.join(format!("lib{}.a", name)),
+        Kind::Bin => match &project.output {
+            Some(output) => dir.join(output),
+            None => out_dir.join(format!("{}{}", project.name.as_deref().unwrap_or(name), std::env::consts::EXE_SUFFIX)),
+        },
+    }
+}
+
+fn final_output(project: &Project, path: &Path, dir: &Path, out_dir: &Path) -> PathBuf {
+    match &project.output {
+        Some(output) => dir.join(output),
+        None => out_dir.join(format!("{}{}", project.name.clone().unwrap_or_else(|| stem(path)), std::env::consts::EXE_SUFFIX)),
+    }
+}
+
+/// Groups the units into levels that only depend on earlier levels
+fn levels(units: &[Unit], names: &[String]) -> Result<Vec<Vec<usize>>> {
+    let mut deps: IndexMap<usize, Vec<usize>> = IndexMap::new();
+    for (i, unit) in units.iter().enumerate() {
+        let mut list = Vec::new();
+        for dep in &unit.deps {
+            let j = names.iter().position(|n| n == dep)
+                .with_context(|| format!("{}: unknown dependency '{}'", unit.source.display(), dep))?;
+            list.push(j);
+        }
+        // 最终链接需要所有目标文件和静态库
+        if unit.kind == Kind::Bin {
+            list.extend((0..units.len()).filter(|&j| matches!(units[j].kind, Kind::Obj | Kind::Staticlib)));
+        }
+        deps.insert(i, list);
+    }
+    
+    let mut done = vec![false; units.len()];
+    let mut levels = Vec::new();
+    while done.iter().any(|d| !d) {
+        let level: Vec<usize> = deps.iter()
+            .filter(|(i, list)| !done[**i] && list.iter().all(|&j| done[j]))
+            .map(|(&i, _)| i)
+            .collect();
+        if level.is_empty() {
+            let cycle: Vec<&str> = (0..units.len()).filter(|&i| !done[i]).map(|i| names[i].as_str()).collect();
+            anyhow::bail!("Dependency cycle between units: {}", cycle.join(", "));
+        }
+        for &i in &level {
+            done[i] = true;
+        }
+        levels.push(level);
+    }
+    Ok(levels)
+}
@@ -0,0 +1,97 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+
+/// Peak memory and CPU time of a finished compiler process (/Qresource-report)
+///
+/// On Unix the figures include the linker and other processes rustc waited
+/// for; on Windows they cover the rustc process itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub peak_rss: u64,
+    pub user: Duration,
+    pub system: Duration,
+}
+
+impl Usage {
+    pub fn summary(&self) -> String {
+        format!(
+            "[icx-rustc] resources: peak RSS {:.1} MB, CPU {:.2} s (user {:.2} s, system {:.2} s)",
+            self.peak_rss as f64 / (1024.0 * 1024.0),
+            (self.user + self.system).as_secs_f64(),
+            self.user.as_secs_f64(),
+            self.system.as_secs_f64()
+        )
+    }
+}
+
+/// Reaps the child and reads its resource usage; `None` while it is still running
+///
+/// The child is reaped with `wait4`, so it must not be waited for or killed
+/// through `Child` afterwards.
+#[cfg(unix)]
+pub fn try_wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Usage)>> {
+    use std::os::unix::process::ExitStatusExt;
+    
+    let flags = if block { 0 } else { libc::WNOHANG };
+    let mut status = 0;
+    // 安全性：rusage 是纯数据结构，全零即合法初值
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = loop {
+        let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, flags, &mut usage) };
+        match pid {
+            -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+            -1 => return Err(io::Error::last_os_error()),
+            pid => break pid,
+        }
+    };
+    if pid == 0 {
+        return Ok(None);
+    }
+    
+    // ru_maxrss 在 macOS 上以字节计，其余平台以 KB 计
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    Ok(Some((ExitStatus::from_raw(status), Usage {
+        peak_rss: usage.ru_maxrss as u64 * scale,
+        user: time(usage.ru_utime),
+        system: time(usage.ru_stime),
+    })))
+}
+
+/// Waits for the child and reads its resource usage; `None` while it is still running
+#[cfg(windows)]
+pub fn try_wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Usage)>> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::FILETIME;
+    use windows_sys::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows_sys::Win32::System::Threading::GetProcessTimes;
+    
+    let status = if block { child.wait()? } else {
+        match child.try_wait()? {
+            Some(status) => status,
+            None => return Ok(None),
+        }
+    };
+    
+    // 进程句柄在 Child 释放前一直有效
+    let handle = child.as_raw_handle();
+    let zero = FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+    let (mut creation, mut exit, mut kernel, mut user) = (zero, zero, zero, zero);
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    counters.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    unsafe {
+        GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user);
+        GetProcessMemoryInfo(handle, &mut counters, counters.cb);
+    }
+    
+    // FILETIME 以 100 ns 为单位
+    let time = |t: FILETIME| {
+        Duration::from_nanos((((t.dwHighDateTime as u64) << 32) | t.dwLowDateTime as u64) * 100)
+    };
+    Ok(Some((status, Usage {
+        peak_rss: counters.PeakWorkingSetSize as u64,
+        user: time(user),
+        system: time(kernel),
+    })))
+}
@@ -0,0 +1,87 @@
+use crate::cli::Args;
+use crate::console;
+use crate::exit_code;
+use crate::i18n;
+use crate::translator;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// /run main.rs -- args...: compiles, runs the program, and exits with its code
+///
+/// The arguments after `--` go to the program instead of rustc. Without
+/// /o or /Fe the executable is built in a temporary directory that is
+/// removed afterwards; a named one is kept. Unlike a compile, the exit
+/// code is the program's own (128 + the signal when one killed it).
+pub fn run<F>(args: &Args, compile: F) -> Result<i32>
+where
+    F: Fn(&Args) -> Result<i32>,
+{
+    if args.compile_only || !args.emit.is_empty() {
+        console::eprint(&format!(
+            "{}: /run builds and runs an executable; it cannot be combined with /c or --emit",
+            i18n::t("icx-rustc error").bright_red().bold()
+        ));
+        return Ok(exit_code::USAGE_ERROR);
+    }
+    let mut build = args.clone();
+    build.run = false;
+    let program_args = std::mem::take(&mut build.raw_args);
+    // /Qfile-opts 按命令行重新解析，程序参数不能混入 rustc 的参数
+    let end = build.command_line.iter().position(|a| a == "--").unwrap_or(build.command_line.len());
+    build.command_line.truncate(end);
+    build.command_line.retain(|a| a != "--run");
+    
+    // 未指定输出时编译到临时目录，运行后删除
+    let temporary = if args.output.is_none() && args.msvc_exe.is_none() {
+        let dir = std::env::temp_dir().join(format!("icx-run-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let stem = args.files.iter()
+            .find(|f| f.extension().is_some_and(|e| e == "rs"))
+            .and_then(|f| f.file_stem())
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "main".to_string());
+        build.output = Some(dir.join(format!("{}{}", stem, std::env::consts::EXE_SUFFIX)));
+        Some(dir)
+    } else {
+        None
+    };
+    
+    let code = execute(&build, &program_args, compile);
+    if let Some(dir) = temporary {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    code
+}
+
+fn execute<F>(build: &Args, program_args: &[String], compile: F) -> Result<i32>
+where
+    F: Fn(&Args) -> Result<i32>,
+{
+    let code = compile(build)?;
+    if code != exit_code::SUCCESS || build.dry_run.is_some() {
+        return Ok(code);
+    }
+    let cmd = translator::translate(build)?;
+    let program = cmd.artifact().map(|a| cmd.resolve(&a)).context("The executable's name cannot be predicted")?;
+    // 相对路径按当前目录解析，而不是 PATH
+    let program = if program.is_relative() { PathBuf::from(".").join(program) } else { program };
+    
+    let status = Command::new(&program)
+        .args(program_args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program.display()))?;
+    Ok(status_code(status))
+}
+
+#[cfg(unix)]
+fn status_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status.code().or_else(|| status.signal().map(|s| 128 + s)).unwrap_or(exit_code::KILLED)
+}
+
+#[cfg(not(unix))]
+fn status_code(status: std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(exit_code::KILLED)
+}
@@ -0,0 +1,114 @@
+use crate::diagnostics::{Diagnostic, DiagnosticSpan};
+use crate::numbering;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Writes the diagnostics of a build as a SARIF 2.1.0 log (/Qsarif)
+///
+/// Every error code and lint becomes a rule; relative file names are
+/// resolved against `%SRCROOT%`, the directory icx-rustc was started in.
+pub fn write(path: &Path, diagnostics: &[Diagnostic]) -> Result<()> {
+    let mut rules: Vec<Value> = Vec::new();
+    let mut rule_ids: Vec<&str> = Vec::new();
+    let mut results = Vec::new();
+    
+    for diag in diagnostics {
+        let mut result = json!({
+            "level": level(&diag.level),
+            "message": { "text": diag.message },
+            "locations": diag.primary_span().map(location).into_iter().collect::<Vec<_>>(),
+        });
+        let related: Vec<Value> = diag.spans.iter()
+            .filter(|s| !s.is_primary)
+            .map(location)
+            .collect();
+        if !related.is_empty() {
+            result["relatedLocations"] = Value::Array(related);
+        }
+        
+        if let Some(code) = diag.code.as_ref().map(|c| c.code.as_str()) {
+            let index = match rule_ids.iter().position(|id| *id == code) {
+                Some(index) => index,
+                None => {
+                    rule_ids.push(code);
+                    rules.push(rule(code, diag));
+                    rules.len() - 1
+                }
+            };
+            result["ruleId"] = json!(code);
+            result["ruleIndex"] = json!(index);
+        }
+        results.push(result);
+    }
+    
+    let root = std::env::current_dir()
+        .map(|dir| file_uri(&format!("{}/", dir.display())))
+        .unwrap_or_default();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "icx-rustc",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "originalUriBaseIds": { "%SRCROOT%": { "uri": root } },
+            "results": results,
+        }]
+    });
+    
+    let text = serde_json::to_string_pretty(&log)?;
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn level(level: &str) -> &'static str {
+    match level {
+        "error" | "error: internal compiler error" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+fn rule(code: &str, diag: &Diagnostic) -> Value {
+    let mut rule = json!({
+        "id": code,
+        "shortDescription": { "text": diag.message },
+    });
+    if code.starts_with('E') {
+        rule["helpUri"] = json!(format!("https://doc.rust-lang.org/error_codes/{}.html", code));
+    }
+    if let Some(number) = numbering::number(code) {
+        rule["properties"] = json!({ "icxNumber": number });
+    }
+    rule
+}
+
+fn location(span: &DiagnosticSpan) -> Value {
+    let file = span.file_name.replace('\\', "/");
+    let artifact = if Path::new(&span.file_name).is_absolute() {
+        json!({ "uri": file_uri(&file) })
+    } else {
+        json!({ "uri": file, "uriBaseId": "%SRCROOT%" })
+    };
+    json!({
+        "physicalLocation": {
+            "artifactLocation": artifact,
+            "region": {
+                "startLine": span.line_start,
+                "startColumn": span.column_start,
+                "endLine": span.line_end,
+                "endColumn": span.column_end,
+            }
+        }
+    })
+}
+
+/// `file:///` URI of an absolute path (`C:\src` -> `file:///C:/src`)
+fn file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/").replace(' ', "%20");
+    format!("file://{}{}", if path.starts_with('/') { "" } else { "/" }, path)
+}
@@ -0,0 +1,191 @@
+use crate::cmdline;
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Writes the translated invocations as a replayable script (`--###=<file>`)
+///
+/// `.bat` / `.cmd` files get cmd.exe syntax, anything else a POSIX shell
+/// script. The script runs exactly what the wrapper would, except that
+/// rustc prints its own human-readable diagnostics instead of JSON.
+pub fn write(path: &Path, commands: &[RustcCommand]) -> Result<()> {
+    let batch = path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("bat") || e.eq_ignore_ascii_case("cmd"));
+    let shell = if batch { Shell::Batch } else { Shell::Posix };
+    
+    let mut lines = shell.prologue();
+    if let Some(dir) = commands.first().and_then(|c| c.cwd.as_ref()) {
+        lines.push(shell.cd(&dir.display().to_string()));
+    }
+    
+    for cmd in commands {
+        // C/C++ 源文件先编译为目标文件
+        for compile in &cmd.c_steps {
+            let mut words = vec![shell.quote(&compile.program.display().to_string())];
+            words.extend(compile.args.iter().map(|a| shell.quote(a)));
+            lines.extend(shell.run(&[], &words.join(" ")));
+        }
+        if cmd.input_files.is_empty() && cmd.link_inputs.is_empty() {
+            continue;
+        }
+        
+        if let Some(parent) = cmd.output.as_ref()
+            .and_then(|out| out.parent())
+            .filter(|p| !p.as_os_str().is_empty())
+        {
+            lines.push(shell.mkdir(&parent.display().to_string()));
+        }
+        
+        // 与执行器一致：只有链接输入时用 #![no_main] 桩 crate 驱动链接
+        let link_only = cmd.input_files.is_empty() && !cmd.link_inputs.is_empty();
+        if link_only {
+            lines.extend(shell.create_stub());
+        }
+        
+        let mut words: Vec<String> = Vec::new();
+        words.extend(cmd.wrapper.iter().map(|w| shell.quote(&w.display().to_string())));
+        words.push(shell.quote(&cmd.executable));
+        words.extend(cmd.args.iter().map(|a| shell.quote(a)));
+        words.extend(cmd.input_files.iter().map(|f| shell.quote(&f.display().to_string())));
+        if link_only {
+            words.push(shell.stub_path().to_string());
+            words.push("--crate-name=icx_link".to_string());
+        }
+        words.extend(cmd.link_inputs.iter().map(|o| shell.quote(&format!("-Clink-arg={}", o.display()))));
+        words.extend(cmd.link_libs.iter().map(|l| shell.quote(&format!("-Clink-arg={}", l))));
+        if let Some(out) = &cmd.output {
+            words.push("-o".to_string());
+            words.push(shell.quote(&out.display().to_string()));
+        }
+        
+        lines.extend(shell.run(&cmd.env_vars, &words.join(" ")));
+        if link_only {
+            lines.push(shell.remove_stub());
+        }
+        if let Some(archive) = &cmd.archive {
+            let mut words = vec![shell.quote(&archive.program.display().to_string())];
+            words.extend(archive.args.iter().map(|a| shell.quote(a)));
+            lines.extend(shell.run(&[], &words.join(" ")));
+        }
+        if let Some(bolt) = &cmd.bolt {
+            let mut words = vec![shell.quote(&bolt.program.display().to_string())];
+            words.extend(bolt.args.iter().map(|a| shell.quote(a)));
+            lines.extend(shell.run(&[], &words.join(" ")));
+            lines.push(shell.rename(&bolt.output.display().to_string(), &bolt.source.display().to_string()));
+        }
+    }
+    
+    let newline = if batch { "\r\n" } else { "\n" };
+    let mut script = lines.join(newline);
+    script.push_str(newline);
+    std::fs::write(path, script)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    
+    #[cfg(unix)]
+    if !batch {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum Shell {
+    Posix,
+    Batch,
+}
+
+impl Shell {
+    fn prologue(self) -> Vec<String> {
+        match self {
+            Shell::Posix => vec![
+                "#!/bin/sh".to_string(),
+                "# generated by icx-rustc --###".to_string(),
+                "set -e".to_string(),
+            ],
+            Shell::Batch => vec![
+                "@echo off".to_string(),
+                "rem generated by icx-rustc --###".to_string(),
+                "setlocal".to_string(),
+            ],
+        }
+    }
+    
+    fn cd(self, dir: &str) -> String {
+        match self {
+            Shell::Posix => format!("cd {}", self.quote(dir)),
+            Shell::Batch => format!("cd /d {}", self.quote(dir)),
+        }
+    }
+    
+    fn mkdir(self, dir: &str) -> String {
+        match self {
+            Shell::Posix => format!("mkdir -p {}", self.quote(dir)),
+            Shell::Batch => format!("if not exist {0} mkdir {0}", self.quote(dir)),
+        }
+    }
+    
+    fn rename(self, from: &str, to: &str) -> String {
+        match self {
+            Shell::Posix => format!("mv -f {} {}", self.quote(from), self.quote(to)),
+            Shell::Batch => format!("move /y {} {} >nul", self.quote(from), self.quote(to)),
+        }
+    }
+    
+    fn create_stub(self) -> Vec<String> {
+        match self {
+            Shell::Posix => vec![
+                "icx_stub=$(mktemp -d)".to_string(),
+                "printf '#![no_main]\\n' > \"$icx_stub/icx_link.rs\"".to_string(),
+            ],
+            Shell::Batch => vec!["echo #![no_main]>\"%TEMP%\\icx_link.rs\"".to_string()],
+        }
+    }
+    
+    fn stub_path(self) -> &'static str {
+        match self {
+            Shell::Posix => "\"$icx_stub/icx_link.rs\"",
+            Shell::Batch => "\"%TEMP%\\icx_link.rs\"",
+        }
+    }
+    
+    fn remove_stub(self) -> String {
+        match self {
+            Shell::Posix => "rm -rf \"$icx_stub\"".to_string(),
+            Shell::Batch => "del \"%TEMP%\\icx_link.rs\"".to_string(),
+        }
+    }
+    
+    /// The command line, with its environment and (for cmd.exe) an error check
+    fn run(self, env: &[(String, String)], command: &str) -> Vec<String> {
+        match self {
+            Shell::Posix => {
+                let mut line: Vec<String> = env.iter()
+                    .map(|(k, v)| format!("{}={}", k, self.quote(v)))
+                    .collect();
+                line.push(command.to_string());
+                vec![line.join(" ")]
+            }
+            Shell::Batch => {
+                let mut lines: Vec<String> = env.iter()
+                    .map(|(k, v)| format!("set \"{}={}\"", k, v.replace('%', "%%")))
+                    .collect();
+                lines.push(command.to_string());
+                lines.push("if errorlevel 1 exit /b %errorlevel%".to_string());
+                lines
+            }
+        }
+    }
+    
+    fn quote(self, word: &str) -> String {
+        match self {
+            Shell::Posix => shlex::try_quote(word)
+                .map(|q| q.into_owned())
+                .unwrap_or_else(|_| word.to_string()),
+            // cmd.exe：批处理中 % 写作 %%，引号外的元字符加 ^
+            Shell::Batch => cmdline::quote_cmd(&word.replace('%', "%%")),
+        }
+    }
+}
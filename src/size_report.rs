@@ -0,0 +1,93 @@
+use crate::demangle::demangle;
+use crate::buildlog;
+use crate::console;
+use crate::diagnostics::format_size;
+use crate::object::{self, Section, SymbolKind};
+use crate::translator::RustcCommand;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use indexmap::IndexMap;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+/// /Qsize-report[:N]: what the linked binary is made of
+///
+/// Totals per allocated section, then the symbols grouped by crate and
+/// the N largest symbols (default 10), like cargo-bloat. A stripped
+/// binary only has the section totals, as does an image linked by
+/// link.exe, whose symbols are in its PDB.
+pub fn print(cmd: &RustcCommand, top: usize) -> Result<()> {
+    let Some(artifact) = cmd.artifact() else {
+        return Ok(());
+    };
+    let path = cmd.resolve(&artifact);
+    let data = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let Some(object) = object::parse(&data) else {
+        console::eprint(&format!("[icx-rustc] warning: /Qsize-report: {} is not an ELF, Mach-O or PE file", artifact.display()));
+        return Ok(());
+    };
+    
+    let mut lines = vec![format!(
+        "{} {} ({})",
+        "Size report:".bold(),
+        artifact.display(),
+        format_size(data.len() as u64)
+    )];
+    let file_total = data.len() as u64;
+    lines.push(format!("  {}", "Sections:".bold()));
+    let mut sections: Vec<&Section> = object.sections.iter().filter(|s| s.alloc && s.size > 0).collect();
+    sections.sort_by_key(|s| Reverse(s.size));
+    for section in sections {
+        lines.push(row(&section.name, section.size, file_total));
+    }
+    
+    // 代码和数据符号，别名只计一次
+    let mut seen = HashSet::new();
+    let symbols: Vec<(String, u64)> = object.symbols.iter()
+        .filter(|s| s.defined && s.size > 0 && s.kind != SymbolKind::Other && seen.insert(s.address))
+        .map(|s| (demangle(&s.name), s.size))
+        .collect();
+    if let Some(pdb) = object.pdb.as_ref().filter(|_| symbols.is_empty()) {
+        lines.push(format!("  (no symbol table; the symbols are in {})", pdb).bright_black().to_string());
+    } else if symbols.is_empty() {
+        lines.push("  (no symbol table; the binary is stripped)".bright_black().to_string());
+    } else {
+        let symbol_total: u64 = symbols.iter().map(|(_, size)| size).sum();
+        let mut crates: IndexMap<String, u64> = IndexMap::new();
+        for (name, size) in &symbols {
+            *crates.entry(crate_of(name)).or_default() += size;
+        }
+        crates.sort_by(|_, a, _, b| b.cmp(a));
+        lines.push(format!("  {}", "Crates:".bold()));
+        for (name, size) in crates.iter().take(top) {
+            lines.push(row(name, *size, symbol_total));
+        }
+        
+        let mut largest: Vec<&(String, u64)> = symbols.iter().collect();
+        largest.sort_by_key(|(_, size)| Reverse(*size));
+        lines.push(format!("  {}", "Largest symbols:".bold()));
+        for (name, size) in largest.into_iter().take(top) {
+            lines.push(format!("    {:>10}  {}", format_size(*size), name));
+        }
+    }
+    
+    for line in lines {
+        buildlog::write("", &line);
+        console::eprint(&line);
+    }
+    Ok(())
+}
+
+fn row(name: &str, size: u64, total: u64) -> String {
+    let share = if total > 0 { size as f64 * 100.0 / total as f64 } else { 0.0 };
+    format!("    {:<24} {:>10} {:>5.1}%", name, format_size(size), share)
+}
+
+/// `std::io::stdio::_print` -> `std`, `<alloc::vec::Vec<T> as Drop>::drop` -> `alloc`
+fn crate_of(name: &str) -> String {
+    let path = name.trim_start_matches(['<', '&', '*']).trim_start_matches("mut ").trim_start_matches("dyn ");
+    match path.split_once("::") {
+        Some((first, _)) if !first.is_empty() && first.chars().all(|c| c.is_alphanumeric() || c == '_') => first.to_string(),
+        _ => "[other]".to_string(),
+    }
+}
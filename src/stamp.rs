@@ -0,0 +1,108 @@
+use crate::bolt;
+use crate::cache::{self, hash_file, hex};
+use crate::translator::{rustc_query, RustcCommand};
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// /Qup-to-date: the compiler's stderr of the last successful compile of
+/// this unit, if that compile still holds
+///
+/// The stamp beside the first output (`<output>.icx-stamp`) records a hash of
+/// the command line, the compiler and its environment, then every source file
+/// rustc read with its size, modification time and hash. A file whose size
+/// and time match is taken as unchanged; otherwise only its hash counts, so a
+/// `touch` does not force a rebuild. Units with C/C++ sources always compile,
+/// since their headers are not tracked. The stderr lines follow, so the
+/// warnings of a skipped compile are replayed.
+pub fn is_current(cmd: &RustcCommand) -> Option<Vec<String>> {
+    let (path, outputs) = (path(cmd)?, cmd.outputs()?);
+    if !cmd.c_steps.is_empty() || !outputs.iter().all(|out| cmd.resolve(out).exists()) {
+        return None;
+    }
+    let text = std::fs::read_to_string(&path).ok()?;
+    let mut lines = text.lines();
+    if Some(format!("flags {}", flags(cmd)).as_str()) != lines.next() {
+        return None;
+    }
+    let mut stderr = Vec::new();
+    let current = lines.all(|line| {
+        if let Some(line) = line.strip_prefix("stderr ") {
+            stderr.push(line.to_string());
+            return true;
+        }
+        let mut fields = line.splitn(4, ' ');
+        let (Some(size), Some(time), Some(hash), Some(file)) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+            return false;
+        };
+        let file = cmd.resolve(Path::new(file));
+        match signature(&file) {
+            Some((s, t)) if s.to_string() == size && t.to_string() == time => true,
+            Some(_) => hash_file(&file).is_ok_and(|h| h == hash),
+            None => false,
+        }
+    });
+    current.then_some(stderr)
+}
+
+/// Records the stamp after a successful compile
+pub fn write(cmd: &RustcCommand, stderr: &[String]) -> Result<()> {
+    let Some(path) = path(cmd) else {
+        return Ok(());
+    };
+    if !cmd.c_steps.is_empty() {
+        return Ok(());
+    }
+    
+    let mut text = format!("flags {}\n", flags(cmd));
+    // dep-info 不包含 extern 与链接输入，单独记录
+    let externs = cmd.args.iter()
+        .filter_map(|a| a.strip_prefix("--extern="))
+        .filter_map(|spec| spec.split_once('='))
+        .map(|(_, file)| PathBuf::from(file));
+    let dep_file = path.with_extension("icx-stamp.d");
+    let sources = cache::source_files(cmd, &dep_file)?;
+    for file in sources.into_iter().chain(externs).chain(cmd.link_inputs.iter().cloned()) {
+        let resolved = cmd.resolve(&file);
+        let (size, time) = signature(&resolved).with_context(|| format!("Failed to read {}", resolved.display()))?;
+        text.push_str(&format!("{} {} {} {}\n", size, time, hash_file(&resolved)?, file.display()));
+    }
+    for line in stderr {
+        text.push_str(&format!("stderr {}\n", line));
+    }
+    std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn path(cmd: &RustcCommand) -> Option<PathBuf> {
+    let output = cmd.resolve(cmd.outputs()?.first()?);
+    let mut name = output.file_name()?.to_os_string();
+    name.push(".icx-stamp");
+    Some(output.with_file_name(name))
+}
+
+/// The command line and everything else that changes the output besides the sources
+fn flags(cmd: &RustcCommand) -> String {
+    let mut hasher = Sha256::new();
+    // 印记格式的版本：之前的印记没有记录 stderr
+    hasher.update("2");
+    hasher.update(cmd.display());
+    hasher.update(rustc_query(&cmd.executable, &["-vV"]).unwrap_or_default());
+    for (key, val) in &cmd.env_vars {
+        hasher.update(format!("{}={}", key, val));
+    }
+    for step in cmd.archive.iter().chain(&cmd.bolt).chain(&cmd.wasm) {
+        hasher.update(step.display());
+    }
+    if let Some(profile) = cmd.bolt.as_ref().and_then(bolt::profile) {
+        hasher.update(hash_file(&cmd.resolve(&profile)).unwrap_or_default());
+    }
+    hex(&hasher.finalize())
+}
+
+/// Size and modification time (nanoseconds since the epoch) of a file
+fn signature(path: &Path) -> Option<(u64, u128)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let time = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    Some((meta.len(), time))
+}
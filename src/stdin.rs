@@ -0,0 +1,59 @@
+use crate::cli::Args;
+use crate::translator::sanitize_crate_name;
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// rustc's own name for a crate read from stdin
+const DEFAULT_NAME: &str = "rust_out";
+
+/// A generated or buffered source in a temporary directory, removed when dropped
+pub struct TempSource(PathBuf);
+
+impl Drop for TempSource {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Replaces a `-` input with a temporary file holding what stdin sends
+///
+/// The file is `<name>.rs` in a directory of its own, so the crate (and
+/// the default output) is named after /Qstdin-name, like rustc's
+/// `rust_out` without it. Diagnostics show the temporary path, whose
+/// lines are those of the piped source.
+pub fn capture(args: &mut Args) -> Result<Option<TempSource>> {
+    let dash = Path::new("-");
+    if !args.files.iter().any(|f| f == dash) {
+        return Ok(None);
+    }
+    if args.files.iter().filter(|f| *f == dash).count() > 1 {
+        bail!("stdin ('-') can only be read once");
+    }
+    let name = args.stdin_name.as_deref().unwrap_or(DEFAULT_NAME);
+    let name = sanitize_crate_name(name.strip_suffix(".rs").unwrap_or(name));
+    
+    let mut source = Vec::new();
+    std::io::stdin().read_to_end(&mut source).context("Failed to read the source from stdin")?;
+    let (guard, file) = temporary("stdin", &name, &source)?;
+    
+    for input in args.files.iter_mut().filter(|f| *f == dash) {
+        *input = file.clone();
+    }
+    // /Qfile-opts 等按命令行重新解析
+    let end = args.command_line.iter().position(|a| a == "--").unwrap_or(args.command_line.len());
+    for word in args.command_line[..end].iter_mut().filter(|w| *w == "-") {
+        *word = file.display().to_string();
+    }
+    Ok(Some(guard))
+}
+
+/// Writes `<name>.rs` to a fresh `icx-<kind>-<pid>` directory
+pub fn temporary(kind: &str, name: &str, source: &[u8]) -> Result<(TempSource, PathBuf)> {
+    let dir = std::env::temp_dir().join(format!("icx-{}-{}", kind, std::process::id()));
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let guard = TempSource(dir.clone());
+    let file = dir.join(format!("{}.rs", name));
+    std::fs::write(&file, source).with_context(|| format!("Failed to write {}", file.display()))?;
+    Ok((guard, file))
+}
@@ -0,0 +1,159 @@
+use crate::cache;
+use crate::cli::Args;
+use crate::console;
+use crate::diagnostics::DiagnosticStats;
+use crate::numbering;
+use crate::resources::Usage;
+use crate::translator::rustc_query;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What happened to one translation unit, for the JSON summary and /Qbuild-report
+#[derive(Debug, Default)]
+pub struct UnitReport {
+    pub input: String,
+    pub code: i32,
+    pub elapsed: Duration,
+    pub errors: u32,
+    pub warnings: u32,
+    /// Restored from the builtin cache without running the compiler
+    pub cached: bool,
+    /// Outputs that exist after a successful compile
+    pub artifacts: Vec<PathBuf>,
+    pub usage: Option<Usage>,
+    /// The translated compiler command
+    pub command: Vec<String>,
+    /// Wall time of each step the wrapper ran (C compiles, rustc, post steps, cache, hooks)
+    pub phases: Vec<(&'static str, Duration)>,
+    /// rustc's own passes in seconds (/Qtime)
+    pub passes: Vec<(String, f64)>,
+}
+
+/// The `--summary-format=json` object
+///
+/// Counts per file and per code come from the JSON diagnostics; the
+/// per-unit counts also include plain-text errors such as linker failures.
+pub fn summary(code: i32, elapsed: Duration, stats: &DiagnosticStats, units: &[UnitReport]) -> Value {
+    let files: serde_json::Map<String, Value> = stats.files.iter()
+        .map(|(file, (errors, warnings))| (file.clone(), json!({ "errors": errors, "warnings": warnings })))
+        .collect();
+    let codes: serde_json::Map<String, Value> = stats.codes.iter()
+        .map(|(code, entry)| (code.clone(), json!({
+            "count": entry.count,
+            "files": entry.files.len(),
+            "icx": numbering::number(code),
+        })))
+        .collect();
+    let errors: u32 = units.iter().map(|u| u.errors).sum();
+    let warnings: u32 = units.iter().map(|u| u.warnings).sum();
+    let units: Vec<Value> = units.iter().map(unit).collect();
+    
+    json!({
+        "status": if code == 0 { "finished" } else { "failed" },
+        "exit_code": code,
+        "errors": errors,
+        "warnings": warnings,
+        "elapsed_ms": elapsed.as_millis() as u64,
+        "files": files,
+        "codes": codes,
+        "units": units,
+    })
+}
+
+/// Writes the summary to `path`, or to stdout
+pub fn write(path: Option<&Path>, summary: &Value) -> Result<()> {
+    let text = serde_json::to_string_pretty(summary)?;
+    match path {
+        Some(path) => std::fs::write(path, text + "\n")
+            .with_context(|| format!("Failed to write {}", path.display())),
+        None => {
+            console::print(&text);
+            Ok(())
+        }
+    }
+}
+
+fn unit(report: &UnitReport) -> Value {
+    let artifacts: Vec<Value> = report.artifacts.iter()
+        .map(|path| json!({
+            "path": path.display().to_string(),
+            "size": std::fs::metadata(path).map(|m| m.len()).ok(),
+        }))
+        .collect();
+    let mut unit = json!({
+        "input": report.input,
+        "exit_code": report.code,
+        "errors": report.errors,
+        "warnings": report.warnings,
+        "elapsed_ms": report.elapsed.as_millis() as u64,
+        "cached": report.cached,
+        "artifacts": artifacts,
+    });
+    if let Some(usage) = &report.usage {
+        unit["resources"] = json!({
+            "peak_rss": usage.peak_rss,
+            "cpu_user_s": usage.user.as_secs_f64(),
+            "cpu_system_s": usage.system.as_secs_f64(),
+        });
+    }
+    unit
+}
+
+/// Writes the /Qbuild-report file: the summary, plus how the build was invoked
+///
+/// The arguments are recorded as given and as normalized, with the
+/// compiler's `-vV` details, every unit's translated command, the time
+/// of each step (`translate` for the whole invocation) and the size and
+/// SHA-256 of every artifact.
+pub fn write_report(
+    path: &Path,
+    args: &Args,
+    rustc: &str,
+    translate: Duration,
+    mut summary: Value,
+    units: &[UnitReport],
+) -> Result<()> {
+    let elapsed = Duration::from_millis(summary["elapsed_ms"].as_u64().unwrap_or_default());
+    let started = SystemTime::now().checked_sub(elapsed + translate).unwrap_or_else(SystemTime::now);
+    let normalized: Vec<&String> = args.command_line.iter().skip(1).collect();
+    let version = rustc_query(rustc, &["-vV"]).unwrap_or_default();
+    let field = |name: &str| version.lines().find_map(|l| l.strip_prefix(name)).map(|v| v.trim().to_string());
+    
+    summary["report_version"] = json!(1);
+    summary["tool"] = json!({ "name": "icx-rustc", "version": env!("CARGO_PKG_VERSION") });
+    summary["invocation"] = json!({
+        "arguments": if args.original.is_empty() { json!(normalized) } else { json!(args.original) },
+        "normalized": normalized,
+        "cwd": std::env::current_dir().ok().map(|d| d.display().to_string()),
+        "started_unix_ms": started.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64),
+    });
+    summary["rustc"] = json!({
+        "executable": rustc,
+        "version": version.lines().next(),
+        "release": field("release:"),
+        "commit_hash": field("commit-hash:"),
+        "host": field("host:"),
+        "llvm_version": field("LLVM version:"),
+    });
+    summary["phases"] = json!([{ "phase": "translate", "elapsed_ms": translate.as_millis() as u64 }]);
+    if let Some(entries) = summary["units"].as_array_mut() {
+        for (entry, unit) in entries.iter_mut().zip(units) {
+            entry["command"] = json!(unit.command);
+            entry["phases"] = unit.phases.iter()
+                .map(|(phase, time)| json!({ "phase": phase, "elapsed_ms": time.as_millis() as u64 }))
+                .collect();
+            entry["rustc_passes"] = unit.passes.iter()
+                .map(|(pass, secs)| json!({ "pass": pass, "seconds": secs }))
+                .collect();
+            for artifact in entry["artifacts"].as_array_mut().into_iter().flatten() {
+                let hash = artifact["path"].as_str().and_then(|p| cache::hash_file(Path::new(p)).ok());
+                artifact["sha256"] = json!(hash);
+            }
+        }
+    }
+    
+    let text = serde_json::to_string_pretty(&summary)?;
+    std::fs::write(path, text + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,126 @@
+use crate::console;
+use crate::diagnostics::Diagnostic;
+use crate::numbering::{self, DiagId};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// A warning silenced only in files matching a glob
+#[derive(Debug, Clone)]
+pub struct Suppression {
+    /// Lint name, or `*` for every warning
+    pub lint: String,
+    pub glob: String,
+}
+
+/// Contents of a /Qdiag-suppress-file
+///
+/// One entry per line, `#` starts a comment:
+///
+/// ```text
+/// dead_code                 # everywhere, passed to rustc as -Adead_code
+/// 20001  legacy/**          # only in files under legacy/
+/// *      vendor/*.rs        # every warning in those files
+/// ```
+///
+/// Entries without a pattern become `-A` flags; the others are filtered
+/// out of the compiler output by `DiagnosticReporter`. Patterns are matched
+/// against file names as rustc reports them; a pattern without `/` matches
+/// the file name in any directory.
+#[derive(Debug, Default)]
+pub struct SuppressFile {
+    pub lints: Vec<String>,
+    pub scoped: Vec<Suppression>,
+}
+
+pub fn load(path: &Path) -> Result<SuppressFile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read suppress file {}", path.display()))?;
+    
+    let mut file = SuppressFile::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut words = line.split_whitespace();
+        let Some(spec) = words.next() else {
+            continue;
+        };
+        let glob = words.next();
+        if words.next().is_some() {
+            anyhow::bail!("{}:{}: expected '<id> [file pattern]'", path.display(), index + 1);
+        }
+        
+        let lint = match numbering::resolve(spec) {
+            _ if spec == "*" => spec.to_string(),
+            Some(DiagId::Lint(lint)) => lint,
+            Some(DiagId::Error(code)) => {
+                console::eprint(&format!(
+                    "[icx-rustc] warning: {}:{}: {} is an error and cannot be suppressed",
+                    path.display(), index + 1, code
+                ));
+                continue;
+            }
+            None => anyhow::bail!("{}:{}: unknown diagnostic '{}'", path.display(), index + 1, spec),
+        };
+        match glob {
+            Some(glob) => file.scoped.push(Suppression { lint, glob: glob.replace('\\', "/") }),
+            None if lint == "*" => file.lints.push("warnings".to_string()),
+            None => file.lints.push(lint),
+        }
+    }
+    Ok(file)
+}
+
+/// Whether a warning is silenced by one of the file-scoped entries
+pub fn is_suppressed(rules: &[Suppression], diag: &Diagnostic) -> bool {
+    if rules.is_empty() || diag.level != "warning" {
+        return false;
+    }
+    let Some(span) = diag.primary_span() else {
+        return false;
+    };
+    let file = span.file_name.replace('\\', "/");
+    let file = file.trim_start_matches("./");
+    let lint = diag.code.as_ref().map(|c| c.code.as_str()).unwrap_or_default();
+    
+    rules.iter().any(|rule| {
+        let name = if rule.glob.contains('/') {
+            file
+        } else {
+            file.rsplit('/').next().unwrap_or(file)
+        };
+        (rule.lint == "*" || rule.lint == lint) && glob_match(rule.glob.trim_start_matches("./"), name)
+    })
+}
+
+/// `*` and `?` stay within one path component, `**` spans directories
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+fn matches(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` 也匹配零层目录
+            matches(rest, path) || (0..path.len())
+                .filter(|&i| path[i] == '/')
+                .any(|i| matches(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| matches(rest, &path[i..])),
+        ['?', rest @ ..] => path.first().is_some_and(|&c| c != '/') && matches(rest, &path[1..]),
+        [c, rest @ ..] => path.first().is_some_and(|p| same_char(*c, *p)) && matches(rest, &path[1..]),
+    }
+}
+
+/// Windows 文件名不区分大小写
+fn same_char(a: char, b: char) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(&b)
+    } else {
+        a == b
+    }
+}
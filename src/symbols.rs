@@ -0,0 +1,85 @@
+use crate::cli::SymbolsMode;
+use crate::console;
+use crate::demangle::demangle;
+use crate::object::{self, Object, SymbolKind};
+use crate::translator::RustcCommand;
+use anyhow::Result;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// /Qsymbols[:exported|undefined]: nm-style listing of what the outputs define and need
+///
+/// The objects of C and C++ sources come first, as they are what a
+/// link error usually involves. Objects, executables and shared
+/// libraries are listed directly, a PE image together with its export
+/// table (link.exe leaves no symbol table in the image), and static
+/// libraries and rlibs member by member. `T` is an exported
+/// function, `D` exported data, `W` a weak definition and `U` a symbol
+/// that must come from elsewhere; local symbols are left out.
+pub fn print(cmd: &RustcCommand, mode: SymbolsMode) -> Result<()> {
+    let objects = cmd.c_steps.iter().map(|step| step.output.clone()).filter(|o| !is_text_output(o));
+    for output in objects.chain(cmd.outputs().unwrap_or_default()) {
+        let path = cmd.resolve(&output);
+        let Ok(data) = std::fs::read(&path) else {
+            continue;
+        };
+        if let Some(object) = object::parse(&data) {
+            console::print(&format!("{}:", output.display()).bold().to_string());
+            list(&object, mode, "  ");
+        } else if let Some(members) = object::members(&data) {
+            console::print(&format!("{}:", output.display()).bold().to_string());
+            for (name, body) in members {
+                // rlib 中的 lib.rmeta 等不是目标文件
+                if let Some(object) = object::parse(body) {
+                    console::print(&format!("  {}:", name).bold().to_string());
+                    list(&object, mode, "    ");
+                }
+            }
+        } else if !is_text_output(&output) {
+            console::eprint(&format!("[icx-rustc] warning: /Qsymbols: {} is not an object file or archive", output.display()));
+        }
+    }
+    Ok(())
+}
+
+fn list(object: &Object, mode: SymbolsMode, indent: &str) {
+    // 按还原后的名称排序并去重
+    let mut entries: BTreeMap<String, char> = BTreeMap::new();
+    for symbol in object.symbols.iter().filter(|s| s.global || !s.defined) {
+        let letter = match (symbol.defined, symbol.weak, symbol.kind) {
+            (false, true, _) => 'w',
+            (false, false, _) => 'U',
+            (true, true, _) => 'W',
+            (true, false, SymbolKind::Function) => 'T',
+            (true, false, _) => 'D',
+        };
+        let shown = match mode {
+            SymbolsMode::All => true,
+            SymbolsMode::Exported => symbol.defined,
+            SymbolsMode::Undefined => !symbol.defined,
+        };
+        if shown {
+            entries.entry(demangle(&symbol.name)).or_insert(letter);
+        }
+    }
+    let exported = entries.values().filter(|l| !matches!(l, 'U' | 'w')).count();
+    for (name, letter) in &entries {
+        let letter = match letter {
+            'U' | 'w' => letter.to_string().bright_red(),
+            _ => letter.to_string().bright_green(),
+        };
+        console::print(&format!("{}{}  {}", indent, letter, name));
+    }
+    let count = match mode {
+        SymbolsMode::All => format!("{} exported, {} undefined", exported, entries.len() - exported),
+        SymbolsMode::Exported => format!("{} exported", exported),
+        SymbolsMode::Undefined => format!("{} undefined", entries.len()),
+    };
+    console::print(&format!("{}{}", indent, count).bright_black().to_string());
+}
+
+/// Outputs such as `.s`, `.d` or `.ll` next to the binary, and bindgen's `.rs` files
+fn is_text_output(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| matches!(e, "s" | "d" | "ll" | "mir" | "bc" | "rmeta" | "rs"))
+}
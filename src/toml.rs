@@ -0,0 +1,378 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+/// Reads TOML into a JSON value, for project files and crate manifests
+///
+/// Tables, arrays of tables, dotted and quoted keys, inline tables,
+/// arrays, all four string forms, integers, floats and booleans. Dates
+/// and times are kept as strings.
+pub fn parse(text: &str) -> Result<Value> {
+    let mut parser = Parser { text, pos: 0, line: 1 };
+    parser.document().map_err(|e| anyhow::anyhow!("line {}: {}", parser.line, e))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> Result<Value> {
+        let mut root = Map::new();
+        // 当前表的路径；最后一段为 [[数组]] 时指向其最后一个元素
+        let mut current: Vec<String> = Vec::new();
+        loop {
+            self.blank();
+            let Some(c) = self.peek() else {
+                return Ok(Value::Object(root));
+            };
+            if c == '[' {
+                self.pos += 1;
+                let array = self.eat('[');
+                self.spaces();
+                current = self.key()?;
+                self.spaces();
+                self.expect(']')?;
+                if array {
+                    self.expect(']')?;
+                    let (last, parents) = current.split_last().context("empty table name")?;
+                    let table = table_at(&mut root, parents)?;
+                    table.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()))
+                        .as_array_mut()
+                        .with_context(|| format!("'{}' is not an array of tables", last))?
+                        .push(Value::Object(Map::new()));
+                } else {
+                    table_at(&mut root, &current)?;
+                }
+            } else {
+                let key = self.key()?;
+                self.spaces();
+                self.expect('=')?;
+                self.spaces();
+                let value = self.value()?;
+                insert(table_at(&mut root, &current)?, &key, value)?;
+            }
+            self.line_end()?;
+        }
+    }
+    
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+    
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+    
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+    
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+    
+    fn expect(&mut self, c: char) -> Result<()> {
+        if !self.eat(c) {
+            bail!("expected '{}'", c);
+        }
+        Ok(())
+    }
+    
+    fn spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+    
+    /// Whitespace, line breaks and comments
+    fn blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.bump();
+                }
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+    
+    fn line_end(&mut self) -> Result<()> {
+        self.spaces();
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.bump();
+            }
+        }
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            Some('\r') if self.rest().starts_with("\r\n") => Ok(()),
+            Some(c) => bail!("unexpected '{}'", c),
+        }
+    }
+    
+    /// `a.b."c.d"` -> [a, b, c.d]
+    fn key(&mut self) -> Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            self.spaces();
+            let part = match self.peek() {
+                Some('"') => self.string()?,
+                Some('\'') => self.string()?,
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        bail!("expected a key");
+                    }
+                    self.text[start..self.pos].to_string()
+                }
+            };
+            parts.push(part);
+            self.spaces();
+            if !self.eat('.') {
+                return Ok(parts);
+            }
+        }
+    }
+    
+    fn value(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some('"' | '\'') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.blank();
+                    if self.eat(']') {
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.blank();
+                    if !self.eat(',') {
+                        self.blank();
+                        self.expect(']')?;
+                        return Ok(Value::Array(items));
+                    }
+                }
+            }
+            Some('{') => {
+                self.bump();
+                let mut table = Map::new();
+                self.spaces();
+                if self.eat('}') {
+                    return Ok(Value::Object(table));
+                }
+                loop {
+                    let key = self.key()?;
+                    self.spaces();
+                    self.expect('=')?;
+                    self.spaces();
+                    let value = self.value()?;
+                    insert(&mut table, &key, value)?;
+                    self.spaces();
+                    if self.eat('}') {
+                        return Ok(Value::Object(table));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            Some(_) => {
+                let start = self.pos;
+                // 日期时间中可以有一个空格（1979-05-27 07:32:00）
+                while let Some(c) = self.peek() {
+                    let time = c == ' ' && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit());
+                    if matches!(c, ',' | ']' | '}' | '#' | '\r' | '\n') || (c == ' ' && !time) {
+                        break;
+                    }
+                    self.bump();
+                }
+                let word = self.text[start..self.pos].trim();
+                Ok(match word {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => number(word).unwrap_or_else(|| Value::String(word.to_string())),
+                })
+            }
+            None => bail!("expected a value"),
+        }
+    }
+    
+    /// Any of `"..."`, `'...'`, `"""..."""` and `'''...'''`
+    fn string(&mut self) -> Result<String> {
+        let quote = self.bump().context("expected a string")?;
+        let triple = self.rest().starts_with(if quote == '"' { "\"\"" } else { "''" });
+        let mut value = String::new();
+        if triple {
+            self.pos += 2;
+            // 紧跟开头引号的换行不算内容
+            if !self.eat('\n') && self.rest().starts_with("\r\n") {
+                self.pos += 1;
+                self.bump();
+            }
+        }
+        loop {
+            let c = self.bump().context("unterminated string")?;
+            if c == quote {
+                if !triple {
+                    return Ok(value);
+                }
+                if self.rest().starts_with(if quote == '"' { "\"\"" } else { "''" }) {
+                    self.pos += 2;
+                    // """a"""" 这样结尾处多出的（至多两个）引号属于内容
+                    for _ in 0..2 {
+                        if !self.eat(quote) {
+                            break;
+                        }
+                        value.push(quote);
+                    }
+                    return Ok(value);
+                }
+                value.push(c);
+                continue;
+            }
+            if c == '\n' && !triple {
+                bail!("unterminated string");
+            }
+            if c != '\\' || quote == '\'' {
+                value.push(c);
+                continue;
+            }
+            match self.bump().context("unterminated string")? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'b' => value.push('\u{8}'),
+                'f' => value.push('\u{c}'),
+                'e' => value.push('\u{1b}'),
+                c @ ('"' | '\\') => value.push(c),
+                c @ ('u' | 'U') => {
+                    let len = if c == 'u' { 4 } else { 8 };
+                    let hex = self.rest().get(..len).context("invalid unicode escape")?;
+                    let code = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).context("invalid unicode escape")?;
+                    value.push(code);
+                    self.pos += len;
+                }
+                // 行尾反斜杠：去掉换行和下一行开头的空白
+                c if triple && c.is_whitespace() => self.blank_no_comment(),
+                c => bail!("unsupported escape '\\{}'", c),
+            }
+        }
+    }
+    
+    fn blank_no_comment(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+}
+
+fn number(word: &str) -> Option<Value> {
+    let digits = word.replace('_', "");
+    let (sign, unsigned) = match digits.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+    };
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(rest) = unsigned.strip_prefix(prefix) {
+            return i64::from_str_radix(rest, radix).ok().map(|n| Value::from(sign * n));
+        }
+    }
+    if let Ok(n) = digits.parse::<i64>() {
+        return Some(Value::from(n));
+    }
+    // inf 与 nan 无法表示为 JSON，按字符串保留
+    digits.parse::<f64>().ok().filter(|f| f.is_finite()).map(Value::from)
+}
+
+/// The table at `path`, created as needed; arrays of tables resolve to their last element
+fn table_at<'a>(root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>> {
+    let mut table = root;
+    for key in path {
+        let entry = table.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+        let entry = match entry {
+            Value::Array(items) => items.last_mut().with_context(|| format!("'{}' is empty", key))?,
+            other => other,
+        };
+        table = entry.as_object_mut().with_context(|| format!("'{}' is not a table", key))?;
+    }
+    Ok(table)
+}
+
+/// Inserts a value under a dotted key
+fn insert(table: &mut Map<String, Value>, key: &[String], value: Value) -> Result<()> {
+    let (last, parents) = key.split_last().context("expected a key")?;
+    let table = table_at(table, parents)?;
+    if table.contains_key(last) {
+        bail!("duplicate key '{}'", last);
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use serde_json::json;
+    
+    #[test]
+    fn multi_line_strings_keep_trailing_quotes() {
+        let value = parse("a = \"\"\"x\"\"\"\"\nb = '''y'''''\nc = \"\"\"\nline\"\"\"\n").unwrap();
+        assert_eq!(value, json!({"a": "x\"", "b": "y''", "c": "line"}));
+    }
+    
+    #[test]
+    fn line_ending_backslash_joins_lines() {
+        let value = parse("a = \"\"\"one \\\n    two \\  \r\n  three\"\"\"\nb = '''raw \\\nkept'''\n").unwrap();
+        assert_eq!(value, json!({"a": "one two three", "b": "raw \\\nkept"}));
+    }
+    
+    #[test]
+    fn dotted_keys_and_arrays_of_tables() {
+        let text = "\
+name.first = \"a\"
+name.\"last.part\" = \"b\"
+
+[[bin]]
+name = \"one\"
+
+[[bin]]
+name = \"two\"
+test.harness = false
+
+[target.'cfg(unix)'.dependencies]
+libc = { version = \"0.2\", default-features = false }
+";
+        assert_eq!(parse(text).unwrap(), json!({
+            "name": {"first": "a", "last.part": "b"},
+            "bin": [{"name": "one"}, {"name": "two", "test": {"harness": false}}],
+            "target": {"cfg(unix)": {"dependencies": {"libc": {"version": "0.2", "default-features": false}}}},
+        }));
+    }
+    
+    #[test]
+    fn duplicate_keys_are_errors() {
+        let error = parse("a = 1\nb = 2\na = 3\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: duplicate key 'a'");
+        assert!(parse("x = { y = 1, y = 2 }\n").is_err());
+        assert!(parse("[t]\nk = 1\n[t]\nk = 2\n").is_err());
+    }
+}
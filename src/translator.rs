@@ -1,268 +1,1633 @@
-﻿use crate::cli::{Args, OptLevel, WarnLevel};
-use anyhow::{Context, Result};
-use std::path::PathBuf;
-use std::process::Command;
-
-#[derive(Debug)]
-pub struct RustcCommand {
-    pub executable: String,
-    pub args: Vec<String>,
-    pub env_vars: Vec<(String, String)>,
-    pub input_files: Vec<PathBuf>,
-    pub output: Option<PathBuf>,
-}
-
-impl RustcCommand {
-    pub fn new() -> Self {
-        Self {
-            executable: "rustc".to_string(),
-            args: Vec::new(),
-            env_vars: Vec::new(),
-            input_files: Vec::new(),
-            output: None,
-        }
-    }
-    
-    pub fn display(&self) -> String {
-        let mut parts = vec![self.executable.clone()];
-        parts.extend(self.args.clone());
-        for file in &self.input_files {
-            parts.push(file.display().to_string());
-        }
-        if let Some(out) = &self.output {
-            parts.push("-o".to_string());
-            parts.push(out.display().to_string());
-        }
-        parts.join(" ")
-    }
-}
-
-pub fn translate(args: &Args) -> Result<RustcCommand> {
-    let mut cmd = RustcCommand::new();
-    
-    // 1. 优化级别
-    translate_optimization(&mut cmd, args)?;
-    
-    // 2. 架构目标
-    translate_architecture(&mut cmd, args)?;
-    
-    // 3. 编译模式
-    if args.compile_only {
-        cmd.args.push("--emit=obj".to_string());
-    }
-    
-    // 4. 输出文件
-    translate_output(&mut cmd, args)?;
-    
-    // 5. 预处理器定义
-    translate_defines(&mut cmd, args)?;
-    
-    // 6. 警告级别
-    translate_warnings(&mut cmd, args)?;
-    
-    // 7. 链接参数
-    translate_linking(&mut cmd, args)?;
-    
-    // 8. Rust 特定
-    translate_rust_specific(&mut cmd, args)?;
-    
-    // 9. 输入文件
-    for file in &args.files {
-        if file.extension().map_or(false, |e| e == "rs") {
-            cmd.input_files.push(file.clone());
-        } else {
-            // 可能是库或其他输入
-            cmd.args.push(file.display().to_string());
-        }
-    }
-    
-    if cmd.input_files.is_empty() && !args.version && !args.help {
-        anyhow::bail!("No input files specified");
-    }
-    
-    // 10. 透传原始参数
-    cmd.args.extend(args.raw_args.clone());
-    
-    Ok(cmd)
-}
-
-fn translate_optimization(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    let level = match (&args.opt_level, &args.msvc_opt) {
-        (Some(l), _) => match l {
-            OptLevel::O0 => "0",
-            OptLevel::O1 => "1",
-            OptLevel::O2 => "2",
-            OptLevel::O3 => "3",
-            OptLevel::Ox => "3", // Ox = O3 for Rust
-        },
-        (None, Some(s)) => match s.as_str() {
-            "0" | "d" => "0",
-            "1" => "1",
-            "2" => "2",
-            "3" | "x" => "3",
-            _ => "2",
-        },
-        (None, None) if args.release => "3",
-        _ => "2", // default
-    };
-    
-    cmd.args.push(format!("-Copt-level={}", level));
-    
-    // LTO for high optimization
-    if level == "3" {
-        cmd.args.push("-Clto=fat".to_string());
-    }
-    
-    Ok(())
-}
-
-fn translate_architecture(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    if args.xhost {
-        // 检测主机架构
-        let target = detect_host_target()?;
-        cmd.args.push(format!("--target={}", target));
-        
-        // 添加目标特性
-        let features = detect_host_features()?;
-        if !features.is_empty() {
-            cmd.args.push(format!("-Ctarget-feature={}", features.join(",")));
-        }
-        return Ok(());
-    }
-    
-    if let Some(arch) = &args.arch {
-        let features = match arch.as_str() {
-            "AVX" => vec!["+avx"],
-            "AVX2" => vec!["+avx2"],
-            "AVX512" | "CORE-AVX512" => vec!["+avx512f", "+avx512vl", "+avx512bw"],
-            "SSE4.2" | "CORE-AVX" => vec!["+sse4.2"],
-            "SSE2" => vec!["+sse2"],
-            _ => {
-                eprintln!("[icx-rustc] warning: unknown arch '{}', using default", arch);
-                vec![]
-            }
-        };
-        
-        if !features.is_empty() {
-            cmd.args.push(format!("-Ctarget-feature={}", features.join(",")));
-        }
-    }
-    
-    Ok(())
-}
-
-fn translate_output(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    // 优先级：-o > /Fe > /Fo
-    let output = args.output.clone()
-        .or_else(|| args.msvc_exe.clone())
-        .or_else(|| args.msvc_obj.clone());
-    
-    if let Some(out) = output {
-        cmd.output = Some(out);
-    } else if args.compile_only && args.files.len() == 1 {
-    // 单文件编译模式
-        let input = &args.files[0];
-        let stem = input.file_stem()
-            .context("Invalid input filename")?;
-        let obj_name = format!("{}.o", stem.to_string_lossy());
-        cmd.output = Some(PathBuf::from(obj_name));
-    }
-    
-    Ok(())
-}
-
-fn translate_defines(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    for def in &args.defines {
-        if let Some((name, value)) = def.split_once('=') {
-            // --cfg feature=\"value\"
-            cmd.args.push(format!("--cfg={}={}", name, value));
-        } else {
-            // --cfg feature
-            cmd.args.push(format!("--cfg={}", def));
-        }
-    }
-    
-    for undef in &args.undefines {
-        eprintln!("[icx-rustc] warning: /U{} not fully supported in Rust", undef);
-    }
-    
-    Ok(())
-}
-
-fn translate_warnings(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    if args.wx {
-        cmd.args.push("-Dwarnings".to_string());
-    }
-    
-    match &args.warn_level {
-        Some(l) => match l.as_str() {
-            "0" => cmd.args.push("-Awarnings".to_string()),
-            "1" => {
-                cmd.args.push("-Wwarnings".to_string());
-                cmd.args.push("-Adead_code".to_string());
-            }
-            "3" | "all" => cmd.args.push("-Wwarnings".to_string()),
-            _ => {}
-        },
-        None => {}
-    }
-    
-    Ok(())
-}
-
-fn translate_linking(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    if !args.link_args.is_empty() {
-        let joined = args.link_args.join(" ");
-        cmd.args.push(format!("-Clink-args={}", shlex::quote(&joined)));
-    }
-    
-    Ok(())
-}
-
-fn translate_rust_specific(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
-    if let Some(edition) = &args.edition {
-        cmd.args.push(format!("--edition={}", edition));
-    }
-    
-    if let Some(crate_type) = &args.crate_type {
-        cmd.args.push(format!("--crate-type={}", crate_type));
-    }
-    
-    if let Some(target) = &args.target {
-        cmd.args.push(format!("--target={}", target));
-    }
-    
-    cmd.args.push("-Ccodegen-units=1".to_string()); // 类似 IPO
-    cmd.args.push("-Cpanic=abort".to_string());     // 类似 MSVC
-    
-    Ok(())
-}
-
-fn detect_host_target() -> Result<String> {
-    // 简化实现，实际应使用 rustc --print target-list
-    #[cfg(target_os = "windows")]
-    return Ok("x86_64-pc-windows-msvc".to_string());
-    
-    #[cfg(target_os = "linux")]
-    return Ok("x86_64-unknown-linux-gnu".to_string());
-    
-    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
-    anyhow::bail!("Unsupported host platform");
-}
-
-fn detect_host_features() -> Result<Vec<String>> {
-    use std::arch::is_x86_feature_detected;
-    
-    let mut features = vec!["+crt-static".to_string()];
-    
-    if is_x86_feature_detected!("avx512f") {
-        features.push("+avx512f".to_string());
-        features.push("+avx512vl".to_string());
-    } else if is_x86_feature_detected!("avx2") {
-        features.push("+avx2".to_string());
-    } else if is_x86_feature_detected!("sse4.2") {
-        features.push("+sse4.2".to_string());
-    }
-    
-    Ok(features)
-}
+use crate::asm_view::AsmView;
+use crate::audit;
+use crate::baseline::Baseline;
+use crate::bolt;
+use crate::cache;
+use crate::cc::{self, CStep};
+use crate::cli::{reparse_with, Args, ColorChoice, DiagFormat, OptLevel, SymbolsMode};
+use crate::cmdline;
+use crate::console;
+use crate::depfile::{self, Depfile};
+use crate::deps;
+use crate::diagnostics::BuildInfo;
+use crate::fuzz;
+use crate::hooks::Hooks;
+use crate::manifest;
+use crate::numbering::{self, DiagId};
+use crate::offload;
+use crate::suggest::did_you_mean;
+use crate::suppress::{self, Suppression};
+use crate::version_info;
+use crate::wasm;
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct RustcCommand {
+    pub executable: String,
+    pub args: Vec<String>,
+    pub env_vars: Vec<(String, String)>,
+    pub input_files: Vec<PathBuf>,
+    pub link_inputs: Vec<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub target: Option<String>,
+    pub timeout: Option<Duration>,
+    pub cwd: Option<PathBuf>,
+    /// External cache wrapper the compiler is launched through (sccache)
+    pub wrapper: Option<PathBuf>,
+    /// Render diagnostics as header and location only (--watch)
+    pub condensed: bool,
+    /// SSH host to compile on (/Qremote)
+    pub remote: Option<String>,
+    /// Collect `-Ztime-passes` output into a phase report (/Qtime)
+    pub time_report: bool,
+    /// Measure peak RSS and CPU time of the compiler (/Qresource-report)
+    pub resource_report: bool,
+    /// Leave out the error/warning summary line (/Qquiet)
+    pub quiet: bool,
+    /// Console rendering of diagnostics (/Qdiag-format)
+    pub diag_format: DiagFormat,
+    /// Print diagnostics grouped per file once the compiler exits (/Qdiag-group)
+    pub diag_group: bool,
+    /// Warnings filtered out per file (/Qdiag-suppress-file)
+    pub suppress: Vec<Suppression>,
+    /// Errors shown before the rest are only counted (/Qdiag-error-limit)
+    pub error_limit: Option<u32>,
+    /// Kill the compiler once `error_limit` errors were shown
+    pub error_limit_abort: bool,
+    /// Plain-text copy of the rendered diagnostics (/Qdiag-file)
+    pub diag_file: Option<PathBuf>,
+    /// Accepted warnings that are not shown (/Qdiag-baseline:check)
+    pub baseline: Option<Arc<Baseline>>,
+    /// Commands run before and after the compile (/Qhook, icx-rustc.toml)
+    pub hooks: Option<Arc<Hooks>>,
+    /// Results are stored in the builtin cache (/Qcache)
+    pub cached: bool,
+    /// Started by cargo as RUSTC_WRAPPER: the JSON goes through to cargo, the
+    /// diagnostics with the ICX rendering as their `rendered` text
+    pub cargo: bool,
+    /// C and C++ compiles and bindgen runs before rustc
+    pub c_steps: Vec<CStep>,
+    /// Libraries linked after the link inputs (the C++ runtime)
+    pub link_libs: Vec<String>,
+    /// Adds the object inputs to the staticlib once rustc wrote it
+    pub archive: Option<CStep>,
+    /// Import library written with a Windows DLL (/IMPLIB)
+    pub implib: Option<PathBuf>,
+    /// llvm-bolt run on the linked binary (/Qbolt)
+    pub bolt: Option<CStep>,
+    /// Binary whose -v /Qstrip savings are reported, against an unstripped build
+    pub strip_report: Option<PathBuf>,
+    /// wasm-bindgen and wasm-opt runs on the linked module (/Qwasm)
+    pub wasm: Vec<CStep>,
+    /// Assembly shown with its source once compiled (/Qasm-view)
+    pub asm_view: Option<AsmView>,
+    /// Symbols listed by /Qsize-report after the link
+    pub size_report: Option<usize>,
+    /// nm-style listing of the outputs (/Qsymbols)
+    pub symbols: Option<SymbolsMode>,
+    /// Make-style dependency file rewritten after the compile (/QMMD)
+    pub depfile: Option<Depfile>,
+    /// Earlier commands of the invocation whose rlibs this one uses
+    pub needs: Vec<usize>,
+}
+
+impl Default for RustcCommand {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RustcCommand {
+    pub fn new() -> Self {
+        Self {
+            executable: "rustc".to_string(),
+            args: Vec::new(),
+            env_vars: Vec::new(),
+            input_files: Vec::new(),
+            link_inputs: Vec::new(),
+            c_steps: Vec::new(),
+            link_libs: Vec::new(),
+            archive: None,
+            implib: None,
+            bolt: None,
+            strip_report: None,
+            wasm: Vec::new(),
+            asm_view: None,
+            size_report: None,
+            symbols: None,
+            depfile: None,
+            needs: Vec::new(),
+            output: None,
+            target: None,
+            timeout: None,
+            cwd: None,
+            wrapper: None,
+            condensed: false,
+            remote: None,
+            time_report: false,
+            resource_report: false,
+            quiet: false,
+            diag_format: DiagFormat::Icx,
+            diag_group: false,
+            suppress: Vec::new(),
+            error_limit: None,
+            error_limit_abort: false,
+            diag_file: None,
+            baseline: None,
+            hooks: None,
+            cached: false,
+            cargo: false,
+        }
+    }
+    
+    pub fn display(&self) -> String {
+        let mut parts: Vec<String> = self.wrapper.iter()
+            .map(|w| w.display().to_string())
+            .collect();
+        parts.extend(self.arguments());
+        cmdline::join(parts.iter().map(String::as_str))
+    }
+    
+    /// The compiler and its arguments, without the cache wrapper
+    pub fn arguments(&self) -> Vec<String> {
+        let mut parts = vec![self.executable.clone()];
+        parts.extend(self.args.clone());
+        for file in &self.input_files {
+            parts.push(file.display().to_string());
+        }
+        for obj in &self.link_inputs {
+            parts.push(format!("-Clink-arg={}", obj.display()));
+        }
+        for lib in &self.link_libs {
+            parts.push(format!("-Clink-arg={}", lib));
+        }
+        if let Some(out) = &self.output {
+            parts.push("-o".to_string());
+            parts.push(out.display().to_string());
+        }
+        parts
+    }
+    
+    /// Resolves a path the way the spawned compiler sees it (relative to --cwd)
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.cwd {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+    
+    /// Path of the main artifact rustc will produce, if it can be predicted
+    ///
+    /// With several crate types it is the first one's.
+    pub fn artifact(&self) -> Option<PathBuf> {
+        self.artifacts()?.into_iter().next()
+    }
+    
+    /// The artifact of the first of `kinds` among the crate types
+    pub fn artifact_of(&self, kinds: &[&str]) -> Option<PathBuf> {
+        let types = crate_types(self);
+        if types.len() < 2 {
+            return self.artifact();
+        }
+        let artifacts = self.artifacts()?;
+        types.iter().zip(artifacts).find(|(t, _)| kinds.contains(t)).map(|(_, a)| a)
+    }
+    
+    /// Whether rustc links an executable or shared library (not with /c
+    /// or an --emit without link)
+    pub fn links(&self) -> bool {
+        let kinds = crate_types(self);
+        let emits: Vec<&str> = self.args.iter().filter_map(|a| a.strip_prefix("--emit=")).flat_map(|s| s.split(',')).collect();
+        !self.input_files.is_empty()
+            && (emits.is_empty() || emits.contains(&"link"))
+            && (kinds.is_empty() || kinds.iter().any(|k| matches!(*k, "bin" | "cdylib" | "dylib")))
+    }
+    
+    /// The artifact of each crate type, in `--crate-type` order
+    pub fn artifacts(&self) -> Option<Vec<PathBuf>> {
+        if let Some(out) = &self.output {
+            return Some(vec![out.clone()]);
+        }
+        
+        let arg_value = |prefix: &str| self.args.iter().find_map(|a| a.strip_prefix(prefix));
+        // 只有 --emit 含 link（不带路径）时才有链接产物；只有 obj 时是 <crate>.o
+        let emits: Vec<&str> = self.args.iter().filter_map(|a| a.strip_prefix("--emit=")).flat_map(|s| s.split(',')).collect();
+        let object = !emits.is_empty() && !emits.contains(&"link") && emits.contains(&"obj");
+        if !emits.is_empty() && !emits.contains(&"link") && !object {
+            return None;
+        }
+        
+        // 与 rustc 的默认命名一致
+        let name = match arg_value("--crate-name=") {
+            Some(name) => name.to_string(),
+            None => self.input_files.first()?
+                .file_stem()?
+                .to_string_lossy()
+                .replace('-', "_"),
+        };
+        // 交叉编译时按目标平台命名
+        let windows = self.target.as_deref().map_or(cfg!(windows), |t| t.contains("-windows"));
+        let apple = self.target.as_deref().map_or(cfg!(target_vendor = "apple"), |t| t.contains("-apple-"));
+        let wasm = self.target.as_deref().is_some_and(|t| t.starts_with("wasm"));
+        let file = |kind: &str| match kind {
+            _ if object => format!("{}.o", name),
+            "lib" | "rlib" => format!("lib{}.rlib", name),
+            "bin" | "cdylib" if wasm => format!("{}.wasm", name),
+            "dylib" | "cdylib" if windows => format!("{}.dll", name),
+            "dylib" | "cdylib" if apple => format!("lib{}.dylib", name),
+            "dylib" | "cdylib" => format!("lib{}.so", name),
+            "staticlib" if windows && self.target.as_deref().is_none_or(|t| t.contains("-msvc")) => format!("{}.lib", name),
+            "staticlib" => format!("lib{}.a", name),
+            _ if wasm => format!("{}.wasm", name),
+            _ if windows => format!("{}.exe", name),
+            _ => name.clone(),
+        };
+        
+        let dir = arg_value("--out-dir=").map(PathBuf::from).unwrap_or_default();
+        let kinds = crate_types(self);
+        // 只有目标文件时各类型共用一个 .o
+        let kinds = if kinds.is_empty() || object { vec!["bin"] } else { kinds };
+        Some(kinds.into_iter().map(|kind| dir.join(file(kind))).collect())
+    }
+    
+    /// Files the command writes, or `None` when they cannot be predicted
+    pub fn outputs(&self) -> Option<Vec<PathBuf>> {
+        let mut outputs = Vec::new();
+        let mut unnamed = false;
+        for spec in self.args.iter().filter_map(|a| a.strip_prefix("--emit=")) {
+            for kind in spec.split(',') {
+                match kind.split_once('=') {
+                    Some((_, path)) => outputs.push(PathBuf::from(path)),
+                    None => unnamed = true,
+                }
+            }
+        }
+        
+        if unnamed || outputs.is_empty() {
+            outputs.extend(self.artifacts()?);
+        }
+        outputs.extend(self.implib.clone());
+        Some(outputs)
+    }
+    
+    /// What the build produced and how, for the lines under the summary
+    pub fn build_info(&self, cache_hit: bool) -> BuildInfo {
+        let artifacts = self.outputs()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|out| {
+                let size = std::fs::metadata(self.resolve(&out)).ok()?.len();
+                Some((out, size))
+            })
+            .collect();
+        
+        // 影响生成代码的选项
+        let flags = self.args.iter()
+            .filter(|a| OPT_FLAGS.iter().any(|f| a.starts_with(f)))
+            .cloned()
+            .collect();
+        
+        let mut cache = Vec::new();
+        if cache_hit {
+            cache.push("cache hit".to_string());
+        } else if self.cached {
+            cache.push("cache miss".to_string());
+        } else if let Some(wrapper) = &self.wrapper {
+            cache.push(wrapper.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+        }
+        if !cache_hit && self.args.iter().any(|a| a.starts_with("-Cincremental=")) {
+            cache.push("incremental".to_string());
+        }
+        
+        BuildInfo {
+            artifacts,
+            target: effective_target(self),
+            flags,
+            cache,
+        }
+    }
+}
+
+/// Codegen options listed in the build summary
+const OPT_FLAGS: &[&str] = &[
+    "-Copt-level=", "-Ctarget-cpu=", "-Ctarget-feature=", "-Clto", "-Ccodegen-units=",
+    "-Cpanic=", "-Coverflow-checks=", "-Cdebug-assertions=", "-Cprofile-use=",
+];
+
+/// Translates the arguments into one rustc invocation per translation unit
+///
+/// rustc compiles a single crate root per call, so `/c` with several `.rs`
+/// inputs becomes one invocation per file, each producing its own object.
+pub fn translate_units(args: &Args) -> Result<Vec<RustcCommand>> {
+    let sources: Vec<&PathBuf> = args.files.iter()
+        .filter(|f| f.extension().is_some_and(|e| e == "rs"))
+        .collect();
+    
+    // 多个源文件一起链接：被其他源文件当作 crate 使用的先编译为 rlib
+    if !args.compile_only && sources.len() > 1 {
+        if let Some(libraries) = libraries(&sources)? {
+            return translate_libraries(args, &sources, &libraries);
+        }
+    }
+    
+    if !args.compile_only || sources.len() < 2 {
+        let unit = match sources.first() {
+            Some(source) => with_file_options(args, source)?,
+            None => args.clone(),
+        };
+        return Ok(vec![translate(&unit)?]);
+    }
+    
+    // /Fo<dir>/ 与 cl 一致：目录形式时每个输入生成 <dir>/<stem>.o
+    let obj_dir = match &args.msvc_obj {
+        Some(fo) if is_dir_spec(fo) => Some(fo.clone()),
+        Some(_) => anyhow::bail!(
+            "/Fo<file> names a single object but {} inputs were given; use /Fo<dir>/",
+            sources.len()
+        ),
+        None => None,
+    };
+    
+    if args.output.is_some() || args.msvc_exe.is_some() {
+        anyhow::bail!("-o cannot be used with /c and multiple input files");
+    }
+    
+    // C/C++ 源文件与资源脚本随第一个翻译单元编译
+    let c_sources: Vec<PathBuf> = args.files.iter().filter(|f| cc::is_source(f) || cc::is_resource(f)).cloned().collect();
+    sources.into_iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let mut unit = with_file_options(args, source)?;
+            unit.files = vec![source.clone()];
+            if i == 0 {
+                unit.files.extend(c_sources.iter().cloned());
+            }
+            unit.msvc_obj = obj_dir.as_ref().map(|dir| object_in(dir, source));
+            translate(&unit)
+        })
+        .collect()
+}
+
+/// Sources used as crates by the others, and the one source that uses them
+struct Libraries {
+    /// Dependencies before dependents, without the root
+    order: Vec<usize>,
+    root: usize,
+    /// The sources each one names in `use` or `extern crate`
+    deps: Vec<Vec<usize>>,
+}
+
+/// How several linked sources depend on each other, when they form one crate graph
+///
+/// A source is a library when another names its crate name (the file
+/// stem) as a crate; that only applies if exactly one source is used by
+/// none of the others and every other source is reachable from it.
+fn libraries(sources: &[&PathBuf]) -> Result<Option<Libraries>> {
+    let names: Vec<String> = sources.iter()
+        .map(|s| sanitize_crate_name(&s.file_stem().unwrap_or_default().to_string_lossy()))
+        .collect();
+    let deps: Vec<Vec<usize>> = sources.iter().enumerate()
+        .map(|(i, source)| {
+            let used = deps::used_crates(std::slice::from_ref(*source));
+            (0..sources.len()).filter(|&j| j != i && used.contains(&names[j])).collect()
+        })
+        .collect();
+    let roots: Vec<usize> = (0..sources.len()).filter(|i| !deps.iter().any(|d| d.contains(i))).collect();
+    let [root] = roots[..] else {
+        return Ok(None);
+    };
+    
+    fn visit(node: usize, deps: &[Vec<usize>], state: &mut [u8], order: &mut Vec<usize>, names: &[String]) -> Result<()> {
+        match state[node] {
+            2 => return Ok(()),
+            1 => anyhow::bail!("Sources use each other as crates ({}); one of them has to be a module", names[node]),
+            _ => {}
+        }
+        state[node] = 1;
+        for &dep in &deps[node] {
+            visit(dep, deps, state, order, names)?;
+        }
+        state[node] = 2;
+        order.push(node);
+        Ok(())
+    }
+    let mut state = vec![0; sources.len()];
+    let mut order = Vec::new();
+    visit(root, &deps, &mut state, &mut order, &names)?;
+    order.pop();
+    if order.len() + 1 != sources.len() {
+        return Ok(None);
+    }
+    Ok(Some(Libraries { order, root, deps }))
+}
+
+/// One rustc call per library source (an rlib in the output directory), then the root
+///
+/// Each command gets `--extern` for the rlibs its source uses and waits
+/// for them (`needs`); the root also takes the other inputs of the
+/// command line.
+fn translate_libraries(args: &Args, sources: &[&PathBuf], libraries: &Libraries) -> Result<Vec<RustcCommand>> {
+    let rlib = |i: usize| {
+        let name = sanitize_crate_name(&sources[i].file_stem().unwrap_or_default().to_string_lossy());
+        in_out_dir(args, PathBuf::from(format!("lib{}.rlib", name)))
+    };
+    let dir = rlib(libraries.root).parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    let index = |source: usize| libraries.order.iter().position(|&i| i == source).unwrap_or_default();
+    
+    let mut commands = Vec::new();
+    for &i in libraries.order.iter().chain(std::iter::once(&libraries.root)) {
+        let mut unit = with_file_options(args, sources[i])?;
+        unit.files = vec![sources[i].clone()];
+        unit.files.extend(libraries.deps[i].iter().map(|&j| rlib(j)));
+        if i == libraries.root {
+            unit.files.extend(args.files.iter().filter(|f| f.extension().is_none_or(|e| e != "rs")).cloned());
+        } else {
+            unit.crate_type = Some("rlib".to_string());
+            unit.crate_name = None;
+            unit.output = Some(rlib(i));
+            unit.msvc_exe = None;
+            unit.emit.clear();
+        }
+        // rlib 的依赖由 rustc 在同一目录中查找
+        unit.raw_args.push(format!("-Ldependency={}", dir.display()));
+        let mut cmd = translate(&unit)?;
+        cmd.needs = libraries.deps[i].iter().map(|&j| index(j)).collect();
+        commands.push(cmd);
+    }
+    Ok(commands)
+}
+
+/// Applies matching /Qfile-opts entries for one translation unit
+fn with_file_options(args: &Args, source: &Path) -> Result<Args> {
+    let mut unit = args.clone();
+    for entry in &args.file_opts {
+        let (file, options) = entry.split_once('=')
+            .with_context(|| format!("Expected /Qfile-opts:<file>=\"<options>\", got '{}'", entry))?;
+        if same_file(Path::new(file), source) {
+            unit = reparse_with(&unit, options)?;
+        }
+    }
+    Ok(unit)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(x), Ok(y)) if x == y)
+}
+
+fn is_dir_spec(path: &Path) -> bool {
+    let s = path.to_string_lossy();
+    s.ends_with('/') || s.ends_with('\\') || path.is_dir()
+}
+
+/// `<dir>/<stem>.o`, the object of `source` under /Fo<dir>/
+fn object_in(dir: &Path, source: &Path) -> PathBuf {
+    let stem = source.file_stem().unwrap_or_default();
+    dir.join(format!("{}.o", stem.to_string_lossy()))
+}
+
+/// The rustc command line for an invocation, without running anything
+///
+/// With several sources, [`translate_units`] gives the command for each
+/// crate the invocation builds.
+pub fn translate(args: &Args) -> Result<RustcCommand> {
+    let mut cmd = RustcCommand::new();
+    
+    // 0. 编译器选择
+    translate_toolchain(&mut cmd, args)?;
+    
+    // 1. 优化级别
+    translate_optimization(&mut cmd, args)?;
+    
+    // 2. 架构目标
+    translate_architecture(&mut cmd, args)?;
+    
+    // 3. 编译模式 / 输出类型
+    translate_emit(&mut cmd, args)?;
+    
+    // 4. 输出文件
+    translate_output(&mut cmd, args)?;
+    
+    // 5. 预处理器定义
+    translate_defines(&mut cmd, args)?;
+    
+    // 6. 警告级别
+    translate_warnings(&mut cmd, args)?;
+    
+    // 7. 链接参数
+    translate_linking(&mut cmd, args)?;
+    
+    // 8. Rust 特定
+    translate_rust_specific(&mut cmd, args)?;
+    
+    // 9. 输入文件
+    let has_sources = args.files.iter().any(|f| f.extension().is_some_and(|e| e == "rs"));
+    let mut c_sources = Vec::new();
+    let mut resources = Vec::new();
+    for file in &args.files {
+        match file.extension().and_then(|e| e.to_str()) {
+            Some("rs") => cmd.input_files.push(file.clone()),
+            _ if cc::is_source(file) => c_sources.push(file.clone()),
+            _ if cc::is_resource(file) => resources.push(file.clone()),
+            // 编译时 rlib 作为依赖 crate，纯链接时与目标文件一起交给链接器
+            Some("rlib") if has_sources => {
+                cmd.args.push(format!("--extern={}={}", rlib_crate_name(file), file.display()));
+            }
+            _ if is_link_input(file) => cmd.link_inputs.push(file.clone()),
+            // 可能是库或其他输入
+            _ => cmd.args.push(file.display().to_string()),
+        }
+    }
+    
+    if cmd.input_files.is_empty() && cmd.link_inputs.is_empty() && c_sources.is_empty() && !args.version && !args.help {
+        anyhow::bail!("No input files specified");
+    }
+    
+    // 10. 透传原始参数
+    cmd.args.extend(args.raw_args.clone());
+    
+    // C/C++ 源文件沿用 Rust 部分的最终设置
+    if !c_sources.is_empty() {
+        let target = effective_target(&cmd);
+        cc::plan(&mut cmd, args, &c_sources, &target)?;
+    }
+    if !resources.is_empty() {
+        let target = effective_target(&cmd);
+        cc::resources(&mut cmd, args, &resources, &target)?;
+    }
+    if !args.bindgen.is_empty() && !cmd.input_files.is_empty() {
+        cc::bindgen(&mut cmd, args)?;
+    }
+    // 静态库：rustc 不使用链接输入，由归档工具并入
+    let kinds = crate_types(&cmd);
+    let staticlib = kinds.contains(&"staticlib");
+    let static_only = staticlib && kinds.len() == 1;
+    let linked = kinds.is_empty() || kinds.iter().any(|k| matches!(*k, "bin" | "cdylib" | "dylib"));
+    translate_import_library(&mut cmd, args)?;
+    if linked && !cmd.input_files.is_empty() {
+        let target = effective_target(&cmd);
+        manifest::translate(&mut cmd, args, &target)?;
+        version_info::translate(&mut cmd, args, &target)?;
+        bolt::translate(&mut cmd, args, &target)?;
+        translate_strip_report(&mut cmd, args, &target);
+        wasm::post_steps(&mut cmd, args)?;
+    }
+    if args.offload.is_some() && !cmd.input_files.is_empty() {
+        offload::translate(&mut cmd, args)?;
+    }
+    if args.fuzz.is_some() && !cmd.input_files.is_empty() {
+        let target = effective_target(&cmd);
+        fuzz::translate(&mut cmd, args, &target)?;
+    }
+    if args.deps.is_some() && !cmd.input_files.is_empty() {
+        deps::translate(&mut cmd, args)?;
+    }
+    if !args.compile_only && !static_only {
+        cmd.size_report = args.size_report;
+    }
+    cmd.symbols = args.symbols;
+    depfile::translate(&mut cmd, args)?;
+    if args.asm_view.is_some() && !cmd.input_files.is_empty() {
+        translate_asm_view(&mut cmd, args);
+    }
+    if staticlib && !cmd.input_files.is_empty() && !cmd.link_inputs.is_empty() {
+        let target = effective_target(&cmd);
+        cmd.archive = Some(cc::archive(&cmd, &target)?);
+    }
+    
+    // 11. 执行环境
+    cmd.timeout = args.timeout.map(Duration::from_secs);
+    cmd.resource_report = args.resource_report;
+    cmd.quiet = args.quiet;
+    cmd.diag_format = args.diag_format;
+    cmd.diag_group = args.diag_group;
+    cmd.error_limit = args.diag_error_limit.filter(|&limit| limit > 0);
+    cmd.error_limit_abort = args.diag_error_limit_abort;
+    // 与 icx 一致：不带文件名时写到 <源文件名>.diag
+    cmd.diag_file = match &args.diag_file {
+        Some(path) if path.as_os_str().is_empty() => cmd.input_files.first()
+            .or(cmd.output.as_ref())
+            .map(|f| f.with_extension("diag")),
+        other => other.clone(),
+    };
+    if let Some(dir) = &args.cwd {
+        if !dir.is_dir() {
+            anyhow::bail!("Working directory '{}' does not exist", dir.display());
+        }
+        cmd.cwd = Some(dir.clone());
+    }
+    cmd.remote = args.remote.clone()
+        .or_else(|| std::env::var("ICX_REMOTE").ok())
+        .filter(|host| !host.is_empty());
+    
+    Ok(cmd)
+}
+
+/// A cargo-driven rustc call with the options of `ICX_FLAGS` added
+///
+/// cargo's arguments keep deciding what is built (crate type, outputs,
+/// profile); only optimization given explicitly, architecture, warnings
+/// and the diagnostics options are taken. They go after cargo's, so they win.
+pub fn translate_wrapped(args: &Args, rustc: &str, cargo_args: Vec<String>) -> Result<RustcCommand> {
+    let mut cmd = RustcCommand::new();
+    cmd.executable = rustc.to_string();
+    cmd.cargo = true;
+    
+    let mut injected = RustcCommand::new();
+    injected.args = cargo_rustflags(args)?;
+    // LTO 由 cargo 的 profile 决定（依赖以 -Cembed-bitcode=no 编译时与 -Clto 冲突）
+    injected.args.retain(|a| !a.starts_with("-Clto"));
+    translate_warnings(&mut injected, args)?;
+    cmd.suppress = injected.suppress;
+    
+    // icx-cargo 已把代码生成选项放进 RUSTFLAGS
+    cmd.args = cargo_args;
+    for flag in injected.args {
+        if !cmd.args.contains(&flag) {
+            cmd.args.push(flag);
+        }
+    }
+    
+    // cargo 自己报告每个 crate 的结果，不再输出摘要行
+    cmd.quiet = true;
+    cmd.timeout = args.timeout.map(Duration::from_secs);
+    cmd.diag_format = args.diag_format;
+    cmd.diag_group = args.diag_group;
+    cmd.error_limit = args.diag_error_limit.filter(|&limit| limit > 0);
+    cmd.error_limit_abort = args.diag_error_limit_abort;
+    Ok(cmd)
+}
+
+/// Codegen flags for the options that decide how code is generated (icx-cargo)
+///
+/// Optimization is only included when given explicitly, so cargo's
+/// profile stays in charge otherwise.
+pub fn cargo_rustflags(args: &Args) -> Result<Vec<String>> {
+    let mut cmd = RustcCommand::new();
+    if args.opt_level.is_some() || args.msvc_opt.is_some() {
+        translate_optimization(&mut cmd, args)?;
+    }
+    translate_architecture(&mut cmd, args)?;
+    Ok(cmd.args)
+}
+
+/// The rustc a compile with these arguments would run (--explain)
+pub fn rustc_executable(args: &Args) -> Result<String> {
+    let mut cmd = RustcCommand::new();
+    translate_toolchain(&mut cmd, args)?;
+    Ok(cmd.executable)
+}
+
+fn translate_toolchain(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    // 优先级：--rustc > ICX_RUSTC > +toolchain > PATH 中的 rustc
+    if let Some(rustc) = &args.rustc {
+        cmd.executable = rustc.display().to_string();
+    } else if let Some(rustc) = std::env::var_os("ICX_RUSTC").filter(|v| !v.is_empty()) {
+        cmd.executable = rustc.to_string_lossy().into_owned();
+    } else if let Some(toolchain) = &args.toolchain {
+        cmd.executable = resolve_toolchain(toolchain)?;
+    }
+    
+    Ok(())
+}
+
+/// Locates the rustc binary of a rustup toolchain
+fn resolve_toolchain(toolchain: &str) -> Result<String> {
+    let output = Command::new("rustup")
+        .args(["which", "--toolchain", toolchain, "rustc"])
+        .output()
+        .context("Failed to run rustup (required for +<toolchain>)")?;
+    
+    if !output.status.success() {
+        anyhow::bail!(
+            "Toolchain '{}' is not installed (try `rustup toolchain install {}`)",
+            toolchain,
+            toolchain
+        );
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn translate_optimization(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let level = match (&args.opt_level, &args.msvc_opt) {
+        (Some(l), _) => match l {
+            OptLevel::O0 => "0",
+            OptLevel::O1 => "1",
+            OptLevel::O2 => "2",
+            OptLevel::O3 => "3",
+            OptLevel::Ox => "3", // Ox = O3 for Rust
+        },
+        (None, Some(s)) => match s.as_str() {
+            "0" | "d" => "0",
+            "1" => "1",
+            "2" => "2",
+            "3" | "x" => "3",
+            _ => "2",
+        },
+        (None, None) if args.release => "3",
+        _ => "2", // default
+    };
+    
+    cmd.args.push(format!("-Copt-level={}", level));
+    
+    // LTO for high optimization
+    if level == "3" {
+        cmd.args.push("-Clto=fat".to_string());
+    }
+    
+    // 运行时检查与优化级别无关
+    if args.overflow_checks {
+        cmd.args.push("-Coverflow-checks=on".to_string());
+    } else if args.no_overflow_checks {
+        cmd.args.push("-Coverflow-checks=off".to_string());
+    }
+    
+    if args.debug_assertions {
+        cmd.args.push("-Cdebug-assertions=on".to_string());
+    } else if args.no_debug_assertions {
+        cmd.args.push("-Cdebug-assertions=off".to_string());
+    }
+    
+    Ok(())
+}
+
+fn translate_architecture(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    if args.xhost {
+        // 由 rustc 启用主机 CPU 的全部特性
+        cmd.args.push("-Ctarget-cpu=native".to_string());
+        
+        if args.verbose {
+            if let Some(features) = query_native_features(&cmd.executable) {
+                console::eprint(&format!("[icx-rustc] xHost features: {}", features.join(",")));
+            }
+        }
+        return Ok(());
+    }
+    
+    if let Some(arch) = &args.arch {
+        let features = match arch.as_str() {
+            "AVX" => vec!["+avx"],
+            "AVX2" => vec!["+avx2"],
+            "AVX512" | "CORE-AVX512" => vec!["+avx512f", "+avx512vl", "+avx512bw"],
+            "SSE4.2" | "CORE-AVX" => vec!["+sse4.2"],
+            "SSE2" => vec!["+sse2"],
+            _ => {
+                console::eprint(&format!("[icx-rustc] warning: unknown arch '{}', using default", arch));
+                vec![]
+            }
+        };
+        
+        if !features.is_empty() {
+            cmd.args.push(format!("-Ctarget-feature={}", features.join(",")));
+        }
+    }
+    
+    Ok(())
+}
+
+/// rustc 的 emit 类型及其默认扩展名（link 由 rustc 按平台决定）
+const EMIT_KINDS: &[(&str, &str)] = &[
+    ("obj", "o"),
+    ("asm", "s"),
+    ("llvm-ir", "ll"),
+    ("llvm-bc", "bc"),
+    ("mir", "mir"),
+    ("metadata", "rmeta"),
+    ("dep-info", "d"),
+    ("link", ""),
+];
+
+fn translate_emit(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    if args.emit.is_empty() {
+        if args.compile_only {
+            cmd.args.push("--emit=obj".to_string());
+        }
+        return Ok(());
+    }
+    
+    // 每种输出使用 <base>.<ext> 命名，base 取自 -o 或首个输入文件
+    let explicit = requested_output(args);
+    let base = explicit.as_ref()
+        .map(|out| out.with_extension(""))
+        .or_else(|| args.files.first()
+            .and_then(|f| f.file_stem())
+            .map(|stem| in_out_dir(args, PathBuf::from(stem))));
+    
+    for kind in &args.emit {
+        let ext = EMIT_KINDS.iter()
+            .find(|(k, _)| k == kind)
+            .map(|(_, ext)| *ext)
+            .with_context(|| format!(
+                "Unknown emit kind '{}' (expected one of: {})",
+                kind,
+                EMIT_KINDS.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(", ")
+            ))?;
+        
+        let path = match (&explicit, ext) {
+            (Some(out), _) if args.emit.len() == 1 || ext.is_empty() => Some(out.clone()),
+            (_, "") => None,
+            _ => base.as_ref().map(|b| b.with_extension(ext)),
+        };
+        
+        match path {
+            Some(path) => cmd.args.push(format!("--emit={}={}", kind, path.display())),
+            None => cmd.args.push(format!("--emit={}", kind)),
+        }
+    }
+    
+    Ok(())
+}
+
+/// /Qasm-view: writes `<output>.s` beside the usual outputs, with line tables
+fn translate_asm_view(cmd: &mut RustcCommand, args: &Args) {
+    let base = cmd.artifact()
+        .or_else(|| cmd.input_files.first().and_then(|f| f.file_stem()).map(|stem| in_out_dir(args, PathBuf::from(stem))))
+        .unwrap_or_else(|| PathBuf::from("out"));
+    let asm = base.with_extension("s");
+    if cmd.args.iter().any(|a| a.starts_with("--emit=")) {
+        cmd.args.push(format!("--emit=asm={}", asm.display()));
+    } else {
+        cmd.args.push(format!("--emit=asm={},link", asm.display()));
+    }
+    // .loc 指令来自行号表（line-tables-only 自 1.71 起可用）
+    if !cmd.args.iter().any(|a| a.starts_with("-Cdebuginfo")) {
+        let level = if supports(&cmd.executable, 71) { "line-tables-only" } else { "1" };
+        cmd.args.push(format!("-Cdebuginfo={}", level));
+    }
+    cmd.asm_view = Some(AsmView {
+        asm,
+        function: args.asm_view.clone().filter(|f| !f.is_empty()),
+        report: args.asm_view_file.clone(),
+    });
+}
+
+/// 优先级：-o > /Fe > /Fo
+/// （/Fo<dir>/ 不是文件名，目录中的目标文件由 translate_output 命名）
+fn requested_output(args: &Args) -> Option<PathBuf> {
+    args.output.clone()
+        .or_else(|| args.msvc_exe.clone())
+        .or_else(|| args.msvc_obj.clone().filter(|fo| !is_dir_spec(fo)))
+        .map(|out| in_out_dir(args, out))
+}
+
+/// Places a relative artifact path under /Qoutdir, if one was given
+fn in_out_dir(args: &Args, path: PathBuf) -> PathBuf {
+    match &args.out_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path,
+    }
+}
+
+fn translate_output(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    // 显式 emit 类型已包含各自的输出路径
+    if args.emit.is_empty() {
+        if let Some(out) = requested_output(args) {
+            cmd.output = Some(out);
+        } else if let Some(first) = link_only_inputs(args).and_then(|objs| objs.first().copied()) {
+        // 链接模式：与 cl 一致，以第一个输入命名
+            let stem = first.file_stem()
+                .context("Invalid input filename")?;
+            let exe_name = format!("{}{}", stem.to_string_lossy(), std::env::consts::EXE_SUFFIX);
+            cmd.output = Some(in_out_dir(args, PathBuf::from(exe_name)));
+        } else if let Some(input) = single_rust_input(args).filter(|_| args.compile_only) {
+        // 单文件编译模式：/Fo<dir>/ 与多个输入时一样放入该目录
+            input.file_stem().context("Invalid input filename")?;
+            cmd.output = Some(match args.msvc_obj.as_ref().filter(|fo| is_dir_spec(fo)) {
+                Some(dir) => object_in(dir, input),
+                None => in_out_dir(args, object_in(Path::new(""), input)),
+            });
+        }
+    }
+    
+    // 其余产物（pdb、dep-info 等）由 rustc 按默认名称放入输出目录
+    if let Some(dir) = &args.out_dir {
+        if cmd.output.is_none() {
+            cmd.args.push(format!("--out-dir={}", dir.display()));
+        }
+    }
+    
+    Ok(())
+}
+
+/// The only `.rs` input (C and C++ inputs beside it are compiled separately)
+fn single_rust_input(args: &Args) -> Option<&PathBuf> {
+    let mut sources = args.files.iter().filter(|f| f.extension().is_some_and(|e| e == "rs"));
+    let first = sources.next()?;
+    (sources.next().is_none() && args.files.iter().all(|f| f == first || cc::is_source(f))).then_some(first)
+}
+
+/// Object files and archives that are handed to the linker
+fn is_link_input(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("o" | "obj" | "a" | "lib" | "rlib" | "res")
+    )
+}
+
+/// Returns the inputs when the invocation is a pure link step (no .rs sources)
+fn link_only_inputs(args: &Args) -> Option<Vec<&PathBuf>> {
+    if args.compile_only || args.files.iter().any(|f| f.extension().is_some_and(|e| e == "rs")) {
+        return None;
+    }
+    let objs: Vec<&PathBuf> = args.files.iter().filter(|f| is_link_input(f) || cc::is_source(f)).collect();
+    (!objs.is_empty()).then_some(objs)
+}
+
+/// `libfoo-1a2b3c.rlib` -> `foo`
+fn rlib_crate_name(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let stem = stem.strip_prefix("lib").unwrap_or(&stem);
+    let name = stem.split('-').next().unwrap_or(stem);
+    sanitize_crate_name(name)
+}
+
+fn translate_defines(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    for def in &args.defines {
+        if let Some((name, value)) = def.split_once('=') {
+            // --cfg feature="value"（rustc 要求值带引号；/DNAME="value" 已带引号）
+            cmd.args.push(format!("--cfg={}=\"{}\"", name, value.trim_matches('"')));
+        } else {
+            // --cfg feature
+            cmd.args.push(format!("--cfg={}", def));
+        }
+    }
+    
+    for undef in &args.undefines {
+        console::eprint(&format!("[icx-rustc] warning: /U{} not fully supported in Rust", undef));
+    }
+    
+    Ok(())
+}
+
+/// /W1: severe warnings only, without the unused and naming-style lints
+const W1_LINTS: &[&str] = &["-Aunused", "-Anonstandard_style", "-Wunused_must_use"];
+
+/// /W2: rustc's defaults without the naming-style lints
+const W2_LINTS: &[&str] = &["-Anonstandard_style"];
+
+/// /W4 and /Wall: rustc's defaults plus idiom and pedantic lints
+const W4_LINTS: &[&str] = &[
+    "-Wrust_2018_idioms",
+    "-Wunused_qualifications",
+    "-Wunused_import_braces",
+    "-Wunused_lifetimes",
+    "-Wunused_macro_rules",
+    "-Wtrivial_casts",
+    "-Wtrivial_numeric_casts",
+    "-Wunreachable_pub",
+    "-Wmeta_variable_misuse",
+    "-Wlet_underscore_drop",
+];
+
+fn translate_warnings(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    // 明确指定 /Qcolor 时 rustc 自身的输出（如 --### 脚本重放）与之一致
+    match args.color {
+        ColorChoice::Always => cmd.args.push("--color=always".to_string()),
+        ColorChoice::Never => cmd.args.push("--color=never".to_string()),
+        ColorChoice::Auto => {}
+    }
+    
+    if args.wx {
+        cmd.args.push("-Dwarnings".to_string());
+    }
+    
+    // 各级别展开为具体的 lint 组，后出现的标志覆盖前面的
+    if let Some(l) = &args.warn_level {
+        let lints: &[&str] = match l.as_str() {
+            "0" => &["-Awarnings"],
+            "1" => W1_LINTS,
+            "2" => W2_LINTS,
+            "3" => &[],
+            "4" | "all" => W4_LINTS,
+            other => {
+                console::eprint(&format!("[icx-rustc] warning: unknown warning level /W{}, using /W3", other));
+                &[]
+            }
+        };
+        cmd.args.extend(lints.iter().map(|l| l.to_string()));
+    }
+    
+    // 抑制文件：全局条目交给 rustc，按文件的条目由 DiagnosticReporter 过滤
+    if let Some(path) = &args.diag_suppress_file {
+        let file = suppress::load(path)?;
+        cmd.args.extend(file.lints.iter().map(|lint| format!("-A{}", lint)));
+        cmd.suppress = file.scoped;
+    }
+    
+    // /wd /we：ICX 编号或 lint 名称，放在 -Dwarnings 等之后以覆盖其设置
+    for (specs, flag, option) in [(&args.disable_diags, "-A", "/wd"), (&args.error_diags, "-D", "/we")] {
+        for spec in specs {
+            match numbering::resolve(spec) {
+                Some(DiagId::Lint(lint)) => cmd.args.push(format!("{}{}", flag, lint)),
+                Some(DiagId::Error(code)) if option == "/wd" => console::eprint(&format!(
+                    "[icx-rustc] warning: {}{}: {} is an error and cannot be disabled",
+                    option, spec, code
+                )),
+                Some(DiagId::Error(_)) => {}
+                None => anyhow::bail!("Unknown diagnostic '{}' in {}", spec, option),
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+fn translate_linking(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    // 每个 /link 值是一个链接器参数；-Clink-args 按空白拆分，含空格的路径会被拆开
+    cmd.args.extend(args.link_args.iter().map(|arg| format!("-Clink-arg={}", arg)));
+    Ok(())
+}
+
+/// /Qlinker:<name>: the linker, in the form the target's toolchain takes it
+///
+/// MSVC targets get `-Clinker` (lld is the toolchain's rust-lld, else
+/// lld-link from PATH); targets linked through cc get `-fuse-ld=<name>`,
+/// which needs `ld.<name>` on PATH. Without an ld.lld there, the
+/// toolchain's rust-lld is used through its gcc-ld wrapper.
+fn translate_linker(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(name) = &args.linker else {
+        return Ok(());
+    };
+    let target = effective_target(cmd);
+    let on_path = |program: &str| which::which(program).is_ok();
+    let (msvc, apple) = (target.contains("-msvc"), target.contains("-apple-"));
+    let elf = !apple && !target.contains("-windows");
+    
+    let words = match name.as_str() {
+        _ if target.starts_with("wasm") => {
+            console::eprint("[icx-rustc] warning: /Qlinker: wasm targets always link with rust-lld, ignored");
+            return Ok(());
+        }
+        "lld" if msvc => match rust_lld(cmd) {
+            Some(_) => vec!["-Clinker=rust-lld".to_string()],
+            None if on_path("lld-link") => vec!["-Clinker=lld-link".to_string()],
+            None => anyhow::bail!("/Qlinker:lld needs lld-link on PATH (or the toolchain's rust-lld)"),
+        },
+        "link" if msvc => vec!["-Clinker=link.exe".to_string()],
+        "link" => anyhow::bail!("/Qlinker:link only links MSVC targets (this is {})", target),
+        _ if msvc => anyhow::bail!("/Qlinker:{} does not link MSVC targets (use lld or link)", name),
+        "lld" => {
+            let program = if apple { "ld64.lld" } else { "ld.lld" };
+            // 工具链自带的 gcc-ld/ld.lld 调用 rust-lld（与 -Clink-self-contained=+linker 相同）
+            let wrapper = rust_lld(cmd)
+                .map(|lld| lld.with_file_name("gcc-ld"))
+                .filter(|dir| !apple && dir.join(format!("ld.lld{}", std::env::consts::EXE_SUFFIX)).is_file());
+            match wrapper {
+                _ if on_path(program) => vec!["-Clink-arg=-fuse-ld=lld".to_string()],
+                Some(dir) => vec!["-Clink-arg=-fuse-ld=lld".to_string(), format!("-Clink-arg=-B{}", dir.display())],
+                None => anyhow::bail!("/Qlinker:lld needs {} on PATH (or the toolchain's rust-lld)", program),
+            }
+        }
+        "mold" | "gold" if !elf => anyhow::bail!("/Qlinker:{} only links ELF targets (this is {})", name, target),
+        "bfd" if apple => anyhow::bail!("/Qlinker:bfd does not link Apple targets"),
+        _ => {
+            let program = format!("ld.{}", name);
+            if !on_path(&program) {
+                anyhow::bail!("/Qlinker:{} needs {} on PATH", name, program);
+            }
+            vec![format!("-Clink-arg=-fuse-ld={}", name)]
+        }
+    };
+    
+    if args.verbose {
+        console::eprint(&format!("[icx-rustc] linker: {} ({})", name, words.join(" ")));
+    }
+    cmd.args.extend(words);
+    Ok(())
+}
+
+/// `rust-lld` of the toolchain (`<sysroot>/lib/rustlib/<host>/bin`)
+fn rust_lld(cmd: &RustcCommand) -> Option<PathBuf> {
+    let sysroot = query_sysroot(&cmd.executable)?;
+    let host = query_host_triple(&cmd.executable)?;
+    let path = Path::new(&sysroot).join("lib/rustlib").join(host).join("bin")
+        .join(format!("rust-lld{}", std::env::consts::EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
+
+/// What `--crate-type` accepts, alone or as a comma list
+const CRATE_TYPES: &[&str] = &["bin", "lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+
+/// The kinds of the last `--crate-type` (`staticlib,cdylib` is two)
+pub fn crate_types(cmd: &RustcCommand) -> Vec<&str> {
+    crate_type(cmd).map(|t| t.split(',').collect()).unwrap_or_default()
+}
+
+/// The last `--crate-type`, including one passed through after `--`
+pub fn crate_type(cmd: &RustcCommand) -> Option<&str> {
+    let mut found = None;
+    let mut words = cmd.args.iter();
+    while let Some(word) = words.next() {
+        if word == "--crate-type" {
+            found = words.next().map(String::as_str);
+        } else if let Some(value) = word.strip_prefix("--crate-type=") {
+            found = Some(value);
+        }
+    }
+    found
+}
+
+/// Windows DLLs: the import library consumers link against
+///
+/// Named like link.exe's default, `<name>.lib` next to the DLL (MinGW:
+/// `lib<name>.dll.a`); /IMPLIB:<file> chooses another name. rustc's own
+/// `.dll.lib` / `.dll.a` in its temporary location is replaced by the
+/// later linker argument.
+fn translate_import_library(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let dll = crate_types(cmd).iter().any(|k| matches!(*k, "cdylib" | "dylib"));
+    let target = if dll || args.implib.is_some() { effective_target(cmd) } else { String::new() };
+    if !dll || !target.contains("-windows") {
+        if args.implib.is_some() {
+            console::eprint("[icx-rustc] warning: /IMPLIB only applies to DLLs built for Windows targets, ignored");
+        }
+        return Ok(());
+    }
+    
+    let msvc = target.contains("-msvc");
+    let implib = match &args.implib {
+        Some(path) => in_out_dir(args, path.clone()),
+        None => {
+            let dll = cmd.artifact_of(&["cdylib", "dylib"]).context("The DLL's name cannot be predicted with --emit; use /IMPLIB")?;
+            let stem = dll.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let name = if msvc { format!("{}.lib", stem) } else { format!("lib{}.dll.a", stem) };
+            dll.with_file_name(name)
+        }
+    };
+    cmd.args.push(if msvc {
+        format!("-Clink-arg=/IMPLIB:{}", implib.display())
+    } else {
+        format!("-Clink-arg=-Wl,--out-implib={}", implib.display())
+    });
+    cmd.implib = Some(implib);
+    Ok(())
+}
+
+fn translate_rust_specific(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    if let Some(edition) = &args.edition {
+        match edition.as_str() {
+            "2021" => require_version(&cmd.executable, "--edition=2021", 56)?,
+            "2024" => require_version(&cmd.executable, "--edition=2024", 85)?,
+            _ => {}
+        }
+        cmd.args.push(format!("--edition={}", edition));
+    }
+    
+    let mut output_name = None;
+    if let Some(crate_type) = &args.crate_type {
+        let kinds: Vec<&str> = crate_type.split(',').map(str::trim).filter(|k| !k.is_empty()).collect();
+        for kind in &kinds {
+            if !CRATE_TYPES.contains(kind) {
+                match did_you_mean(kind, CRATE_TYPES.iter().copied()) {
+                    Some(suggestion) => anyhow::bail!("Unknown crate type '{}' (did you mean '{}'?)", kind, suggestion),
+                    None => anyhow::bail!("Unknown crate type '{}' (expected {})", kind, CRATE_TYPES.join(", ")),
+                }
+            }
+        }
+        cmd.args.push(format!("--crate-type={}", kinds.join(",")));
+        // 多种类型时 rustc 忽略 -o：改用它的目录作为 --out-dir、文件名作为 crate 名
+        if kinds.len() > 1 {
+            if let Some(out) = cmd.output.take() {
+                let dir = out.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                cmd.args.push(format!("--out-dir={}", dir.display()));
+                let stem = out.file_stem().unwrap_or_default().to_string_lossy();
+                let stem = if out.extension().is_some() { stem.strip_prefix("lib").unwrap_or(&stem) } else { &stem };
+                output_name = Some(sanitize_crate_name(stem));
+            }
+        }
+    } else if args.ar {
+        cmd.args.push("--crate-type=staticlib".to_string());
+    }
+    
+    // 显式指定优先，否则从首个 .rs 文件名推导并清理非法字符
+    if let Some(name) = args.crate_name.as_ref().or(output_name.as_ref()) {
+        cmd.args.push(format!("--crate-name={}", name));
+    } else if let Some(stem) = args.files.iter()
+        .find(|f| f.extension().is_some_and(|e| e == "rs"))
+        .and_then(|f| f.file_stem())
+    {
+        let stem = stem.to_string_lossy();
+        let name = sanitize_crate_name(&stem);
+        if name != stem {
+            cmd.args.push(format!("--crate-name={}", name));
+        }
+    }
+    
+    if let Some(target) = &args.target {
+        let triple = resolve_target(&cmd.executable, target)?;
+        cmd.args.push(format!("--target={}", triple));
+        cmd.target = Some(triple);
+    }
+    wasm::translate(cmd, args)?;
+    translate_linker(cmd, args)?;
+    
+    translate_sysroot(cmd, args)?;
+    translate_unstable(cmd, args)?;
+    
+    if args.repro {
+        translate_reproducible(cmd, args)?;
+    }
+    
+    if let Some(mode) = &args.split_debug {
+        translate_split_debug(cmd, mode)?;
+    }
+    
+    if args.coverage {
+        require_version(&cmd.executable, "/Qcov", 60)?;
+        cmd.args.push("-Cinstrument-coverage".to_string());
+    }
+    
+    if let Some(strip) = &args.strip {
+        require_version(&cmd.executable, "/Qstrip", 59)?;
+        match strip.as_str() {
+            "symbols" | "debuginfo" => cmd.args.push(format!("-Cstrip={}", strip)),
+            other => anyhow::bail!(
+                "Unknown strip mode '{}' (expected symbols or debuginfo)", other
+            ),
+        }
+    }
+    
+    if args.time {
+        translate_time_report(cmd);
+    }
+    
+    cmd.args.push("-Ccodegen-units=1".to_string()); // 类似 IPO
+    cmd.args.push("-Cpanic=abort".to_string());     // 类似 MSVC
+    
+    Ok(())
+}
+
+/// /Qunstable:<flag>: `-Z<flag>`, which only nightly compilers accept
+///
+/// With /Qunstable-bootstrap a stable or beta compiler accepts them as well,
+/// through `RUSTC_BOOTSTRAP=1`; that toolchain makes no promises about them,
+/// hence the warning.
+fn translate_unstable(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    if args.unstable_bootstrap && !RustcVersion::detect(&cmd.executable).is_some_and(|v| v.nightly) {
+        console::eprint("[icx-rustc] warning: /Qunstable-bootstrap sets RUSTC_BOOTSTRAP=1; -Z options may change or break with any release");
+        cmd.env_vars.push(("RUSTC_BOOTSTRAP".to_string(), "1".to_string()));
+    }
+    for flag in &args.unstable {
+        let flag = flag.strip_prefix("-Z").unwrap_or(flag);
+        if flag.is_empty() {
+            anyhow::bail!("/Qunstable needs a -Z option (e.g. /Qunstable:sanitizer=address)");
+        }
+        require_nightly(cmd, &format!("/Qunstable:{}", flag))?;
+        cmd.args.push(format!("-Z{}", flag));
+    }
+    
+    Ok(())
+}
+
+/// -v /Qstrip: reports how much `-Cstrip` saved, against a second build
+/// without it into a temporary directory, so the binary itself is the one
+/// a build without -v links
+///
+/// MSVC targets are left out, as their symbols and debug info are in the
+/// PDB; so are units with several crate types.
+fn translate_strip_report(cmd: &mut RustcCommand, args: &Args, target: &str) {
+    if args.strip.is_none() || !args.verbose || target.contains("-msvc") || crate_types(cmd).len() > 1 {
+        return;
+    }
+    cmd.strip_report = cmd.artifact();
+}
+
+/// /Qtime: `-Ztime-passes`, which only nightly compilers accept
+fn translate_time_report(cmd: &mut RustcCommand) {
+    if let Err(e) = require_nightly(cmd, "/Qtime") {
+        console::eprint(&format!("[icx-rustc] warning: {}, ignored", e));
+        return;
+    }
+    
+    cmd.args.push("-Ztime-passes".to_string());
+    cmd.time_report = true;
+}
+
+/// Release and channel of a compiler, from the (cached) `rustc -vV`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RustcVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Accepts `-Z` options (a nightly or locally built toolchain)
+    pub nightly: bool,
+}
+
+impl RustcVersion {
+    /// None when the compiler cannot be run or answers in an unknown format
+    pub fn detect(rustc: &str) -> Option<Self> {
+        let output = rustc_query(rustc, &["-vV"])?;
+        let release = output.lines().find_map(|l| l.strip_prefix("release: "))?.trim();
+        let (number, channel) = release.split_once('-').unwrap_or((release, ""));
+        let mut parts = number.split('.').map(|p| p.parse::<u32>().ok());
+        let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0));
+        Some(Self { major, minor, patch, nightly: channel.starts_with("nightly") || channel.starts_with("dev") })
+    }
+    
+    /// Whether this is 1.<minor> or later
+    pub fn at_least(&self, minor: u32) -> bool {
+        self.major > 1 || self.minor >= minor
+    }
+}
+
+impl std::fmt::Display for RustcVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}{}", self.major, self.minor, self.patch, if self.nightly { "-nightly" } else { "" })
+    }
+}
+
+/// Whether the compiler is 1.<minor> or later; assumed when it cannot be queried
+pub fn supports(rustc: &str, minor: u32) -> bool {
+    RustcVersion::detect(rustc).is_none_or(|v| v.at_least(minor))
+}
+
+/// Fails before rustc would reject an option it does not know yet
+pub fn require_version(rustc: &str, what: &str, minor: u32) -> Result<()> {
+    match RustcVersion::detect(rustc) {
+        Some(version) if !version.at_least(minor) => anyhow::bail!(
+            "{} requires rustc >= 1.{} (found {}; try rustup update)", what, minor, version
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Fails for `-Z` options on a stable or beta compiler, unless it runs
+/// with `RUSTC_BOOTSTRAP=1` (/Qunstable-bootstrap or the environment)
+pub fn require_nightly(cmd: &RustcCommand, what: &str) -> Result<()> {
+    let bootstrap = cmd.env_vars.iter().any(|(k, v)| k == "RUSTC_BOOTSTRAP" && v == "1")
+        || std::env::var("RUSTC_BOOTSTRAP").is_ok_and(|v| v == "1");
+    match RustcVersion::detect(&cmd.executable) {
+        Some(version) if !version.nightly && !bootstrap => anyhow::bail!(
+            "{} requires a nightly rustc (found {}; try +nightly or /Qunstable-bootstrap)", what, version
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn translate_sysroot(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    if let Some(sysroot) = &args.sysroot {
+        if !sysroot.is_dir() {
+            anyhow::bail!("Sysroot '{}' is not a directory", sysroot.display());
+        }
+        cmd.args.push(format!("--sysroot={}", sysroot.display()));
+        
+        if args.verbose {
+            console::eprint(&format!("[icx-rustc] sysroot: {} (override)", sysroot.display()));
+        }
+    } else if args.verbose {
+        if let Some(sysroot) = query_sysroot(&cmd.executable) {
+            console::eprint(&format!("[icx-rustc] sysroot: {} (default)", sysroot));
+        }
+    }
+    
+    Ok(())
+}
+
+fn translate_reproducible(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    // 路径重映射：后出现的规则优先，当前目录通常位于 home 之下
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    if let Some(home) = home.filter(|h| !h.is_empty()) {
+        cmd.args.push(format!("--remap-path-prefix={}=~", Path::new(&home).display()));
+    }
+    let cwd = std::env::current_dir().context("Failed to read current directory")?;
+    cmd.args.push(format!("--remap-path-prefix={}=.", cwd.display()));
+    
+    // 元数据哈希只依赖 crate 名，不依赖检出路径
+    let crate_name = args.crate_name.clone()
+        .or_else(|| args.files.iter()
+            .find(|f| f.extension().is_some_and(|e| e == "rs"))
+            .and_then(|f| f.file_stem())
+            .map(|stem| sanitize_crate_name(&stem.to_string_lossy())))
+        .unwrap_or_else(|| "icx".to_string());
+    cmd.args.push(format!("-Cmetadata={}", crate_name));
+    
+    // 链接器时间戳 / UUID
+    let triple = effective_target(cmd);
+    if triple.contains("msvc") {
+        cmd.args.push("-Clink-arg=/Brepro".to_string());
+    } else if triple.contains("apple") {
+        cmd.args.push("-Clink-arg=-Wl,-no_uuid".to_string());
+    } else {
+        cmd.args.push("-Clink-arg=-Wl,--build-id=sha1".to_string());
+    }
+    
+    Ok(())
+}
+
+fn translate_split_debug(cmd: &mut RustcCommand, mode: &str) -> Result<()> {
+    let triple = effective_target(cmd);
+    // macOS 上自 1.51 起稳定，其他平台自 1.65 起
+    require_version(&cmd.executable, "/Qsplit-debug", if triple.contains("apple") { 51 } else { 65 })?;
+    
+    // 默认值：macOS 生成 dSYM，Linux 生成 .dwp，Windows 生成 .pdb（均为 packed）
+    let mode = match mode {
+        "default" | "packed" => "packed",
+        "unpacked" if triple.contains("msvc") => {
+            console::eprint("[icx-rustc] warning: MSVC targets only support packed debug info (.pdb)");
+            "packed"
+        }
+        "unpacked" => "unpacked",
+        "off" => "off",
+        other => anyhow::bail!(
+            "Unknown split debug mode '{}' (expected packed, unpacked or off)", other
+        ),
+    };
+    
+    // 拆分需要先有调试信息
+    if mode != "off" && !cmd.args.iter().any(|a| a.starts_with("-Cdebuginfo")) {
+        cmd.args.push("-Cdebuginfo=2".to_string());
+    }
+    cmd.args.push(format!("-Csplit-debuginfo={}", mode));
+    
+    Ok(())
+}
+
+/// Target triple being compiled for (explicit --target or the host)
+fn effective_target(cmd: &RustcCommand) -> String {
+    cmd.target.clone()
+        .or_else(|| query_host_triple(&cmd.executable))
+        .unwrap_or_default()
+}
+
+/// Stdout of a successful informational rustc call (`-vV`, `--print ...`)
+///
+/// Results are kept for the life of the process, keyed by the compiler, the
+/// toolchain rustup would pick for it and, beyond `-vV` itself, the `-vV`
+/// output, so a name such as `rustc` is not answered for another toolchain.
+/// `--print` answers (target-list, cfg, target-features, sysroot) are also
+/// kept in the cache directory, keyed by the `rustc -vV` output, so later
+/// invocations pay only for `-vV`.
+pub fn rustc_query(rustc: &str, args: &[&str]) -> Option<String> {
+    static RESULTS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+    
+    let version = if args == ["-vV"] { String::new() } else { rustc_query(rustc, &["-vV"]).unwrap_or_default() };
+    let key = [rustc, &toolchain_env(), &version, &args.join("\0")].join("\0");
+    if let Some((_, stdout)) = RESULTS.lock().ok()?.iter().find(|(k, _)| *k == key) {
+        return Some(stdout.clone());
+    }
+    
+    let stored = (args.first() == Some(&"--print")).then(|| stored_query(rustc, args)).flatten();
+    if let Some(stdout) = stored.as_deref().and_then(read_stored_query) {
+        RESULTS.lock().ok()?.push((key, stdout.clone()));
+        return Some(stdout);
+    }
+    
+    let output = Command::new(rustc)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if let Some(path) = &stored {
+        write_stored_query(path, &stdout);
+    }
+    RESULTS.lock().ok()?.push((key, stdout.clone()));
+    Some(stdout)
+}
+
+/// What decides the toolchain behind a rustup proxy: `RUSTUP_TOOLCHAIN`,
+/// else the rust-toolchain.toml or override for the working directory
+fn toolchain_env() -> String {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+    [var("RUSTUP_TOOLCHAIN"), var("RUSTUP_HOME"), cwd].join("\0")
+}
+
+/// `<cache>/rustc/<hash of rustc -vV>/<hash of the query>`
+///
+/// `-Ctarget-cpu=native` answers also depend on the machine, which matters
+/// when the cache directory is on a shared home.
+fn stored_query(rustc: &str, args: &[&str]) -> Option<PathBuf> {
+    let version = rustc_query(rustc, &["-vV"])?;
+    let mut query = args.join("\0");
+    if args.iter().any(|a| a.ends_with("=native")) {
+        query.push_str(&format!("\0host={}", audit::host().unwrap_or_default()));
+    }
+    let dir = cache::hex(&Sha256::digest(version.as_bytes()));
+    let file = cache::hex(&Sha256::digest(query.as_bytes()));
+    Some(cache::cache_root().join("rustc").join(&dir[..16]).join(&file[..16]))
+}
+
+fn read_stored_query(path: &Path) -> Option<String> {
+    let stdout = std::fs::read_to_string(path).ok()?;
+    // 同版本的另一份工具链可能已被删除：sysroot 不在了就重新查询
+    let sysroot = Path::new(stdout.trim());
+    if sysroot.is_absolute() && !stdout.trim().contains('\n') && !sysroot.is_dir() {
+        return None;
+    }
+    Some(stdout)
+}
+
+/// Best effort: a cache that cannot be written only costs the next query
+fn write_stored_query(path: &Path, stdout: &str) {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    // 先写临时文件再改名，并行的调用不会读到写了一半的结果
+    let temp = dir.join(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+    let written = std::fs::create_dir_all(dir).is_ok() && std::fs::write(&temp, stdout).is_ok();
+    if !written || std::fs::rename(&temp, path).is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+}
+
+/// Host triple from `rustc -vV`
+fn query_host_triple(rustc: &str) -> Option<String> {
+    rustc_query(rustc, &["-vV"])?
+        .lines()
+        .find_map(|l| l.strip_prefix("host: "))
+        .map(|h| h.trim().to_string())
+}
+
+fn query_sysroot(rustc: &str) -> Option<String> {
+    rustc_query(rustc, &["--print", "sysroot"]).map(|s| s.trim().to_string())
+}
+
+/// 常用目标别名
+const TARGET_ALIASES: &[(&str, &str)] = &[
+    ("win64", "x86_64-pc-windows-msvc"),
+    ("win32", "i686-pc-windows-msvc"),
+    ("win-arm64", "aarch64-pc-windows-msvc"),
+    ("linux-x64", "x86_64-unknown-linux-gnu"),
+    ("linux-x86", "i686-unknown-linux-gnu"),
+    ("linux-arm64", "aarch64-unknown-linux-gnu"),
+    ("macos-x64", "x86_64-apple-darwin"),
+    ("macos-arm64", "aarch64-apple-darwin"),
+    ("musl", "x86_64-unknown-linux-musl"),
+    ("musl-arm64", "aarch64-unknown-linux-musl"),
+    ("wasm", "wasm32-unknown-unknown"),
+    ("wasi", "wasm32-wasip1"),
+];
+
+/// Expands a target alias and checks the triple against `rustc --print target-list`
+fn resolve_target(rustc: &str, target: &str) -> Result<String> {
+    if let Some((_, triple)) = TARGET_ALIASES.iter().find(|(alias, _)| *alias == target) {
+        return Ok(triple.to_string());
+    }
+    
+    // 自定义目标规格文件直接透传
+    if target.ends_with(".json") {
+        return Ok(target.to_string());
+    }
+    
+    // 无法查询时不做校验，交给 rustc 报错
+    let Some(known) = query_target_list(rustc) else {
+        return Ok(target.to_string());
+    };
+    
+    if known.iter().any(|t| t == target) {
+        return Ok(target.to_string());
+    }
+    
+    let candidates = known.iter()
+        .map(String::as_str)
+        .chain(TARGET_ALIASES.iter().map(|(alias, _)| *alias));
+    match did_you_mean(target, candidates) {
+        Some(suggestion) => anyhow::bail!(
+            "Unknown target '{}' (did you mean '{}'?)", target, suggestion
+        ),
+        None => anyhow::bail!(
+            "Unknown target '{}' (see `rustc --print target-list`)", target
+        ),
+    }
+}
+
+fn query_target_list(rustc: &str) -> Option<Vec<String>> {
+    Some(rustc_query(rustc, &["--print", "target-list"])?
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Turns a file stem into a valid crate name (`my-tool.v2` -> `my_tool_v2`)
+pub fn sanitize_crate_name(stem: &str) -> String {
+    let mut name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    
+    name
+}
+
+/// Lists the target features rustc enables for `-Ctarget-cpu=native`
+fn query_native_features(rustc: &str) -> Option<Vec<String>> {
+    Some(rustc_query(rustc, &["--print", "cfg", "-Ctarget-cpu=native"])?
+        .lines()
+        .filter_map(|l| l.strip_prefix("target_feature=\""))
+        .map(|f| f.trim_end_matches('"').to_string())
+        .collect())
+}
@@ -0,0 +1,158 @@
+use crate::cache;
+use crate::cc::{self, CStep};
+use crate::cli::Args;
+use crate::translator::{self, RustcCommand};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::path::PathBuf;
+
+/// /Qversion-info keys and their Windows `StringFileInfo` names
+const FIELDS: &[(&str, &str)] = &[
+    ("version", ""),
+    ("file-version", "FileVersion"),
+    ("product-version", "ProductVersion"),
+    ("product", "ProductName"),
+    ("company", "CompanyName"),
+    ("description", "FileDescription"),
+    ("copyright", "LegalCopyright"),
+];
+
+/// /Qversion-info:1.2.3,product=App,company=Acme: stamps version information into the binary
+///
+/// `version` (or a bare version) sets both the file and the product
+/// version. Windows targets get a VERSIONINFO resource, compiled like a
+/// `.rc` input; ELF binaries get one `Key=Value` string per field in
+/// `.comment` (`readelf -p .comment`), Mach-O binaries an embedded
+/// `__TEXT,__info_plist` as `otool -P` shows it. The object carrying
+/// them is compiled by rustc itself.
+pub fn translate(cmd: &mut RustcCommand, args: &Args, target: &str) -> Result<()> {
+    if args.version_info.is_empty() || args.compile_only {
+        return Ok(());
+    }
+    let fields = parse(&args.version_info)?;
+    let name = cmd.artifact_of(&["bin", "cdylib", "dylib"])
+        .and_then(|a| a.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "app".to_string());
+    
+    if target.contains("-windows") {
+        let file = cmd.artifact_of(&["bin", "cdylib", "dylib"]).and_then(|a| a.file_name().map(|f| f.to_string_lossy().into_owned()));
+        let script = cache::generated_file("version-info", &resource(&fields, file.as_deref(), dll(cmd)), &format!("{}-version.rc", name))?;
+        return cc::resources(cmd, args, &[script], target);
+    }
+    
+    let (section, text) = if target.contains("-apple-") {
+        ("__TEXT,__info_plist", plist(&fields))
+    } else {
+        (".comment", fields.iter().map(|(k, v)| format!("{}={}\0", k, v)).collect())
+    };
+    let source = cache::generated_file("version-info", &object_source(section, &text), &format!("{}_version.rs", name))?;
+    let output = cc::object_path(args, &PathBuf::from(format!("{}-version", name)), target.contains("-msvc"));
+    let mut words = vec![
+        "--crate-type=lib".to_string(),
+        "--emit=obj".to_string(),
+        "--crate-name=icx_version_info".to_string(),
+        "-Cpanic=abort".to_string(),
+    ];
+    if let Some(target) = &cmd.target {
+        words.push(format!("--target={}", target));
+    }
+    words.extend([source.display().to_string(), "-o".to_string(), output.display().to_string()]);
+    cmd.c_steps.push(CStep { program: PathBuf::from(&cmd.executable), args: words, source, output: output.clone() });
+    cmd.link_inputs.push(output);
+    Ok(())
+}
+
+/// Fields by their Windows names, in a fixed order
+fn parse(specs: &[String]) -> Result<IndexMap<&'static str, String>> {
+    let mut fields = IndexMap::new();
+    for spec in specs {
+        let (key, value) = spec.split_once('=').unwrap_or(("version", spec.as_str()));
+        let &(key, name) = FIELDS.iter()
+            .find(|(k, _)| *k == key.trim())
+            .with_context(|| format!(
+                "Unknown /Qversion-info field '{}' (expected one of: {})",
+                key,
+                FIELDS.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(", ")
+            ))?;
+        let value = value.trim().to_string();
+        if key == "version" {
+            fields.entry("FileVersion").or_insert_with(|| value.clone());
+            fields.entry("ProductVersion").or_insert(value);
+        } else {
+            fields.insert(name, value);
+        }
+    }
+    // 固定顺序，使相同的选项生成相同的文件
+    fields.sort_by(|a, _, b, _| {
+        let index = |k: &str| FIELDS.iter().position(|(_, n)| *n == k);
+        index(a).cmp(&index(b))
+    });
+    Ok(fields)
+}
+
+fn dll(cmd: &RustcCommand) -> bool {
+    translator::crate_types(cmd).iter().any(|k| matches!(*k, "cdylib" | "dylib"))
+}
+
+/// `1.2.3` -> `1,2,3,0` for the binary FILEVERSION / PRODUCTVERSION
+fn numeric(version: &str) -> String {
+    let mut parts: Vec<u16> = version.split(['.', '-', '+'])
+        .map_while(|p| p.parse().ok())
+        .take(4)
+        .collect();
+    parts.resize(4, 0);
+    parts.iter().map(u16::to_string).collect::<Vec<_>>().join(",")
+}
+
+fn resource(fields: &IndexMap<&str, String>, file: Option<&str>, dll: bool) -> String {
+    let quote = |v: &str| v.replace('"', "\"\"");
+    let file_version = fields.get("FileVersion").map_or("0,0,0,0".to_string(), |v| numeric(v));
+    let product_version = fields.get("ProductVersion").map_or("0,0,0,0".to_string(), |v| numeric(v));
+    let mut rc = format!(
+        "1 VERSIONINFO\nFILEVERSION {}\nPRODUCTVERSION {}\nFILEOS 0x40004\nFILETYPE {}\nBEGIN\n  BLOCK \"StringFileInfo\"\n  BEGIN\n    BLOCK \"040904B0\"\n    BEGIN\n",
+        file_version,
+        product_version,
+        if dll { "0x2" } else { "0x1" }
+    );
+    for (name, value) in fields {
+        rc.push_str(&format!("      VALUE \"{}\", \"{}\"\n", name, quote(value)));
+    }
+    if let Some(file) = file {
+        rc.push_str(&format!("      VALUE \"OriginalFilename\", \"{}\"\n", quote(file)));
+    }
+    rc.push_str("    END\n  END\n  BLOCK \"VarFileInfo\"\n  BEGIN\n    VALUE \"Translation\", 0x409, 1200\n  END\nEND\n");
+    rc
+}
+
+fn plist(fields: &IndexMap<&str, String>) -> String {
+    let escape = |v: &str| v.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n",
+    ));
+    for (name, value) in fields {
+        let key = match *name {
+            "FileVersion" => "CFBundleVersion",
+            "ProductVersion" => "CFBundleShortVersionString",
+            "ProductName" => "CFBundleName",
+            "LegalCopyright" => "NSHumanReadableCopyright",
+            "FileDescription" => "CFBundleGetInfoString",
+            _ => continue,
+        };
+        xml.push_str(&format!("  <key>{}</key>\n  <string>{}</string>\n", key, escape(value)));
+    }
+    xml.push_str("</dict>\n</plist>\n");
+    xml
+}
+
+/// A `#![no_std]` crate whose only item is the data in `section`
+fn object_source(section: &str, text: &str) -> String {
+    let bytes = text.as_bytes();
+    format!(
+        "#![no_std]\n#[used]\n#[link_section = \"{}\"]\nstatic VERSION_INFO: [u8; {}] = *b\"{}\";\n",
+        section,
+        bytes.len(),
+        bytes.iter().flat_map(|b| std::ascii::escape_default(*b)).map(char::from).collect::<String>()
+    )
+}
@@ -0,0 +1,113 @@
+use crate::cc::CStep;
+use crate::cli::Args;
+use crate::translator::RustcCommand;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+/// What /Qwasm[:wasi][,bindgen][,opt] asked for
+struct Mode {
+    wasi: bool,
+    bindgen: bool,
+    opt: bool,
+}
+
+fn mode(spec: &str) -> Result<Mode> {
+    let mut mode = Mode { wasi: false, bindgen: false, opt: false };
+    for word in spec.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        match word.to_ascii_lowercase().as_str() {
+            "unknown" | "web" => mode.wasi = false,
+            "wasi" => mode.wasi = true,
+            "bindgen" => mode.bindgen = true,
+            "opt" => mode.opt = true,
+            _ => bail!("Unknown /Qwasm option '{}' (expected wasi, bindgen or opt)", word),
+        }
+    }
+    if mode.wasi && mode.bindgen {
+        bail!("/Qwasm: wasm-bindgen needs wasm32-unknown-unknown, not WASI");
+    }
+    Ok(mode)
+}
+
+/// /Qwasm: target, crate type and code generation defaults for WebAssembly
+///
+/// Selects `wasm32-unknown-unknown` with a `cdylib` (the exported
+/// functions are the module's interface), or `wasm32-wasip1` with /Qwasm:wasi,
+/// where `main` stays the entry point. --target and --crate-type still
+/// win. Panics abort already; fat LTO is added at every level, since it
+/// is what keeps the standard library out of small modules.
+pub fn translate(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(spec) = &args.wasm else {
+        return Ok(());
+    };
+    let mode = mode(spec)?;
+    if cmd.target.is_none() {
+        let triple = if mode.wasi { "wasm32-wasip1" } else { "wasm32-unknown-unknown" };
+        cmd.args.push(format!("--target={}", triple));
+        cmd.target = Some(triple.to_string());
+    }
+    if args.crate_type.is_none() && !mode.wasi {
+        cmd.args.push("--crate-type=cdylib".to_string());
+    }
+    if !cmd.args.iter().any(|a| a.starts_with("-Clto")) {
+        cmd.args.push("-Clto=fat".to_string());
+    }
+    Ok(())
+}
+
+/// /Qwasm:bindgen and /Qwasm:opt: wasm-bindgen and wasm-opt runs on the module
+///
+/// wasm-bindgen (or ICX_WASM_BINDGEN) writes the JavaScript glue and the
+/// module it binds to `pkg/` beside the output, for `--target web`;
+/// wasm-opt (or ICX_WASM_OPT) then optimizes that module, or the output
+/// itself, in place.
+pub fn post_steps(cmd: &mut RustcCommand, args: &Args) -> Result<()> {
+    let Some(spec) = &args.wasm else {
+        return Ok(());
+    };
+    let mode = mode(spec)?;
+    if args.compile_only || !mode.bindgen && !mode.opt {
+        return Ok(());
+    }
+    let module = cmd.artifact().context("The module's name cannot be predicted with --emit")?;
+    
+    let mut optimized = module.clone();
+    if mode.bindgen {
+        let program = tool("ICX_WASM_BINDGEN", "wasm-bindgen", "/Qwasm:bindgen needs wasm-bindgen on PATH (cargo install wasm-bindgen-cli) or ICX_WASM_BINDGEN")?;
+        let dir = module.with_file_name("pkg");
+        let stem = module.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        optimized = dir.join(format!("{}_bg.wasm", stem));
+        let args = vec![
+            "--target".to_string(),
+            "web".to_string(),
+            "--out-dir".to_string(),
+            dir.display().to_string(),
+            module.display().to_string(),
+        ];
+        cmd.wasm.push(CStep { program, args, source: module.clone(), output: dir });
+    }
+    if mode.opt {
+        let program = tool("ICX_WASM_OPT", "wasm-opt", "/Qwasm:opt needs wasm-opt (binaryen) on PATH or ICX_WASM_OPT")?;
+        let level = match cmd.args.iter().rev().find_map(|a| a.strip_prefix("-Copt-level=")) {
+            Some("0") => "-O0",
+            Some("1" | "s" | "z") => "-Oz",
+            Some("3") => "-O3",
+            _ => "-O2",
+        };
+        let mut args = vec![level.to_string()];
+        // rustc 默认启用的 WebAssembly 特性，wasm-opt 需要显式允许
+        args.extend(FEATURES.iter().map(|f| format!("--enable-{}", f)));
+        args.extend([optimized.display().to_string(), "-o".to_string(), optimized.display().to_string()]);
+        cmd.wasm.push(CStep { program, args, source: optimized.clone(), output: optimized });
+    }
+    Ok(())
+}
+
+/// Features rustc enables for wasm32 by default
+const FEATURES: &[&str] = &["bulk-memory", "multivalue", "mutable-globals", "nontrapping-float-to-int", "reference-types", "sign-ext"];
+
+fn tool(variable: &str, name: &str, missing: &str) -> Result<PathBuf> {
+    match std::env::var_os(variable) {
+        Some(program) => Ok(PathBuf::from(program)),
+        None => which::which(name).context(missing.to_string()),
+    }
+}
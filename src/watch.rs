@@ -0,0 +1,80 @@
+use crate::cache;
+use crate::interrupt;
+use crate::translator::RustcCommand;
+use anyhow::Result;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Rebuilds whenever one of the sources changes (--watch)
+///
+/// The watched set is every crate root, extern and link input plus the
+/// module files rustc reports in dep-info, refreshed after each build.
+/// Runs until the process is interrupted, then returns the interrupted status.
+pub fn run<F>(commands: &[RustcCommand], mut build: F) -> Result<i32>
+where
+    F: FnMut() -> Result<i32>,
+{
+    let dep_file = std::env::temp_dir().join(format!("icx-rustc-watch-{}.d", std::process::id()));
+    let mut files: Vec<PathBuf> = Vec::new();
+    
+    loop {
+        let code = build()?;
+        if interrupt::requested() {
+            let _ = std::fs::remove_file(&dep_file);
+            return Ok(code);
+        }
+        
+        // dep-info 失败（例如解析错误）时沿用上一次的文件列表
+        for cmd in commands {
+            let sources = cache::source_files(cmd, &dep_file).unwrap_or_default();
+            let externs = cmd.args.iter()
+                .filter_map(|a| a.strip_prefix("--extern="))
+                .filter_map(|spec| spec.split_once('='))
+                .map(|(_, path)| PathBuf::from(path));
+            // C/C++ 目标文件与绑定每次构建都会重写，监视其源文件
+            let objects: Vec<&PathBuf> = cmd.c_steps.iter().map(|c| &c.output).collect();
+            let watched = cmd.input_files.iter().cloned()
+                .chain(sources)
+                .chain(externs)
+                .chain(cmd.link_inputs.iter().filter(|f| !objects.contains(f)).cloned())
+                .chain(cmd.c_steps.iter().map(|c| c.source.clone()))
+                .map(|f| cmd.resolve(&f));
+            for file in watched {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        
+        eprintln!(
+            "{} watching {} file(s) for changes (Ctrl+C to stop)",
+            "[icx-rustc]".bright_blue().bold(),
+            files.len()
+        );
+        
+        let snapshot: Vec<Option<SystemTime>> = files.iter().map(|f| modified(f)).collect();
+        let changed = loop {
+            std::thread::sleep(Duration::from_millis(300));
+            if interrupt::requested() {
+                let _ = std::fs::remove_file(&dep_file);
+                return Ok(interrupt::exit_status());
+            }
+            if let Some(i) = (0..files.len()).find(|&i| modified(&files[i]) != snapshot[i]) {
+                break i;
+            }
+        };
+        
+        // 编辑器保存通常分多次写入，稍等片刻再编译
+        std::thread::sleep(Duration::from_millis(100));
+        eprintln!(
+            "{} {} changed, rebuilding",
+            "[icx-rustc]".bright_blue().bold(),
+            files[changed].display()
+        );
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
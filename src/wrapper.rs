@@ -0,0 +1,69 @@
+use crate::audit;
+use crate::cli::{self, ColorChoice};
+use crate::config;
+use crate::console;
+use crate::executor;
+use crate::exit_code;
+use crate::i18n;
+use crate::translator;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Whether cargo started us as `RUSTC_WRAPPER`: `icx-rustc /path/to/rustc <args>`
+///
+/// Like sccache, the first argument naming a `rustc` executable is what
+/// tells this mode apart from a normal command line.
+pub fn is_invocation(raw: &[String]) -> bool {
+    raw.get(1).is_some_and(|first| {
+        let path = Path::new(first);
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let is_exe = path.extension().is_none_or(|e| e.eq_ignore_ascii_case("exe"));
+        stem == "rustc" && is_exe
+    })
+}
+
+/// Runs one compiler call for cargo
+///
+/// Compiles get the options from `ICX_FLAGS` (e.g. `/arch:AVX2 /W4`), over
+/// those of icx-rustc.toml and `ICX_OPTIONS`, and the wrapper's
+/// diagnostics; cargo's queries (`-vV`, `--print`) and anything else not
+/// reporting JSON diagnostics run unchanged.
+pub fn run(raw: &[String]) -> Result<i32> {
+    let rustc = &raw[1];
+    let cargo_args: Vec<String> = raw[2..].to_vec();
+    
+    let compiles = cargo_args.iter().any(|a| a == "--error-format=json")
+        && !cargo_args.iter().any(|a| a == "-" || a.starts_with("--print") || a == "-vV" || a == "-V");
+    if !compiles {
+        let status = Command::new(rustc)
+            .args(&cargo_args)
+            .status()
+            .with_context(|| format!("Failed to run {}", rustc))?;
+        return Ok(status.code().unwrap_or(exit_code::KILLED));
+    }
+    
+    let flags = std::env::var("ICX_FLAGS").unwrap_or_default();
+    let words = shlex::split(&flags).with_context(|| format!("Invalid ICX_FLAGS '{}'", flags))?;
+    let config = config::load()?;
+    let mut args = cli::parse_layered(&config, words).context("Invalid ICX_FLAGS")?;
+    args.original = raw[1..].to_vec();
+    // cargo 检测到彩色终端时请求 diagnostic-rendered-ansi
+    let ansi = cargo_args.iter().any(|a| a.starts_with("--json=") && a.contains("diagnostic-rendered-ansi"));
+    let color = match args.color {
+        ColorChoice::Auto if ansi => ColorChoice::Always,
+        ColorChoice::Auto => ColorChoice::Never,
+        explicit => explicit,
+    };
+    console::init_color(color);
+    i18n::init(args.locale.as_deref());
+    
+    let cmd = translator::translate_wrapped(&args, rustc, cargo_args)?;
+    audit::record(&config, &args, std::slice::from_ref(&cmd))?;
+    if args.verbose {
+        console::eprint(&format!("[icx-rustc] {}", cmd.display()));
+    }
+    let compiled = executor::run(&cmd)?;
+    compiled.report(&cmd, compiled.code);
+    Ok(compiled.code)
+}